@@ -0,0 +1,397 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+use near_sdk::{env, near_bindgen, require, AccountId, NearToken, Promise};
+
+use crate::{
+    AdaptiveCrossChain, AdaptiveCrossChainExt, Balance, CrossChainOrder, EventLevel, OrderStatus, OrderView, StorageStore,
+    TGAS,
+};
+
+const DAY_NS: u64 = 86_400_000_000_000;
+
+// Terminal orders are indexed in hourly buckets per status
+pub const ARCHIVE_BUCKET_NS: u64 = 3_600_000_000_000;
+
+// tick() stops archiving once less than this much gas is left
+pub const GAS_RESERVED_FOR_TICK: u64 = 15 * TGAS;
+
+// get_health() only counts this many eligible buckets
+pub const MAX_HEALTH_SCAN_BUCKETS: usize = 50;
+
+/// How long full records of terminal orders are kept before tick() archives
+/// them, in nanoseconds after the terminal transition.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RetentionPolicy {
+    pub completed: U64,
+    pub cancelled: U64,
+    pub expired: U64,
+    pub purge_grace: U64, // After which the maker or owner can purge_order early
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            completed: U64(7 * DAY_NS),
+            cancelled: U64(2 * DAY_NS),
+            expired: U64(30 * DAY_NS),
+            purge_grace: U64(DAY_NS),
+        }
+    }
+}
+
+impl RetentionPolicy {
+    fn retention_for(&self, status_code: u8) -> u64 {
+        match status_code {
+            0 => self.completed.0,
+            1 => self.cancelled.0,
+            _ => self.expired.0,
+        }
+    }
+}
+
+/// Summary kept after the full order record has been pruned.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ArchivedOrder {
+    pub order_id: u64,
+    pub maker: AccountId,
+    pub status: OrderStatus,
+    pub amount_in: U128,
+    pub hashlock: String,
+    pub created_at: U64,
+    pub terminal_at: U64,
+    pub archived_at: U64,
+    pub scrubbed: bool, // Maker deleted metadata and callback registration
+    pub intervention_id: Option<u64>, // Set when an admin resolved the order
+}
+
+/// An order as stored: the full record, or its summary once archived.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub enum OrderRecord {
+    Live(Box<OrderView>),
+    Archived(ArchivedOrder),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TickReport {
+    pub refunded: u32,
+    pub archived: u32,
+    pub storage_released: U64, // Bytes
+    pub out_of_gas: bool,      // More eligible orders may remain
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct HealthReport {
+    pub terminal_orders_indexed: u64,
+    pub archival_backlog: u64,     // Indexed orders already past retention
+    pub backlog_truncated: bool,   // Scan hit MAX_HEALTH_SCAN_BUCKETS
+    pub oldest_terminal_at: Option<U64>,
+    pub archived_orders: u64,
+    pub retention_policy: RetentionPolicy,
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    // Applies to the next tick(); archived orders are never restored
+    pub fn set_retention_policy(&mut self, policy: RetentionPolicy) {
+        self.assert_owner();
+        env::log_str(&format!(
+            "Retention policy set: completed {}ns, cancelled {}ns, expired {}ns, purge grace {}ns",
+            policy.completed.0, policy.cancelled.0, policy.expired.0, policy.purge_grace.0
+        ));
+        self.retention_policy = policy;
+    }
+
+    /// Permissionless maintenance. Refunds expired orders in priority order,
+    /// then archives the oldest terminal orders that are past retention,
+    /// until the gas budget runs low.
+    pub fn tick(&mut self) -> TickReport {
+        let refunded = self.sweep_expired(GAS_RESERVED_FOR_TICK);
+        let now = env::block_timestamp();
+        let storage_before = env::storage_usage();
+        let mut archived = 0;
+        let mut out_of_gas = false;
+
+        'statuses: for status_code in 0..3u8 {
+            while let Some(key) = self.terminal_index.ceil_key(&(status_code, 0)) {
+                if key.0 != status_code || !self.bucket_expired(key, now) {
+                    break;
+                }
+                let mut order_ids = self.terminal_index.get(&key).unwrap_or_default();
+                while let Some(order_id) = order_ids.pop() {
+                    if env::prepaid_gas().as_gas() - env::used_gas().as_gas() < GAS_RESERVED_FOR_TICK {
+                        order_ids.push(order_id);
+                        let index_before = env::storage_usage();
+                        self.terminal_index.insert(&key, &order_ids);
+                        self.record_storage(StorageStore::Indexes, index_before);
+                        out_of_gas = true;
+                        break 'statuses;
+                    }
+                    self.terminal_indexed_count -= 1;
+                    // Orders purged early are already archived
+                    if self.archive_order(order_id, now) {
+                        archived += 1;
+                    }
+                }
+                let index_before = env::storage_usage();
+                self.terminal_index.remove(&key);
+                self.record_storage(StorageStore::Indexes, index_before);
+            }
+        }
+
+        // Archived summaries can outgrow tiny records, so never underflow
+        let storage_released = storage_before.saturating_sub(env::storage_usage());
+        if archived > 0 {
+            env::log_str(&format!(
+                "Archived {} orders, released {} bytes",
+                archived, storage_released
+            ));
+            self.emit_event(
+                EventLevel::Standard,
+                "orders_archived",
+                json!({ "archived": archived, "storage_released": storage_released }),
+            );
+        }
+
+        TickReport {
+            refunded,
+            archived,
+            storage_released: U64(storage_released),
+            out_of_gas,
+        }
+    }
+
+    pub fn get_health(&self) -> HealthReport {
+        let now = env::block_timestamp();
+        let mut archival_backlog = 0;
+        let mut scanned = 0;
+        let mut backlog_truncated = false;
+        let mut oldest_terminal_at: Option<u64> = None;
+
+        for status_code in 0..3u8 {
+            for (key, order_ids) in self.terminal_index.iter_from((status_code, 0)) {
+                if key.0 != status_code {
+                    break;
+                }
+                let bucket_start = key.1 * ARCHIVE_BUCKET_NS;
+                oldest_terminal_at = Some(oldest_terminal_at.map_or(bucket_start, |t| t.min(bucket_start)));
+                if !self.bucket_expired(key, now) {
+                    break;
+                }
+                if scanned == MAX_HEALTH_SCAN_BUCKETS {
+                    backlog_truncated = true;
+                    break;
+                }
+                archival_backlog += order_ids.len() as u64;
+                scanned += 1;
+            }
+        }
+
+        HealthReport {
+            terminal_orders_indexed: self.terminal_indexed_count,
+            archival_backlog,
+            backlog_truncated,
+            oldest_terminal_at: oldest_terminal_at.map(U64),
+            archived_orders: self.archived_count,
+            retention_policy: self.retention_policy.clone(),
+        }
+    }
+
+    /// Archives a terminal order ahead of its retention, once `purge_grace`
+    /// has passed since it ended. Only its maker and the owner can purge
+    /// it. Returns the bytes released.
+    pub fn purge_order(&mut self, order_id: u64) -> U64 {
+        let order = self.orders.get(&order_id).expect("Order not found");
+        let caller = env::predecessor_account_id();
+        require!(
+            caller == order.maker || caller == self.owner,
+            "Only the maker or the owner can purge an order"
+        );
+        require!(terminal_status_code(&order.status).is_some(), "Order has not ended");
+        let now = env::block_timestamp();
+        let terminal_at = order.terminal_at.unwrap_or(order.created_at).0;
+        require!(
+            terminal_at.saturating_add(self.retention_policy.purge_grace.0) <= now,
+            "Purge grace period has not passed"
+        );
+
+        let storage_before = env::storage_usage();
+        self.archive_order(order_id, now);
+        let storage_released = storage_before.saturating_sub(env::storage_usage());
+        self.emit_event(
+            EventLevel::Standard,
+            "order_purged",
+            json!({ "order_id": order_id, "by": caller, "storage_released": storage_released }),
+        );
+        U64(storage_released)
+    }
+
+    pub fn get_archived_order(&self, order_id: u64) -> Option<ArchivedOrder> {
+        self.archived_orders.get(&order_id)
+    }
+
+    // Falls back to the summary for archived and purged orders
+    pub fn get_order_record(&self, order_id: u64) -> Option<OrderRecord> {
+        match self.orders.get(&order_id) {
+            Some(order) => Some(OrderRecord::Live(Box::new(self.public_order_view(order)))),
+            None => self.archived_orders.get(&order_id).map(OrderRecord::Archived),
+        }
+    }
+
+    pub fn get_storage_refund(&self, account_id: AccountId) -> U128 {
+        U128(self.storage_refunds.get(&account_id).unwrap_or(0))
+    }
+
+    pub fn withdraw_storage_refund(&mut self) -> Promise {
+        let account_id = env::predecessor_account_id();
+        let amount = self.storage_refunds.remove(&account_id).unwrap_or(0);
+        require!(amount > 0, "No storage refund available");
+        Promise::new(account_id).transfer(NearToken::from_yoctonear(amount))
+    }
+}
+
+impl AdaptiveCrossChain {
+    /// Records the terminal transition time so tick() can find the order
+    /// once its retention has passed. Called by the status transition helper.
+    pub(crate) fn index_terminal(&mut self, order: &CrossChainOrder, terminal_at: u64) {
+        let status_code = match terminal_status_code(&order.status) {
+            Some(code) => code,
+            None => return,
+        };
+        let key = (status_code, terminal_at / ARCHIVE_BUCKET_NS);
+        let storage_before = env::storage_usage();
+        let mut order_ids = self.terminal_index.get(&key).unwrap_or_default();
+        order_ids.push(order.order_id);
+        self.terminal_index.insert(&key, &order_ids);
+        self.record_storage(StorageStore::Indexes, storage_before);
+        self.terminal_indexed_count += 1;
+    }
+
+    // A bucket is due once its newest possible entry is past retention
+    fn bucket_expired(&self, key: (u8, u64), now: u64) -> bool {
+        let bucket_end = (key.1 + 1) * ARCHIVE_BUCKET_NS;
+        let retention = self.retention_policy.retention_for(key.0) / self.retention_divisor();
+        bucket_end.saturating_add(retention) <= now
+    }
+
+    // Replaces the full record with a summary and drops per-order storage.
+    // The maker paid for their metadata and callback registration, so that
+    // part is credited back; the rest was funded by the contract. The
+    // terminal index entry is left for tick() to drop. False if the order
+    // was already archived.
+    fn archive_order(&mut self, order_id: u64, now: u64) -> bool {
+        let storage_before = env::storage_usage();
+        let order = match self.orders.remove(&order_id) {
+            Some(order) => order,
+            None => return false,
+        };
+        // A hashlock may already belong to a newer order
+        for hashlock in std::iter::once(&order.hashlock).chain(order.taker_hashlock.as_ref()) {
+            if self.hashlock_to_order.get(hashlock) == Some(order_id) {
+                self.hashlock_to_order.remove(hashlock);
+            }
+        }
+        self.used_secret_indices.remove(&order_id);
+        self.record_storage(StorageStore::Orders, storage_before);
+
+        self.count_archived(&order);
+        let storage_before = env::storage_usage();
+        if let Some(mut history) = self.slippage_history.remove(&order_id) {
+            history.clear();
+        }
+        self.history_heads.remove(&order_id);
+        self.record_storage(StorageStore::Histories, storage_before);
+
+        let mut refund = self.release_order_metadata(order_id);
+        refund += self.order_storage_deposits.remove(&order_id).unwrap_or(0);
+        let storage_before = env::storage_usage();
+        if self.settlement_callbacks.remove(&order_id).is_some() {
+            let released = storage_before - env::storage_usage();
+            refund += released as Balance * env::storage_byte_cost().as_yoctonear();
+            self.record_storage(StorageStore::Notifications, storage_before);
+        }
+        if refund > 0 {
+            let credited = self.storage_refunds.get(&order.maker).unwrap_or(0);
+            self.storage_refunds.insert(&order.maker, &(credited + refund));
+        }
+
+        let storage_before = env::storage_usage();
+        self.archived_orders.insert(&order_id, &ArchivedOrder {
+            order_id,
+            maker: order.maker,
+            status: order.status,
+            amount_in: order.amount_in,
+            hashlock: order.hashlock,
+            created_at: order.created_at,
+            terminal_at: order.terminal_at.unwrap_or(U64(now)),
+            archived_at: U64(now),
+            scrubbed: false,
+            intervention_id: self.order_interventions.get(&order_id),
+        });
+        self.record_storage(StorageStore::Archive, storage_before);
+        self.archived_count += 1;
+        true
+    }
+}
+
+fn terminal_status_code(status: &OrderStatus) -> Option<u8> {
+    match status {
+        OrderStatus::Completed => Some(0),
+        OrderStatus::Cancelled => Some(1),
+        OrderStatus::Expired => Some(2),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use crate::test_utils::ONE_NEAR;
+
+    #[test]
+    fn purged_orders_leave_only_their_summary() {
+        set_clock(0, 1);
+        let mut contract = setup();
+        let params = order_params(&contract, 1, ONE_NEAR);
+        let order_id = create_order_with(&mut contract, params, ONE_NEAR);
+        let order = contract.orders.get(&order_id).unwrap();
+        call(maker(), 0);
+        let _ = contract.cancel_order(order_id);
+
+        let early = run_or_revert(&mut contract, |c| {
+            call(maker(), 0);
+            c.purge_order(order_id)
+        });
+        assert!(early.unwrap_err().contains("Purge grace period has not passed"));
+
+        set_clock(DAY_NS, 2);
+        call(maker(), 0);
+        let storage_before = env::storage_usage();
+        let released = contract.purge_order(order_id).0;
+        assert!(released > 0);
+        assert_eq!(env::storage_usage(), storage_before - released);
+        assert_eq!(event_data("order_purged").unwrap()["storage_released"], released);
+
+        assert!(contract.hashlock_to_order.get(&order.hashlock).is_none());
+        assert!(contract.slippage_log(order_id).is_none());
+        assert_eq!(contract.get_slippage_history_len(order_id), 0);
+        assert!(contract.get_order(order_id).is_none());
+        let summary = match contract.get_order_record(order_id) {
+            Some(OrderRecord::Archived(summary)) => summary,
+            _ => panic!("the purged order has no summary"),
+        };
+        assert_eq!(summary.maker, maker());
+        assert!(matches!(summary.status, OrderStatus::Cancelled));
+        assert_eq!(summary.amount_in, order.amount_in);
+        assert_eq!(summary.hashlock, order.hashlock);
+        assert_eq!(summary.archived_at.0, DAY_NS);
+        assert_eq!(contract.get_archived_order(order_id).unwrap().order_id, order_id);
+    }
+}
@@ -0,0 +1,45 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U64;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId};
+
+use crate::limits;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, StorageStore};
+
+/// Append-only record of privileged or privacy-relevant actions. Entries
+/// describe what happened, never the data involved.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuditEntry {
+    pub timestamp: U64,
+    pub actor: AccountId,
+    pub action: String,
+    pub order_id: Option<u64>,
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    pub fn get_audit_log(&self, from_index: u64, limit: u64) -> Vec<AuditEntry> {
+        let limit = limit.min(limits::MAX_BATCH_SIZE as u64);
+        (from_index..self.audit_log.len().min(from_index.saturating_add(limit)))
+            .filter_map(|index| self.audit_log.get(index))
+            .collect()
+    }
+
+    pub fn get_audit_log_len(&self) -> u64 {
+        self.audit_log.len()
+    }
+}
+
+impl AdaptiveCrossChain {
+    pub(crate) fn audit(&mut self, action: &str, order_id: Option<u64>) {
+        let storage_before = env::storage_usage();
+        self.audit_log.push(&AuditEntry {
+            timestamp: U64(env::block_timestamp()),
+            actor: env::predecessor_account_id(),
+            action: action.to_string(),
+            order_id,
+        });
+        self.record_storage(StorageStore::AuditLog, storage_before);
+    }
+}
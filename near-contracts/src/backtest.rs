@@ -0,0 +1,72 @@
+use near_sdk::json_types::U64;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, require};
+
+use crate::limits;
+use crate::pairs::{self, SlippageInputs, SlippageModel};
+use crate::roles::Role;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt};
+
+/// One recorded slippage decision next to what `candidate` would have set.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BacktestPoint {
+    pub timestamp: U64,
+    pub actual_slippage: u64,
+    pub candidate_slippage: u64,
+    pub volatility_score: u64,
+    pub cross_chain_delay: u64,
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    /// Replays the order's slippage history through `candidate` using the
+    /// volatility score and bridge delay recorded with each decision. Only
+    /// the last MAX_BATCH_SIZE entries are replayed; nothing is written.
+    pub fn backtest_slippage(&self, order_id: u64, candidate: SlippageModel) -> Vec<BacktestPoint> {
+        require!(
+            self.has_role(env::predecessor_account_id(), Role::Operator),
+            "Caller lacks the Operator role"
+        );
+        let order = self.orders.get(&order_id).expect("Order not found");
+        let history = match self.slippage_log(order_id) {
+            Some(history) => history,
+            None => return Vec::new(),
+        };
+
+        let start = history.len().saturating_sub(limits::MAX_BATCH_SIZE as u64);
+        // Updates are clamped against the previous value; when the replay is
+        // truncated, start from the last decision before the window
+        let mut previous = if start > 0 {
+            history.get(start - 1).map(|entry| entry.slippage)
+        } else {
+            None
+        };
+
+        let mut points = Vec::new();
+        for index in start..history.len() {
+            let entry = history.get(index).unwrap();
+            let inputs = SlippageInputs {
+                amount: order.amount_in.0,
+                target_chain_id: order.target_chain_id,
+                chain_premium_bps: self.chain_premium_bps(order.target_chain_id),
+                volatility_score: entry.volatility_score,
+                bridge_delay_secs: entry.cross_chain_delay,
+            };
+            let raw = pairs::model_slippage(&candidate, &inputs);
+            let candidate_slippage = match previous {
+                Some(current) => pairs::apply_max_deviation(current, raw, order.max_slippage_deviation),
+                None => raw,
+            };
+            previous = Some(candidate_slippage);
+            points.push(BacktestPoint {
+                timestamp: entry.timestamp,
+                actual_slippage: entry.slippage,
+                candidate_slippage,
+                volatility_score: entry.volatility_score,
+                cross_chain_delay: entry.cross_chain_delay,
+            });
+        }
+        points
+    }
+}
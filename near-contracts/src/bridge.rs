@@ -0,0 +1,1241 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+use near_sdk::{env, ext_contract, near_bindgen, require, AccountId, Gas, PromiseResult};
+
+use crate::encoding::{self, BridgeAction, PayloadEncoding};
+use crate::limits;
+use crate::normalize;
+use crate::validation::{Violation, ERR_INVALID_BRIDGE_ROUTE, ERR_ROUTE_TOO_SLOW};
+use crate::{
+    AdaptiveCrossChain, AdaptiveCrossChainExt, BridgeMessage, CrossChainOrder, EventLevel, OrderStatus, GAS_FOR_CROSS_CHAIN_CALL,
+};
+
+pub const ERR_CONFLICTING_ACK: &str = "ERR_CONFLICTING_ACK";
+pub const ERR_PAYLOAD_TOO_LARGE: &str = "ERR_PAYLOAD_TOO_LARGE";
+pub const ERR_UNKNOWN_BRIDGE_ACTION: &str = "ERR_UNKNOWN_BRIDGE_ACTION";
+pub const ERR_BRIDGE_REPLAY: &str = "ERR_BRIDGE_REPLAY";
+
+// Action of the messages an oversized payload is split into
+pub const CHUNK_ACTION: &str = "chunk";
+
+pub const GAS_FOR_BRIDGE_CALLBACK: Gas = Gas::from_tgas(10);
+
+// A route limit must leave room for the chunk header and at least one word
+const MIN_ROUTE_PAYLOAD_BYTES: u32 = (encoding::CHUNK_HEADER_LEN + 32) as u32;
+
+#[ext_contract(ext_bridge)]
+#[allow(dead_code)] // Only the generated call builders are used
+pub trait MessageBridge {
+    fn submit_message(&mut self, message: BridgeMessage);
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum BridgeKind {
+    LightClient, // Trust-minimized, slow
+    Optimistic,  // Fast, relies on a challenge period
+}
+
+/// One message bridge the contract can dispatch through.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BridgeConfig {
+    pub account: AccountId,
+    pub kind: BridgeKind,
+    pub fee: U128,          // Charged to the maker at creation
+    pub delay: U64,         // Expected delivery delay in seconds
+    pub enabled: bool,
+    pub chain_ids: Vec<u64>, // Empty serves every chain
+    pub max_payload_bytes: Option<u32>, // None allows the contract-wide limit
+}
+
+impl BridgeConfig {
+    pub fn serves(&self, chain_id: u64) -> bool {
+        self.chain_ids.is_empty() || self.chain_ids.contains(&chain_id)
+    }
+
+    pub fn payload_limit(&self) -> usize {
+        self.max_payload_bytes
+            .map(|max| max as usize)
+            .unwrap_or(limits::MAX_BRIDGE_DATA_LEN / 2)
+    }
+}
+
+/// Delivery progress of a payload that was split into chunk messages on
+/// consecutive nonces of its lane. The first nonce is the message id.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ChunkedMessage {
+    pub order_id: u64,
+    pub action: String,     // Action of the original message
+    pub commitment: String, // Commitment over the reassembled payload
+    pub total: u16,
+    pub delivered: u16,
+    pub failed: u16,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DecodedField {
+    pub name: String,
+    pub value: String,
+}
+
+/// The payload an order message would carry if dispatched now.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PayloadPreview {
+    pub encoding: PayloadEncoding,
+    pub action: String,
+    pub hex_bytes: String,
+    pub commitment: String,
+    pub decoded_fields: Vec<DecodedField>, // Read back from hex_bytes
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BridgeRoute {
+    pub route_id: u8,
+    pub config: BridgeConfig,
+    pub is_default: bool, // Default for the chain that was queried
+}
+
+/// Outbound nonce lane for one destination chain. Lanes are independent so
+/// a stuck chain never blocks delivery to the others.
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct LaneState {
+    pub next_nonce: u64,
+    pub last_acked: u64,    // Highest nonce acknowledged
+    pub acked_through: u64, // Every nonce up to here is acknowledged
+    pub pending_count: u64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum AckOutcome {
+    Delivered,
+    Failed, // Rejected on the destination chain; counted as a dead letter
+}
+
+/// Terminal acknowledgement of one message, kept so retried acks are
+/// no-ops.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AckRecord {
+    pub outcome: AckOutcome,
+    pub route_id: u8,
+    pub acked_at: U64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum MessageStatus {
+    Pending, // Queued or handed to the bridge, result not in yet
+    Sent,    // Bridge account accepted it; waiting for the ack
+    Acked,   // Pruned from the outbox
+    Failed,  // Hand-off failed; retry_bridge_message sends it again
+}
+
+/// Outbound message awaiting its ack. Retries keep the message id and the
+/// lane nonce.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct QueuedMessage {
+    pub message_id: u64,
+    pub message: BridgeMessage,
+    pub status: MessageStatus,
+    pub attempts: u32, // Hand-offs to the bridge so far
+    pub queued_at: U64,
+    pub last_attempt_at: U64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LaneStatus {
+    pub chain_id: u64,
+    pub next_nonce: u64,
+    pub last_acked: u64,
+    pub acked_through: u64,
+    pub pending_count: u64,
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    /// Bridge acknowledgement for an outbound message. `outcome` defaults
+    /// to Delivered. Side effects apply exactly once: repeating an ack is a
+    /// no-op, contradicting one fails with ERR_CONFLICTING_ACK, and acks
+    /// may arrive in any order. The message is then pruned from the outbox.
+    /// Acking a "create_order" or "create_dual_order" message confirms the
+    /// order is mirrored on Ethereum.
+    pub fn ack_bridge_message(&mut self, message_id: u64, outcome: Option<AckOutcome>) {
+        let outcome = outcome.unwrap_or(AckOutcome::Delivered);
+        let caller = env::predecessor_account_id();
+
+        if let Some((chain_id, nonce)) = self.acked_message_lanes.get(&message_id) {
+            let record = self.acked_messages.get(&(chain_id, nonce)).expect("Ack record not found");
+            require!(
+                self.is_route_account(record.route_id, chain_id, &caller),
+                "Only the bridge can acknowledge messages"
+            );
+            if record.outcome != outcome {
+                Violation::new(
+                    ERR_CONFLICTING_ACK,
+                    format!(
+                        "message {} was already acknowledged as {}",
+                        message_id,
+                        outcome_name(record.outcome)
+                    ),
+                )
+                .panic();
+            }
+            env::log_str(&format!("Duplicate ack for message {} ignored", message_id));
+            return;
+        }
+
+        let message = self.outbox.get(&message_id).expect("Message not pending").message;
+        require!(
+            self.is_route_account(message.route_id, message.chain_id, &caller),
+            "Only the bridge can acknowledge messages"
+        );
+        let (chain_id, nonce) = (message.chain_id, message.nonce);
+        self.outbox.remove(&message_id);
+        self.acked_message_lanes.insert(&message_id, &(chain_id, nonce));
+        self.acked_messages.insert(&(chain_id, nonce), &AckRecord {
+            outcome,
+            route_id: message.route_id,
+            acked_at: U64(env::block_timestamp()),
+        });
+
+        let mut lane = self.lane(chain_id);
+        lane.pending_count -= 1;
+        self.pending_bridge_messages -= 1;
+        lane.last_acked = lane.last_acked.max(nonce);
+        while lane.acked_through < lane.next_nonce
+            && self.acked_messages.get(&(chain_id, lane.acked_through + 1)).is_some()
+        {
+            lane.acked_through += 1;
+        }
+        self.outbound_lanes.insert(&chain_id, &lane);
+
+        // A chunked payload takes effect once its last chunk is delivered
+        let delivered_action = if message.action == CHUNK_ACTION {
+            self.record_chunk_ack(&message, outcome)
+        } else if outcome == AckOutcome::Delivered {
+            Some(message.action.clone())
+        } else {
+            None
+        };
+        if outcome == AckOutcome::Failed {
+            self.dead_letter_count += 1;
+        }
+        if matches!(delivered_action.as_deref(), Some("create_order" | "create_dual_order")) {
+            self.mark_mirror_confirmed(message.order_id, "bridge_ack");
+        }
+
+        env::log_str(&format!(
+            "Bridge message {} acknowledged as {}: {} for order {} (chain {}, nonce {})",
+            message_id,
+            outcome_name(outcome),
+            message.action,
+            message.order_id,
+            chain_id,
+            nonce
+        ));
+    }
+
+    /// Result of handing a message to its route's bridge account. A failed
+    /// hand-off leaves the message in the outbox as Failed and emits
+    /// `bridge_message_failed` so relayers can retry it; once it is out of
+    /// retries the order is flagged stuck.
+    #[private]
+    pub fn on_bridge_message_result(&mut self, message_id: u64) -> bool {
+        let sent = matches!(env::promise_result(0), PromiseResult::Successful(_));
+        let mut queued = match self.outbox.get(&message_id) {
+            Some(queued) => queued,
+            None => return sent, // Acknowledged in the meantime
+        };
+        let message = &queued.message;
+        if sent {
+            env::log_str(&format!(
+                "Bridge message {} ({} for order {}) accepted by route {}",
+                message_id, message.action, message.order_id, message.route_id
+            ));
+            queued.status = MessageStatus::Sent;
+            self.outbox.insert(&message_id, &queued);
+            return true;
+        }
+
+        let retries_left = (self.max_bridge_retries + 1).saturating_sub(queued.attempts);
+        self.emit_event(
+            EventLevel::Minimal,
+            "bridge_message_failed",
+            json!({
+                "message_id": message_id,
+                "order_id": message.order_id,
+                "chain_id": message.chain_id,
+                "nonce": message.nonce,
+                "route_id": message.route_id,
+                "action": message.action,
+                "attempts": queued.attempts,
+                "retries_left": retries_left,
+            }),
+        );
+        if retries_left == 0 {
+            self.flag_stuck(
+                message.order_id,
+                format!("bridge message {} failed {} times", message_id, queued.attempts),
+                None,
+                None,
+            );
+        }
+        queued.status = MessageStatus::Failed;
+        self.outbox.insert(&message_id, &queued);
+        false
+    }
+
+    /// Sends a Failed message again, on a fallback route if its own was
+    /// disabled. Anyone can call it, up to `max_bridge_retries` times per
+    /// message.
+    pub fn retry_bridge_message(&mut self, message_id: u64) {
+        let mut queued = self.outbox.get(&message_id).expect("Message not pending");
+        require!(queued.status == MessageStatus::Failed, "Only failed messages can be retried");
+        require!(
+            queued.attempts <= self.max_bridge_retries,
+            "Bridge message is out of retries"
+        );
+        queued.message.route_id = self.dispatch_route(&queued.message);
+        self.dispatch_bridge_message(queued);
+    }
+
+    pub fn set_max_bridge_retries(&mut self, max_retries: u32) {
+        self.assert_owner();
+        env::log_str(&format!(
+            "max_bridge_retries set from {} to {}",
+            self.max_bridge_retries, max_retries
+        ));
+        self.max_bridge_retries = max_retries;
+        self.audit("set_max_bridge_retries", None);
+    }
+
+    /// Messages not acknowledged yet, in no particular order
+    pub fn get_pending_messages(&self, from_index: u64, limit: u64) -> Vec<QueuedMessage> {
+        let limit = limit.min(limits::MAX_BATCH_SIZE as u64);
+        self.outbox
+            .values()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    // None once acknowledged; see get_message_status and get_ack_record
+    pub fn get_message(&self, message_id: u64) -> Option<QueuedMessage> {
+        self.outbox.get(&message_id)
+    }
+
+    pub fn get_message_status(&self, message_id: u64) -> Option<MessageStatus> {
+        match self.outbox.get(&message_id) {
+            Some(queued) => Some(queued.status),
+            None => self.acked_message_lanes.get(&message_id).map(|_| MessageStatus::Acked),
+        }
+    }
+
+    pub fn get_ack_record(&self, chain_id: u64, nonce: u64) -> Option<AckRecord> {
+        self.acked_messages.get(&(chain_id, nonce))
+    }
+
+    pub fn get_chunked_message(&self, chain_id: u64, message_id: u64) -> Option<ChunkedMessage> {
+        self.chunked_messages.get(&(chain_id, message_id))
+    }
+
+    pub fn set_payload_encoding(&mut self, chain_id: u64, encoding: PayloadEncoding) {
+        self.assert_owner();
+        self.payload_encodings.insert(&chain_id, &encoding);
+        env::log_str(&format!(
+            "Payload encoding for chain {} set to {}",
+            chain_id,
+            encoding.id()
+        ));
+    }
+
+    pub fn add_bridge_route(&mut self, route_id: u8, config: BridgeConfig) {
+        self.assert_owner();
+        require!(self.bridges.get(&route_id).is_none(), "Route already exists");
+        assert_payload_limit(&config);
+        self.bridges.insert(&route_id, &config);
+        env::log_str(&format!("Bridge route {} added: {}", route_id, config.account));
+    }
+
+    pub fn update_bridge_route(&mut self, route_id: u8, config: BridgeConfig) {
+        self.assert_owner();
+        require!(self.bridges.get(&route_id).is_some(), "Route not found");
+        assert_payload_limit(&config);
+        self.bridges.insert(&route_id, &config);
+        env::log_str(&format!("Bridge route {} updated", route_id));
+    }
+
+    // The bridge given at init, i.e. the account of route 0
+    pub fn set_bridge_contract(&mut self, bridge_contract: AccountId) {
+        self.assert_owner();
+        let mut config = self.bridges.get(&0).expect("Route not found");
+        env::log_str(&format!(
+            "bridge_contract set from {} to {}",
+            config.account, bridge_contract
+        ));
+        config.account = bridge_contract;
+        self.bridges.insert(&0, &config);
+        self.audit("set_bridge_contract", None);
+    }
+
+    // In-flight orders on a disabled route fall back to another route
+    pub fn set_bridge_route_enabled(&mut self, route_id: u8, enabled: bool) {
+        self.assert_owner();
+        let mut config = self.bridges.get(&route_id).expect("Route not found");
+        config.enabled = enabled;
+        self.bridges.insert(&route_id, &config);
+        env::log_str(&format!("Bridge route {} enabled: {}", route_id, enabled));
+    }
+
+    // Only disabled routes can be removed. Messages already sent on them
+    // can then be acked by any enabled route serving their chain.
+    pub fn remove_bridge_route(&mut self, route_id: u8) {
+        self.assert_owner();
+        let config = self.bridges.get(&route_id).expect("Route not found");
+        require!(!config.enabled, "Disable the route before removing it");
+        self.bridges.remove(&route_id);
+        env::log_str(&format!("Bridge route {} removed", route_id));
+    }
+
+    pub fn set_default_bridge_route(&mut self, chain_id: u64, route_id: u8) {
+        self.assert_owner();
+        let config = self.bridges.get(&route_id).expect("Route not found");
+        require!(config.serves(chain_id), "Route does not serve this chain");
+        self.default_routes.insert(&chain_id, &route_id);
+        env::log_str(&format!("Default bridge route for chain {} set to {}", chain_id, route_id));
+    }
+
+    // View functions
+    pub fn get_supported_bridges(&self, chain_id: u64) -> Vec<BridgeRoute> {
+        let default_route = self.default_route(chain_id);
+        self.bridges
+            .iter()
+            .filter(|(_, config)| config.enabled && config.serves(chain_id))
+            .map(|(route_id, config)| BridgeRoute {
+                route_id,
+                config,
+                is_default: default_route == Some(route_id),
+            })
+            .collect()
+    }
+
+    pub fn get_bridge_route(&self, route_id: u8) -> Option<BridgeConfig> {
+        self.bridges.get(&route_id)
+    }
+
+    pub fn get_payload_encoding(&self, chain_id: u64) -> PayloadEncoding {
+        self.payload_encoding_for(chain_id)
+    }
+
+    /// Rebuilds the payload through the same builder the dispatcher uses.
+    /// The nonce is only assigned on dispatch, so none is consumed here.
+    pub fn get_ethereum_payload(&self, order_id: u64, message_kind: BridgeAction) -> PayloadPreview {
+        let order = self.orders.get(&order_id).expect("Order not found");
+        let message = self.build_bridge_message(&order, message_kind.as_str());
+        let bytes = hex::decode(&message.data).expect("Invalid payload hex");
+        let (action_code, fields) =
+            encoding::decode(message.encoding, &bytes).expect("Payload does not decode");
+        let decoded_fields = encoding::field_names(action_code)
+            .iter()
+            .zip(fields.iter())
+            .map(|(name, field)| DecodedField {
+                name: name.to_string(),
+                value: encoding::display_field(field),
+            })
+            .collect();
+
+        PayloadPreview {
+            encoding: message.encoding,
+            action: message.action,
+            hex_bytes: message.data,
+            commitment: message.commitment,
+            decoded_fields,
+        }
+    }
+
+    pub fn get_lane_status(&self, chain_id: u64) -> LaneStatus {
+        let lane = self.lane(chain_id);
+        LaneStatus {
+            chain_id,
+            next_nonce: lane.next_nonce,
+            last_acked: lane.last_acked,
+            acked_through: lane.acked_through,
+            pending_count: lane.pending_count,
+        }
+    }
+}
+
+// Instructions from the Ethereum side. `message.chain_id` is the chain the
+// instruction comes from and `nonce` its sequence on that chain.
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    /// Executes one instruction for the order `message.order_id`. Only a
+    /// bridge account serving the chain or the EthProver may call it, and
+    /// each (chain_id, nonce) executes once. The action decides what `data`
+    /// carries, hex encoded:
+    /// - "lock_order": the NEAR account of the resolver that escrowed on
+    ///   Ethereum; locks the rest of the order for it
+    /// - "complete_order": the secret revealed on Ethereum; claims the lock
+    ///   for its taker
+    /// - "cancel_order": nothing; cancels an Active order and refunds the maker
+    pub fn execute_bridge_message(&mut self, message: BridgeMessage, nonce: u64) {
+        self.assert_not_paused();
+        limits::assert_len(message.data.len(), limits::MAX_BRIDGE_DATA_LEN, limits::ERR_BRIDGE_DATA_TOO_LONG);
+        let caller = env::predecessor_account_id();
+        require!(
+            self.is_route_account(message.route_id, message.chain_id, &caller)
+                || self.eth_prover.as_ref() == Some(&caller),
+            "Only the bridge can execute messages"
+        );
+        if !self.executed_bridge_nonces.insert(&(message.chain_id, nonce)) {
+            Violation::new(
+                ERR_BRIDGE_REPLAY,
+                format!("message {}:{} was already executed", message.chain_id, nonce),
+            )
+            .panic();
+        }
+        let order = self.orders.get(&message.order_id).expect("Order not found");
+        require!(
+            order.target_chain_id == message.chain_id,
+            "Order does not target the message's chain"
+        );
+
+        match message.action.as_str() {
+            "lock_order" => {
+                let resolver: AccountId = decode_utf8(&message.data)
+                    .and_then(|account| account.parse().ok())
+                    .expect("lock_order data must be a hex-encoded account id");
+                self.internal_lock(resolver, order.order_id, None);
+            }
+            "complete_order" => {
+                let secret = decode_utf8(&message.data).expect("complete_order data must be the hex-encoded secret");
+                require!(matches!(order.status, OrderStatus::Locked), "Order not in locked state");
+                let taker = order.taker.clone().expect("Locked order without a taker");
+                self.settle_claim(order.hashlock.clone(), secret, taker, None, 0);
+            }
+            "cancel_order" => {
+                require!(matches!(order.status, OrderStatus::Active), "Order not active");
+                self.internal_cancel(order);
+            }
+            action => Violation::new(
+                ERR_UNKNOWN_BRIDGE_ACTION,
+                format!("unknown bridge action {}", action),
+            )
+            .panic(),
+        }
+
+        self.emit_event(
+            EventLevel::Minimal,
+            "bridge_message_executed",
+            json!({
+                "order_id": message.order_id,
+                "chain_id": message.chain_id,
+                "nonce": nonce,
+                "action": message.action,
+                "executed_by": caller,
+            }),
+        );
+    }
+
+    pub fn is_bridge_nonce_executed(&self, chain_id: u64, nonce: u64) -> bool {
+        self.executed_bridge_nonces.contains(&(chain_id, nonce))
+    }
+}
+
+impl AdaptiveCrossChain {
+    pub(crate) fn payload_encoding_for(&self, chain_id: u64) -> PayloadEncoding {
+        self.payload_encodings.get(&chain_id).unwrap_or(PayloadEncoding::Abi)
+    }
+
+    pub(crate) fn build_bridge_message(&self, order: &CrossChainOrder, action: &str) -> BridgeMessage {
+        let encoding = self.payload_encoding_for(order.target_chain_id);
+        let fields = encoding::order_message_fields(order, action);
+        let payload = encoding::encode(encoding, encoding::action_code(action), &fields);
+        limits::assert_len(payload.len() * 2, limits::MAX_BRIDGE_DATA_LEN, limits::ERR_BRIDGE_DATA_TOO_LONG);
+
+        BridgeMessage {
+            order_id: order.order_id,
+            chain_id: order.target_chain_id,
+            nonce: 0,
+            route_id: order.bridge_route,
+            target_contract: self.counterpart_contract(order.target_chain_id),
+            action: action.to_string(),
+            encoding,
+            commitment: hex::encode(encoding::commitment(encoding, &payload)),
+            data: hex::encode(payload),
+        }
+    }
+
+    /// Oversized single-order payloads are rejected at creation rather than
+    /// chunked.
+    pub(crate) fn payload_size_violation(&self, message: &BridgeMessage) -> Option<Violation> {
+        let config = self.bridges.get(&message.route_id)?;
+        let len = message.data.len() / 2;
+        if len > config.payload_limit() {
+            return Some(Violation::new(
+                ERR_PAYLOAD_TOO_LARGE,
+                format!(
+                    "{} payload is {} bytes, route {} carries at most {}",
+                    message.action,
+                    len,
+                    message.route_id,
+                    config.payload_limit()
+                ),
+            ));
+        }
+        None
+    }
+
+    // Payloads over the limit of the route they go out on are split into
+    // chunk messages
+    pub(crate) fn send_bridge_message(&mut self, mut message: BridgeMessage) {
+        message.route_id = self.dispatch_route(&message);
+        if self.bridges.get(&message.route_id).is_none() {
+            self.flag_stuck(
+                message.order_id,
+                format!("route {} was removed and no route serves chain {}", message.route_id, message.chain_id),
+                None,
+                None,
+            );
+        }
+        let limit = self
+            .bridges
+            .get(&message.route_id)
+            .map(|config| config.payload_limit())
+            .unwrap_or(limits::MAX_BRIDGE_DATA_LEN / 2);
+        if message.data.len() / 2 > limit {
+            self.send_chunked(message, limit);
+        } else {
+            self.enqueue_bridge_message(message);
+        }
+    }
+
+    // Chunks go out on consecutive nonces, so the first one is a message id
+    // the receiver can reassemble by
+    fn send_chunked(&mut self, message: BridgeMessage, limit: usize) {
+        let payload = hex::decode(&message.data).expect("Invalid payload hex");
+        let message_id = self.lane(message.chain_id).next_nonce + 1;
+        let chunks = encoding::chunk_payload(message_id, &payload, limit);
+        self.chunked_messages.insert(&(message.chain_id, message_id), &ChunkedMessage {
+            order_id: message.order_id,
+            action: message.action.clone(),
+            commitment: message.commitment.clone(),
+            total: chunks.len() as u16,
+            delivered: 0,
+            failed: 0,
+        });
+        env::log_str(&format!(
+            "Bridge message {} for order {} split into {} chunks (chain {}, message id {})",
+            message.action,
+            message.order_id,
+            chunks.len(),
+            message.chain_id,
+            message_id
+        ));
+
+        for chunk in chunks {
+            self.enqueue_bridge_message(BridgeMessage {
+                order_id: message.order_id,
+                chain_id: message.chain_id,
+                nonce: 0,
+                route_id: message.route_id,
+                target_contract: message.target_contract.clone(),
+                action: CHUNK_ACTION.to_string(),
+                encoding: message.encoding,
+                commitment: hex::encode(encoding::commitment(message.encoding, &chunk)),
+                data: hex::encode(chunk),
+            });
+        }
+    }
+
+    // Returns the original action once every chunk has been delivered
+    fn record_chunk_ack(&mut self, message: &BridgeMessage, outcome: AckOutcome) -> Option<String> {
+        let bytes = hex::decode(&message.data).ok()?;
+        let header = encoding::read_chunk_header(&bytes)?;
+        let key = (message.chain_id, header.message_id);
+        let mut chunked = self.chunked_messages.get(&key).filter(|c| c.total == header.total)?;
+        env::log_str(&format!(
+            "Chunk {}/{} of message {} acknowledged",
+            header.index + 1,
+            header.total,
+            header.message_id
+        ));
+        match outcome {
+            AckOutcome::Delivered => chunked.delivered += 1,
+            AckOutcome::Failed => chunked.failed += 1,
+        }
+        self.chunked_messages.insert(&key, &chunked);
+        if chunked.delivered == chunked.total {
+            Some(chunked.action)
+        } else {
+            None
+        }
+    }
+
+    // Stamps the message with the next nonce of its chain's lane and a
+    // message id, and keeps it in the outbox until the bridge acknowledges it.
+    fn enqueue_bridge_message(&mut self, mut message: BridgeMessage) {
+        let mut lane = self.lane(message.chain_id);
+        lane.next_nonce += 1;
+        lane.pending_count += 1;
+        self.pending_bridge_messages += 1;
+        message.nonce = lane.next_nonce;
+        self.outbound_lanes.insert(&message.chain_id, &lane);
+        self.next_message_id += 1;
+        self.dispatch_bridge_message(QueuedMessage {
+            message_id: self.next_message_id,
+            message,
+            status: MessageStatus::Pending,
+            attempts: 0,
+            queued_at: U64(env::block_timestamp()),
+            last_attempt_at: U64(0),
+        });
+    }
+
+    // Calls the route's bridge account with the message, so it is only
+    // reachable from change methods. Messages on a disabled or removed
+    // route are marked Failed without using up an attempt.
+    fn dispatch_bridge_message(&mut self, mut queued: QueuedMessage) {
+        let message_id = queued.message_id;
+        queued.last_attempt_at = U64(env::block_timestamp());
+        let account = match self.bridges.get(&queued.message.route_id) {
+            Some(config) if config.enabled => config.account,
+            _ => {
+                queued.status = MessageStatus::Failed;
+                self.outbox.insert(&message_id, &queued);
+                return;
+            }
+        };
+        queued.status = MessageStatus::Pending;
+        queued.attempts += 1;
+        self.outbox.insert(&message_id, &queued);
+
+        let message = queued.message;
+        env::log_str(&format!(
+            "Bridge message {} sent: {} for order {} (chain {}, nonce {}, route {}, attempt {})",
+            message_id, message.action, message.order_id, message.chain_id, message.nonce, message.route_id, queued.attempts
+        ));
+        ext_bridge::ext(account)
+            .with_static_gas(GAS_FOR_CROSS_CHAIN_CALL)
+            .submit_message(message)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_BRIDGE_CALLBACK)
+                    .on_bridge_message_result(message_id),
+            );
+    }
+
+    /// Route for a new order: the requested one or the chain's default.
+    pub(crate) fn resolve_bridge_route(
+        &self,
+        requested: Option<u8>,
+        chain_id: u64,
+    ) -> Result<(u8, BridgeConfig), Violation> {
+        let route_id = match requested.or_else(|| self.default_route(chain_id)) {
+            Some(route_id) => route_id,
+            None => {
+                return Err(Violation::new(
+                    ERR_INVALID_BRIDGE_ROUTE,
+                    format!("no enabled bridge route serves chain {}", chain_id),
+                ))
+            }
+        };
+        match self.bridges.get(&route_id) {
+            Some(config) if config.enabled && config.serves(chain_id) => Ok((route_id, config)),
+            _ => Err(Violation::new(
+                ERR_INVALID_BRIDGE_ROUTE,
+                format!("route {} is not enabled for chain {}", route_id, chain_id),
+            )),
+        }
+    }
+
+    // The hashlock must outlive delivery, or the order could expire before
+    // the other chain hears about it
+    pub(crate) fn route_delay_violation(&self, config: &BridgeConfig, timelock_duration_secs: u64) -> Option<Violation> {
+        if config.delay.0 >= timelock_duration_secs {
+            return Some(Violation::new(
+                ERR_ROUTE_TOO_SLOW,
+                format!(
+                    "route delay of {}s does not fit the {}s timelock",
+                    config.delay.0, timelock_duration_secs
+                ),
+            ));
+        }
+        None
+    }
+
+    pub(crate) fn route_delay_secs(&self, route_id: u8) -> u64 {
+        self.bridges.get(&route_id).map(|config| config.delay.0).unwrap_or(0)
+    }
+
+    // Explicit default, else the lowest enabled route serving the chain
+    fn default_route(&self, chain_id: u64) -> Option<u8> {
+        let enabled = |route_id: &u8| {
+            self.bridges
+                .get(route_id)
+                .map(|config| config.enabled && config.serves(chain_id))
+                .unwrap_or(false)
+        };
+        self.default_routes
+            .get(&chain_id)
+            .filter(enabled)
+            .or_else(|| {
+                self.bridges
+                    .iter()
+                    .filter(|(_, config)| config.enabled && config.serves(chain_id))
+                    .map(|(route_id, _)| route_id)
+                    .min()
+            })
+    }
+
+    // Keeps the order's snapshotted route while it is enabled. Otherwise the
+    // message goes out on a fallback route; with none it is held as Failed
+    // on the original route and can be retried once a route is enabled.
+    fn dispatch_route(&self, message: &BridgeMessage) -> u8 {
+        let snapshot = self.bridges.get(&message.route_id);
+        if snapshot.map(|config| config.enabled).unwrap_or(false) {
+            return message.route_id;
+        }
+        match self.default_route(message.chain_id) {
+            Some(fallback) => {
+                self.emit_event(
+                    EventLevel::Standard,
+                    "bridge_route_fallback",
+                    json!({
+                        "order_id": message.order_id,
+                        "from_route": message.route_id,
+                        "to_route": fallback,
+                        "action": message.action,
+                    }),
+                );
+                fallback
+            }
+            None => {
+                env::log_str(&format!(
+                    "No enabled bridge route for chain {}, message for order {} held",
+                    message.chain_id, message.order_id
+                ));
+                message.route_id
+            }
+        }
+    }
+
+    // A disabled route, or one scoped to other chains, speaks for nobody
+    fn is_route_account(&self, route_id: u8, chain_id: u64, account: &AccountId) -> bool {
+        let speaks_for = |config: &BridgeConfig| config.enabled && config.serves(chain_id) && config.account == *account;
+        match self.bridges.get(&route_id) {
+            Some(config) => speaks_for(&config),
+            None => self.bridges.iter().any(|(_, config)| speaks_for(&config)),
+        }
+    }
+
+    fn lane(&self, chain_id: u64) -> LaneState {
+        self.outbound_lanes.get(&chain_id).unwrap_or_default()
+    }
+}
+
+fn assert_payload_limit(config: &BridgeConfig) {
+    if let Some(max) = config.max_payload_bytes {
+        require!(
+            (MIN_ROUTE_PAYLOAD_BYTES..=(limits::MAX_BRIDGE_DATA_LEN / 2) as u32).contains(&max),
+            format!(
+                "max_payload_bytes must be between {} and {}",
+                MIN_ROUTE_PAYLOAD_BYTES,
+                limits::MAX_BRIDGE_DATA_LEN / 2
+            )
+        );
+    }
+}
+
+fn decode_utf8(data: &str) -> Option<String> {
+    let bytes = hex::decode(normalize::strip_hex_prefix(data)).ok()?;
+    String::from_utf8(bytes).ok()
+}
+
+fn outcome_name(outcome: AckOutcome) -> &'static str {
+    match outcome {
+        AckOutcome::Delivered => "Delivered",
+        AckOutcome::Failed => "Failed",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::get_logs;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::test_utils::*;
+    use crate::timing::NS_PER_SEC;
+    use crate::validation::OrderParams;
+
+    const START: u64 = 1_700_000_000 * NS_PER_SEC;
+
+    // The message the last dispatch put in the outbox
+    fn last_sent(contract: &AdaptiveCrossChain) -> BridgeMessage {
+        contract.get_message(contract.next_message_id).unwrap().message
+    }
+
+    fn preview(contract: &AdaptiveCrossChain, order_id: u64, kind: BridgeAction) -> PayloadPreview {
+        let state = borsh::to_vec(contract).unwrap();
+        let lane = contract.get_lane_status(1);
+        let preview = contract.get_ethereum_payload(order_id, kind);
+        assert!(borsh::to_vec(contract).unwrap() == state, "the preview changed the contract state");
+        assert_eq!(contract.get_lane_status(1).next_nonce, lane.next_nonce);
+        preview
+    }
+
+    fn assert_preview_matches(preview: &PayloadPreview, sent: &BridgeMessage) {
+        assert_eq!(preview.action, sent.action);
+        assert!(preview.encoding == sent.encoding);
+        assert_eq!(preview.hex_bytes, sent.data);
+        assert_eq!(preview.commitment, sent.commitment);
+    }
+
+    fn field<'a>(preview: &'a PayloadPreview, name: &str) -> &'a str {
+        &preview.decoded_fields.iter().find(|field| field.name == name).unwrap().value
+    }
+
+    // Six creates and two cancels, all still waiting for their ack
+    fn populated_outbox() -> (AdaptiveCrossChain, Vec<(u64, AckOutcome)>) {
+        set_clock(START, 1);
+        let mut contract = setup();
+        for n in 1..=6 {
+            set_clock(START, n as u64);
+            let params = order_params(&contract, n, ONE_NEAR);
+            create_order_with(&mut contract, params, ONE_NEAR);
+        }
+        for order_id in [2, 5] {
+            call(maker(), 0);
+            let _ = contract.cancel_order(order_id);
+        }
+        let mut rng = StdRng::seed_from_u64(234);
+        let acks = (1..=contract.next_message_id)
+            .map(|message_id| {
+                let outcome = if rng.gen_range(0..3) == 0 { AckOutcome::Failed } else { AckOutcome::Delivered };
+                (message_id, outcome)
+            })
+            .collect();
+        (contract, acks)
+    }
+
+    fn ack_as(
+        contract: &mut AdaptiveCrossChain,
+        caller: AccountId,
+        message_id: u64,
+        outcome: AckOutcome,
+    ) -> Result<(), String> {
+        run_or_revert(contract, |c| {
+            call(caller, 0);
+            c.ack_bridge_message(message_id, Some(outcome))
+        })
+    }
+
+    fn ack(contract: &mut AdaptiveCrossChain, message_id: u64, outcome: AckOutcome) -> Result<(), String> {
+        ack_as(contract, bridge(), message_id, outcome)
+    }
+
+    fn opposite(outcome: AckOutcome) -> AckOutcome {
+        match outcome {
+            AckOutcome::Delivered => AckOutcome::Failed,
+            AckOutcome::Failed => AckOutcome::Delivered,
+        }
+    }
+
+    #[test]
+    fn retried_and_reordered_acks_end_in_the_well_ordered_state() {
+        let (mut expected, acks) = populated_outbox();
+        for (message_id, outcome) in &acks {
+            ack(&mut expected, *message_id, *outcome).unwrap();
+        }
+        let lane = expected.get_lane_status(1);
+        assert_eq!((lane.acked_through, lane.last_acked, lane.pending_count), (8, 8, 0));
+        let expected = (borsh::to_vec(&expected).unwrap(), storage());
+
+        for seed in 0..8 {
+            let (mut contract, mut acks) = populated_outbox();
+            let mut rng = StdRng::seed_from_u64(2340 + seed);
+            acks.shuffle(&mut rng);
+            for (message_id, outcome) in &acks {
+                if rng.gen() {
+                    let stranger = ack_as(&mut contract, resolver(), *message_id, *outcome);
+                    assert!(stranger.unwrap_err().contains("Only the bridge"));
+                }
+                ack(&mut contract, *message_id, *outcome).unwrap();
+                let conflict = ack(&mut contract, *message_id, opposite(*outcome)).unwrap_err();
+                assert!(conflict.contains(ERR_CONFLICTING_ACK), "{}", conflict);
+                if rng.gen() {
+                    ack(&mut contract, *message_id, *outcome).unwrap();
+                }
+            }
+            // A relayer replaying everything changes nothing
+            for (message_id, outcome) in &acks {
+                ack(&mut contract, *message_id, *outcome).unwrap();
+            }
+            assert!((borsh::to_vec(&contract).unwrap(), storage()) == expected, "seed {}", seed);
+        }
+    }
+
+    // Out-of-order acks only move acked_through once the gap is filled
+    #[test]
+    fn acked_through_waits_for_the_gap() {
+        let (mut contract, _) = populated_outbox();
+        for message_id in [3, 4, 8] {
+            ack(&mut contract, message_id, AckOutcome::Delivered).unwrap();
+        }
+        let lane = contract.get_lane_status(1);
+        assert_eq!((lane.acked_through, lane.last_acked, lane.pending_count), (0, 8, 5));
+        ack(&mut contract, 1, AckOutcome::Delivered).unwrap();
+        assert_eq!(contract.get_lane_status(1).acked_through, 1);
+        ack(&mut contract, 2, AckOutcome::Failed).unwrap();
+        assert_eq!(contract.get_lane_status(1).acked_through, 4);
+        assert_eq!(contract.dead_letter_count, 1);
+        assert_eq!(contract.pending_bridge_messages, 3);
+        assert!(ack(&mut contract, 9, AckOutcome::Delivered).unwrap_err().contains("Message not pending"));
+    }
+
+    fn limit_route_payload(contract: &mut AdaptiveCrossChain, max_payload_bytes: u32) {
+        let config = contract.get_bridge_route(0).unwrap();
+        call(owner(), 0);
+        contract.update_bridge_route(0, BridgeConfig { max_payload_bytes: Some(max_payload_bytes), ..config });
+    }
+
+    #[test]
+    fn oversized_order_payloads_are_rejected_at_creation() {
+        let mut contract = setup();
+        limit_route_payload(&mut contract, MIN_ROUTE_PAYLOAD_BYTES);
+        let params = order_params(&contract, 1, ONE_NEAR);
+        let rejection = run_or_revert(&mut contract, |c| create_order_with(c, params, ONE_NEAR)).unwrap_err();
+        assert!(rejection.contains(ERR_PAYLOAD_TOO_LARGE), "{}", rejection);
+        assert_eq!(contract.get_lane_status(1).next_nonce, 0);
+    }
+
+    // A payload that outgrows its route after creation goes out in chunks
+    // that only count once each is delivered intact
+    #[test]
+    fn oversized_messages_are_sent_and_tracked_in_chunks() {
+        set_clock(START, 1);
+        let mut contract = setup();
+        let params = order_params(&contract, 1, ONE_NEAR);
+        let order_id = create_order_with(&mut contract, params, ONE_NEAR);
+        limit_route_payload(&mut contract, MIN_ROUTE_PAYLOAD_BYTES);
+        set_clock(START + 301 * NS_PER_SEC, 2);
+        call(maker(), 0);
+        contract.update_order_slippage(order_id);
+
+        let payload = hex::decode(preview(&contract, order_id, BridgeAction::Amend).hex_bytes).unwrap();
+        let body_len = MIN_ROUTE_PAYLOAD_BYTES as usize - encoding::CHUNK_HEADER_LEN;
+        let total = payload.len().div_ceil(body_len) as u64;
+        assert!(total > 1);
+        let first = 2; // Message 1 is the create
+        let chunked = contract.get_chunked_message(1, first).unwrap();
+        assert_eq!((chunked.action.as_str(), chunked.total as u64), ("update_slippage", total));
+
+        let mut reassembled = vec![];
+        for message_id in first..first + total {
+            let message = contract.get_message(message_id).unwrap().message;
+            assert_eq!(message.action, CHUNK_ACTION);
+            assert!(message.data.len() / 2 <= MIN_ROUTE_PAYLOAD_BYTES as usize);
+            reassembled.extend_from_slice(&hex::decode(&message.data).unwrap()[encoding::CHUNK_HEADER_LEN..]);
+        }
+        assert!(reassembled == payload);
+
+        // Corrupt the last chunk before its ack arrives
+        let last = first + total - 1;
+        let mut queued = contract.get_message(last).unwrap();
+        queued.message.data.replace_range(..2, "ff");
+        contract.outbox.insert(&last, &queued);
+        for message_id in first..=last {
+            ack(&mut contract, message_id, AckOutcome::Delivered).unwrap();
+        }
+        let chunked = contract.get_chunked_message(1, first).unwrap();
+        assert_eq!((chunked.delivered as u64, chunked.failed), (total - 1, 0));
+    }
+
+    #[test]
+    fn preview_matches_what_dispatch_enqueues() {
+        for encoding in [PayloadEncoding::Abi, PayloadEncoding::PackedV1] {
+            set_clock(START, 1);
+            let mut contract = setup();
+            contract.set_payload_encoding(1, encoding);
+            let params = order_params(&contract, 1, ONE_NEAR);
+            let order_id = create_order_with(&mut contract, params, ONE_NEAR);
+
+            let created = preview(&contract, order_id, BridgeAction::Create);
+            assert_preview_matches(&created, &last_sent(&contract));
+            assert_eq!(field(&created, "orderId"), order_id.to_string());
+            assert_eq!(field(&created, "maker"), maker().as_str());
+            assert_eq!(field(&created, "tokenOut"), TOKEN_OUT);
+            assert_eq!(field(&created, "amountIn"), contract.orders.get(&order_id).unwrap().amount_in.0.to_string());
+
+            set_clock(START + 301 * NS_PER_SEC, 2);
+            call(maker(), 0);
+            contract.update_order_slippage(order_id);
+            assert_preview_matches(&preview(&contract, order_id, BridgeAction::Amend), &last_sent(&contract));
+
+            call(maker(), 0);
+            let _ = contract.cancel_order(order_id);
+            let sent = last_sent(&contract);
+            assert_preview_matches(&preview(&contract, order_id, BridgeAction::Cancel), &sent);
+            assert_eq!(sent.nonce, 3);
+        }
+    }
+
+    // Claims are never dispatched by this side, but their payload is the
+    // order reference every order message shares
+    #[test]
+    fn cancel_and_claim_payloads_golden_bytes() {
+        let mut contract = setup();
+        let params = order_params(&contract, 1, ONE_NEAR);
+        let order_id = create_order_with(&mut contract, params, ONE_NEAR);
+
+        let abi = preview(&contract, order_id, BridgeAction::Cancel);
+        assert_eq!(
+            abi.hex_bytes,
+            concat!(
+                "0000000000000000000000000000000000000000000000000000000000000003", // action
+                "0000000000000000000000000000000000000000000000000000000000000001", // orderId
+            )
+        );
+        call(owner(), 0);
+        contract.set_payload_encoding(1, PayloadEncoding::PackedV1);
+        let packed = preview(&contract, order_id, BridgeAction::Claim);
+        assert_eq!(packed.hex_bytes, concat!("01", "04", "0000000000000001"));
+        assert_eq!(packed.action, "claim");
+        assert_eq!(packed.decoded_fields.len(), 1);
+        assert_eq!(field(&packed, "orderId"), "1");
+    }
+
+    // Route 1 is a second bridge that only serves Ethereum
+    fn scoped_route(contract: &mut AdaptiveCrossChain) -> AccountId {
+        let account: AccountId = "scoped-bridge.near".parse().unwrap();
+        call(owner(), 0);
+        contract.add_bridge_route(1, BridgeConfig {
+            account: account.clone(),
+            kind: BridgeKind::LightClient,
+            fee: U128(0),
+            delay: U64(900),
+            enabled: true,
+            chain_ids: vec![1],
+            max_payload_bytes: None,
+        });
+        account
+    }
+
+    fn instruction(order_id: u64, chain_id: u64, action: &str) -> BridgeMessage {
+        BridgeMessage {
+            order_id,
+            chain_id,
+            nonce: 0,
+            route_id: 1,
+            target_contract: ETHEREUM_CONTRACT.to_string(),
+            action: action.to_string(),
+            encoding: PayloadEncoding::Abi,
+            data: String::new(),
+            commitment: String::new(),
+        }
+    }
+
+    #[test]
+    fn a_route_only_speaks_for_its_own_chains_while_enabled() {
+        let mut contract = setup();
+        let scoped = scoped_route(&mut contract);
+        let params = OrderParams { bridge_route: Some(1), ..order_params(&contract, 1, ONE_NEAR) };
+        let order_id = create_order_with(&mut contract, params, ONE_NEAR);
+        let create_id = contract.next_message_id;
+        assert_eq!(last_sent(&contract).route_id, 1);
+
+        // Naming its own route does not let the bridge act for another chain
+        let elsewhere = run_or_revert(&mut contract, |c| {
+            call(scoped.clone(), 0);
+            c.execute_bridge_message(instruction(order_id, 137, "cancel_order"), 1)
+        });
+        assert!(elsewhere.unwrap_err().contains("Only the bridge can execute messages"));
+
+        // Nor does a disabled route speak for anyone
+        call(owner(), 0);
+        contract.set_bridge_route_enabled(1, false);
+        let disabled = ack_as(&mut contract, scoped.clone(), create_id, AckOutcome::Delivered);
+        assert!(disabled.unwrap_err().contains("Only the bridge can acknowledge messages"));
+        let disabled = run_or_revert(&mut contract, |c| {
+            call(scoped.clone(), 0);
+            c.execute_bridge_message(instruction(order_id, 1, "cancel_order"), 1)
+        });
+        assert!(disabled.unwrap_err().contains("Only the bridge can execute messages"));
+
+        call(owner(), 0);
+        contract.set_bridge_route_enabled(1, true);
+        ack_as(&mut contract, scoped.clone(), create_id, AckOutcome::Delivered).unwrap();
+        call(scoped, 0);
+        contract.execute_bridge_message(instruction(order_id, 1, "cancel_order"), 1);
+        assert!(matches!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Cancelled));
+    }
+
+    fn hand_off(contract: &mut AdaptiveCrossChain, message_id: u64, accepted: bool) -> bool {
+        callback(accepted);
+        contract.on_bridge_message_result(message_id)
+    }
+
+    // An accepted hand-off waits for its ack as Sent. A failed one stays
+    // in the outbox as Failed, counting its attempts, until the last retry
+    // flags the order stuck
+    #[test]
+    fn hand_off_results_update_the_outbox() {
+        let mut contract = setup();
+        let params = order_params(&contract, 1, ONE_NEAR);
+        let accepted_order = create_order_with(&mut contract, params, ONE_NEAR);
+        let accepted = contract.next_message_id;
+        assert!(contract.get_message_status(accepted) == Some(MessageStatus::Pending));
+        assert!(hand_off(&mut contract, accepted, true));
+        assert!(contract.get_message_status(accepted) == Some(MessageStatus::Sent));
+        assert!(event_data("bridge_message_failed").is_none());
+        assert!(get_logs().iter().any(|log| log.contains("accepted by route")));
+        assert!(contract.stuck_orders.get(&accepted_order).is_none());
+
+        let params = order_params(&contract, 2, ONE_NEAR);
+        let failing_order = create_order_with(&mut contract, params, ONE_NEAR);
+        let failing = contract.next_message_id;
+        let retries = contract.max_bridge_retries;
+        for attempt in 1..=retries + 1 {
+            if attempt > 1 {
+                call(resolver(), 0);
+                contract.retry_bridge_message(failing);
+            }
+            assert!(!hand_off(&mut contract, failing, false));
+            let queued = contract.get_message(failing).unwrap();
+            assert!(queued.status == MessageStatus::Failed);
+            assert_eq!(queued.attempts, attempt);
+
+            let event = event_data("bridge_message_failed").unwrap();
+            assert_eq!(event["message_id"], failing);
+            assert_eq!(event["order_id"], failing_order);
+            assert_eq!(event["attempts"], attempt);
+            assert_eq!(event["retries_left"], retries + 1 - attempt);
+            assert_eq!(contract.stuck_orders.get(&failing_order).is_some(), attempt == retries + 1);
+        }
+        assert_eq!(contract.dead_letter_count, 0);
+        let exhausted = run_or_revert(&mut contract, |c| {
+            call(resolver(), 0);
+            c.retry_bridge_message(failing)
+        });
+        assert!(exhausted.unwrap_err().contains("out of retries"));
+
+        // Results for messages acked in the meantime change nothing
+        contract.outbox.remove(&accepted);
+        assert!(!hand_off(&mut contract, accepted, false));
+        assert!(contract.get_message(accepted).is_none());
+        assert!(event_data("bridge_message_failed").is_none());
+    }
+}
@@ -0,0 +1,154 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+use near_sdk::{env, near_bindgen, require};
+
+use crate::normalize;
+use crate::validation::Violation;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, EventLevel};
+
+pub const ERR_UNSUPPORTED_CHAIN: &str = "ERR_UNSUPPORTED_CHAIN";
+pub const ERR_NO_DESTINATION_CONTRACT: &str = "ERR_NO_DESTINATION_CONTRACT";
+
+pub const MAX_CHAINS: u64 = 64;
+pub const MAX_CHAIN_NAME_LEN: usize = 32;
+
+/// Per-chain parameters. Orders can only target enabled chains.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ChainConfig {
+    pub chain_id: u64,
+    pub name: String,
+    pub slippage_premium_bps: u64, // Replaces the pair model's per-chain premium
+    pub bridge_delay_seconds: u64, // Floor under the route's own delay
+    pub enabled: bool,
+    pub destination_contract: Option<String>, // Escrow contract bridge messages to the chain go to
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    /// Adds or replaces a chain. Orders already open keep their target;
+    /// new parameters apply from their next slippage update.
+    pub fn set_chain_config(&mut self, config: ChainConfig) {
+        self.assert_owner();
+        require!(config.chain_id > 0, "chain_id must be positive");
+        require!(
+            !config.name.is_empty() && config.name.len() <= MAX_CHAIN_NAME_LEN,
+            "Chain name must be 1 to 32 bytes"
+        );
+        require!(
+            self.chains.get(&config.chain_id).is_some() || self.chains.len() < MAX_CHAINS,
+            "Chain registry is full"
+        );
+        let destination_contract = config.destination_contract.as_deref().map(|address| {
+            normalize::eth_address(address).unwrap_or_else(|violation| violation.panic())
+        });
+        let config = ChainConfig { destination_contract, ..config };
+
+        self.chains.insert(&config.chain_id, &config);
+        self.audit("set_chain_config", None);
+        self.emit_event(
+            EventLevel::Standard,
+            "chain_configured",
+            json!({
+                "chain_id": config.chain_id,
+                "name": config.name,
+                "slippage_premium_bps": config.slippage_premium_bps,
+                "bridge_delay_seconds": config.bridge_delay_seconds,
+                "enabled": config.enabled,
+                "destination_contract": config.destination_contract,
+            }),
+        );
+    }
+
+    // Messages already sent keep the address they were sent to; later
+    // messages of open orders go to the new one
+    pub fn set_destination_contract(&mut self, chain_id: u64, address: String) {
+        self.assert_owner();
+        let mut config = self.chains.get(&chain_id).expect("Chain not configured");
+        let address = normalize::eth_address(&address).unwrap_or_else(|violation| violation.panic());
+        env::log_str(&format!(
+            "destination_contract of chain {} set from {:?} to {}",
+            chain_id, config.destination_contract, address
+        ));
+        config.destination_contract = Some(address);
+        self.chains.insert(&chain_id, &config);
+        self.audit("set_destination_contract", None);
+        self.emit_event(
+            EventLevel::Standard,
+            "destination_contract_set",
+            json!({
+                "chain_id": chain_id,
+                "destination_contract": config.destination_contract,
+            }),
+        );
+    }
+
+    // Stops new orders to the chain; open ones settle or refund as usual
+    pub fn disable_chain(&mut self, chain_id: u64) {
+        self.assert_owner();
+        let mut config = self.chains.get(&chain_id).expect("Chain not configured");
+        require!(config.enabled, "Chain is already disabled");
+        config.enabled = false;
+        self.chains.insert(&chain_id, &config);
+        self.audit("disable_chain", None);
+        self.emit_event(
+            EventLevel::Standard,
+            "chain_disabled",
+            json!({ "chain_id": chain_id }),
+        );
+    }
+
+    pub fn get_chain_config(&self, chain_id: u64) -> Option<ChainConfig> {
+        self.chains.get(&chain_id)
+    }
+
+    // Enabled chains only; the registry is capped at MAX_CHAINS entries
+    pub fn get_supported_chains(&self) -> Vec<ChainConfig> {
+        self.chains
+            .values()
+            .filter(|config| config.enabled)
+            .collect()
+    }
+}
+
+impl AdaptiveCrossChain {
+    pub(crate) fn chain_violation(&self, chain_id: u64) -> Option<Violation> {
+        match self.chains.get(&chain_id) {
+            Some(config) if !config.enabled => Some(Violation::new(
+                ERR_UNSUPPORTED_CHAIN,
+                format!("chain {} is disabled", chain_id),
+            )),
+            Some(config) if config.destination_contract.is_none() => Some(Violation::new(
+                ERR_NO_DESTINATION_CONTRACT,
+                format!("chain {} has no destination contract", chain_id),
+            )),
+            Some(_) => None,
+            None => Some(Violation::new(
+                ERR_UNSUPPORTED_CHAIN,
+                format!("chain {} is not configured", chain_id),
+            )),
+        }
+    }
+
+    pub(crate) fn chain_premium_bps(&self, chain_id: u64) -> Option<u64> {
+        self.chains.get(&chain_id).map(|config| config.slippage_premium_bps)
+    }
+
+    pub(crate) fn chain_delay_secs(&self, chain_id: u64) -> u64 {
+        self.chains
+            .get(&chain_id)
+            .map(|config| config.bridge_delay_seconds)
+            .unwrap_or(0)
+    }
+
+    // Falls back to the init counterpart for orders created before their
+    // chain was registered or had a destination of its own, which is where
+    // their earlier messages went
+    pub(crate) fn counterpart_contract(&self, chain_id: u64) -> String {
+        self.chains
+            .get(&chain_id)
+            .and_then(|config| config.destination_contract)
+            .unwrap_or_else(|| self.ethereum_contract.clone())
+    }
+}
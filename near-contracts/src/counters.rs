@@ -0,0 +1,419 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::UnorderedSet;
+use near_sdk::{near_bindgen, AccountId};
+
+use crate::limits;
+use crate::normalize;
+use crate::roles::Role;
+use crate::storage::StorageKey;
+use crate::validation::{self, Violation};
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, Balance, CrossChainOrder, OrderStatus, OrderView};
+
+// New deployments and migrated state start with this many open orders per maker
+pub const DEFAULT_MAX_OPEN_ORDERS_PER_USER: u64 = 100;
+
+/// Orders currently stored in each status. Archived orders are no longer
+/// counted.
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct StatusCounts {
+    pub active: u64,
+    pub locked: u64,
+    pub completed: u64,
+    pub expired: u64,
+    pub cancelled: u64,
+    pub claiming: u64,
+}
+
+impl StatusCounts {
+    fn get(&self, status: &OrderStatus) -> u64 {
+        match status {
+            OrderStatus::Active => self.active,
+            OrderStatus::Locked => self.locked,
+            OrderStatus::Completed => self.completed,
+            OrderStatus::Expired => self.expired,
+            OrderStatus::Cancelled => self.cancelled,
+            OrderStatus::Claiming => self.claiming,
+        }
+    }
+
+    fn slot(&mut self, status: &OrderStatus) -> &mut u64 {
+        match status {
+            OrderStatus::Active => &mut self.active,
+            OrderStatus::Locked => &mut self.locked,
+            OrderStatus::Completed => &mut self.completed,
+            OrderStatus::Expired => &mut self.expired,
+            OrderStatus::Cancelled => &mut self.cancelled,
+            OrderStatus::Claiming => &mut self.claiming,
+        }
+    }
+}
+
+/// IDs of the stored orders in each status, moved along with the counts
+/// above so status queries never scan the order book.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct StatusIndex {
+    pub active: UnorderedSet<u64>,
+    pub locked: UnorderedSet<u64>,
+    pub completed: UnorderedSet<u64>,
+    pub expired: UnorderedSet<u64>,
+    pub cancelled: UnorderedSet<u64>,
+    pub claiming: UnorderedSet<u64>,
+}
+
+impl Default for StatusIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StatusIndex {
+    pub fn new() -> Self {
+        Self {
+            active: UnorderedSet::new(b"Wa".to_vec()),
+            locked: UnorderedSet::new(b"Wl".to_vec()),
+            completed: UnorderedSet::new(b"Wc".to_vec()),
+            expired: UnorderedSet::new(b"We".to_vec()),
+            cancelled: UnorderedSet::new(b"Wx".to_vec()),
+            claiming: UnorderedSet::new(StorageKey::ClaimingOrders),
+        }
+    }
+
+    fn get(&self, status: &OrderStatus) -> &UnorderedSet<u64> {
+        match status {
+            OrderStatus::Active => &self.active,
+            OrderStatus::Locked => &self.locked,
+            OrderStatus::Completed => &self.completed,
+            OrderStatus::Expired => &self.expired,
+            OrderStatus::Cancelled => &self.cancelled,
+            OrderStatus::Claiming => &self.claiming,
+        }
+    }
+
+    fn slot(&mut self, status: &OrderStatus) -> &mut UnorderedSet<u64> {
+        match status {
+            OrderStatus::Active => &mut self.active,
+            OrderStatus::Locked => &mut self.locked,
+            OrderStatus::Completed => &mut self.completed,
+            OrderStatus::Expired => &mut self.expired,
+            OrderStatus::Cancelled => &mut self.cancelled,
+            OrderStatus::Claiming => &mut self.claiming,
+        }
+    }
+}
+
+// Counters below are maintained on write so these getters never scan
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    pub fn get_orders_count_by_status(&self, status: OrderStatus) -> u64 {
+        self.status_counts.get(&status)
+    }
+
+    /// Page through the stored orders in `status`, `limit` capped at
+    /// MAX_BATCH_SIZE. Removing an order from a status moves the last one
+    /// into its slot, so pages are stable only while no order leaves it.
+    pub fn get_orders_by_status(&self, status: OrderStatus, from_index: u64, limit: u64) -> Vec<OrderView> {
+        let limit = limit.min(limits::MAX_BATCH_SIZE as u64);
+        let ids = self.status_index.get(&status).as_vector();
+        (from_index..ids.len().min(from_index.saturating_add(limit)))
+            .filter_map(|index| ids.get(index))
+            .filter_map(|order_id| self.orders.get(&order_id))
+            .map(|order| self.public_order_view(order))
+            .collect()
+    }
+
+    pub fn get_user_order_count(&self, account: AccountId) -> u64 {
+        self.user_orders.get(&account).map(|orders| orders.len()).unwrap_or(0)
+    }
+
+    // Active and Locked orders of the account
+    pub fn get_open_order_count(&self, account_id: AccountId) -> u64 {
+        self.user_open_counts.get(&account_id).unwrap_or(0)
+    }
+
+    // Orders of the account that reached a terminal status
+    pub fn get_user_history_count(&self, account: AccountId) -> u64 {
+        self.user_history_counts.get(&account).unwrap_or(0)
+    }
+
+    pub fn get_chain_active_count(&self, chain_id: u64) -> u64 {
+        self.chain_active_counts.get(&chain_id).unwrap_or(0)
+    }
+
+    pub fn get_token_out_active_count(&self, token: String) -> u64 {
+        let key = normalize::eth_address(&token).unwrap_or_else(|_| token.to_lowercase());
+        self.token_out_active_counts.get(&key).unwrap_or(0)
+    }
+
+    pub fn get_pending_bridge_message_count(&self) -> u64 {
+        self.pending_bridge_messages
+    }
+
+    // Bridge messages acknowledged as failed on the destination chain
+    pub fn get_dead_letter_count(&self) -> u64 {
+        self.dead_letter_count
+    }
+}
+
+impl AdaptiveCrossChain {
+    pub(crate) fn count_created(&mut self, order: &CrossChainOrder) {
+        *self.status_counts.slot(&order.status) += 1;
+        self.status_index.slot(&order.status).insert(&order.order_id);
+        self.open_book_value += order.amount_remaining.0;
+        self.adjust_active_counts(order, true);
+        let open = self.user_open_counts.get(&order.maker).unwrap_or(0);
+        self.user_open_counts.insert(&order.maker, &(open + 1));
+        self.record_created(order);
+    }
+
+    /// Called by the status transition helper after `order.status` changed.
+    pub(crate) fn count_transition(&mut self, order: &CrossChainOrder, from: &OrderStatus) {
+        *self.status_counts.slot(from) -= 1;
+        *self.status_counts.slot(&order.status) += 1;
+        self.status_index.slot(from).remove(&order.order_id);
+        self.status_index.slot(&order.status).insert(&order.order_id);
+        self.record_transition(order, from);
+
+        if is_open(from) && !is_open(&order.status) {
+            self.adjust_active_counts(order, false);
+            self.open_book_value -= order.amount_remaining.0;
+            self.release_value_locked(order, order.amount_remaining.0);
+            let history = self.user_history_counts.get(&order.maker).unwrap_or(0);
+            self.user_history_counts.insert(&order.maker, &(history + 1));
+            let open = self.user_open_counts.get(&order.maker).unwrap_or(0);
+            if open <= 1 {
+                self.user_open_counts.remove(&order.maker);
+            } else {
+                self.user_open_counts.insert(&order.maker, &(open - 1));
+            }
+        }
+    }
+
+    // A claim paid out part of an open order
+    pub(crate) fn count_fill(&mut self, order: &CrossChainOrder, amount: Balance) {
+        self.open_book_value -= amount;
+        self.release_value_locked(order, amount);
+    }
+
+    // Market makers are exempt, as they are from the per-block limit
+    pub(crate) fn open_order_violation(&self, maker: &AccountId) -> Option<Violation> {
+        if self.has_role(maker.clone(), Role::MarketMaker) {
+            return None;
+        }
+        if self.user_open_counts.get(maker).unwrap_or(0) >= self.max_open_orders_per_user {
+            return Some(Violation::new(
+                validation::ERR_OPEN_ORDER_LIMIT,
+                format!(
+                    "at most {} open orders per account; complete or cancel one first",
+                    self.max_open_orders_per_user
+                ),
+            ));
+        }
+        None
+    }
+
+    pub(crate) fn count_archived(&mut self, order: &CrossChainOrder) {
+        *self.status_counts.slot(&order.status) -= 1;
+        self.status_index.slot(&order.status).remove(&order.order_id);
+    }
+
+    // Active and Locked orders both count as active for chain and token
+    fn adjust_active_counts(&mut self, order: &CrossChainOrder, increment: bool) {
+        let chain = self.chain_active_counts.get(&order.target_chain_id).unwrap_or(0);
+        let token_key = order.token_out.to_lowercase();
+        let token = self.token_out_active_counts.get(&token_key).unwrap_or(0);
+        if increment {
+            self.chain_active_counts.insert(&order.target_chain_id, &(chain + 1));
+            self.token_out_active_counts.insert(&token_key, &(token + 1));
+        } else {
+            self.chain_active_counts.insert(&order.target_chain_id, &(chain - 1));
+            self.token_out_active_counts.insert(&token_key, &(token - 1));
+        }
+    }
+}
+
+fn is_open(status: &OrderStatus) -> bool {
+    matches!(status, OrderStatus::Active | OrderStatus::Locked | OrderStatus::Claiming)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use near_sdk::test_utils::accounts;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::bridge::AckOutcome;
+    use crate::test_utils::*;
+
+    const AMOUNT: u128 = 10 * ONE_NEAR;
+    const STATUSES: [OrderStatus; 6] = [
+        OrderStatus::Active,
+        OrderStatus::Locked,
+        OrderStatus::Completed,
+        OrderStatus::Expired,
+        OrderStatus::Cancelled,
+        OrderStatus::Claiming,
+    ];
+    const CHAINS: [u64; 2] = [1, 137];
+    const TOKENS: [&str; 3] = [
+        "0x2222222222222222222222222222222222222222",
+        "0x3333333333333333333333333333333333333333",
+        "0xaAaAaAaaAaAaAaaAaAAAAAAAAaaaAaAaAaaAaaAa",
+    ];
+
+    fn makers() -> Vec<AccountId> {
+        vec![accounts(1), accounts(3), accounts(4)]
+    }
+
+    // Pages of 7, so every view is read across several pages
+    fn page_all<T>(page: impl Fn(u64, u64) -> Vec<T>) -> Vec<T> {
+        let mut all = Vec::new();
+        loop {
+            let next = page(all.len() as u64, 7);
+            if next.is_empty() {
+                return all;
+            }
+            all.extend(next);
+        }
+    }
+
+    fn same(a: &OrderStatus, b: &OrderStatus) -> bool {
+        std::mem::discriminant(a) == std::mem::discriminant(b)
+    }
+
+    fn assert_counters_match_views(contract: &AdaptiveCrossChain) {
+        let orders = page_all(|from, limit| contract.get_orders(from, limit));
+        for status in STATUSES {
+            let paged = page_all(|from, limit| contract.get_orders_by_status(status.clone(), from, limit));
+            assert!(paged.iter().all(|order| same(&order.status, &status)));
+            let scanned = orders.iter().filter(|order| same(&order.status, &status)).count() as u64;
+            assert_eq!(contract.get_orders_count_by_status(status.clone()), paged.len() as u64);
+            assert_eq!(paged.len() as u64, scanned);
+        }
+        for maker in makers() {
+            let own = orders.iter().filter(|order| order.maker == maker);
+            assert_eq!(contract.get_user_order_count(maker.clone()), contract.get_user_orders(maker.clone()).len() as u64);
+            assert_eq!(contract.get_user_order_count(maker.clone()), own.clone().count() as u64);
+            let history = own.clone().filter(|order| !is_open(&order.status)).count() as u64;
+            assert_eq!(contract.get_user_history_count(maker.clone()), history);
+            let open = own.filter(|order| is_open(&order.status)).count() as u64;
+            assert_eq!(contract.get_open_order_count(maker), open);
+        }
+        for chain_id in CHAINS {
+            let active = orders
+                .iter()
+                .filter(|order| order.target_chain_id == chain_id && is_open(&order.status))
+                .count() as u64;
+            assert_eq!(contract.get_chain_active_count(chain_id), active);
+        }
+        for token in TOKENS {
+            let active = orders
+                .iter()
+                .filter(|order| order.token_out.eq_ignore_ascii_case(token) && is_open(&order.status))
+                .count() as u64;
+            assert_eq!(contract.get_token_out_active_count(token.to_string()), active);
+            assert_eq!(contract.get_token_out_active_count(token.to_uppercase().replace("0X", "0x")), active);
+        }
+
+        let pending = page_all(|from, limit| contract.get_pending_messages(from, limit));
+        assert_eq!(contract.get_pending_bridge_message_count(), pending.len() as u64);
+        let dead_letters = CHAINS
+            .iter()
+            .flat_map(|&chain_id| {
+                let lane = contract.get_lane_status(chain_id);
+                (0..=lane.next_nonce).filter_map(move |nonce| contract.get_ack_record(chain_id, nonce))
+            })
+            .filter(|record| record.outcome == AckOutcome::Failed)
+            .count() as u64;
+        assert_eq!(contract.get_dead_letter_count(), dead_letters);
+    }
+
+    // A random order in `status` whose timelock has not passed yet
+    fn pick(rng: &mut StdRng, contract: &AdaptiveCrossChain, status: OrderStatus) -> Option<u64> {
+        let ids: Vec<u64> = page_all(|from, limit| contract.get_orders_by_status(status.clone(), from, limit))
+            .into_iter()
+            .map(|order| order.order_id)
+            .filter(|order_id| !contract.is_order_refundable(*order_id))
+            .collect();
+        (!ids.is_empty()).then(|| ids[rng.gen_range(0..ids.len())])
+    }
+
+    fn pick_refundable(rng: &mut StdRng, contract: &AdaptiveCrossChain) -> Option<u64> {
+        let ids: Vec<u64> = page_all(|from, limit| contract.get_orders(from, limit))
+            .into_iter()
+            .map(|order| order.order_id)
+            .filter(|order_id| contract.is_order_refundable(*order_id))
+            .collect();
+        (!ids.is_empty()).then(|| ids[rng.gen_range(0..ids.len())])
+    }
+
+    // Creates, locks, claims, cancels, refunds and acknowledges at random,
+    // checking every counter against its view after each step
+    #[test]
+    fn counters_match_exhaustive_paging() {
+        let mut contract = setup();
+        call(owner(), 0);
+        contract.set_destination_contract(137, "0x4444444444444444444444444444444444444444".to_string());
+
+        let mut rng = StdRng::seed_from_u64(220);
+        let mut secrets: HashMap<u64, u8> = HashMap::new();
+        let mut now = 0;
+        for step in 1..=160u64 {
+            set_clock(now, step);
+            match rng.gen_range(0..10) {
+                0..=2 if secrets.len() < 200 => {
+                    let n = secrets.len() as u8 + 1;
+                    let mut params = order_params(&contract, n, AMOUNT);
+                    params.target_chain_id = CHAINS[rng.gen_range(0..CHAINS.len())];
+                    params.token_out = TOKENS[rng.gen_range(0..TOKENS.len())].to_string();
+                    let maker = makers()[rng.gen_range(0..3)].clone();
+                    let order_id = create_order_as(&mut contract, maker, params, AMOUNT + ONE_NEAR);
+                    secrets.insert(order_id, n);
+                }
+                3 => {
+                    if let Some(order_id) = pick(&mut rng, &contract, OrderStatus::Active) {
+                        lock(&mut contract, order_id);
+                    }
+                }
+                4 => {
+                    if let Some(order_id) = pick(&mut rng, &contract, OrderStatus::Locked) {
+                        claim(&mut contract, order_id, secrets[&order_id]);
+                    }
+                }
+                5 => {
+                    if let Some(order_id) = pick(&mut rng, &contract, OrderStatus::Active) {
+                        let maker = contract.orders.get(&order_id).unwrap().maker;
+                        call(maker, 0);
+                        let _ = contract.cancel_order(order_id);
+                    }
+                }
+                6 => {
+                    now += 30_000 * 1_000_000_000;
+                    set_clock(now, step);
+                    call(resolver(), 0);
+                    if let Some(order_id) = pick_refundable(&mut rng, &contract) {
+                        let _ = contract.refund_expired_order(order_id);
+                    }
+                }
+                _ => {
+                    let pending = page_all(|from, limit| contract.get_pending_messages(from, limit));
+                    if !pending.is_empty() {
+                        let message_id = pending[rng.gen_range(0..pending.len())].message_id;
+                        let outcome = if rng.gen_range(0..3) == 0 { AckOutcome::Failed } else { AckOutcome::Delivered };
+                        call(bridge(), 0);
+                        contract.ack_bridge_message(message_id, Some(outcome));
+                    }
+                }
+            }
+            assert_counters_match_views(&contract);
+        }
+
+        // The workload reached every status the counters track but Claiming
+        for status in [OrderStatus::Active, OrderStatus::Locked, OrderStatus::Completed, OrderStatus::Expired, OrderStatus::Cancelled] {
+            assert!(contract.get_orders_count_by_status(status) > 0);
+        }
+        assert!(contract.get_dead_letter_count() > 0);
+    }
+}
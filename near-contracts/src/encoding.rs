@@ -0,0 +1,634 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::env;
+
+use crate::eth_proof::parse_eth_address;
+use crate::{timing, CrossChainOrder, HashlockMode};
+
+// Version header byte written at the start of every PackedV1 payload
+pub const PACKED_V1_VERSION: u8 = 1;
+
+/// Wire format used for outbound bridge payloads, selectable per chain.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum PayloadEncoding {
+    Abi,      // abi.encode with 32-byte words
+    PackedV1, // Fixed-width fields, no padding, one-byte version header
+}
+
+impl PayloadEncoding {
+    pub fn id(self) -> u8 {
+        match self {
+            PayloadEncoding::Abi => 0,
+            PayloadEncoding::PackedV1 => 1,
+        }
+    }
+}
+
+/// Order message types sent to Ethereum.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum BridgeAction {
+    Create,
+    Amend, // Slippage update
+    Cancel,
+    Claim,
+    ExtendTimelock,
+    Reprice,
+    CreateDual, // Create message of a dual-hashlock order
+}
+
+impl BridgeAction {
+    /// The action name carried in BridgeMessage.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BridgeAction::Create => "create_order",
+            BridgeAction::Amend => "update_slippage",
+            BridgeAction::Cancel => "cancel",
+            BridgeAction::Claim => "claim",
+            BridgeAction::ExtendTimelock => "extend_timelock",
+            BridgeAction::Reprice => "update_price",
+            BridgeAction::CreateDual => "create_dual_order",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum PayloadField {
+    U64(u64),
+    U128(u128),
+    Address([u8; 20]),
+    Bytes32([u8; 32]),
+    Str(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    U64,
+    U128,
+    Address,
+    Bytes32,
+    Str,
+}
+
+// Create message: orderId, maker, tokenOut, amountIn, basePrice, slippage,
+// maxSlippageDeviation, targetChainId, hashlock, timelock (unix seconds)
+pub const CREATE_ORDER_SCHEMA: &[FieldKind] = &[
+    FieldKind::U64,
+    FieldKind::Str,
+    FieldKind::Address,
+    FieldKind::U128,
+    FieldKind::U128,
+    FieldKind::U64,
+    FieldKind::U64,
+    FieldKind::U64,
+    FieldKind::Bytes32,
+    FieldKind::U64,
+];
+
+// Dual-hashlock create message: the create fields, then takerHashlock and
+// hashlockMode (1 = both secrets, 2 = either)
+pub const CREATE_DUAL_ORDER_SCHEMA: &[FieldKind] = &[
+    FieldKind::U64,
+    FieldKind::Str,
+    FieldKind::Address,
+    FieldKind::U128,
+    FieldKind::U128,
+    FieldKind::U64,
+    FieldKind::U64,
+    FieldKind::U64,
+    FieldKind::Bytes32,
+    FieldKind::U64,
+    FieldKind::Bytes32,
+    FieldKind::U64,
+];
+
+// Slippage update message: orderId, slippage
+pub const UPDATE_SLIPPAGE_SCHEMA: &[FieldKind] = &[FieldKind::U64, FieldKind::U64];
+
+// Timelock extension message: orderId, timelock (unix seconds)
+pub const EXTEND_TIMELOCK_SCHEMA: &[FieldKind] = &[FieldKind::U64, FieldKind::U64];
+
+// Reprice message: orderId, basePrice, maxSlippageDeviation
+pub const UPDATE_PRICE_SCHEMA: &[FieldKind] = &[FieldKind::U64, FieldKind::U128, FieldKind::U64];
+
+// Any other order message: orderId
+pub const ORDER_REF_SCHEMA: &[FieldKind] = &[FieldKind::U64];
+
+const CREATE_ORDER_FIELD_NAMES: &[&str] = &[
+    "orderId",
+    "maker",
+    "tokenOut",
+    "amountIn",
+    "basePrice",
+    "slippage",
+    "maxSlippageDeviation",
+    "targetChainId",
+    "hashlock",
+    "timelock",
+];
+const CREATE_DUAL_ORDER_FIELD_NAMES: &[&str] = &[
+    "orderId",
+    "maker",
+    "tokenOut",
+    "amountIn",
+    "basePrice",
+    "slippage",
+    "maxSlippageDeviation",
+    "targetChainId",
+    "hashlock",
+    "timelock",
+    "takerHashlock",
+    "hashlockMode",
+];
+const UPDATE_SLIPPAGE_FIELD_NAMES: &[&str] = &["orderId", "slippage"];
+const EXTEND_TIMELOCK_FIELD_NAMES: &[&str] = &["orderId", "timelock"];
+const UPDATE_PRICE_FIELD_NAMES: &[&str] = &["orderId", "basePrice", "maxSlippageDeviation"];
+const ORDER_REF_FIELD_NAMES: &[&str] = &["orderId"];
+
+pub fn action_code(action: &str) -> u8 {
+    match action {
+        "create_order" => 1,
+        "update_slippage" => 2,
+        "cancel" => 3,
+        "claim" => 4,
+        "extend_timelock" => 5,
+        "update_price" => 6,
+        "create_dual_order" => 7,
+        _ => 0,
+    }
+}
+
+pub fn schema_for(action_code: u8) -> &'static [FieldKind] {
+    match action_code {
+        1 => CREATE_ORDER_SCHEMA,
+        2 => UPDATE_SLIPPAGE_SCHEMA,
+        5 => EXTEND_TIMELOCK_SCHEMA,
+        6 => UPDATE_PRICE_SCHEMA,
+        7 => CREATE_DUAL_ORDER_SCHEMA,
+        _ => ORDER_REF_SCHEMA,
+    }
+}
+
+/// The create action for `order`; dual-hashlock orders have their own.
+pub fn create_action(order: &CrossChainOrder) -> &'static str {
+    if order.taker_hashlock.is_some() {
+        BridgeAction::CreateDual.as_str()
+    } else {
+        BridgeAction::Create.as_str()
+    }
+}
+
+/// Builds the field list the Ethereum contract expects for `action`.
+pub fn order_message_fields(order: &CrossChainOrder, action: &str) -> Vec<PayloadField> {
+    match action_code(action) {
+        7 => {
+            let mut fields = order_message_fields(order, BridgeAction::Create.as_str());
+            let taker_hashlock = order.taker_hashlock.as_deref().expect("Order has no taker hashlock");
+            fields.push(PayloadField::Bytes32(hashlock_bytes(taker_hashlock)));
+            fields.push(PayloadField::U64(match order.hashlock_mode {
+                HashlockMode::Either => 2,
+                _ => 1,
+            }));
+            fields
+        }
+        1 => vec![
+            PayloadField::U64(order.order_id),
+            PayloadField::Str(order.maker.to_string()),
+            PayloadField::Address(
+                parse_eth_address(&order.token_out).expect("Invalid token_out address"),
+            ),
+            PayloadField::U128(order.amount_in.0),
+            PayloadField::U128(order.base_price.0),
+            PayloadField::U64(order.current_slippage),
+            PayloadField::U64(order.max_slippage_deviation),
+            PayloadField::U64(order.target_chain_id),
+            PayloadField::Bytes32(hashlock_bytes(&order.hashlock)),
+            PayloadField::U64(timing::timelock_secs(order)),
+        ],
+        2 => vec![
+            PayloadField::U64(order.order_id),
+            PayloadField::U64(order.current_slippage),
+        ],
+        5 => vec![
+            PayloadField::U64(order.order_id),
+            PayloadField::U64(timing::timelock_secs(order)),
+        ],
+        6 => vec![
+            PayloadField::U64(order.order_id),
+            PayloadField::U128(order.base_price.0),
+            PayloadField::U64(order.max_slippage_deviation),
+        ],
+        _ => vec![PayloadField::U64(order.order_id)],
+    }
+}
+
+/// Field names in schema order, as the Ethereum contract calls them.
+pub fn field_names(action_code: u8) -> &'static [&'static str] {
+    match action_code {
+        1 => CREATE_ORDER_FIELD_NAMES,
+        2 => UPDATE_SLIPPAGE_FIELD_NAMES,
+        5 => EXTEND_TIMELOCK_FIELD_NAMES,
+        6 => UPDATE_PRICE_FIELD_NAMES,
+        7 => CREATE_DUAL_ORDER_FIELD_NAMES,
+        _ => ORDER_REF_FIELD_NAMES,
+    }
+}
+
+// Integers in decimal, addresses and fixed bytes as 0x-prefixed hex
+pub fn display_field(field: &PayloadField) -> String {
+    match field {
+        PayloadField::U64(v) => v.to_string(),
+        PayloadField::U128(v) => v.to_string(),
+        PayloadField::Address(a) => format!("0x{}", hex::encode(a)),
+        PayloadField::Bytes32(b) => format!("0x{}", hex::encode(b)),
+        PayloadField::Str(s) => s.clone(),
+    }
+}
+
+pub fn encode(encoding: PayloadEncoding, action_code: u8, fields: &[PayloadField]) -> Vec<u8> {
+    match encoding {
+        PayloadEncoding::Abi => encode_abi(action_code, fields),
+        PayloadEncoding::PackedV1 => encode_packed_v1(action_code, fields),
+    }
+}
+
+pub fn decode(encoding: PayloadEncoding, bytes: &[u8]) -> Option<(u8, Vec<PayloadField>)> {
+    match encoding {
+        PayloadEncoding::Abi => decode_abi(bytes),
+        PayloadEncoding::PackedV1 => decode_packed_v1(bytes),
+    }
+}
+
+/// Commitment over the encoding id and payload, so the receiver knows how
+/// to parse what it is verifying.
+pub fn commitment(encoding: PayloadEncoding, payload: &[u8]) -> [u8; 32] {
+    let mut preimage = Vec::with_capacity(payload.len() + 1);
+    preimage.push(encoding.id());
+    preimage.extend_from_slice(payload);
+    let mut out = [0u8; 32];
+    out.copy_from_slice(&env::keccak256(&preimage));
+    out
+}
+
+// Chunk header: messageId (u64), index (u16), total (u16) and the keccak256
+// of the chunk body, all big-endian, followed by the body
+pub const CHUNK_HEADER_LEN: usize = 8 + 2 + 2 + 32;
+
+pub struct ChunkHeader {
+    pub message_id: u64,
+    pub index: u16,
+    pub total: u16,
+}
+
+/// Splits `payload` into chunks of at most `max_chunk_len` bytes, header
+/// included. The same inputs always produce the same chunks.
+pub fn chunk_payload(message_id: u64, payload: &[u8], max_chunk_len: usize) -> Vec<Vec<u8>> {
+    let bodies: Vec<&[u8]> = payload.chunks(max_chunk_len - CHUNK_HEADER_LEN).collect();
+    let total = bodies.len() as u16;
+    bodies
+        .iter()
+        .enumerate()
+        .map(|(index, body)| {
+            let mut chunk = Vec::with_capacity(CHUNK_HEADER_LEN + body.len());
+            chunk.extend_from_slice(&message_id.to_be_bytes());
+            chunk.extend_from_slice(&(index as u16).to_be_bytes());
+            chunk.extend_from_slice(&total.to_be_bytes());
+            chunk.extend_from_slice(&env::keccak256(body));
+            chunk.extend_from_slice(body);
+            chunk
+        })
+        .collect()
+}
+
+/// Header of a chunk, or None if it is truncated or the body does not
+/// match its checksum.
+pub fn read_chunk_header(chunk: &[u8]) -> Option<ChunkHeader> {
+    let mut cursor = chunk;
+    let message_id = u64::from_be_bytes(take(&mut cursor, 8)?.try_into().ok()?);
+    let index = u16::from_be_bytes(take(&mut cursor, 2)?.try_into().ok()?);
+    let total = u16::from_be_bytes(take(&mut cursor, 2)?.try_into().ok()?);
+    let checksum = take(&mut cursor, 32)?;
+    if index >= total || env::keccak256(cursor) != checksum {
+        return None;
+    }
+    Some(ChunkHeader { message_id, index, total })
+}
+
+fn hashlock_bytes(hashlock: &str) -> [u8; 32] {
+    let bytes = hex::decode(hashlock).expect("Invalid hashlock");
+    let mut out = [0u8; 32];
+    out.copy_from_slice(&bytes);
+    out
+}
+
+// PackedV1: [version][action] then each field big-endian at its natural
+// width; strings are prefixed with a u16 length.
+fn encode_packed_v1(action_code: u8, fields: &[PayloadField]) -> Vec<u8> {
+    let mut out = vec![PACKED_V1_VERSION, action_code];
+    for field in fields {
+        match field {
+            PayloadField::U64(v) => out.extend_from_slice(&v.to_be_bytes()),
+            PayloadField::U128(v) => out.extend_from_slice(&v.to_be_bytes()),
+            PayloadField::Address(a) => out.extend_from_slice(a),
+            PayloadField::Bytes32(b) => out.extend_from_slice(b),
+            PayloadField::Str(s) => {
+                out.extend_from_slice(&(s.len() as u16).to_be_bytes());
+                out.extend_from_slice(s.as_bytes());
+            }
+        }
+    }
+    out
+}
+
+fn decode_packed_v1(bytes: &[u8]) -> Option<(u8, Vec<PayloadField>)> {
+    if bytes.len() < 2 || bytes[0] != PACKED_V1_VERSION {
+        return None;
+    }
+    let action_code = bytes[1];
+    let mut cursor = &bytes[2..];
+    let mut fields = Vec::new();
+    for kind in schema_for(action_code) {
+        let field = match kind {
+            FieldKind::U64 => PayloadField::U64(u64::from_be_bytes(take(&mut cursor, 8)?.try_into().ok()?)),
+            FieldKind::U128 => PayloadField::U128(u128::from_be_bytes(take(&mut cursor, 16)?.try_into().ok()?)),
+            FieldKind::Address => PayloadField::Address(take(&mut cursor, 20)?.try_into().ok()?),
+            FieldKind::Bytes32 => PayloadField::Bytes32(take(&mut cursor, 32)?.try_into().ok()?),
+            FieldKind::Str => {
+                let len = u16::from_be_bytes(take(&mut cursor, 2)?.try_into().ok()?) as usize;
+                PayloadField::Str(String::from_utf8(take(&mut cursor, len)?.to_vec()).ok()?)
+            }
+        };
+        fields.push(field);
+    }
+    if !cursor.is_empty() {
+        return None;
+    }
+    Some((action_code, fields))
+}
+
+// Abi: abi.encode(uint8 action, fields...) with standard head/tail layout
+fn encode_abi(action_code: u8, fields: &[PayloadField]) -> Vec<u8> {
+    let head_len = 32 * (fields.len() + 1);
+    let mut head = Vec::with_capacity(head_len);
+    let mut tail = Vec::new();
+
+    head.extend_from_slice(&uint_word(action_code as u128));
+    for field in fields {
+        match field {
+            PayloadField::U64(v) => head.extend_from_slice(&uint_word(*v as u128)),
+            PayloadField::U128(v) => head.extend_from_slice(&uint_word(*v)),
+            PayloadField::Address(a) => {
+                let mut word = [0u8; 32];
+                word[12..].copy_from_slice(a);
+                head.extend_from_slice(&word);
+            }
+            PayloadField::Bytes32(b) => head.extend_from_slice(b),
+            PayloadField::Str(s) => {
+                head.extend_from_slice(&uint_word((head_len + tail.len()) as u128));
+                tail.extend_from_slice(&uint_word(s.len() as u128));
+                tail.extend_from_slice(s.as_bytes());
+                tail.resize(tail.len() + (32 - s.len() % 32) % 32, 0);
+            }
+        }
+    }
+
+    head.extend_from_slice(&tail);
+    head
+}
+
+fn decode_abi(bytes: &[u8]) -> Option<(u8, Vec<PayloadField>)> {
+    let action_word = word_at(bytes, 0)?;
+    let action_code = word_to_uint(action_word, 1)? as u8;
+    let mut fields = Vec::new();
+    for (index, kind) in schema_for(action_code).iter().enumerate() {
+        let word = word_at(bytes, 32 * (index + 1))?;
+        let field = match kind {
+            FieldKind::U64 => PayloadField::U64(word_to_uint(word, 8)? as u64),
+            FieldKind::U128 => PayloadField::U128(word_to_uint(word, 16)?),
+            FieldKind::Address => {
+                if word[..12].iter().any(|b| *b != 0) {
+                    return None;
+                }
+                PayloadField::Address(word[12..].try_into().ok()?)
+            }
+            FieldKind::Bytes32 => PayloadField::Bytes32(word.try_into().ok()?),
+            FieldKind::Str => {
+                let offset = word_to_uint(word, 8)? as usize;
+                let len = word_to_uint(word_at(bytes, offset)?, 8)? as usize;
+                let start = offset.checked_add(32)?;
+                let data = bytes.get(start..start.checked_add(len)?)?;
+                PayloadField::Str(String::from_utf8(data.to_vec()).ok()?)
+            }
+        };
+        fields.push(field);
+    }
+    Some((action_code, fields))
+}
+
+fn take<'a>(cursor: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if cursor.len() < len {
+        return None;
+    }
+    let (head, rest) = cursor.split_at(len);
+    *cursor = rest;
+    Some(head)
+}
+
+fn uint_word(value: u128) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[16..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+fn word_at(bytes: &[u8], offset: usize) -> Option<&[u8]> {
+    bytes.get(offset..offset.checked_add(32)?)
+}
+
+// Reads a big-endian uint that must fit in `width` bytes
+fn word_to_uint(word: &[u8], width: usize) -> Option<u128> {
+    if word[..32 - width].iter().any(|b| *b != 0) {
+        return None;
+    }
+    Some(word[32 - width..].iter().fold(0u128, |acc, b| (acc << 8) | *b as u128))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_fields() -> Vec<PayloadField> {
+        vec![
+            PayloadField::U64(7),
+            PayloadField::Str("alice.near".to_string()),
+            PayloadField::Address([0x22; 20]),
+            PayloadField::U128(1_000_000),
+            PayloadField::U128(2_000),
+            PayloadField::U64(50),
+            PayloadField::U64(25),
+            PayloadField::U64(1),
+            PayloadField::Bytes32([0xab; 32]),
+            PayloadField::U64(1_700_000_000),
+        ]
+    }
+
+    #[test]
+    fn packed_v1_golden_bytes() {
+        let expected = concat!(
+            "01",                                       // version
+            "01",                                       // create_order
+            "0000000000000007",                         // orderId
+            "000a", "616c6963652e6e656172",             // maker
+            "2222222222222222222222222222222222222222", // tokenOut
+            "000000000000000000000000000f4240",         // amountIn
+            "000000000000000000000000000007d0",         // basePrice
+            "0000000000000032",                         // slippage
+            "0000000000000019",                         // maxSlippageDeviation
+            "0000000000000001",                         // targetChainId
+            "abababababababababababababababababababababababababababababababab",
+            "000000006553f100",                         // timelock
+        );
+        assert_eq!(hex::encode(encode(PayloadEncoding::PackedV1, 1, &create_fields())), expected);
+    }
+
+    #[test]
+    fn abi_golden_bytes() {
+        let expected = concat!(
+            "0000000000000000000000000000000000000000000000000000000000000001", // action
+            "0000000000000000000000000000000000000000000000000000000000000007", // orderId
+            "0000000000000000000000000000000000000000000000000000000000000160", // maker offset
+            "0000000000000000000000002222222222222222222222222222222222222222", // tokenOut
+            "00000000000000000000000000000000000000000000000000000000000f4240", // amountIn
+            "00000000000000000000000000000000000000000000000000000000000007d0", // basePrice
+            "0000000000000000000000000000000000000000000000000000000000000032", // slippage
+            "0000000000000000000000000000000000000000000000000000000000000019", // maxSlippageDeviation
+            "0000000000000000000000000000000000000000000000000000000000000001", // targetChainId
+            "abababababababababababababababababababababababababababababababab", // hashlock
+            "000000000000000000000000000000000000000000000000000000006553f100", // timelock
+            "000000000000000000000000000000000000000000000000000000000000000a", // maker length
+            "616c6963652e6e65617200000000000000000000000000000000000000000000", // maker
+        );
+        assert_eq!(hex::encode(encode(PayloadEncoding::Abi, 1, &create_fields())), expected);
+    }
+
+    #[test]
+    fn small_messages_golden_bytes() {
+        let fields = [PayloadField::U64(7), PayloadField::U64(50)];
+        assert_eq!(
+            hex::encode(encode(PayloadEncoding::PackedV1, 2, &fields)),
+            "010200000000000000070000000000000032"
+        );
+        assert_eq!(
+            hex::encode(encode(PayloadEncoding::Abi, 2, &fields)),
+            concat!(
+                "0000000000000000000000000000000000000000000000000000000000000002",
+                "0000000000000000000000000000000000000000000000000000000000000007",
+                "0000000000000000000000000000000000000000000000000000000000000032",
+            )
+        );
+    }
+
+    #[test]
+    fn every_schema_round_trips_in_both_encodings() {
+        let mut dual = create_fields();
+        dual.push(PayloadField::Bytes32([0xcd; 32]));
+        dual.push(PayloadField::U64(2));
+        let messages = [
+            (1, create_fields()),
+            (2, vec![PayloadField::U64(7), PayloadField::U64(50)]),
+            (3, vec![PayloadField::U64(7)]),
+            (4, vec![PayloadField::U64(u64::MAX)]),
+            (5, vec![PayloadField::U64(7), PayloadField::U64(1_700_086_400)]),
+            (6, vec![PayloadField::U64(7), PayloadField::U128(u128::MAX), PayloadField::U64(0)]),
+            (7, dual),
+        ];
+        for encoding in [PayloadEncoding::Abi, PayloadEncoding::PackedV1] {
+            for (action_code, fields) in &messages {
+                let bytes = encode(encoding, *action_code, fields);
+                assert_eq!(decode(encoding, &bytes), Some((*action_code, fields.clone())));
+            }
+        }
+    }
+
+    #[test]
+    fn packed_v1_rejects_malformed_payloads() {
+        let bytes = encode(PayloadEncoding::PackedV1, 1, &create_fields());
+        let mut wrong_version = bytes.clone();
+        wrong_version[0] = 2;
+        assert_eq!(decode(PayloadEncoding::PackedV1, &wrong_version), None);
+        assert_eq!(decode(PayloadEncoding::PackedV1, &bytes[..bytes.len() - 1]), None);
+        let mut trailing = bytes;
+        trailing.push(0);
+        assert_eq!(decode(PayloadEncoding::PackedV1, &trailing), None);
+    }
+
+    #[test]
+    fn commitment_covers_the_encoding_id() {
+        let payload = encode(PayloadEncoding::Abi, 3, &[PayloadField::U64(7)]);
+        assert_ne!(
+            commitment(PayloadEncoding::Abi, &payload),
+            commitment(PayloadEncoding::PackedV1, &payload)
+        );
+        let mut preimage = vec![PayloadEncoding::PackedV1.id()];
+        preimage.extend_from_slice(&payload);
+        assert_eq!(commitment(PayloadEncoding::PackedV1, &payload).to_vec(), env::keccak256(&preimage));
+    }
+
+    // keccak256 of 32 zero bytes and of a single zero byte
+    const ZERO_WORD_HASH: &str = "290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e563";
+    const ZERO_BYTE_HASH: &str = "bc36789e7a1e281436464229828f817d6612f7b477d66591ff96a9e064bcc98a";
+
+    #[test]
+    fn chunk_golden_bytes() {
+        let chunks = chunk_payload(9, &[0; 65], CHUNK_HEADER_LEN + 32);
+        let header = |index: &str| format!("0000000000000009{}0003", index);
+        assert_eq!(
+            chunks.iter().map(hex::encode).collect::<Vec<_>>(),
+            [
+                format!("{}{}{}", header("0000"), ZERO_WORD_HASH, "00".repeat(32)),
+                format!("{}{}{}", header("0001"), ZERO_WORD_HASH, "00".repeat(32)),
+                format!("{}{}{}", header("0002"), ZERO_BYTE_HASH, "00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn chunk_boundaries() {
+        let limit = CHUNK_HEADER_LEN + 32;
+        let sizes = |len: usize| {
+            chunk_payload(1, &vec![7; len], limit).iter().map(|chunk| chunk.len() - CHUNK_HEADER_LEN).collect::<Vec<_>>()
+        };
+        assert_eq!(sizes(1), [1]);
+        assert_eq!(sizes(32), [32]);
+        assert_eq!(sizes(33), [32, 1]);
+        assert_eq!(sizes(63), [32, 31]);
+        assert_eq!(sizes(64), [32, 32]);
+        assert_eq!(sizes(65), [32, 32, 1]);
+        // Deterministic, and every chunk reads back in order
+        let chunks = chunk_payload(4, &[3; 100], limit);
+        assert!(chunks == chunk_payload(4, &[3; 100], limit));
+        for (index, chunk) in chunks.iter().enumerate() {
+            assert!(chunk.len() <= limit);
+            let header = read_chunk_header(chunk).unwrap();
+            assert_eq!((header.message_id, header.index, header.total), (4, index as u16, 4));
+        }
+    }
+
+    #[test]
+    fn corrupted_chunks_are_rejected() {
+        let chunk = chunk_payload(9, &[0; 40], CHUNK_HEADER_LEN + 32).remove(0);
+        assert!(read_chunk_header(&chunk).is_some());
+
+        let mut body = chunk.clone();
+        body[CHUNK_HEADER_LEN + 5] ^= 1;
+        assert!(read_chunk_header(&body).is_none());
+        let mut checksum = chunk.clone();
+        checksum[12] ^= 1;
+        assert!(read_chunk_header(&checksum).is_none());
+        let mut index = chunk.clone();
+        index[8..10].copy_from_slice(&2u16.to_be_bytes()); // Index 2 of 2
+        assert!(read_chunk_header(&index).is_none());
+        assert!(read_chunk_header(&chunk[..CHUNK_HEADER_LEN - 1]).is_none());
+        assert!(read_chunk_header(&chunk[..chunk.len() - 1]).is_none());
+    }
+}
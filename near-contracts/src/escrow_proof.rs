@@ -0,0 +1,241 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::{self, json};
+use near_sdk::{env, ext_contract, near_bindgen, require, AccountId, Promise, PromiseResult};
+
+use crate::eth_proof::{decode_escrow_locked, decode_header_number, decode_log_entry, ext_eth_prover, parse_eth_address};
+use crate::inbound;
+use crate::limits;
+use crate::normalize;
+use crate::{
+    timing, AdaptiveCrossChain, AdaptiveCrossChainExt, EthProof, EventLevel, ExpiryStage, OrderStatus,
+    GAS_FOR_CROSS_CHAIN_CALL, GAS_FOR_PROOF_CALLBACK,
+};
+
+/// How resolvers prove their destination escrow before a lock.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EscrowProofConfig {
+    pub required: bool,                  // lock_order and lock_partial then refuse to lock
+    pub prover: Option<AccountId>,       // None uses eth_prover
+    pub eth_client: Option<AccountId>,   // Light client asked for the head block
+    pub min_confirmations: u64,          // Needs eth_client when positive
+    pub escrow_contract: Option<String>, // Emitter of EscrowLocked; None uses the chain's counterpart
+}
+
+#[ext_contract(ext_eth_client)]
+#[allow(dead_code)] // Only the generated call builders are used
+pub trait EthClient {
+    fn last_block_number(&self) -> u64;
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    pub fn set_escrow_proof_config(&mut self, config: EscrowProofConfig) {
+        self.assert_owner();
+        require!(
+            config.min_confirmations == 0 || config.eth_client.is_some(),
+            "min_confirmations needs an eth_client"
+        );
+        let escrow_contract = config
+            .escrow_contract
+            .as_deref()
+            .map(|address| normalize::eth_address(address).unwrap_or_else(|violation| violation.panic()));
+        self.escrow_proof = EscrowProofConfig { escrow_contract, ..config };
+        self.audit("set_escrow_proof_config", None);
+        env::log_str(&format!(
+            "Escrow proofs required: {}, min confirmations {}",
+            self.escrow_proof.required, self.escrow_proof.min_confirmations
+        ));
+    }
+
+    pub fn get_escrow_proof_config(&self) -> EscrowProofConfig {
+        self.escrow_proof.clone()
+    }
+
+    /// Locks the order for the resolver named in an EscrowLocked log once
+    /// the prover has verified it. The escrow must carry the order's
+    /// hashlock, fill part of what is left of it and refund before the
+    /// order's timelock, so the resolver can still claim here after the
+    /// secret is revealed there. Anyone may submit the proof. A proof the
+    /// prover rejects leaves the order untouched and emits
+    /// `eth_escrow_rejected`.
+    pub fn confirm_eth_escrow(&mut self, order_id: u64, proof: EthProof) -> Promise {
+        self.assert_not_paused();
+        limits::assert_proof_len(&proof);
+        let prover = self
+            .escrow_proof
+            .prover
+            .clone()
+            .or_else(|| self.eth_prover.clone())
+            .expect("EthProver not configured");
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        require!(matches!(order.status, OrderStatus::Active), "Order not active");
+        require!(
+            timing::current_expiry_stage(&order) == ExpiryStage::BeforeTimelock,
+            "Order expired"
+        );
+        require!(
+            !timing::quote_expired(&order, env::block_timestamp()),
+            "Order quote expired"
+        );
+
+        let log = decode_log_entry(&proof.log_entry_data).expect("Invalid log entry");
+        let escrow_contract = self
+            .escrow_proof
+            .escrow_contract
+            .clone()
+            .unwrap_or_else(|| self.counterpart_contract(order.target_chain_id));
+        require!(
+            Some(log.address) == parse_eth_address(&escrow_contract),
+            "Log not emitted by the escrow contract"
+        );
+        let escrow = decode_escrow_locked(&log).expect("Log is not EscrowLocked");
+        require!(escrow.order_id == order_id, "Escrow is for another order");
+        require!(
+            hex::encode(escrow.hashlock) == order.hashlock,
+            "Escrow hashlock does not match the order"
+        );
+        require!(
+            escrow.amount > 0 && escrow.amount <= order.amount_remaining.0,
+            "Escrow amount does not fit the remaining amount"
+        );
+        // The proof is checked against a timestamp deadline
+        self.migrate_timelock(&mut order);
+        self.save_order(&order);
+        let now_secs = env::block_timestamp() / timing::NS_PER_SEC;
+        require!(
+            escrow.timelock > now_secs && escrow.timelock < timing::timelock_secs(&order),
+            "Escrow timelock must end before the order's"
+        );
+        let resolver: AccountId = escrow.resolver.parse().expect("Invalid resolver account");
+        require!(resolver != order.maker, "Maker cannot lock their own order");
+        self.assert_permitted_resolver(&resolver);
+        self.assert_min_stake(&resolver);
+
+        let proof_key = inbound::proof_key(&proof);
+        require!(
+            !self.consumed_escrow_proofs.contains(&proof_key),
+            "Proof already used"
+        );
+        let block_number = decode_header_number(&proof.header_data).expect("Invalid block header");
+        let min_confirmations = self.escrow_proof.min_confirmations;
+
+        let verification = ext_eth_prover::ext(prover)
+            .with_static_gas(GAS_FOR_CROSS_CHAIN_CALL)
+            .verify_log_entry(
+                proof.log_index,
+                proof.log_entry_data,
+                proof.receipt_index,
+                proof.receipt_data,
+                proof.header_data,
+                proof.proof,
+                false,
+            );
+        let verification = match (&self.escrow_proof.eth_client, min_confirmations) {
+            (Some(eth_client), confirmations) if confirmations > 0 => verification.and(
+                ext_eth_client::ext(eth_client.clone())
+                    .with_static_gas(GAS_FOR_PROOF_CALLBACK)
+                    .last_block_number(),
+            ),
+            _ => verification,
+        };
+        verification.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_CROSS_CHAIN_CALL)
+                .on_eth_escrow_verified(
+                    order_id,
+                    proof_key,
+                    resolver,
+                    U128(escrow.amount),
+                    block_number,
+                    min_confirmations,
+                ),
+        )
+    }
+
+    /// The proof is only spent when the lock goes through, so a rejected
+    /// one can be submitted again, e.g. once it has enough confirmations.
+    #[private]
+    pub fn on_eth_escrow_verified(
+        &mut self,
+        order_id: u64,
+        proof_key: String,
+        resolver: AccountId,
+        amount: U128,
+        block_number: u64,
+        min_confirmations: u64,
+    ) -> bool {
+        let verified = match env::promise_result(0) {
+            PromiseResult::Successful(value) => bool::try_from_slice(&value).unwrap_or(false),
+            _ => false,
+        };
+        let head = if min_confirmations > 0 {
+            match env::promise_result(1) {
+                PromiseResult::Successful(value) => serde_json::from_slice::<u64>(&value).ok(),
+                _ => None,
+            }
+        } else {
+            None
+        };
+        let order = self.orders.get(&order_id);
+
+        let rejection = if !verified {
+            Some("proof not verified by the prover".to_string())
+        } else if min_confirmations > 0
+            && head.is_none_or(|head| head < block_number.saturating_add(min_confirmations))
+        {
+            Some(format!(
+                "block {} has fewer than {} confirmations",
+                block_number, min_confirmations
+            ))
+        } else if self.consumed_escrow_proofs.contains(&proof_key) {
+            Some("proof already used".to_string())
+        } else {
+            match &order {
+                Some(order)
+                    if matches!(order.status, OrderStatus::Active)
+                        && timing::current_expiry_stage(order) == ExpiryStage::BeforeTimelock
+                        && !timing::quote_expired(order, env::block_timestamp())
+                        && amount.0 <= order.amount_remaining.0 =>
+                {
+                    None
+                }
+                _ => Some("order can no longer be locked".to_string()),
+            }
+        };
+        if let Some(reason) = rejection {
+            env::log_str(&format!("Escrow proof rejected for order {}: {}", order_id, reason));
+            self.emit_event(
+                EventLevel::Minimal,
+                "eth_escrow_rejected",
+                json!({ "order_id": order_id, "resolver": resolver, "reason": reason }),
+            );
+            return false;
+        }
+
+        self.consumed_escrow_proofs.insert(&proof_key);
+        self.internal_lock(resolver.clone(), order_id, Some(amount.0));
+        self.emit_event(
+            EventLevel::Standard,
+            "eth_escrow_verified",
+            json!({
+                "order_id": order_id,
+                "resolver": resolver,
+                "amount": amount,
+                "block_number": block_number,
+            }),
+        );
+        true
+    }
+}
+
+impl AdaptiveCrossChain {
+    pub(crate) fn assert_lock_without_proof_allowed(&self) {
+        require!(
+            !self.escrow_proof.required,
+            "Locks need a verified escrow; use confirm_eth_escrow"
+        );
+    }
+}
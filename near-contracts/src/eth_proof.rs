@@ -0,0 +1,244 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, ext_contract};
+
+use crate::normalize;
+
+// Event emitted by the Ethereum contract once it has mirrored a NEAR order:
+// OrderCreated(uint64 indexed orderId, bytes32 indexed hashlock)
+pub const ORDER_CREATED_EVENT: &str = "OrderCreated(uint64,bytes32)";
+
+// Escrow for an inbound (Ethereum -> NEAR) order:
+// EscrowCreated(bytes32 indexed orderHash, bytes32 indexed hashlock, uint256 amount, string recipient)
+pub const ESCROW_CREATED_EVENT: &str = "EscrowCreated(bytes32,bytes32,uint256,string)";
+
+// Resolver escrow on the destination chain backing a lock of a NEAR order.
+// `amount` is the part of the NEAR order it fills and `timelock` its refund
+// deadline in seconds:
+// EscrowLocked(uint64 indexed orderId, bytes32 indexed hashlock, uint256 amount, uint256 timelock, string resolver)
+pub const ESCROW_LOCKED_EVENT: &str = "EscrowLocked(uint64,bytes32,uint256,uint256,string)";
+
+/// Receipt proof for a single Ethereum log entry, as accepted by the
+/// Rainbow Bridge EthProver.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EthProof {
+    pub log_index: u64,
+    pub log_entry_data: Vec<u8>,
+    pub receipt_index: u64,
+    pub receipt_data: Vec<u8>,
+    pub header_data: Vec<u8>,
+    pub proof: Vec<Vec<u8>>,
+}
+
+/// Decoded Ethereum log entry (emitter, topics, data).
+pub struct EthLogEntry {
+    pub address: [u8; 20],
+    pub topics: Vec<[u8; 32]>,
+    pub data: Vec<u8>,
+}
+
+#[ext_contract(ext_eth_prover)]
+#[allow(dead_code)] // Only the generated call builders are used
+pub trait EthProver {
+    #[result_serializer(borsh)]
+    fn verify_log_entry(
+        &self,
+        #[serializer(borsh)] log_index: u64,
+        #[serializer(borsh)] log_entry_data: Vec<u8>,
+        #[serializer(borsh)] receipt_index: u64,
+        #[serializer(borsh)] receipt_data: Vec<u8>,
+        #[serializer(borsh)] header_data: Vec<u8>,
+        #[serializer(borsh)] proof: Vec<Vec<u8>>,
+        #[serializer(borsh)] skip_bridge_call: bool,
+    ) -> bool;
+}
+
+pub fn event_topic(signature: &str) -> [u8; 32] {
+    to_word(&env::keccak256(signature.as_bytes()))
+}
+
+pub fn u64_topic(value: u64) -> [u8; 32] {
+    let mut topic = [0u8; 32];
+    topic[24..].copy_from_slice(&value.to_be_bytes());
+    topic
+}
+
+/// Parses a 0x-prefixed (or bare) hex Ethereum address.
+pub fn parse_eth_address(address: &str) -> Option<[u8; 20]> {
+    let bytes = hex::decode(normalize::strip_hex_prefix(address)).ok()?;
+    if bytes.len() != 20 {
+        return None;
+    }
+    let mut out = [0u8; 20];
+    out.copy_from_slice(&bytes);
+    Some(out)
+}
+
+/// Fields of an EscrowCreated log.
+pub struct EscrowCreated {
+    pub order_hash: [u8; 32],
+    pub hashlock: [u8; 32],
+    pub amount: u128,
+    pub recipient: String, // NEAR account id or 0x Ethereum address
+}
+
+/// Reads EscrowCreated from a decoded log; the emitter is checked by the caller.
+pub fn decode_escrow_created(log: &EthLogEntry) -> Option<EscrowCreated> {
+    if log.topics.len() != 3 || log.topics[0] != event_topic(ESCROW_CREATED_EVENT) {
+        return None;
+    }
+    // data = abi.encode(uint256 amount, string recipient)
+    let amount_word = log.data.get(0..32)?;
+    if amount_word[..16].iter().any(|b| *b != 0) {
+        return None;
+    }
+    let offset = abi_len(log.data.get(32..64)?)?;
+    let len = abi_len(log.data.get(offset..offset.checked_add(32)?)?)?;
+    let start = offset.checked_add(32)?;
+    let recipient = log.data.get(start..start.checked_add(len)?)?;
+
+    Some(EscrowCreated {
+        order_hash: log.topics[1],
+        hashlock: log.topics[2],
+        amount: u128::from_be_bytes(amount_word[16..].try_into().ok()?),
+        recipient: String::from_utf8(recipient.to_vec()).ok()?,
+    })
+}
+
+/// Fields of an EscrowLocked log.
+pub struct EscrowLocked {
+    pub order_id: u64,
+    pub hashlock: [u8; 32],
+    pub amount: u128,
+    pub timelock: u64,    // Seconds
+    pub resolver: String, // NEAR account of the resolver
+}
+
+/// Reads EscrowLocked from a decoded log; the emitter is checked by the caller.
+pub fn decode_escrow_locked(log: &EthLogEntry) -> Option<EscrowLocked> {
+    if log.topics.len() != 3 || log.topics[0] != event_topic(ESCROW_LOCKED_EVENT) {
+        return None;
+    }
+    // data = abi.encode(uint256 amount, uint256 timelock, string resolver)
+    let amount_word = log.data.get(0..32)?;
+    if amount_word[..16].iter().any(|b| *b != 0) {
+        return None;
+    }
+    let timelock = abi_u64(log.data.get(32..64)?)?;
+    let offset = abi_len(log.data.get(64..96)?)?;
+    let len = abi_len(log.data.get(offset..offset.checked_add(32)?)?)?;
+    let start = offset.checked_add(32)?;
+    let resolver = log.data.get(start..start.checked_add(len)?)?;
+
+    Some(EscrowLocked {
+        order_id: abi_u64(&log.topics[1])?,
+        hashlock: log.topics[2],
+        amount: u128::from_be_bytes(amount_word[16..].try_into().ok()?),
+        timelock,
+        resolver: String::from_utf8(resolver.to_vec()).ok()?,
+    })
+}
+
+/// Block number of an RLP-encoded block header, its ninth field.
+pub fn decode_header_number(data: &[u8]) -> Option<u64> {
+    let (is_list, mut fields, rest) = rlp_item(data)?;
+    if !is_list || !rest.is_empty() {
+        return None;
+    }
+    for _ in 0..8 {
+        let (_, _, next) = rlp_item(fields)?;
+        fields = next;
+    }
+    let (is_list, number, _) = rlp_item(fields)?;
+    if is_list || number.len() > 8 {
+        return None;
+    }
+    Some(number.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64))
+}
+
+/// Decodes an RLP-encoded log entry: [address, [topics...], data].
+pub fn decode_log_entry(data: &[u8]) -> Option<EthLogEntry> {
+    let (is_list, payload, rest) = rlp_item(data)?;
+    if !is_list || !rest.is_empty() {
+        return None;
+    }
+
+    let (address_is_list, address, payload) = rlp_item(payload)?;
+    let (topics_is_list, mut topics_payload, payload) = rlp_item(payload)?;
+    let (data_is_list, log_data, payload) = rlp_item(payload)?;
+    if address_is_list || !topics_is_list || data_is_list || !payload.is_empty() {
+        return None;
+    }
+    if address.len() != 20 {
+        return None;
+    }
+
+    let mut topics = Vec::new();
+    while !topics_payload.is_empty() {
+        let (topic_is_list, topic, rest) = rlp_item(topics_payload)?;
+        if topic_is_list || topic.len() != 32 {
+            return None;
+        }
+        topics.push(to_word(topic));
+        topics_payload = rest;
+    }
+
+    let mut address_bytes = [0u8; 20];
+    address_bytes.copy_from_slice(address);
+    Some(EthLogEntry {
+        address: address_bytes,
+        topics,
+        data: log_data.to_vec(),
+    })
+}
+
+// Splits the first RLP item off `data`, returning (is_list, payload, rest).
+fn rlp_item(data: &[u8]) -> Option<(bool, &[u8], &[u8])> {
+    let prefix = *data.first()?;
+    let (is_list, offset, len) = match prefix {
+        0x00..=0x7f => return Some((false, &data[..1], &data[1..])),
+        0x80..=0xb7 => (false, 1, (prefix - 0x80) as usize),
+        0xb8..=0xbf => {
+            let len_of_len = (prefix - 0xb7) as usize;
+            (false, 1 + len_of_len, read_len(data.get(1..1 + len_of_len)?)?)
+        }
+        0xc0..=0xf7 => (true, 1, (prefix - 0xc0) as usize),
+        0xf8..=0xff => {
+            let len_of_len = (prefix - 0xf7) as usize;
+            (true, 1 + len_of_len, read_len(data.get(1..1 + len_of_len)?)?)
+        }
+    };
+    let end = offset.checked_add(len)?;
+    let payload = data.get(offset..end)?;
+    Some((is_list, payload, &data[end..]))
+}
+
+fn read_len(bytes: &[u8]) -> Option<usize> {
+    if bytes.len() > 8 {
+        return None;
+    }
+    Some(bytes.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize))
+}
+
+// A uint256 length or offset word that must fit in 8 bytes
+fn abi_len(word: &[u8]) -> Option<usize> {
+    if word[..24].iter().any(|b| *b != 0) {
+        return None;
+    }
+    read_len(&word[24..])
+}
+
+// A uint256 word that must fit in a u64
+fn abi_u64(word: &[u8]) -> Option<u64> {
+    if word.len() != 32 || word[..24].iter().any(|b| *b != 0) {
+        return None;
+    }
+    Some(word[24..].iter().fold(0u64, |acc, b| (acc << 8) | *b as u64))
+}
+
+fn to_word(bytes: &[u8]) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word.copy_from_slice(bytes);
+    word
+}
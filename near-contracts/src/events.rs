@@ -0,0 +1,189 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::{json, Value};
+use near_sdk::{env, near_bindgen};
+
+use crate::views;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, CrossChainOrder};
+
+pub const EVENT_STANDARD: &str = "adaptive_cross_chain";
+pub const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+/// How much the contract logs. Each event declares the lowest level at
+/// which it is emitted.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(crate = "near_sdk::serde")]
+pub enum EventLevel {
+    Minimal,  // Only what the Ethereum relayer needs
+    Standard, // Lifecycle and slippage events
+    Verbose,  // Gas reports and per-update detail
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    pub fn set_event_verbosity(&mut self, level: EventLevel) {
+        self.assert_owner();
+        self.event_verbosity = level;
+        env::log_str(&format!("event_verbosity set to {}", level_name(level)));
+    }
+}
+
+impl AdaptiveCrossChain {
+    /// Emits a NEP-297 event if the configured verbosity includes `level`.
+    /// Call sites only declare the level; all gating happens here.
+    pub(crate) fn emit_event(&self, level: EventLevel, event: &str, data: Value) {
+        if level > self.event_verbosity {
+            return;
+        }
+        let log = json!({
+            "standard": EVENT_STANDARD,
+            "version": EVENT_STANDARD_VERSION,
+            "event": event,
+            "data": [data],
+        });
+        env::log_str(&format!("EVENT_JSON:{}", log));
+    }
+
+    /// Emits an order lifecycle event. Every lifecycle event carries the
+    /// same order fields so indexers can follow an order from any of them;
+    /// `extra` adds the event's own fields on top.
+    pub(crate) fn emit_order_event(&self, level: EventLevel, event: &str, order: &CrossChainOrder, extra: Value) {
+        let mut data = json!({
+            "order_id": order.order_id,
+            "maker": order.maker,
+            "token_in": order.token_in,
+            "token_out": order.token_out,
+            "amount_in": views::public_amount(order),
+            "hashlock": order.hashlock,
+            "target_chain_id": order.target_chain_id,
+        });
+        if let (Some(fields), Value::Object(extra)) = (data.as_object_mut(), extra) {
+            fields.extend(extra);
+        }
+        self.emit_event(level, event, data);
+    }
+
+    // Verbose-only: gas burnt so far by the current call
+    pub(crate) fn emit_gas_report(&self, method: &str, order_id: u64) {
+        self.emit_event(
+            EventLevel::Verbose,
+            "gas_report",
+            json!({
+                "method": method,
+                "order_id": order_id,
+                "used_gas": env::used_gas().as_gas().to_string(),
+                "prepaid_gas": env::prepaid_gas().as_gas().to_string(),
+            }),
+        );
+    }
+}
+
+fn level_name(level: EventLevel) -> &'static str {
+    match level {
+        EventLevel::Minimal => "Minimal",
+        EventLevel::Standard => "Standard",
+        EventLevel::Verbose => "Verbose",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::serde_json;
+    use near_sdk::test_utils::get_logs;
+
+    use super::*;
+    use crate::ft::PendingClaim;
+    use crate::test_utils::*;
+
+    const AMOUNT: u128 = 10 * ONE_NEAR;
+
+    // Event names logged by the call just made
+    fn events() -> Vec<String> {
+        get_logs()
+            .iter()
+            .filter_map(|log| log.strip_prefix("EVENT_JSON:"))
+            .map(|log| serde_json::from_str::<Value>(log).unwrap()["event"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    // Events of an order created, locked, claimed and settled, then of a
+    // second order repriced by a slippage update and cancelled, at `level`
+    fn lifecycle(level: EventLevel) -> Vec<String> {
+        set_clock(0, 0);
+        let mut contract = setup();
+        call(owner(), 0);
+        contract.set_event_verbosity(level);
+        let mut logged = Vec::new();
+
+        let params = order_params(&contract, 1, AMOUNT);
+        let order_id = create_order_with(&mut contract, params, AMOUNT + ONE_NEAR);
+        logged.extend(events());
+        lock(&mut contract, order_id);
+        logged.extend(events());
+        call(resolver(), 0);
+        let hashlock = contract.orders.get(&order_id).unwrap().hashlock;
+        let _ = contract.claim_with_secret(hashlock, secret(1), None);
+        logged.extend(events());
+        let claim: PendingClaim =
+            serde_json::from_value(scheduled_calls("on_claim_transfer")[0]["claim"].clone()).unwrap();
+        callback(true);
+        contract.on_claim_transfer(order_id, claim);
+        logged.extend(events());
+
+        let params = order_params(&contract, 2, AMOUNT);
+        let order_id = create_order_with(&mut contract, params, AMOUNT + ONE_NEAR);
+        logged.extend(events());
+        set_clock(301_000_000_000, 0);
+        call(maker(), 0);
+        contract.update_order_slippage(order_id);
+        logged.extend(events());
+        call(maker(), 0);
+        let _ = contract.cancel_order(order_id);
+        logged.extend(events());
+        logged
+    }
+
+    #[test]
+    fn minimal_keeps_what_the_relayer_needs() {
+        assert_eq!(
+            lifecycle(EventLevel::Minimal),
+            ["order_created", "order_locked", "order_claimed", "order_created", "order_cancelled"]
+        );
+    }
+
+    #[test]
+    fn standard_adds_slippage_events() {
+        assert_eq!(
+            lifecycle(EventLevel::Standard),
+            ["order_created", "order_locked", "order_claimed", "order_created", "slippage_updated", "order_cancelled"]
+        );
+    }
+
+    #[test]
+    fn verbose_adds_gas_reports_and_update_detail() {
+        assert_eq!(
+            lifecycle(EventLevel::Verbose),
+            [
+                "order_created",
+                "gas_report",
+                "order_locked",
+                "gas_report",
+                "order_claimed",
+                "gas_report",
+                "order_created",
+                "gas_report",
+                "slippage_updated",
+                "slippage_update_detail",
+                "order_cancelled",
+            ]
+        );
+    }
+
+    #[test]
+    fn only_the_owner_sets_the_verbosity() {
+        let mut contract = setup();
+        assert_rejected_unchanged(&mut contract, maker(), 0, "Only owner", |c| {
+            c.set_event_verbosity(EventLevel::Verbose)
+        });
+    }
+}
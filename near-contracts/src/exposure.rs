@@ -0,0 +1,127 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId};
+
+use crate::fees::bps_of;
+use crate::roles::Role;
+use crate::validation::Violation;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, Balance, CrossChainOrder};
+
+pub const ERR_EXPOSURE_CAP: &str = "ERR_EXPOSURE_CAP";
+
+/// Caps on a resolver's open exposure (amount_in summed over the orders
+/// it has locked). The lower of the two applies; `None` disables a cap.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ExposureLimits {
+    pub max_absolute: Option<U128>,
+    pub max_book_bps: Option<u64>, // Share of the total open book value
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ResolverStats {
+    pub account_id: AccountId,
+    pub completed_fills: u64,
+    pub open_exposure: U128,
+    pub exposure_cap: Option<U128>,
+    pub headroom: Option<U128>,
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    pub fn set_exposure_limits(&mut self, limits: ExposureLimits) {
+        self.assert_owner();
+        self.exposure_limits = limits;
+        env::log_str("Resolver exposure limits updated");
+    }
+
+    // Individual cap for a whitelisted resolver, replacing the global caps
+    pub fn set_exposure_override(&mut self, resolver: AccountId, cap: Option<U128>) {
+        self.assert_role(Role::Admin);
+        match cap {
+            Some(cap) => {
+                self.exposure_overrides.insert(&resolver, &cap.0);
+            }
+            None => {
+                self.exposure_overrides.remove(&resolver);
+            }
+        }
+        self.audit("set_exposure_override", None);
+        env::log_str(&format!("Exposure override for {} set to {:?}", resolver, cap.map(|c| c.0)));
+    }
+
+    pub fn get_resolver_exposure(&self, account: AccountId) -> U128 {
+        U128(self.resolver_exposure.get(&account).unwrap_or(0))
+    }
+
+    pub fn get_resolver_stats(&self, account: AccountId) -> ResolverStats {
+        let exposure = self.resolver_exposure.get(&account).unwrap_or(0);
+        let cap = self.exposure_cap(&account);
+        ResolverStats {
+            completed_fills: self.resolver_fills.get(&account).unwrap_or(0),
+            open_exposure: U128(exposure),
+            exposure_cap: cap.map(U128),
+            headroom: cap.map(|cap| U128(cap.saturating_sub(exposure))),
+            account_id: account,
+        }
+    }
+}
+
+impl AdaptiveCrossChain {
+    /// Adds `order` to the resolver's exposure, failing with the remaining
+    /// headroom if that would exceed its cap. Every lock path calls this.
+    pub(crate) fn reserve_exposure(&mut self, resolver: &AccountId, order: &CrossChainOrder) {
+        let exposure = self.resolver_exposure.get(resolver).unwrap_or(0);
+        let amount = order.locked_amount.0;
+        if let Some(cap) = self.exposure_cap(resolver) {
+            if exposure + amount > cap {
+                Violation::new(
+                    ERR_EXPOSURE_CAP,
+                    format!(
+                        "locking {} would exceed the exposure cap; available headroom is {}",
+                        amount,
+                        cap.saturating_sub(exposure)
+                    ),
+                )
+                .panic();
+            }
+        }
+        self.resolver_exposure.insert(resolver, &(exposure + amount));
+    }
+
+    /// Called by the status transition helper whenever an order leaves
+    /// Locked: claim, revert, release or expiry.
+    pub(crate) fn release_exposure(&mut self, order: &CrossChainOrder) {
+        if let Some(resolver) = &order.taker {
+            self.reduce_exposure(resolver, order.locked_amount.0);
+        }
+    }
+
+    // Part of a lock was claimed while the rest stays locked
+    pub(crate) fn reduce_exposure(&mut self, resolver: &AccountId, amount: Balance) {
+        let exposure = self.resolver_exposure.get(resolver).unwrap_or(0);
+        let remaining = exposure.saturating_sub(amount);
+        if remaining == 0 {
+            self.resolver_exposure.remove(resolver);
+        } else {
+            self.resolver_exposure.insert(resolver, &remaining);
+        }
+    }
+
+    fn exposure_cap(&self, resolver: &AccountId) -> Option<Balance> {
+        if let Some(cap) = self.exposure_overrides.get(resolver) {
+            return Some(cap);
+        }
+        let absolute = self.exposure_limits.max_absolute.map(|cap| cap.0);
+        let book_share = self
+            .exposure_limits
+            .max_book_bps
+            .map(|bps| bps_of(self.open_book_value, bps));
+        match (absolute, book_share) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, require, AccountId, NearToken, Promise};
+
+use crate::promotions::FeeContext;
+use crate::roles::Role;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, Balance, CrossChainOrder, OrderStatus};
+
+pub const BPS_DENOMINATOR: u128 = 10_000;
+pub const MAX_PROTOCOL_FEE_BPS: u64 = 100;
+
+/// Fee components applied when an order settles.
+///
+/// Exemptions are scoped per component: a fee-exempt maker only waives the
+/// maker-side protocol fee. Resolver-side and referral components must be
+/// exempted through their own party's entry.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeeBreakdown {
+    pub gross_amount: U128,
+    pub protocol_fee: U128,
+    pub net_amount: U128,
+    pub maker_exempt: bool,
+    pub order_fee_waived: bool,
+    pub protocol_fee_bps: u64,     // After any promotion
+    pub promotion_id: Option<u64>, // Promotion that lowered the fee
+}
+
+/// Fee breakdown for a hypothetical order, computed with the same helpers
+/// used at settlement.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeePreview {
+    pub maker: AccountId,
+    pub referrer: Option<AccountId>,
+    pub target_chain_id: u64,
+    pub amount_in: U128,
+    pub protocol_fee_bps: u64,
+    pub protocol_fee: U128,
+    pub maker_exempt: bool,
+    pub promotion_id: Option<u64>,
+    pub bridge_route: Option<u8>,
+    pub bridge_fee: U128,          // Route fee attached on top of amount_in
+    pub net_escrow: U128,          // Amount held in escrow after creation
+    pub expected_net_payout: U128, // Amount the claimer receives
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    pub fn preview_fees(
+        &self,
+        maker: AccountId,
+        amount_in: U128,
+        target_chain_id: u64,
+        referrer: Option<AccountId>,
+        bridge_route: Option<u8>,
+        token_out: Option<String>,
+    ) -> FeePreview {
+        // Priced as an exact amount_in, which pays the route fee on top
+        let route = self.resolve_bridge_route(bridge_route, target_chain_id).ok();
+        let bridge_fee = route.as_ref().map(|(_, config)| config.fee.0).unwrap_or(0);
+        let net_escrow = amount_in.0;
+        let context = FeeContext {
+            target_chain_id,
+            token_in: "near",
+            token_out: token_out.as_deref(),
+        };
+        let fees = self.compute_fees(&maker, false, net_escrow, &context);
+
+        FeePreview {
+            maker,
+            referrer,
+            target_chain_id,
+            amount_in,
+            protocol_fee_bps: fees.protocol_fee_bps,
+            protocol_fee: fees.protocol_fee,
+            maker_exempt: fees.maker_exempt,
+            promotion_id: fees.promotion_id,
+            bridge_route: route.map(|(route_id, _)| route_id),
+            bridge_fee: U128(bridge_fee),
+            net_escrow: U128(net_escrow),
+            expected_net_payout: fees.net_amount,
+        }
+    }
+
+    // Deducted from the claimed amount of every settlement
+    pub fn set_protocol_fee_bps(&mut self, protocol_fee_bps: u64) {
+        self.assert_owner();
+        require!(
+            protocol_fee_bps <= MAX_PROTOCOL_FEE_BPS,
+            format!("Protocol fee cannot exceed {} bps", MAX_PROTOCOL_FEE_BPS)
+        );
+        env::log_str(&format!(
+            "protocol_fee_bps set from {} to {}",
+            self.protocol_fee_bps, protocol_fee_bps
+        ));
+        self.protocol_fee_bps = protocol_fee_bps;
+        self.audit("set_protocol_fee_bps", None);
+    }
+
+    pub fn get_protocol_fee_bps(&self) -> u64 {
+        self.protocol_fee_bps
+    }
+
+    // Native protocol fees; token fees are in get_collected_ft_fees
+    pub fn get_collected_fees(&self) -> U128 {
+        U128(self.collected_fees)
+    }
+
+    pub fn withdraw_fees(&mut self, receiver: AccountId, amount: U128) -> Promise {
+        self.assert_owner();
+        require!(
+            amount.0 > 0 && amount.0 <= self.collected_fees,
+            "Amount exceeds the collected fees"
+        );
+        self.collected_fees -= amount.0;
+        self.audit("withdraw_fees", None);
+        env::log_str(&format!("Withdrew {} of protocol fees to {}", amount.0, receiver));
+        Promise::new(receiver).transfer(NearToken::from_yoctonear(amount.0))
+    }
+
+    pub fn add_fee_exempt(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        if self.fee_exempt.insert(&account_id) {
+            env::log_str(&format!("Fee exemption granted: {}", account_id));
+        }
+    }
+
+    pub fn remove_fee_exempt(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        if self.fee_exempt.remove(&account_id) {
+            env::log_str(&format!("Fee exemption revoked: {}", account_id));
+        }
+    }
+
+    pub fn is_fee_exempt(&self, account_id: AccountId) -> bool {
+        self.fee_exempt.contains(&account_id)
+    }
+
+    // Goodwill waiver on a single order, e.g. after an incident
+    pub fn set_fee_waived(&mut self, order_id: u64, waived: bool) {
+        self.assert_role(Role::Treasurer);
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        require!(
+            matches!(order.status, OrderStatus::Active | OrderStatus::Locked),
+            "Fees can only be waived before completion"
+        );
+        order.fee_waived = waived;
+        self.save_order(&order);
+
+        env::log_str(&format!(
+            "Fee waiver for order {} set to {} by {}",
+            order_id,
+            waived,
+            env::predecessor_account_id()
+        ));
+    }
+}
+
+impl AdaptiveCrossChain {
+    pub(crate) fn compute_settlement_fees(
+        &self,
+        order: &CrossChainOrder,
+        amount: Balance,
+    ) -> FeeBreakdown {
+        let context = FeeContext {
+            target_chain_id: order.target_chain_id,
+            token_in: order.token_in.as_str(),
+            token_out: Some(&order.token_out),
+        };
+        self.compute_fees(&order.maker, order.fee_waived, amount, &context)
+    }
+
+    // Single fee code path shared by settlement and previews
+    fn compute_fees(
+        &self,
+        maker: &AccountId,
+        fee_waived: bool,
+        amount: Balance,
+        context: &FeeContext,
+    ) -> FeeBreakdown {
+        let maker_exempt = self.fee_exempt.contains(maker);
+        let promotion = if maker_exempt || fee_waived || self.protocol_fee_bps == 0 {
+            None
+        } else {
+            self.best_promotion(context, amount)
+        };
+        let protocol_fee_bps = match &promotion {
+            Some(promotion) => self.protocol_fee_bps.saturating_sub(promotion.fee_discount_bps),
+            None => self.protocol_fee_bps,
+        };
+        let protocol_fee = if maker_exempt || fee_waived {
+            0
+        } else {
+            bps_of(amount, protocol_fee_bps)
+        };
+
+        FeeBreakdown {
+            gross_amount: U128(amount),
+            protocol_fee: U128(protocol_fee),
+            net_amount: U128(amount - protocol_fee),
+            maker_exempt,
+            order_fee_waived: fee_waived,
+            promotion_id: promotion.map(|promotion| promotion.id),
+            protocol_fee_bps,
+        }
+    }
+}
+
+// Rounds down so fees never exceed the quoted rate
+pub(crate) fn bps_of(amount: Balance, bps: u64) -> Balance {
+    if bps == 0 {
+        return 0;
+    }
+    amount / BPS_DENOMINATOR * bps as u128 + amount % BPS_DENOMINATOR * bps as u128 / BPS_DENOMINATOR
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::json_types::{U128, U64};
+
+    use crate::test_utils::*;
+    use crate::{BridgeConfig, BridgeKind};
+
+    const AMOUNT: u128 = 10 * ONE_NEAR;
+
+    fn add_fee_route(contract: &mut crate::AdaptiveCrossChain) {
+        call(owner(), 0);
+        contract.add_bridge_route(1, BridgeConfig {
+            account: bridge(),
+            kind: BridgeKind::LightClient,
+            fee: U128(ONE_NEAR / 100),
+            delay: U64(900),
+            enabled: true,
+            chain_ids: vec![],
+            max_payload_bytes: None,
+        });
+        contract.set_protocol_fee_bps(30);
+    }
+
+    // Creates an order for exactly AMOUNT on the fee route and settles it,
+    // checking every preview figure against what the order was charged
+    fn assert_preview_matches_settlement(contract: &mut crate::AdaptiveCrossChain) {
+        let preview = contract.preview_fees(maker(), U128(AMOUNT), 1, None, Some(1), Some(TOKEN_OUT.to_string()));
+        let mut params = order_params(contract, 1, AMOUNT);
+        params.bridge_route = Some(1);
+        let order_id = create_order_with(contract, params, AMOUNT + ONE_NEAR);
+
+        let order = contract.orders.get(&order_id).unwrap();
+        assert_eq!(order.amount_in, preview.net_escrow);
+        assert_eq!(contract.collected_bridge_fees, preview.bridge_fee.0);
+
+        let fees_before = contract.get_collected_fees().0;
+        lock(contract, order_id);
+        let claim = claim(contract, order_id, 1);
+        assert_eq!(claim.fees.protocol_fee, preview.protocol_fee);
+        assert_eq!(claim.fees.net_amount, preview.expected_net_payout);
+        assert_eq!(claim.fees.protocol_fee_bps, preview.protocol_fee_bps);
+        assert_eq!(contract.get_collected_fees().0 - fees_before, preview.protocol_fee.0);
+    }
+
+    #[test]
+    fn preview_matches_charged_fees() {
+        let mut contract = setup();
+        add_fee_route(&mut contract);
+        assert_preview_matches_settlement(&mut contract);
+    }
+
+    #[test]
+    fn preview_matches_charged_fees_for_exempt_maker() {
+        let mut contract = setup();
+        add_fee_route(&mut contract);
+        contract.add_fee_exempt(maker());
+        let preview = contract.preview_fees(maker(), U128(AMOUNT), 1, None, Some(1), None);
+        assert!(preview.maker_exempt);
+        assert_eq!(preview.protocol_fee.0, 0);
+        assert_preview_matches_settlement(&mut contract);
+    }
+}
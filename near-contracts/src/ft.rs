@@ -0,0 +1,456 @@
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::{self, json};
+use near_sdk::{env, ext_contract, near_bindgen, require, AccountId, Gas, NearToken, Promise, PromiseOrValue, PromiseResult};
+
+use crate::fees::FeeBreakdown;
+use crate::incoming::IncomingFundingMsg;
+use crate::limits;
+use crate::storage::StorageCharge;
+use crate::{
+    AdaptiveCrossChain, AdaptiveCrossChainExt, Balance, CrossChainOrder, DutchAuction, EventLevel, HashAlgorithm, HashlockMode,
+    OrderParams,
+    OrderStatus,
+};
+
+// token_in of orders escrowing the attached native deposit
+pub const NATIVE_TOKEN: &str = "near";
+
+pub const GAS_FOR_FT_TRANSFER: Gas = Gas::from_tgas(10);
+pub const GAS_FOR_FT_PAYOUT_CALLBACK: Gas = Gas::from_tgas(5);
+// Finishing the claim can notify a settlement callback and refund an order
+// that ran out of fill attempts
+pub const GAS_FOR_CLAIM_CALLBACK: Gas = Gas::from_tgas(80);
+
+#[ext_contract(ext_ft)]
+#[allow(dead_code)] // Only the generated call builders are used
+pub trait FungibleToken {
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+}
+
+/// `msg` of an ft_transfer_call that opens an order. The transferred
+/// amount is the escrow; bridge fees and callbacks are native-only.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtOrderMsg {
+    pub token_out: String,
+    pub base_price: U128,
+    pub max_slippage_deviation: Option<u64>,
+    pub target_chain_id: u64,
+    pub hashlock: String,
+    pub hash_algorithm: Option<HashAlgorithm>,
+    pub secret_parts: Option<u32>,
+    pub private_economics: Option<bool>,
+    pub bridge_route: Option<u8>,
+    pub skip_reconfirmation: Option<bool>,
+    pub auction: Option<DutchAuction>,
+    pub timelock_duration: Option<U64>,
+    pub allow_public_slippage_updates: Option<bool>,
+    pub validity_duration: Option<U64>,
+    pub memo: Option<String>,
+    pub taker_hashlock: Option<String>,
+    pub hashlock_mode: Option<HashlockMode>,
+}
+
+/// A claim whose payout is being made, carried through the transfer
+/// callback so the secret is only stored once the payee is paid.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingClaim {
+    pub receiver: AccountId, // Resolver whose lock is claimed
+    pub payee: AccountId,
+    pub filled: U128,
+    pub secret: Option<String>,       // Preimage of `hashlock`, or a Merkle leaf
+    pub taker_secret: Option<String>, // Preimage of `taker_hashlock`
+    pub secret_index: Option<u32>,
+    pub relayer: AccountId, // Paid relayer_fee out of the gas sponsorship
+    pub relayer_fee: U128,
+    pub fees: FeeBreakdown,
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    /// NEP-141 receiver. Opens an order escrowing the transferred tokens
+    /// for `sender_id`. Transfers of unsupported tokens are returned in full;
+    /// invalid orders panic, which also refunds the sender.
+    pub fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        let token = env::predecessor_account_id();
+        if !self.supported_fts.contains(&token) {
+            env::log_str(&format!("Token {} is not supported, returning {}", token, amount.0));
+            return PromiseOrValue::Value(amount);
+        }
+        limits::assert_len(msg.len(), limits::MAX_FT_MSG_LEN, limits::ERR_FT_MSG_TOO_LONG);
+        if let Ok(funding) = serde_json::from_str::<IncomingFundingMsg>(&msg) {
+            self.fund_incoming_order(sender_id, token, amount.0, funding.fund_incoming);
+            return PromiseOrValue::Value(U128(0));
+        }
+        let order: FtOrderMsg = serde_json::from_str(&msg).expect("Invalid order msg");
+
+        let params = OrderParams {
+            token_out: order.token_out,
+            amount_in: amount,
+            base_price: order.base_price,
+            max_slippage_deviation: order.max_slippage_deviation,
+            target_chain_id: order.target_chain_id,
+            hashlock: order.hashlock,
+            hash_algorithm: order.hash_algorithm,
+            secret_parts: order.secret_parts,
+            private_economics: order.private_economics,
+            settlement_callback: None,
+            bridge_route: order.bridge_route,
+            gas_sponsorship: None,
+            skip_reconfirmation: order.skip_reconfirmation,
+            auction: order.auction,
+            timelock_duration: order.timelock_duration,
+            allow_public_slippage_updates: order.allow_public_slippage_updates,
+            validity_duration: order.validity_duration,
+            memo: order.memo,
+            taker_hashlock: order.taker_hashlock,
+            hashlock_mode: order.hashlock_mode,
+        };
+        // Storage is charged in NEAR, which a token transfer cannot carry
+        self.internal_create_order(sender_id, token, params, StorageCharge::None);
+        PromiseOrValue::Value(U128(0))
+    }
+
+    pub fn add_supported_ft(&mut self, token: AccountId) {
+        self.assert_owner();
+        self.supported_fts.insert(&token);
+        env::log_str(&format!("Token {} supported as token_in", token));
+    }
+
+    // Open orders in the token still settle and refund
+    pub fn remove_supported_ft(&mut self, token: AccountId) {
+        self.assert_owner();
+        self.supported_fts.remove(&token);
+        env::log_str(&format!("Token {} no longer supported as token_in", token));
+    }
+
+    pub fn get_supported_fts(&self) -> Vec<AccountId> {
+        self.supported_fts.to_vec()
+    }
+
+    /// Native payouts come back here too, with `token` "near". An order
+    /// that was refunded or cancelled stays so; the amount is kept for the
+    /// receiver instead of reopening the order.
+    #[private]
+    pub fn on_ft_payout(&mut self, token: AccountId, receiver: AccountId, amount: U128) -> bool {
+        let paid = matches!(env::promise_result(0), PromiseResult::Successful(_));
+        if !paid {
+            let key = (token.clone(), receiver.clone());
+            let owed = self.failed_ft_payouts.get(&key).unwrap_or(0);
+            self.failed_ft_payouts.insert(&key, &(owed + amount.0));
+            env::log_str(&format!(
+                "Payout of {} {} to {} failed, kept for withdraw_ft_payout",
+                amount.0, token, receiver
+            ));
+            self.emit_event(
+                EventLevel::Standard,
+                "payout_failed",
+                json!({ "token": token, "receiver": receiver, "amount": amount }),
+            );
+        }
+        paid
+    }
+
+    // Retries payouts that failed, e.g. because the receiver was not
+    // registered with the token yet or its account was deleted
+    pub fn withdraw_ft_payout(&mut self, token: AccountId) -> Promise {
+        let receiver = env::predecessor_account_id();
+        let amount = self
+            .failed_ft_payouts
+            .remove(&(token.clone(), receiver.clone()))
+            .unwrap_or(0);
+        require!(amount > 0, "No failed payout for this token");
+        self.ft_payout(token, receiver, amount)
+    }
+
+    /// Finishes a claim. A failed transfer, e.g. to a payee not registered
+    /// with the token or a deleted account, puts the order back to Locked
+    /// with its secret unrevealed and a Merkle leaf unspent, so the claim
+    /// can be retried.
+    #[private]
+    pub fn on_claim_transfer(&mut self, order_id: u64, claim: PendingClaim) -> bool {
+        let paid = matches!(env::promise_result(0), PromiseResult::Successful(_));
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        require!(matches!(order.status, OrderStatus::Claiming), "Order has no claim in flight");
+        self.transition_status(&mut order, OrderStatus::Locked);
+        if !paid {
+            if let Some(index) = claim.secret_index {
+                let mut used = self.used_secret_indices.get(&order_id).unwrap_or_default();
+                used.retain(|used_index| *used_index != index);
+                self.used_secret_indices.insert(&order_id, &used);
+            }
+            self.save_order(&order);
+            env::log_str(&format!(
+                "Transfer of {} {} to {} failed, order {} is locked again",
+                claim.fees.net_amount.0, order.token_in, claim.payee, order_id
+            ));
+            self.emit_order_event(
+                EventLevel::Standard,
+                "claim_failed",
+                &order,
+                json!({
+                    "taker": claim.receiver,
+                    "payee": claim.payee,
+                    "filled": claim.filled,
+                    "net_amount": claim.fees.net_amount,
+                }),
+            );
+            return false;
+        }
+        let (order, completed) = self.complete_fill(order, &claim);
+        // A lock that used the last fill attempt ends the order once it
+        // reopens
+        if !completed {
+            self.expire_if_out_of_attempts(order);
+        }
+        true
+    }
+
+    pub fn get_failed_ft_payout(&self, token: AccountId, receiver: AccountId) -> U128 {
+        U128(self.failed_ft_payouts.get(&(token, receiver)).unwrap_or(0))
+    }
+
+    pub fn get_collected_ft_fees(&self, token: AccountId) -> U128 {
+        U128(self.collected_ft_fees.get(&token).unwrap_or(0))
+    }
+
+    pub fn withdraw_ft_fees(&mut self, token: AccountId, receiver: AccountId, amount: U128) -> Promise {
+        self.assert_owner();
+        let collected = self.collected_ft_fees.get(&token).unwrap_or(0);
+        require!(amount.0 > 0 && amount.0 <= collected, "Amount exceeds the collected fees");
+        self.collected_ft_fees.insert(&token, &(collected - amount.0));
+        self.audit("withdraw_ft_fees", None);
+        env::log_str(&format!("Withdrew {} {} of protocol fees to {}", amount.0, token, receiver));
+        self.ft_payout(token, receiver, amount.0)
+    }
+}
+
+impl AdaptiveCrossChain {
+    /// Pays out of an order's escrow in the order's token_in. Every refund
+    /// and cancel goes through here; claims use `start_claim`.
+    pub(crate) fn pay_out(&self, order: &CrossChainOrder, receiver: AccountId, amount: Balance) -> Promise {
+        self.ft_payout(order.token_in.clone(), receiver, amount)
+    }
+
+    /// Marks the order Claiming and transfers the claim's net amount. The
+    /// claim only takes effect in `on_claim_transfer`.
+    pub(crate) fn start_claim(&mut self, mut order: CrossChainOrder, claim: PendingClaim) -> Promise {
+        let order_id = order.order_id;
+        self.transition_status(&mut order, OrderStatus::Claiming);
+        self.save_order(&order);
+        env::log_str(&format!(
+            "Order {} claim of {} waiting for the transfer to {}",
+            order_id, claim.filled.0, claim.payee
+        ));
+        transfer(order.token_in.clone(), claim.payee.clone(), claim.fees.net_amount.0).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_CLAIM_CALLBACK)
+                .on_claim_transfer(order_id, claim),
+        )
+    }
+
+    pub(crate) fn record_protocol_fee(&mut self, order: &CrossChainOrder, fee: Balance) {
+        if is_native(order.token_in.as_str()) {
+            self.collected_fees += fee;
+        } else if fee > 0 {
+            let collected = self.collected_ft_fees.get(&order.token_in).unwrap_or(0);
+            self.collected_ft_fees.insert(&order.token_in, &(collected + fee));
+        }
+    }
+
+    // Sponsorships are always NEAR; a failed refund is kept for
+    // withdraw_ft_payout like any other payout
+    pub(crate) fn refund_sponsorship(&self, order: &CrossChainOrder) -> Promise {
+        self.ft_payout(NATIVE_TOKEN.parse().unwrap(), order.maker.clone(), order.gas_sponsorship.0)
+    }
+
+    pub(crate) fn ft_payout(&self, token: AccountId, receiver: AccountId, amount: Balance) -> Promise {
+        transfer(token.clone(), receiver.clone(), amount).then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_FT_PAYOUT_CALLBACK)
+                    .on_ft_payout(token, receiver, U128(amount)),
+            )
+    }
+}
+
+// Sends `amount` of `token`, native NEAR included, without a callback
+fn transfer(token: AccountId, receiver: AccountId, amount: Balance) -> Promise {
+    if is_native(token.as_str()) {
+        Promise::new(receiver).transfer(NearToken::from_yoctonear(amount))
+    } else {
+        ext_ft::ext(token)
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(receiver, U128(amount), None)
+    }
+}
+
+pub fn is_native(token_in: &str) -> bool {
+    token_in == NATIVE_TOKEN
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::{accounts, get_logs};
+
+    use super::*;
+    use crate::test_utils::*;
+    use crate::test_utils::ONE_NEAR;
+
+    fn token() -> AccountId {
+        "usdc.near".parse().unwrap()
+    }
+
+    fn ft_order(contract: &mut AdaptiveCrossChain, amount: Balance) -> u64 {
+        call(owner(), 0);
+        contract.add_supported_ft(token());
+        let params = order_params(contract, 1, amount);
+        let msg = json!({
+            "token_out": params.token_out,
+            "base_price": params.base_price,
+            "target_chain_id": params.target_chain_id,
+            "hashlock": params.hashlock,
+        });
+        call(token(), 0);
+        let _ = contract.ft_on_transfer(maker(), U128(amount), msg.to_string());
+        contract.next_order_id - 1
+    }
+
+    // Claims with secret(1) and returns the claim the transfer callback gets
+    fn start(contract: &mut AdaptiveCrossChain, order_id: u64, receiver_id: Option<AccountId>) -> PendingClaim {
+        call(resolver(), 0);
+        let hashlock = contract.orders.get(&order_id).unwrap().hashlock;
+        let _ = contract.claim_with_secret(hashlock, secret(1), receiver_id);
+        let args = scheduled_calls("on_claim_transfer").pop().expect("claim scheduled no callback");
+        serde_json::from_value(args["claim"].clone()).unwrap()
+    }
+
+    fn status(contract: &AdaptiveCrossChain, order_id: u64) -> OrderStatus {
+        contract.orders.get(&order_id).unwrap().status
+    }
+
+    #[test]
+    fn token_claims_to_an_unregistered_receiver_can_be_retried() {
+        let mut contract = setup();
+        let order_id = ft_order(&mut contract, 1_000_000);
+        lock(&mut contract, order_id);
+
+        let claim = start(&mut contract, order_id, None);
+        let transfer = scheduled_calls("ft_transfer").pop().unwrap();
+        assert_eq!(transfer["receiver_id"], resolver().as_str());
+        assert_eq!(transfer["amount"], claim.fees.net_amount.0.to_string());
+        assert!(matches!(status(&contract, order_id), OrderStatus::Claiming));
+        // One claim in flight at a time
+        let hashlock = contract.orders.get(&order_id).unwrap().hashlock;
+        assert!(try_call(&mut contract, |c| {
+            call(resolver(), 0);
+            c.claim_with_secret(hashlock, secret(1), None)
+        })
+        .is_none());
+
+        // The resolver is not registered with the token
+        callback(false);
+        assert!(!contract.on_claim_transfer(order_id, claim));
+        assert!(get_logs().iter().any(|log| log.contains("\"claim_failed\"")));
+        let order = contract.orders.get(&order_id).unwrap();
+        assert!(matches!(order.status, OrderStatus::Locked));
+        assert!(order.secret.is_none());
+        assert_eq!(contract.get_collected_ft_fees(token()).0, 0);
+
+        let claim = start(&mut contract, order_id, Some(accounts(3)));
+        assert_eq!(scheduled_calls("ft_transfer").pop().unwrap()["receiver_id"], accounts(3).as_str());
+        callback(true);
+        assert!(contract.on_claim_transfer(order_id, claim));
+        let order = contract.orders.get(&order_id).unwrap();
+        assert!(matches!(order.status, OrderStatus::Completed));
+        assert_eq!(order.secret, Some(secret(1)));
+    }
+
+    #[test]
+    fn failed_native_claims_reopen_the_lock() {
+        let mut contract = setup();
+        let params = order_params(&contract, 1, ONE_NEAR);
+        let order_id = create_order_with(&mut contract, params, ONE_NEAR);
+        lock(&mut contract, order_id);
+
+        let claim = start(&mut contract, order_id, Some("deleted.near".parse().unwrap()));
+        assert_eq!(transfers(), vec![("deleted.near".parse().unwrap(), claim.fees.net_amount.0)]);
+        callback(false);
+        assert!(!contract.on_claim_transfer(order_id, claim));
+        assert!(matches!(status(&contract, order_id), OrderStatus::Locked));
+        assert_eq!(contract.collected_fees, 0);
+
+        let claim = start(&mut contract, order_id, None);
+        assert_eq!(transfers(), vec![(resolver(), claim.fees.net_amount.0)]);
+        callback(true);
+        assert!(contract.on_claim_transfer(order_id, claim));
+        assert!(matches!(status(&contract, order_id), OrderStatus::Completed));
+    }
+
+    // A failed refund leaves the order terminal and the amount withdrawable
+    #[test]
+    fn failed_refunds_are_kept_for_the_maker() {
+        let mut contract = setup();
+        let params = order_params(&contract, 1, ONE_NEAR);
+        let order_id = create_order_with(&mut contract, params, ONE_NEAR);
+        let escrow = contract.orders.get(&order_id).unwrap().amount_in.0;
+        call(maker(), 0);
+        let _ = contract.cancel_order(order_id);
+        let payout = scheduled_calls("on_ft_payout").pop().unwrap();
+        assert_eq!(payout["amount"], escrow.to_string());
+
+        callback(false);
+        assert!(!contract.on_ft_payout(NATIVE_TOKEN.parse().unwrap(), maker(), U128(escrow)));
+        assert!(get_logs().iter().any(|log| log.contains("\"payout_failed\"")));
+        assert!(matches!(status(&contract, order_id), OrderStatus::Cancelled));
+        assert_eq!(contract.get_failed_ft_payout(NATIVE_TOKEN.parse().unwrap(), maker()).0, escrow);
+
+        call(maker(), 0);
+        let _ = contract.withdraw_ft_payout(NATIVE_TOKEN.parse().unwrap());
+        assert_eq!(transfers(), vec![(maker(), escrow)]);
+        assert_eq!(contract.get_failed_ft_payout(NATIVE_TOKEN.parse().unwrap(), maker()).0, 0);
+        let again = run_or_revert(&mut contract, |c| {
+            call(maker(), 0);
+            let _ = c.withdraw_ft_payout(NATIVE_TOKEN.parse().unwrap());
+        });
+        assert!(again.unwrap_err().contains("No failed payout"));
+    }
+
+    // Cancels and expiry refunds return the sponsorship through the same
+    // failure-recording payout as the escrow
+    #[test]
+    fn failed_sponsorship_refunds_are_kept_for_the_maker() {
+        let mut contract = setup();
+        let sponsorship = ONE_NEAR / 2;
+        let sponsored = |contract: &mut AdaptiveCrossChain, n| {
+            let params = OrderParams { gas_sponsorship: Some(U128(sponsorship)), ..order_params(contract, n, ONE_NEAR) };
+            create_order_with(contract, params, 2 * ONE_NEAR)
+        };
+        let cancelled = sponsored(&mut contract, 1);
+        let expired = sponsored(&mut contract, 2);
+        let native: AccountId = NATIVE_TOKEN.parse().unwrap();
+        let refunded_amounts = || {
+            scheduled_calls("on_ft_payout")
+                .into_iter()
+                .map(|args| args["amount"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        call(maker(), 0);
+        let _ = contract.cancel_order(cancelled);
+        assert!(refunded_amounts().contains(&sponsorship.to_string()));
+        callback(false);
+        assert!(!contract.on_ft_payout(native.clone(), maker(), U128(sponsorship)));
+        assert_eq!(contract.get_failed_ft_payout(native.clone(), maker()).0, sponsorship);
+
+        set_clock(contract.orders.get(&expired).unwrap().timelock_ns.0 + 1, 10);
+        call(resolver(), 0);
+        let _ = contract.refund_expired_order(expired);
+        assert!(refunded_amounts().contains(&sponsorship.to_string()));
+        callback(false);
+        assert!(!contract.on_ft_payout(native.clone(), maker(), U128(sponsorship)));
+        assert_eq!(contract.get_failed_ft_payout(native, maker()).0, 2 * sponsorship);
+    }
+}
@@ -0,0 +1,368 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, require, AccountId, NearToken, Promise, PromiseResult};
+
+use crate::eth_proof::{decode_escrow_created, decode_log_entry, ext_eth_prover, parse_eth_address};
+use crate::limits;
+use crate::normalize;
+use crate::roles::Role;
+use crate::{
+    AdaptiveCrossChain, AdaptiveCrossChainExt, Balance, EthProof, HashAlgorithm, GAS_FOR_CROSS_CHAIN_CALL, GAS_FOR_PROOF_CALLBACK,
+};
+
+// Time the recipient has to reveal the secret once a resolver has filled
+pub const INBOUND_CLAIM_WINDOW_NS: u64 = 86_400_000_000_000;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum InboundStatus {
+    Registered, // Ethereum escrow known, waiting for a resolver
+    Filled,     // Resolver locked the NEAR payout
+    Completed,  // Recipient revealed the secret
+    Refunded,   // Claim window passed, resolver repaid
+}
+
+/// NEAR side of an Ethereum -> NEAR order, backed by an Ethereum escrow.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct InboundOrder {
+    pub order_hash: String, // Hex, as emitted by the Ethereum contract
+    pub hashlock: String,
+    pub amount: U128,
+    pub recipient: String,  // NEAR account id or 0x Ethereum address
+    pub proof_verified: bool, // false when only asserted by a relayer
+    pub registered_at: U64,
+    pub fillable_at: U64,   // Finality lock before a resolver may fill
+    pub resolver: Option<AccountId>,
+    pub filled_at: Option<U64>,
+    pub status: InboundStatus,
+}
+
+/// Relayer-asserted orders carry custody risk, so they get a lower cap
+/// and wait longer for Ethereum finality than proof-verified ones.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct InboundLimits {
+    pub relayer_max_amount: U128,
+    pub relayer_finality_lock: U64,
+    pub proof_max_amount: U128,
+    pub proof_finality_lock: U64,
+}
+
+impl Default for InboundLimits {
+    fn default() -> Self {
+        Self {
+            relayer_max_amount: U128(100_000_000_000_000_000_000_000_000), // 100 NEAR
+            relayer_finality_lock: U64(3_600_000_000_000),                 // 1 hour
+            proof_max_amount: U128(10_000_000_000_000_000_000_000_000_000), // 10000 NEAR
+            proof_finality_lock: U64(0), // The proof is already past finality
+        }
+    }
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    pub fn register_inbound_order(
+        &mut self,
+        order_hash: String,
+        hashlock: String,
+        amount: U128,
+        recipient: String,
+    ) {
+        self.assert_role(Role::Relayer);
+        self.assert_not_paused();
+        limits::assert_len(order_hash.len(), limits::MAX_ORDER_REF_LEN, limits::ERR_ORDER_REF_TOO_LONG);
+        let order_hash = normalize::hex_id(&order_hash);
+        let hashlock = normalize::hex_id(&hashlock);
+        require!(
+            self.load_inbound_order(&order_hash).is_none(),
+            "Inbound order already registered"
+        );
+        let order = self.new_inbound_order(order_hash, hashlock, amount.0, recipient, false);
+        self.insert_inbound_order(order);
+    }
+
+    /// Trust-minimized registration: the EthProver must confirm our Ethereum
+    /// contract emitted EscrowCreated. Nothing is created until it does.
+    pub fn register_inbound_order_with_proof(&mut self, proof: EthProof) -> Promise {
+        self.assert_not_paused();
+        limits::assert_proof_len(&proof);
+        let prover = self.eth_prover.clone().expect("EthProver not configured");
+
+        let log = decode_log_entry(&proof.log_entry_data).expect("Invalid log entry");
+        let ethereum_contract = parse_eth_address(&self.ethereum_contract)
+            .expect("Invalid Ethereum contract address");
+        require!(log.address == ethereum_contract, "Log not emitted by Ethereum contract");
+        let escrow = decode_escrow_created(&log).expect("Log is not EscrowCreated");
+
+        let order_hash = hex::encode(escrow.order_hash);
+        let hashlock = hex::encode(escrow.hashlock);
+        if let Some(existing) = self.load_inbound_order(&order_hash) {
+            require!(!existing.proof_verified, "Inbound order already registered");
+        }
+        // Validate up front so the callback cannot fail on bad input
+        self.assert_inbound_params(&hashlock, escrow.amount, &escrow.recipient, true);
+
+        // Reserved now and released if verification fails, so the same
+        // receipt cannot be in flight twice
+        let proof_key = proof_key(&proof);
+        require!(
+            self.consumed_inbound_proofs.insert(&proof_key),
+            "Proof already used"
+        );
+
+        ext_eth_prover::ext(prover)
+            .with_static_gas(GAS_FOR_CROSS_CHAIN_CALL)
+            .verify_log_entry(
+                proof.log_index,
+                proof.log_entry_data,
+                proof.receipt_index,
+                proof.receipt_data,
+                proof.header_data,
+                proof.proof,
+                false,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_PROOF_CALLBACK)
+                    .on_inbound_proof_verified(
+                        proof_key,
+                        order_hash,
+                        hashlock,
+                        U128(escrow.amount),
+                        escrow.recipient,
+                    ),
+            )
+    }
+
+    #[private]
+    pub fn on_inbound_proof_verified(
+        &mut self,
+        proof_key: String,
+        order_hash: String,
+        hashlock: String,
+        amount: U128,
+        recipient: String,
+    ) -> bool {
+        let verified = match env::promise_result(0) {
+            PromiseResult::Successful(value) => bool::try_from_slice(&value).unwrap_or(false),
+            _ => false,
+        };
+        if !verified {
+            self.consumed_inbound_proofs.remove(&proof_key);
+            env::log_str(&format!("Inbound proof rejected for {}", order_hash));
+            return false;
+        }
+
+        // A relayer may have registered the same escrow meanwhile; the proof
+        // upgrades it if the details agree, otherwise the proof wins before fill
+        let order = match self.load_inbound_order(&order_hash) {
+            Some(existing) if existing.proof_verified => {
+                env::log_str(&format!("Inbound order {} already proof-verified", order_hash));
+                return false;
+            }
+            Some(existing) if existing.status != InboundStatus::Registered => {
+                if existing.hashlock != hashlock
+                    || existing.amount != amount
+                    || existing.recipient != recipient
+                {
+                    self.consumed_inbound_proofs.remove(&proof_key);
+                    env::log_str(&format!(
+                        "Inbound order {} already filled with details that disagree with the proof",
+                        order_hash
+                    ));
+                    return false;
+                }
+                InboundOrder { proof_verified: true, ..existing }
+            }
+            _ => self.new_inbound_order(order_hash, hashlock, amount.0, recipient, true),
+        };
+        self.insert_inbound_order(order);
+        true
+    }
+
+    #[payable]
+    pub fn fill_inbound_order(&mut self, order_hash: String) {
+        self.assert_not_paused();
+        let mut order = self.load_inbound_order(&order_hash).expect("Inbound order not found");
+        require!(order.status == InboundStatus::Registered, "Inbound order not fillable");
+        require!(
+            env::block_timestamp() >= order.fillable_at.0,
+            "Inbound order still in finality lock"
+        );
+        require!(
+            env::attached_deposit().as_yoctonear() == order.amount.0,
+            "Deposit must equal the inbound amount"
+        );
+
+        order.status = InboundStatus::Filled;
+        order.resolver = Some(env::predecessor_account_id());
+        order.filled_at = Some(U64(env::block_timestamp()));
+        self.inbound_orders.insert(&order.order_hash, &order);
+
+        env::log_str(&format!(
+            "Inbound order {} filled by {}",
+            order_hash,
+            env::predecessor_account_id()
+        ));
+    }
+
+    // Pays the recipient, or credits a shadow balance when the recipient
+    // is an Ethereum address
+    pub fn claim_inbound_order(&mut self, order_hash: String, secret: String) {
+        self.assert_not_paused();
+        limits::assert_len(secret.len(), limits::MAX_SECRET_LEN, limits::ERR_SECRET_TOO_LONG);
+        let mut order = self.load_inbound_order(&order_hash).expect("Inbound order not found");
+        require!(order.status == InboundStatus::Filled, "Inbound order not filled");
+        require!(
+            self.generate_hashlock(&secret, HashAlgorithm::Sha256) == normalize::hex_id(&order.hashlock),
+            "Invalid secret"
+        );
+        let filled_at = order.filled_at.expect("Missing fill time").0;
+        require!(
+            env::block_timestamp() < filled_at + INBOUND_CLAIM_WINDOW_NS,
+            "Claim window has passed"
+        );
+
+        order.status = InboundStatus::Completed;
+        self.inbound_orders.insert(&order.order_hash, &order);
+        let resolver = order.resolver.clone().expect("Missing resolver");
+
+        match parse_eth_address(&order.recipient) {
+            Some(address) => {
+                self.credit_shadow_balance(&address, inbound_id(&order.order_hash), order.amount.0, resolver);
+            }
+            None => {
+                let recipient: AccountId = order.recipient.parse().expect("Invalid recipient");
+                Promise::new(recipient).transfer(NearToken::from_yoctonear(order.amount.0));
+            }
+        }
+
+        env::log_str(&format!(
+            "Inbound order {} claimed, secret revealed: {}",
+            order_hash, secret
+        ));
+    }
+
+    pub fn refund_inbound_order(&mut self, order_hash: String) -> Promise {
+        let mut order = self.load_inbound_order(&order_hash).expect("Inbound order not found");
+        require!(order.status == InboundStatus::Filled, "Inbound order not filled");
+        let filled_at = order.filled_at.expect("Missing fill time").0;
+        require!(
+            env::block_timestamp() >= filled_at + INBOUND_CLAIM_WINDOW_NS,
+            "Claim window still open"
+        );
+
+        order.status = InboundStatus::Refunded;
+        self.inbound_orders.insert(&order.order_hash, &order);
+        let resolver = order.resolver.expect("Missing resolver");
+        env::log_str(&format!("Inbound order {} refunded to {}", order_hash, resolver));
+        Promise::new(resolver).transfer(NearToken::from_yoctonear(order.amount.0))
+    }
+
+    pub fn set_inbound_limits(&mut self, limits: InboundLimits) {
+        self.assert_owner();
+        require!(
+            limits.relayer_max_amount.0 <= limits.proof_max_amount.0
+                && limits.relayer_finality_lock.0 >= limits.proof_finality_lock.0,
+            "Relayer limits must be at least as strict as proof limits"
+        );
+        self.inbound_limits = limits;
+    }
+
+    pub fn get_inbound_order(&self, order_hash: String) -> Option<InboundOrder> {
+        self.inbound_orders
+            .get(&normalize::hex_id(&order_hash))
+            .or_else(|| self.inbound_orders.get(&order_hash))
+    }
+}
+
+impl AdaptiveCrossChain {
+    fn assert_inbound_params(&self, hashlock: &str, amount: Balance, recipient: &str, proof_verified: bool) {
+        limits::assert_len(hashlock.len(), limits::MAX_HASHLOCK_LEN, limits::ERR_HASHLOCK_TOO_LONG);
+        require!(amount > 0, "Inbound amount must be positive");
+        let max_amount = if proof_verified {
+            self.inbound_limits.proof_max_amount.0
+        } else {
+            self.inbound_limits.relayer_max_amount.0
+        };
+        require!(amount <= max_amount, "Inbound amount exceeds limit");
+        require!(
+            normalize::recipient(recipient).is_ok(),
+            "Recipient must be a NEAR account or Ethereum address"
+        );
+    }
+
+    fn new_inbound_order(
+        &self,
+        order_hash: String,
+        hashlock: String,
+        amount: Balance,
+        recipient: String,
+        proof_verified: bool,
+    ) -> InboundOrder {
+        self.assert_inbound_params(&hashlock, amount, &recipient, proof_verified);
+        let recipient = normalize::recipient(&recipient).unwrap_or_else(|violation| violation.panic());
+        let now = env::block_timestamp();
+        let finality_lock = if proof_verified {
+            self.inbound_limits.proof_finality_lock.0
+        } else {
+            self.inbound_limits.relayer_finality_lock.0
+        };
+
+        InboundOrder {
+            order_hash,
+            hashlock,
+            amount: U128(amount),
+            recipient,
+            proof_verified,
+            registered_at: U64(now),
+            fillable_at: U64(now + finality_lock),
+            resolver: None,
+            filled_at: None,
+            status: InboundStatus::Registered,
+        }
+    }
+
+    /// Looks up by canonical hash. Orders registered before normalization
+    /// may sit under the hash as the relayer sent it; those are moved to the
+    /// canonical key on first access.
+    fn load_inbound_order(&mut self, order_hash: &str) -> Option<InboundOrder> {
+        let key = normalize::hex_id(order_hash);
+        if let Some(order) = self.inbound_orders.get(&key) {
+            return Some(order);
+        }
+        if key == order_hash {
+            return None;
+        }
+        let mut order = self.inbound_orders.remove(&order_hash.to_string())?;
+        order.order_hash = key;
+        order.hashlock = normalize::hex_id(&order.hashlock);
+        self.inbound_orders.insert(&order.order_hash, &order);
+        Some(order)
+    }
+
+    fn insert_inbound_order(&mut self, order: InboundOrder) {
+        self.inbound_orders.insert(&order.order_hash, &order);
+        env::log_str(&format!(
+            "Inbound order registered: {}, amount {}, recipient {}, proof verified: {}",
+            order.order_hash, order.amount.0, order.recipient, order.proof_verified
+        ));
+    }
+}
+
+// Identifies one log in one receipt
+pub(crate) fn proof_key(proof: &EthProof) -> String {
+    let mut preimage = proof.receipt_data.clone();
+    preimage.extend_from_slice(&proof.header_data);
+    preimage.extend_from_slice(&proof.log_index.to_be_bytes());
+    hex::encode(env::keccak256(&preimage))
+}
+
+// Shadow balances are keyed by a numeric order id; inbound orders use
+// the leading bytes of their hash
+fn inbound_id(order_hash: &str) -> u64 {
+    let bytes = hex::decode(order_hash).unwrap_or_default();
+    bytes.iter().take(8).fold(0u64, |acc, b| (acc << 8) | *b as u64)
+}
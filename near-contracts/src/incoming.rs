@@ -0,0 +1,277 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+use near_sdk::{env, near_bindgen, require, AccountId, Promise};
+
+use crate::ft;
+use crate::limits;
+use crate::normalize;
+use crate::timing;
+use crate::validation;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, Balance, EventLevel, HashAlgorithm};
+
+// Open incoming orders listed per recipient; registering more fails
+pub const MAX_INCOMING_PER_RECIPIENT: usize = 50;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum IncomingStatus {
+    AwaitingDeposit, // Registered without NEAR; funded by ft_transfer_call
+    Funded,          // Claimable by the recipient until timelock_ns
+    Claimed,         // Recipient revealed the secret and was paid
+    Reclaimed,       // Resolver took the deposit back, or cancelled before funding
+}
+
+/// NEAR-side fill of an Ethereum-originated order. A registered resolver
+/// escrows what the recipient receives here, against the hashlock of the
+/// Ethereum order. Unrelated to outgoing orders and to inbound orders,
+/// which are backed by the Ethereum escrow itself.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct IncomingOrder {
+    pub eth_order_ref: String, // Order on Ethereum, as the resolver knows it
+    pub resolver: AccountId,
+    pub recipient: AccountId,
+    pub token: AccountId, // "near" for native deposits
+    pub amount: U128,
+    pub hashlock: String,
+    pub hash_algorithm: HashAlgorithm,
+    pub timelock_ns: U64, // Claims before, reclaims from this timestamp
+    pub registered_at: U64,
+    pub status: IncomingStatus,
+    pub secret: Option<String>, // Revealed by the claim
+    pub settled_at: Option<U64>,
+}
+
+/// `msg` of an ft_transfer_call funding an incoming order.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct IncomingFundingMsg {
+    pub fund_incoming: String, // Hashlock of the order
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    /// Registered resolvers open an incoming order for `recipient`. The
+    /// attached NEAR is the deposit; without one the order waits for the
+    /// resolver to fund it with `ft_transfer_call` and a
+    /// `{"fund_incoming": "<hashlock>"}` message.
+    #[payable]
+    pub fn register_incoming_order(
+        &mut self,
+        eth_order_ref: String,
+        recipient: AccountId,
+        hashlock: String,
+        timelock_ns: U64,
+        hash_algorithm: Option<HashAlgorithm>, // Default Sha256; Keccak256 to match EVM HTLCs
+    ) {
+        self.assert_not_paused();
+        let resolver = env::predecessor_account_id();
+        require!(
+            self.registered_resolvers.contains(&resolver),
+            "Resolver is not registered"
+        );
+        limits::assert_len(eth_order_ref.len(), limits::MAX_ORDER_REF_LEN, limits::ERR_ORDER_REF_TOO_LONG);
+        limits::assert_len(hashlock.len(), limits::MAX_HASHLOCK_LEN, limits::ERR_HASHLOCK_TOO_LONG);
+        let hashlock = normalize::hex_id(&hashlock);
+        require!(
+            hashlock.len() == 64 && hex::decode(&hashlock).is_ok(),
+            "hashlock must be 64 hex characters"
+        );
+        require!(
+            self.incoming_orders.get(&hashlock).is_none(),
+            "Incoming order already registered for this hashlock"
+        );
+        let now = env::block_timestamp();
+        require!(
+            timelock_ns.0 > now
+                && timelock_ns.0 <= timing::timelock_deadline_ns(self.max_timelock_duration.0, now),
+            "timelock_ns must be in the future and within the maximum timelock duration"
+        );
+        require!(recipient != resolver, "Resolver cannot be the recipient");
+        let mut waiting = self.incoming_by_recipient.get(&recipient).unwrap_or_default();
+        require!(
+            waiting.len() < MAX_INCOMING_PER_RECIPIENT,
+            "Recipient has too many open incoming orders"
+        );
+
+        let deposit = env::attached_deposit().as_yoctonear();
+        let order = IncomingOrder {
+            eth_order_ref,
+            resolver,
+            recipient: recipient.clone(),
+            token: ft::NATIVE_TOKEN.parse().unwrap(),
+            amount: U128(deposit),
+            hashlock: hashlock.clone(),
+            hash_algorithm: hash_algorithm.unwrap_or(HashAlgorithm::Sha256),
+            timelock_ns,
+            registered_at: U64(now),
+            status: if deposit > 0 {
+                IncomingStatus::Funded
+            } else {
+                IncomingStatus::AwaitingDeposit
+            },
+            secret: None,
+            settled_at: None,
+        };
+        self.incoming_orders.insert(&hashlock, &order);
+        waiting.push(hashlock);
+        self.incoming_by_recipient.insert(&recipient, &waiting);
+
+        env::log_str(&format!(
+            "Incoming order {} registered by {} for {}",
+            order.hashlock, order.resolver, order.recipient
+        ));
+        self.emit_incoming_event("incoming_order_registered", &order);
+        if order.status == IncomingStatus::Funded {
+            self.emit_incoming_event("incoming_order_funded", &order);
+        }
+    }
+
+    /// Pays the deposit to the recipient with the secret revealed on
+    /// Ethereum. Anyone can submit it; the funds always go to the
+    /// recipient. The secret is published in `incoming_order_claimed`.
+    pub fn claim_incoming(&mut self, hashlock: String, secret: String) -> Promise {
+        self.assert_not_paused();
+        limits::assert_len(hashlock.len(), limits::MAX_HASHLOCK_LEN, limits::ERR_HASHLOCK_TOO_LONG);
+        limits::assert_len(secret.len(), limits::MAX_SECRET_LEN, limits::ERR_SECRET_TOO_LONG);
+        if let Some(violation) = validation::secret_violation(&secret) {
+            violation.panic();
+        }
+        let hashlock = normalize::hex_id(&hashlock);
+        let mut order = self.incoming_orders.get(&hashlock).expect("Incoming order not found");
+        require!(order.status == IncomingStatus::Funded, "Incoming order is not funded");
+        require!(env::block_timestamp() < order.timelock_ns.0, "Incoming order timelock has passed");
+        require!(
+            self.generate_hashlock(&secret, order.hash_algorithm) == hashlock,
+            "Invalid secret"
+        );
+
+        order.secret = Some(secret);
+        self.settle_incoming(&mut order, IncomingStatus::Claimed);
+        env::log_str(&format!(
+            "Incoming order {} claimed, {} {} paid to {}",
+            hashlock, order.amount.0, order.token, order.recipient
+        ));
+        self.emit_incoming_event("incoming_order_claimed", &order);
+        self.ft_payout(order.token.clone(), order.recipient.clone(), order.amount.0)
+    }
+
+    /// Returns the deposit to the resolver once the timelock has passed
+    /// without a claim. Only the resolver can call it.
+    pub fn reclaim_incoming(&mut self, hashlock: String) -> Promise {
+        limits::assert_len(hashlock.len(), limits::MAX_HASHLOCK_LEN, limits::ERR_HASHLOCK_TOO_LONG);
+        let hashlock = normalize::hex_id(&hashlock);
+        let mut order = self.incoming_orders.get(&hashlock).expect("Incoming order not found");
+        require!(
+            env::predecessor_account_id() == order.resolver,
+            "Only the resolver can reclaim this order"
+        );
+        require!(order.status == IncomingStatus::Funded, "Incoming order is not funded");
+        require!(
+            env::block_timestamp() >= order.timelock_ns.0,
+            "Incoming order timelock has not passed"
+        );
+
+        self.settle_incoming(&mut order, IncomingStatus::Reclaimed);
+        env::log_str(&format!(
+            "Incoming order {} reclaimed, {} {} returned to {}",
+            hashlock, order.amount.0, order.token, order.resolver
+        ));
+        self.emit_incoming_event("incoming_order_reclaimed", &order);
+        self.ft_payout(order.token.clone(), order.resolver.clone(), order.amount.0)
+    }
+
+    // Closes an order that was never funded
+    pub fn cancel_incoming_order(&mut self, hashlock: String) {
+        limits::assert_len(hashlock.len(), limits::MAX_HASHLOCK_LEN, limits::ERR_HASHLOCK_TOO_LONG);
+        let hashlock = normalize::hex_id(&hashlock);
+        let mut order = self.incoming_orders.get(&hashlock).expect("Incoming order not found");
+        require!(
+            env::predecessor_account_id() == order.resolver,
+            "Only the resolver can cancel this order"
+        );
+        require!(
+            order.status == IncomingStatus::AwaitingDeposit,
+            "Only orders awaiting their deposit can be cancelled"
+        );
+        self.settle_incoming(&mut order, IncomingStatus::Reclaimed);
+        env::log_str(&format!("Incoming order {} cancelled before funding", hashlock));
+        self.emit_incoming_event("incoming_order_reclaimed", &order);
+    }
+
+    pub fn get_incoming_order(&self, hashlock: String) -> Option<IncomingOrder> {
+        self.incoming_orders.get(&normalize::hex_id(&hashlock))
+    }
+
+    /// Incoming orders of the recipient that are not settled yet, funded
+    /// or still awaiting their deposit.
+    pub fn get_incoming_orders_for(&self, recipient: AccountId) -> Vec<IncomingOrder> {
+        self.incoming_by_recipient
+            .get(&recipient)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|hashlock| self.incoming_orders.get(hashlock))
+            .collect()
+    }
+}
+
+impl AdaptiveCrossChain {
+    /// Funds an order waiting for its deposit with the tokens of an
+    /// ft_transfer_call from its resolver.
+    pub(crate) fn fund_incoming_order(&mut self, sender_id: AccountId, token: AccountId, amount: Balance, hashlock: String) {
+        self.assert_not_paused();
+        limits::assert_len(hashlock.len(), limits::MAX_HASHLOCK_LEN, limits::ERR_HASHLOCK_TOO_LONG);
+        let hashlock = normalize::hex_id(&hashlock);
+        let mut order = self.incoming_orders.get(&hashlock).expect("Incoming order not found");
+        require!(sender_id == order.resolver, "Only the resolver can fund this order");
+        require!(
+            order.status == IncomingStatus::AwaitingDeposit,
+            "Incoming order is already funded or settled"
+        );
+        require!(amount > 0, "Deposit must be positive");
+        require!(env::block_timestamp() < order.timelock_ns.0, "Incoming order timelock has passed");
+
+        order.token = token;
+        order.amount = U128(amount);
+        order.status = IncomingStatus::Funded;
+        self.incoming_orders.insert(&hashlock, &order);
+        env::log_str(&format!(
+            "Incoming order {} funded with {} {}",
+            hashlock, amount, order.token
+        ));
+        self.emit_incoming_event("incoming_order_funded", &order);
+    }
+
+    // Settled orders stay readable by hashlock but leave the recipient's list
+    fn settle_incoming(&mut self, order: &mut IncomingOrder, status: IncomingStatus) {
+        order.status = status;
+        order.settled_at = Some(U64(env::block_timestamp()));
+        self.incoming_orders.insert(&order.hashlock, order);
+        let mut waiting = self.incoming_by_recipient.get(&order.recipient).unwrap_or_default();
+        waiting.retain(|hashlock| *hashlock != order.hashlock);
+        if waiting.is_empty() {
+            self.incoming_by_recipient.remove(&order.recipient);
+        } else {
+            self.incoming_by_recipient.insert(&order.recipient, &waiting);
+        }
+    }
+
+    fn emit_incoming_event(&self, event: &str, order: &IncomingOrder) {
+        self.emit_event(
+            EventLevel::Minimal,
+            event,
+            json!({
+                "hashlock": order.hashlock,
+                "eth_order_ref": order.eth_order_ref,
+                "resolver": order.resolver,
+                "recipient": order.recipient,
+                "token": order.token,
+                "amount": order.amount,
+                "timelock_ns": order.timelock_ns,
+                "secret": order.secret,
+            }),
+        );
+    }
+}
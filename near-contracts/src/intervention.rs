@@ -0,0 +1,291 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U64;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+use near_sdk::{env, near_bindgen, require, AccountId};
+
+use crate::limits;
+use crate::roles::Role;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, CrossChainOrder, EventLevel, OrderStatus, OrderView};
+
+// Delay between an Admin proposing a resolution and the owner executing it
+pub const ADMIN_RESOLUTION_DELAY_NS: u64 = 24 * 3_600_000_000_000;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum AdminResolution {
+    RefundMaker,                      // Unfilled escrow and sponsorship back to the maker
+    PayTaker { receiver: AccountId }, // Unfilled escrow to the receiver, no protocol fee
+    MarkCompletedNoTransfer,          // Settled outside the contract; funds stay put
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StuckFlag {
+    pub reason: String,
+    pub flagged_by: Option<AccountId>, // None when flagged by the contract itself
+    pub flagged_at: U64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingResolution {
+    pub resolution: AdminResolution,
+    pub justification: String,
+    pub proposed_by: AccountId,
+    pub executable_at: U64,
+}
+
+/// Permanent record of one intervention, including the order exactly as it
+/// was before the resolution. Never archived or pruned.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Intervention {
+    pub order_id: u64,
+    pub resolution: AdminResolution,
+    pub justification: String,
+    pub stuck_reason: String,
+    pub proposed_by: AccountId,
+    pub executed_by: AccountId,
+    pub executed_at: U64,
+    pub prior_order: CrossChainOrder,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct InterventionView {
+    pub intervention_id: u64,
+    pub order_id: u64,
+    pub resolution: AdminResolution,
+    pub justification: String,
+    pub stuck_reason: String,
+    pub proposed_by: AccountId,
+    pub executed_by: AccountId,
+    pub executed_at: U64,
+    pub prior_order: OrderView,
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    // The evidence URI (explorer link, incident report) only goes into the event
+    pub fn flag_order_stuck(&mut self, order_id: u64, reason: String, evidence_uri: Option<String>) {
+        self.assert_role(Role::Operator);
+        limits::assert_len(reason.len(), limits::MAX_JUSTIFICATION_LEN, limits::ERR_JUSTIFICATION_TOO_LONG);
+        limits::assert_len(
+            evidence_uri.as_ref().map_or(0, |uri| uri.len()),
+            limits::MAX_EVIDENCE_URI_LEN,
+            limits::ERR_EVIDENCE_URI_TOO_LONG,
+        );
+        let order = self.orders.get(&order_id).expect("Order not found");
+        require!(is_open(&order), "Only open orders can be flagged as stuck");
+        self.flag_stuck(order_id, reason, Some(env::predecessor_account_id()), evidence_uri);
+        self.audit("flag_order_stuck", Some(order_id));
+    }
+
+    // Also drops any pending resolution for the order
+    pub fn clear_stuck_flag(&mut self, order_id: u64) {
+        self.assert_role(Role::Operator);
+        require!(self.stuck_orders.remove(&order_id).is_some(), "Order is not flagged as stuck");
+        self.pending_resolutions.remove(&order_id);
+        self.audit("clear_stuck_flag", Some(order_id));
+        env::log_str(&format!("Order {} is no longer flagged as stuck", order_id));
+    }
+
+    /// First half of an admin resolution. The owner can execute it with
+    /// `admin_resolve_order` once ADMIN_RESOLUTION_DELAY_NS has passed.
+    pub fn propose_admin_resolution(&mut self, order_id: u64, resolution: AdminResolution, justification: String) {
+        self.assert_role(Role::Admin);
+        let proposer = env::predecessor_account_id();
+        require!(proposer != self.owner, "The owner cannot propose resolutions");
+        require!(!justification.trim().is_empty(), "Justification is required");
+        limits::assert_len(justification.len(), limits::MAX_JUSTIFICATION_LEN, limits::ERR_JUSTIFICATION_TOO_LONG);
+        require!(self.stuck_orders.get(&order_id).is_some(), "Order is not flagged as stuck");
+
+        let executable_at = env::block_timestamp() + ADMIN_RESOLUTION_DELAY_NS;
+        self.pending_resolutions.insert(&order_id, &PendingResolution {
+            resolution: resolution.clone(),
+            justification,
+            proposed_by: proposer.clone(),
+            executable_at: U64(executable_at),
+        });
+        self.audit("propose_admin_resolution", Some(order_id));
+        self.emit_event(
+            EventLevel::Minimal,
+            "admin_resolution_proposed",
+            json!({
+                "order_id": order_id,
+                "resolution": resolution,
+                "proposed_by": proposer,
+                "executable_at": U64(executable_at),
+            }),
+        );
+    }
+
+    pub fn cancel_admin_resolution(&mut self, order_id: u64) {
+        let pending = self.pending_resolutions.get(&order_id).expect("No pending resolution");
+        let caller = env::predecessor_account_id();
+        require!(
+            caller == self.owner || caller == pending.proposed_by,
+            "Only the owner or the proposer can cancel"
+        );
+        self.pending_resolutions.remove(&order_id);
+        self.audit("cancel_admin_resolution", Some(order_id));
+        env::log_str(&format!("Admin resolution for order {} cancelled", order_id));
+    }
+
+    /// Resolves a stuck order by force. Executes a resolution an Admin
+    /// proposed at least ADMIN_RESOLUTION_DELAY_NS ago; `resolution` and
+    /// `justification` must repeat the proposal exactly so the owner signs
+    /// off on what they see.
+    pub fn admin_resolve_order(
+        &mut self,
+        order_id: u64,
+        resolution: AdminResolution,
+        justification: String,
+    ) {
+        self.assert_owner();
+        let stuck = self.stuck_orders.get(&order_id).expect("Order is not flagged as stuck");
+        let pending = self.pending_resolutions.get(&order_id).expect("No pending resolution");
+        require!(
+            pending.resolution == resolution && pending.justification == justification,
+            "Resolution does not match the proposal"
+        );
+        require!(
+            env::block_timestamp() >= pending.executable_at.0,
+            "Resolution is still timelocked"
+        );
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        require!(is_open(&order), "Order is no longer open");
+
+        let prior_order = order.clone();
+        let status = match resolution {
+            AdminResolution::RefundMaker => OrderStatus::Cancelled,
+            _ => OrderStatus::Completed,
+        };
+        self.transition_status(&mut order, status);
+        self.save_order(&order);
+        self.stuck_orders.remove(&order_id);
+        self.pending_resolutions.remove(&order_id);
+
+        match &resolution {
+            AdminResolution::RefundMaker => {
+                self.pay_out(&order, order.maker.clone(), order.amount_remaining.0);
+            }
+            AdminResolution::PayTaker { receiver } => {
+                self.record_completed_fill(&order.maker, receiver, order.amount_remaining.0);
+                self.record_pair_class_completed(self.pair_class(order.token_in.as_str(), &order.token_out));
+                self.pay_out(&order, receiver.clone(), order.amount_remaining.0);
+            }
+            AdminResolution::MarkCompletedNoTransfer => {}
+        }
+        if order.gas_sponsorship.0 > 0 && resolution != AdminResolution::MarkCompletedNoTransfer {
+            self.refund_sponsorship(&order);
+        }
+        self.notify_settlement(&order);
+
+        let intervention_id = self.interventions.len();
+        self.interventions.push(&Intervention {
+            order_id,
+            resolution: resolution.clone(),
+            justification: justification.clone(),
+            stuck_reason: stuck.reason,
+            proposed_by: pending.proposed_by.clone(),
+            executed_by: env::predecessor_account_id(),
+            executed_at: U64(env::block_timestamp()),
+            prior_order,
+        });
+        self.order_interventions.insert(&order_id, &intervention_id);
+        self.admin_interventions_total += 1;
+        self.audit("admin_resolve_order", Some(order_id));
+
+        env::log_str(&format!(
+            "ADMIN INTERVENTION #{} on order {}: {}",
+            self.admin_interventions_total,
+            order_id,
+            justification
+        ));
+        self.emit_event(
+            EventLevel::Minimal,
+            "admin_intervention",
+            json!({
+                "intervention_id": intervention_id,
+                "order_id": order_id,
+                "resolution": resolution,
+                "justification": justification,
+                "proposed_by": pending.proposed_by,
+                "executed_by": env::predecessor_account_id(),
+                "admin_interventions_total": self.admin_interventions_total,
+            }),
+        );
+    }
+
+    pub fn get_admin_interventions_total(&self) -> u64 {
+        self.admin_interventions_total
+    }
+
+    pub fn get_intervention(&self, intervention_id: u64) -> Option<InterventionView> {
+        let intervention = self.interventions.get(intervention_id)?;
+        Some(InterventionView {
+            intervention_id,
+            order_id: intervention.order_id,
+            resolution: intervention.resolution,
+            justification: intervention.justification,
+            stuck_reason: intervention.stuck_reason,
+            proposed_by: intervention.proposed_by,
+            executed_by: intervention.executed_by,
+            executed_at: intervention.executed_at,
+            prior_order: self.public_order_view(intervention.prior_order),
+        })
+    }
+
+    pub fn get_order_intervention(&self, order_id: u64) -> Option<InterventionView> {
+        self.get_intervention(self.order_interventions.get(&order_id)?)
+    }
+
+    pub fn get_stuck_orders(&self, from_index: u64, limit: u64) -> Vec<(u64, StuckFlag)> {
+        let limit = limit.min(limits::MAX_BATCH_SIZE as u64);
+        self.stuck_orders
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    pub fn get_pending_resolution(&self, order_id: u64) -> Option<PendingResolution> {
+        self.pending_resolutions.get(&order_id)
+    }
+}
+
+impl AdaptiveCrossChain {
+    /// Marks an open order as eligible for admin resolution. Repeated flags
+    /// keep the first reason.
+    pub(crate) fn flag_stuck(
+        &mut self,
+        order_id: u64,
+        reason: String,
+        flagged_by: Option<AccountId>,
+        evidence_uri: Option<String>,
+    ) {
+        if self.stuck_orders.get(&order_id).is_some() {
+            return;
+        }
+        self.stuck_orders.insert(&order_id, &StuckFlag {
+            reason: reason.clone(),
+            flagged_by: flagged_by.clone(),
+            flagged_at: U64(env::block_timestamp()),
+        });
+        self.emit_event(
+            EventLevel::Minimal,
+            "order_flagged_stuck",
+            json!({
+                "order_id": order_id,
+                "reason": reason,
+                "flagged_by": flagged_by,
+                "evidence_uri": evidence_uri,
+            }),
+        );
+    }
+}
+
+fn is_open(order: &CrossChainOrder) -> bool {
+    matches!(order.status, OrderStatus::Active | OrderStatus::Locked)
+}
@@ -0,0 +1,121 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::json;
+use near_sdk::{env, near_bindgen, require, AccountId, NearToken, Promise};
+
+use crate::limits;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, EventLevel};
+
+// Unless an order opted into public updates at creation, only its maker and
+// the keepers registered here may update its slippage.
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    pub fn add_keeper(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        if self.keepers.insert(&account_id) {
+            self.audit("add_keeper", None);
+            self.emit_event(
+                EventLevel::Standard,
+                "keeper_added",
+                json!({ "account_id": account_id }),
+            );
+        }
+    }
+
+    pub fn remove_keeper(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        if self.keepers.remove(&account_id) {
+            self.audit("remove_keeper", None);
+            self.emit_event(
+                EventLevel::Standard,
+                "keeper_removed",
+                json!({ "account_id": account_id }),
+            );
+        }
+    }
+
+    pub fn is_keeper(&self, account_id: AccountId) -> bool {
+        self.keepers.contains(&account_id)
+    }
+
+    pub fn get_keepers(&self, from_index: u64, limit: u64) -> Vec<AccountId> {
+        let limit = limit.min(limits::MAX_BATCH_SIZE as u64);
+        self.keepers
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .collect()
+    }
+}
+
+// Callers are paid for slippage updates that were due and moved the value.
+// Rewards come out of a pool anyone can fund and, if the owner allows it,
+// out of native protocol fees once the pool runs dry.
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    // 0 turns rewards off
+    pub fn set_keeper_reward(&mut self, reward: U128, from_protocol_fees: bool) {
+        self.assert_owner();
+        env::log_str(&format!(
+            "keeper_reward set from {} to {}, from protocol fees: {}",
+            self.keeper_reward, reward.0, from_protocol_fees
+        ));
+        self.keeper_reward = reward.0;
+        self.keeper_rewards_from_fees = from_protocol_fees;
+        self.audit("set_keeper_reward", None);
+    }
+
+    #[payable]
+    pub fn fund_keeper_rewards(&mut self) -> U128 {
+        let deposit = env::attached_deposit().as_yoctonear();
+        require!(deposit > 0, "Attach the NEAR to add to the pool");
+        self.keeper_reward_pool += deposit;
+        env::log_str(&format!(
+            "{} added {} to the keeper reward pool, now {}",
+            env::predecessor_account_id(),
+            deposit,
+            self.keeper_reward_pool
+        ));
+        U128(self.keeper_reward_pool)
+    }
+
+    pub fn get_keeper_reward_pool(&self) -> U128 {
+        U128(self.keeper_reward_pool)
+    }
+
+    pub fn get_keeper_reward(&self) -> U128 {
+        U128(self.keeper_reward)
+    }
+}
+
+impl AdaptiveCrossChain {
+    /// Pays the keeper of a slippage update. The caller has already checked
+    /// that the update was due and changed the slippage. Nothing is paid
+    /// when neither source can cover the reward.
+    pub(crate) fn pay_keeper_reward(&mut self, order_id: u64, keeper: AccountId) {
+        let reward = self.keeper_reward;
+        if reward == 0 {
+            return;
+        }
+        let source = if self.keeper_reward_pool >= reward {
+            self.keeper_reward_pool -= reward;
+            "pool"
+        } else if self.keeper_rewards_from_fees && self.collected_fees >= reward {
+            self.collected_fees -= reward;
+            "protocol_fees"
+        } else {
+            env::log_str(&format!("Keeper reward for order {} unfunded, not paid", order_id));
+            return;
+        };
+        self.emit_event(
+            EventLevel::Standard,
+            "keeper_rewarded",
+            json!({
+                "order_id": order_id,
+                "keeper": keeper,
+                "reward": U128(reward),
+                "source": source,
+            }),
+        );
+        Promise::new(keeper).transfer(NearToken::from_yoctonear(reward));
+    }
+}
@@ -0,0 +1,221 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId};
+
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, Balance};
+
+pub const LEADERBOARD_SIZE: usize = 20;
+pub const VOLUME_WINDOW_DAYS: u64 = 30;
+const NS_PER_DAY: u64 = 86_400_000_000_000;
+
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct LeaderboardEntry {
+    pub account_id: AccountId,
+    pub metric: Balance,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LeaderboardRow {
+    pub rank: u32,
+    pub account_id: AccountId,
+    pub metric: U128,
+}
+
+/// Completed volume per day, oldest first, covering at most the window.
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct DailyVolume {
+    pub days: Vec<(u64, Balance)>,
+}
+
+impl DailyVolume {
+    fn add(&mut self, today: u64, amount: Balance) {
+        self.prune(today);
+        match self.days.last_mut() {
+            Some((day, volume)) if *day == today => *volume += amount,
+            _ => self.days.push((today, amount)),
+        }
+    }
+
+    fn prune(&mut self, today: u64) {
+        self.days.retain(|(day, _)| day + VOLUME_WINDOW_DAYS > today);
+    }
+
+    fn total(&self, today: u64) -> Balance {
+        self.days
+            .iter()
+            .filter(|(day, _)| day + VOLUME_WINDOW_DAYS > today)
+            .map(|(_, volume)| volume)
+            .sum()
+    }
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    pub fn get_maker_leaderboard(&self) -> Vec<LeaderboardRow> {
+        to_rows(&self.maker_leaderboard.get().unwrap_or_default())
+    }
+
+    pub fn get_resolver_leaderboard(&self) -> Vec<LeaderboardRow> {
+        to_rows(&self.resolver_leaderboard.get().unwrap_or_default())
+    }
+}
+
+impl AdaptiveCrossChain {
+    /// Credits a completed order to its maker and resolver, updating both
+    /// leaderboards incrementally.
+    pub(crate) fn record_completed_fill(
+        &mut self,
+        maker: &AccountId,
+        resolver: &AccountId,
+        amount: Balance,
+    ) {
+        let today = env::block_timestamp() / NS_PER_DAY;
+
+        let mut volume = self.maker_volume.get(maker).unwrap_or_default();
+        volume.add(today, amount);
+        self.maker_volume.insert(maker, &volume);
+
+        // Rolling volumes decay without updates, so refresh the listed
+        // entries before ranking the new value against them.
+        let mut board = self.maker_leaderboard.get().unwrap_or_default();
+        for entry in board.iter_mut() {
+            entry.metric = self
+                .maker_volume
+                .get(&entry.account_id)
+                .map(|v| v.total(today))
+                .unwrap_or(0);
+        }
+        board.sort_by_key(|entry| std::cmp::Reverse(entry.metric));
+        upsert(&mut board, maker, volume.total(today));
+        self.maker_leaderboard.set(&board);
+
+        let fills = self.resolver_fills.get(resolver).unwrap_or(0) + 1;
+        self.resolver_fills.insert(resolver, &fills);
+        let mut board = self.resolver_leaderboard.get().unwrap_or_default();
+        upsert(&mut board, resolver, fills as Balance);
+        self.resolver_leaderboard.set(&board);
+    }
+}
+
+// Moves `account` to its position for `metric`. Ties rank the account that
+// reached the value first higher, so an update never overtakes an equal
+// incumbent. Entries beyond LEADERBOARD_SIZE are evicted; a metric of zero
+// drops the account from the board.
+fn upsert(board: &mut Vec<LeaderboardEntry>, account: &AccountId, metric: Balance) {
+    board.retain(|entry| entry.account_id != *account);
+    if metric > 0 {
+        let position = board
+            .iter()
+            .position(|entry| entry.metric < metric)
+            .unwrap_or(board.len());
+        board.insert(position, LeaderboardEntry {
+            account_id: account.clone(),
+            metric,
+        });
+    }
+    board.retain(|entry| entry.metric > 0);
+    board.truncate(LEADERBOARD_SIZE);
+}
+
+fn to_rows(board: &[LeaderboardEntry]) -> Vec<LeaderboardRow> {
+    board
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| LeaderboardRow {
+            rank: index as u32 + 1,
+            account_id: entry.account_id.clone(),
+            metric: U128(entry.metric),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn account(n: usize) -> AccountId {
+        format!("account{}.near", n).parse().unwrap()
+    }
+
+    fn makers(contract: &AdaptiveCrossChain) -> Vec<(AccountId, Balance)> {
+        contract
+            .get_maker_leaderboard()
+            .into_iter()
+            .map(|row| (row.account_id, row.metric.0))
+            .collect()
+    }
+
+    #[test]
+    fn fills_enter_and_rerank_both_boards() {
+        let mut contract = setup();
+        contract.record_completed_fill(&account(1), &resolver(), 100);
+        contract.record_completed_fill(&account(2), &resolver(), 300);
+        contract.record_completed_fill(&account(3), &account(9), 200);
+        assert_eq!(makers(&contract), vec![(account(2), 300), (account(3), 200), (account(1), 100)]);
+
+        // account1 overtakes both with a second fill
+        contract.record_completed_fill(&account(1), &account(9), 250);
+        assert_eq!(makers(&contract), vec![(account(1), 350), (account(2), 300), (account(3), 200)]);
+        let rows = contract.get_maker_leaderboard();
+        assert_eq!(rows.iter().map(|row| row.rank).collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        let resolvers = contract.get_resolver_leaderboard();
+        assert_eq!(resolvers.len(), 2);
+        assert_eq!((resolvers[0].account_id.clone(), resolvers[0].metric.0), (resolver(), 2));
+        assert_eq!((resolvers[1].rank, resolvers[1].metric.0), (2, 2));
+        assert_eq!(resolvers[1].account_id, account(9));
+    }
+
+    // The incumbent keeps its place on an equal metric
+    #[test]
+    fn ties_rank_the_earlier_account_higher() {
+        let mut contract = setup();
+        contract.record_completed_fill(&account(1), &resolver(), 100);
+        contract.record_completed_fill(&account(2), &resolver(), 100);
+        assert_eq!(makers(&contract), vec![(account(1), 100), (account(2), 100)]);
+        contract.record_completed_fill(&account(2), &resolver(), 0);
+        assert_eq!(makers(&contract)[0].0, account(1));
+    }
+
+    #[test]
+    fn the_tail_is_evicted_at_the_size_limit() {
+        let mut contract = setup();
+        for n in 1..=LEADERBOARD_SIZE {
+            contract.record_completed_fill(&account(n), &resolver(), 10 * n as Balance);
+        }
+        assert_eq!(makers(&contract).len(), LEADERBOARD_SIZE);
+        assert_eq!(makers(&contract).last().unwrap().0, account(1));
+
+        // A newcomer below the tail stays off the board
+        contract.record_completed_fill(&account(100), &resolver(), 5);
+        assert!(!makers(&contract).iter().any(|(id, _)| *id == account(100)));
+
+        // One above it pushes the lowest out
+        contract.record_completed_fill(&account(101), &resolver(), 15);
+        let board = makers(&contract);
+        assert_eq!(board.len(), LEADERBOARD_SIZE);
+        assert!(!board.iter().any(|(id, _)| *id == account(1)));
+        assert_eq!(board.last().unwrap(), &(account(101), 15));
+        assert_eq!(board[0], (account(LEADERBOARD_SIZE), 10 * LEADERBOARD_SIZE as Balance));
+    }
+
+    // Volume older than the window no longer counts, so a maker can
+    // drop below one still trading
+    #[test]
+    fn volume_outside_the_window_decays() {
+        let mut contract = setup();
+        contract.record_completed_fill(&account(1), &resolver(), 500);
+        set_clock(10 * NS_PER_DAY, 0);
+        call(owner(), 0);
+        contract.record_completed_fill(&account(2), &resolver(), 300);
+        assert_eq!(makers(&contract), vec![(account(1), 500), (account(2), 300)]);
+
+        set_clock(VOLUME_WINDOW_DAYS * NS_PER_DAY, 0);
+        call(owner(), 0);
+        contract.record_completed_fill(&account(3), &resolver(), 100);
+        assert_eq!(makers(&contract), vec![(account(2), 300), (account(3), 100)]);
+    }
+}
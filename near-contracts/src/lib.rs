@@ -1,15 +1,122 @@
+// Contract methods take each optional order field as its own JSON argument,
+// and near_bindgen generates wrappers with the same signatures
+#![allow(clippy::too_many_arguments)]
+
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LookupMap, UnorderedMap, Vector};
+use near_sdk::collections::{LazyOption, LookupMap, LookupSet, TreeMap, UnorderedMap, UnorderedSet, Vector};
 use near_sdk::json_types::{U128, U64};
 use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
 use near_sdk::{
-    env, near_bindgen, AccountId, Balance, BlockHeight, Gas, PanicOnDefault, 
-    Promise, PromiseResult, PublicKey, CryptoHash
+    env, near_bindgen, require, AccountId, BlockHeight, Gas, NearToken, PanicOnDefault,
+    Promise, PromiseResult, PublicKey, StorageUsage
+};
+
+mod archive;
+mod audit;
+mod encoding;
+mod escrow_proof;
+mod eth_proof;
+mod events;
+mod exposure;
+mod fees;
+mod ft;
+mod inbound;
+mod incoming;
+mod intervention;
+mod keepers;
+mod leaderboard;
+mod limits;
+mod merkle;
+mod metadata;
+mod migration;
+mod normalize;
+mod onboarding;
+mod oracle;
+mod pairs;
+mod pause;
+mod promotions;
+#[cfg(test)]
+mod properties;
+mod quote;
+mod reconfirm;
+mod refunds;
+mod resolvers;
+mod backtest;
+mod bridge;
+mod chains;
+mod counters;
+mod roles;
+mod shadow;
+mod signed;
+mod sizes;
+mod sponsorship;
+mod stats;
+mod storage;
+#[cfg(test)]
+mod test_utils;
+mod timing;
+mod tokens;
+mod tvl;
+mod validation;
+mod views;
+mod webhooks;
+
+pub use archive::{ArchivedOrder, HealthReport, OrderRecord, RetentionPolicy, TickReport};
+pub use backtest::BacktestPoint;
+pub use audit::AuditEntry;
+pub use bridge::{
+    AckOutcome, AckRecord, BridgeConfig, BridgeKind, BridgeRoute, ChunkedMessage, DecodedField, LaneState, LaneStatus,
+    MessageStatus, PayloadPreview, QueuedMessage,
 };
-use sha2::{Digest, Sha256};
+pub use chains::ChainConfig;
+pub use counters::{StatusCounts, StatusIndex};
+pub use encoding::{BridgeAction, PayloadEncoding};
+pub use escrow_proof::EscrowProofConfig;
+pub use eth_proof::EthProof;
+pub use events::EventLevel;
+pub use exposure::{ExposureLimits, ResolverStats};
+pub use fees::{FeeBreakdown, FeePreview};
+pub use ft::{FtOrderMsg, PendingClaim};
+pub use inbound::{InboundLimits, InboundOrder, InboundStatus};
+pub use incoming::{IncomingFundingMsg, IncomingOrder, IncomingStatus};
+pub use intervention::{AdminResolution, Intervention, InterventionView, PendingResolution, StuckFlag};
+pub use leaderboard::{DailyVolume, LeaderboardEntry, LeaderboardRow};
+pub use limits::InputLimits;
+pub use metadata::OrderMetadata;
+pub use migration::{OrderStore, VersionedOrder};
+pub use onboarding::OnboardReceipt;
+pub use oracle::{OracleConfig, PriceReport, VolatilitySource};
+pub use pairs::{PairClass, PairClassStats, PairConfig, SlippageInputs, SlippageModel};
+pub use promotions::{Promotion, PromotionScope};
+pub use quote::QuoteView;
+pub use refunds::{ExpiryEntry, RefundPriority, RefundQueueEntry, SweepReport};
+pub use resolvers::{ResolverBonding, SlashRecord};
+pub use roles::Role;
+pub use shadow::{ClaimableBalance, EthAddress, ShadowAccountView};
+pub use signed::{OrderPayload, SignedOrderPayload};
+pub use sizes::OrderSizeLimits;
+pub use stats::{ChainVolume, ProtocolStats, ProtocolTotals};
+pub use storage::{StorageStore, StoreReport, StoreUsage};
+pub use timing::{ClaimWindows, DutchAuction, ExpiryStage, OrderPhase, TimelockPreview};
+pub use tokens::TokenOutPolicy;
+pub use validation::{OrderParams, ValidationReport, Violation};
+pub use views::{OrderView, OrdersByIds};
+pub use webhooks::{NotificationStatus, OrderOutcome, SettlementCallback};
+use eth_proof::{decode_log_entry, event_topic, ext_eth_prover, parse_eth_address, u64_topic};
+use storage::StorageCharge;
+
+/// Amounts in yoctoNEAR. near-sdk 5 replaced its alias with NearToken;
+/// state and arguments keep plain u128 and convert at the env boundary.
+pub type Balance = u128;
 
 pub const TGAS: u64 = 1_000_000_000_000;
-pub const GAS_FOR_CROSS_CHAIN_CALL: Gas = Gas(50 * TGAS);
+pub const GAS_FOR_CROSS_CHAIN_CALL: Gas = Gas::from_tgas(50);
+pub const GAS_FOR_PROOF_CALLBACK: Gas = Gas::from_tgas(10);
+// Gas one cancellation can use: the bridge cancel message and its
+// callback, then everything a refund uses
+pub const GAS_PER_CANCEL: u64 =
+    GAS_FOR_CROSS_CHAIN_CALL.as_gas() + bridge::GAS_FOR_BRIDGE_CALLBACK.as_gas() + refunds::GAS_PER_REFUND;
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
@@ -19,17 +126,61 @@ pub struct CrossChainOrder {
     pub token_in: AccountId,      // NEAR token (near, wrap.near, etc.)
     pub token_out: String,        // ETH token address
     pub amount_in: U128,
+    pub amount_remaining: U128,   // Escrow not yet claimed by a resolver
+    pub locked_amount: U128,      // Portion held by the current lock
     pub base_price: U128,         // Base price without slippage
     pub current_slippage: u64,    // Current dynamic slippage in basis points
     pub max_slippage_deviation: u64,
     pub target_chain_id: u64,     // Ethereum = 1, Polygon = 137, etc.
     pub hashlock: String,         // 32-byte hash (hex encoded)
-    pub timelock: U64,           // Block height for timelock
-    pub secret: Option<String>,   // Secret that unlocks the hashlock
+    pub timelock: U64,            // Legacy block-height timelock, 0 once timelock_ns is set
+    pub timelock_ns: U64,         // Deadline as a block timestamp; 0 on legacy orders
+    pub secret: Option<String>,   // Revealed by the claim; None until then
     pub status: OrderStatus,
     pub created_at: U64,
     pub last_slippage_update: U64,
-    pub fill_attempts: u64,
+    pub fill_attempts: u64,       // Locks and registered failed attempts
+    pub last_fill_attempt_at: Option<U64>,
+    pub mirror_confirmed: bool,   // Destination chain acknowledged the order
+    pub fee_waived: bool,         // Treasurer waived protocol fees
+    pub private_economics: bool,  // Hide amount and price until locked
+    pub terminal_at: Option<U64>, // Timestamp of the terminal transition
+    pub bridge_route: u8,         // Route chosen at creation
+    pub taker: Option<AccountId>, // Resolver holding the lock
+    pub locked_at: Option<U64>,   // Timestamp of the lock
+    pub gas_sponsorship: U128,    // Maker-funded relayer fees for the claim
+    pub baseline_slippage: u64,   // Slippage the maker last agreed to
+    pub needs_reconfirmation: bool, // Drifted past the threshold; locks blocked
+    pub skip_reconfirmation: bool,  // Maker opted out at creation
+    pub hash_algorithm: HashAlgorithm, // How the hashlock was derived from the secret
+    pub secret_parts: Option<u32>, // Set when hashlock is the Merkle root of that many secrets
+    pub auction: Option<DutchAuction>, // Price decays from created_at instead of base_price
+    pub locked_price: Option<U128>, // Price snapshot of the current lock
+    pub timelock_extension: U64,    // Seconds the maker has added to timelock
+    pub exclusive_claim_until: Option<U64>, // End of the taker's exclusive claim window
+    pub public_slippage_updates: bool, // Anyone may update slippage, not just the maker and keepers
+    pub valid_until: Option<U64>,   // Quote deadline; past it the order cannot be locked
+    pub memo: Option<String>,       // Maker's own reference; stored and returned, never read
+    pub taker_hashlock: Option<String>, // Second hashlock of dual-hashlock orders; `hashlock` is the maker's
+    pub hashlock_mode: HashlockMode,
+    pub taker_secret: Option<String>,   // Preimage of taker_hashlock, once a claim revealed it
+    pub economics_revealed: bool,       // Set by the first lock; private economics stay public after it
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum HashAlgorithm {
+    Sha256,    // Default
+    Keccak256, // What EVM HTLCs use
+}
+
+/// Which preimages release a dual-hashlock order, as on its EVM escrow.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum HashlockMode {
+    Single, // Default: `hashlock` only
+    Both,   // The maker's and the taker's secret together
+    Either, // Either secret on its own
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
@@ -40,43 +191,240 @@ pub enum OrderStatus {
     Completed,   // Successfully completed
     Expired,     // Timelock expired
     Cancelled,   // Cancelled by maker
+    Claiming,    // Claimed, waiting for the token transfer to the taker
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct SlippageHistory {
     pub timestamp: U64,
-    pub slippage: u64,
+    pub slippage: u64,           // Applied, after smoothing and the deviation clamp
+    pub raw_slippage: u64,       // Model output for this update
+    pub smoothed_slippage: u64,  // Time-weighted average of recent raw values
     pub volatility_score: u64,
+    pub volatility_source: VolatilitySource,
     pub cross_chain_delay: u64,  // Expected bridge delay in seconds
 }
 
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct BridgeMessage {
     pub order_id: u64,
+    pub chain_id: u64,            // Destination chain (outbound lane)
+    pub nonce: u64,               // Lane nonce, stamped at send time
+    pub route_id: u8,             // Bridge route the message went out on
     pub target_contract: String,  // Ethereum contract address
-    pub action: String,          // "create_order", "claim", "cancel"
-    pub data: String,           // Encoded message data
+    pub action: String,          // "create_order", "claim", "cancel", "chunk"
+    pub encoding: PayloadEncoding,
+    pub data: String,           // Encoded message data (hex)
+    pub commitment: String,     // keccak256(encoding id ++ payload), hex
+}
+
+// Per-account creation counter for the current block (fixed size)
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct CreationWindow {
+    pub block_height: BlockHeight,
+    pub count: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContractConfig {
+    pub owner: AccountId,
+    pub ethereum_contract: String, // Fallback counterpart; chains carry their own destination_contract
+    pub bridge_routes: Vec<BridgeRoute>,
+    pub eth_prover: Option<AccountId>,
+    pub escrow_proof: EscrowProofConfig,
+    pub oracle: OracleConfig,
+    pub slippage_update_interval: U64,
+    pub smoothing_window: u64,
+    pub max_history_entries: u64,
+    pub max_slippage_change: u64,
+    pub fill_attempt_limit: u64,
+    pub max_bridge_retries: u32,
+    pub default_timelock_duration: U64,
+    pub claim_windows: ClaimWindows,
+    pub min_timelock_duration: U64,
+    pub max_timelock_duration: U64,
+    pub allow_unconfirmed_locks: bool,
+    pub reconfirm_threshold_bps: u64,
+    pub token_out_policy: TokenOutPolicy,
+    pub require_token_whitelist: bool,
+    pub protocol_fee_bps: u64,
+    pub keeper_reward: U128,
+    pub keeper_rewards_from_fees: bool,
+    pub sweep_bounty: U128,
+    pub sweep_bounty_from_refunds: bool,
+    pub max_creates_per_block_per_account: u32,
+    pub max_open_orders_per_user: u64,
+    pub input_limits: InputLimits,
+    pub retention_policy: RetentionPolicy,
+    pub peg_break_threshold_bps: u64,
+    pub event_verbosity: EventLevel,
+    pub inbound_limits: InboundLimits,
+    pub exposure_limits: ExposureLimits,
+    pub max_total_value_locked: Option<U128>,
+    pub paused: bool,
 }
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct AdaptiveCrossChain {
-    pub orders: UnorderedMap<u64, CrossChainOrder>,
+    pub orders: OrderStore,
     pub user_orders: LookupMap<AccountId, Vector<u64>>,
     pub hashlock_to_order: LookupMap<String, u64>,
-    pub slippage_history: LookupMap<u64, Vector<SlippageHistory>>,
+    pub used_secret_indices: LookupMap<u64, Vec<u32>>, // Merkle leaves claimed per order
+    pub slippage_history: LookupMap<u64, Vector<SlippageHistory>>, // See storage::History
+    pub history_heads: LookupMap<u64, u64>, // Ring head of each wrapped history
     pub next_order_id: u64,
     pub owner: AccountId,
-    pub ethereum_contract: String,  // Ethereum contract address
-    pub bridge_contract: AccountId, // Rainbow Bridge contract
+    pub pending_owner: Option<AccountId>, // Proposed owner until they accept
+    pub paused: bool,               // Circuit breaker; see pause.rs
+    pub ethereum_contract: String,  // Init counterpart, for orders whose chain has no destination_contract
+    pub bridges: UnorderedMap<u8, BridgeConfig>, // By route id
+    pub default_routes: LookupMap<u64, u8>,      // Per target chain
+    pub chains: UnorderedMap<u64, ChainConfig>,  // Chains orders can target
+    pub eth_prover: Option<AccountId>, // Rainbow Bridge EthProver
+    pub escrow_proof: EscrowProofConfig,
+    pub consumed_escrow_proofs: LookupSet<String>, // Escrow logs that backed a lock
+    pub oracle: OracleConfig,          // Price feed for volatility
     
     // Protocol parameters
+    pub smoothing_window: u64,          // Raw calculations averaged per update, 1 = none
+    pub max_history_entries: u64,       // Slippage history kept per order
     pub slippage_update_interval: U64,  // 5 minutes in nanoseconds
     pub max_slippage_change: u64,       // 100 basis points (1%)
-    pub fill_attempt_limit: u64,        // 10 attempts
-    pub default_timelock_duration: U64, // 24 hours in blocks
+    pub fill_attempt_limit: u64,        // 10 attempts, then the order expires
+    pub fill_attempt_reporters: LookupSet<(u64, AccountId)>, // Resolvers that registered an attempt per order
+    pub default_timelock_duration: U64, // 24 hours, in seconds
+    pub claim_windows: ClaimWindows,
+    pub min_timelock_duration: U64,     // Bounds on a maker's timelock_duration, in seconds
+    pub max_timelock_duration: U64,
+    pub allow_unconfirmed_locks: bool,  // Permissionless mode: skip mirror check
+    pub reconfirm_threshold_bps: u64,   // Slippage drift that pauses an order, 0 = off
+    pub awaiting_reconfirmation: UnorderedSet<u64>,
+    pub token_out_policy: TokenOutPolicy,
+    pub token_out_denylist: UnorderedSet<EthAddress>, // Iterable for listing
+    pub token_out_allowlist: UnorderedSet<EthAddress>,
+    pub supported_tokens: LookupMap<u64, Vec<EthAddress>>, // Per target chain
+    pub require_token_whitelist: bool,                     // Off: any token_out the policy allows
+    pub max_creates_per_block_per_account: u32, // 3 orders per block
+    pub max_open_orders_per_user: u64,          // Active and Locked orders per maker
+    pub creation_windows: LookupMap<AccountId, CreationWindow>,
+    pub payload_encodings: LookupMap<u64, PayloadEncoding>, // Per target chain, default Abi
+    pub outbound_lanes: LookupMap<u64, LaneState>,          // Per target chain
+    pub outbox: UnorderedMap<u64, QueuedMessage>,           // Message id -> awaiting ack
+    pub next_message_id: u64,
+    pub max_bridge_retries: u32,                            // Re-sends of a failed hand-off
+    pub acked_messages: LookupMap<(u64, u64), AckRecord>,   // Terminal ack per (chain_id, nonce)
+    pub acked_message_lanes: LookupMap<u64, (u64, u64)>,     // Message id -> (chain_id, nonce), once acked
+    pub executed_bridge_nonces: LookupSet<(u64, u64)>,      // (source chain_id, nonce) of inbound instructions
+    pub chunked_messages: LookupMap<(u64, u64), ChunkedMessage>, // (chain_id, message id) of split payloads
+    pub settlement_callbacks: LookupMap<u64, SettlementCallback>,
+    pub order_metadata: LookupMap<u64, OrderMetadata>,
+    pub audit_log: Vector<AuditEntry>,
+    pub event_verbosity: EventLevel,
+
+    // Forced resolution of stuck orders
+    pub stuck_orders: UnorderedMap<u64, StuckFlag>,
+    pub pending_resolutions: LookupMap<u64, PendingResolution>,
+    pub interventions: Vector<Intervention>,
+    pub order_interventions: LookupMap<u64, u64>,           // order_id -> index in interventions
+    pub admin_interventions_total: u64,
+
+    // Pair registry
+    pub pairs: LookupMap<String, PairConfig>,               // "token_in:token_out"
+    pub slippage_models: LookupMap<PairClass, SlippageModel>, // Overrides of the class defaults
+    pub pair_class_stats: LookupMap<PairClass, PairClassStats>,
+    pub peg_break_threshold_bps: u64,                       // Pegged pairs suspend beyond this
+
+    // Expiry refunds
+    pub expiry_index: TreeMap<u64, Vec<ExpiryEntry>>, // Timelock bucket -> open orders
+    pub refund_priority: RefundPriority,
+
+    // Retention and archival
+    pub retention_policy: RetentionPolicy,
+    pub terminal_index: TreeMap<(u8, u64), Vec<u64>>, // (status, hour bucket) -> order ids
+    pub terminal_indexed_count: u64,
+    pub archived_orders: LookupMap<u64, ArchivedOrder>,
+    pub archived_count: u64,
+    pub storage_refunds: LookupMap<AccountId, Balance>,
+    pub order_storage_deposits: LookupMap<u64, Balance>, // Paid by the maker, refunded on archival
+    pub last_order_storage_bytes: StorageUsage, // Written by the latest native order, for estimates
+
+    // Claimable payouts for Ethereum recipients without a NEAR account
+    pub shadow_balances: LookupMap<EthAddress, Vec<ClaimableBalance>>,
+    pub shadow_nonces: LookupMap<EthAddress, u64>,
+
+    // Ethereum -> NEAR orders, keyed by the Ethereum order hash (hex)
+    pub inbound_orders: LookupMap<String, InboundOrder>,
+    pub consumed_inbound_proofs: LookupSet<String>,
+    pub inbound_limits: InboundLimits,
+
+    // Resolver-funded NEAR side of Ethereum-originated orders, by hashlock
+    pub incoming_orders: LookupMap<String, IncomingOrder>,
+    pub incoming_by_recipient: LookupMap<AccountId, Vec<String>>, // Hashlocks of unsettled orders
+    pub order_signing_keys: LookupMap<AccountId, PublicKey>, // Keys accepted by create_order_for
+    pub order_nonces: LookupMap<AccountId, u64>, // Last nonce each maker signed with
+    pub order_balances: LookupMap<AccountId, Balance>, // NEAR pre-deposited for signed orders
+
+    // NEP-141 tokens accepted as token_in
+    pub supported_fts: UnorderedSet<AccountId>,
+    pub order_size_limits: LookupMap<AccountId, OrderSizeLimits>, // By token_in, "near" for native
+    pub failed_ft_payouts: LookupMap<(AccountId, AccountId), Balance>, // (token, receiver)
+
+    // Fees and access control
+    pub protocol_fee_bps: u64,
+    pub collected_fees: Balance,
+    pub collected_bridge_fees: Balance,
+    pub keepers: UnorderedSet<AccountId>, // May update slippage of any order
+    pub keeper_reward: Balance,          // Per paid slippage update, 0 = off
+    pub keeper_reward_pool: Balance,
+    pub keeper_rewards_from_fees: bool,  // Fall back to collected_fees when the pool is short
+    pub sweep_bounty: Balance,           // Per order refunded by sweep_expired_orders, 0 = off
+    pub sweep_bounty_from_refunds: bool, // Fall back to native refunds when the pool is short
+    pub collected_ft_fees: LookupMap<AccountId, Balance>,   // Protocol fees of NEP-141 orders, per token
+    pub fee_exempt: LookupSet<AccountId>,
+    pub promotions: Vector<Promotion>,
+    pub next_promotion_id: u64,
+    pub roles: LookupMap<AccountId, Vec<Role>>,
+
+    // Resolver counterparty risk
+    pub resolver_exposure: LookupMap<AccountId, Balance>, // Over Locked orders
+    pub exposure_limits: ExposureLimits,
+    pub exposure_overrides: LookupMap<AccountId, Balance>,
+
+    // Resolver registry
+    pub registered_resolvers: UnorderedSet<AccountId>,
+    pub permissioned_mode: bool, // Only registered resolvers lock and claim
+    pub resolver_stakes: LookupMap<AccountId, Balance>,
+    pub resolver_bonding: ResolverBonding,
+    pub slash_history: Vector<SlashRecord>,
+
+    // Storage accounting
+    pub store_usage: StoreUsage,
+    pub storage_budgets: LookupMap<StorageStore, StorageUsage>,
+
+    // Statistics
+    pub status_counts: StatusCounts,
+    pub status_index: StatusIndex,
+    pub open_book_value: Balance, // amount_remaining over Active and Locked orders
+    pub total_value_locked: Balance, // The same over native orders only
+    pub max_total_value_locked: Option<Balance>, // None = uncapped
+    pub user_history_counts: LookupMap<AccountId, u64>,
+    pub user_open_counts: LookupMap<AccountId, u64>, // Active and Locked orders
+    pub chain_active_counts: LookupMap<u64, u64>,
+    pub token_out_active_counts: LookupMap<String, u64>, // Lowercased address
+    pub pending_bridge_messages: u64,
+    pub dead_letter_count: u64,
+    pub maker_volume: LookupMap<AccountId, DailyVolume>,
+    pub resolver_fills: LookupMap<AccountId, u64>,
+    pub protocol_totals: ProtocolTotals,
+    pub chain_volumes: LookupMap<u64, Balance>, // Native volume in, per configured chain
+    pub maker_leaderboard: LazyOption<Vec<LeaderboardEntry>>,
+    pub resolver_leaderboard: LazyOption<Vec<LeaderboardEntry>>,
+
+    pub state_version: u32, // Layout version; see migration.rs
 }
 
 #[near_bindgen]
@@ -86,19 +434,161 @@ impl AdaptiveCrossChain {
         ethereum_contract: String,
         bridge_contract: AccountId,
     ) -> Self {
+        // Route 0 is the bridge given at init and serves every chain
+        let mut bridges = UnorderedMap::new(b"B");
+        bridges.insert(&0, &BridgeConfig {
+            account: bridge_contract,
+            kind: BridgeKind::LightClient,
+            fee: U128(0),
+            delay: U64(900),
+            enabled: true,
+            chain_ids: vec![],
+            max_payload_bytes: None,
+        });
+
+        // The chains served before the registry existed, with the Major
+        // pair premiums they were charged. The init counterpart is the
+        // Ethereum escrow; Polygon takes orders once set_destination_contract
+        // gives it its own
+        let mut chains = UnorderedMap::new(b"3");
+        for (chain_id, name, premium) in [(1, "Ethereum", 25), (137, "Polygon", 50)] {
+            chains.insert(&chain_id, &ChainConfig {
+                chain_id,
+                name: name.to_string(),
+                slippage_premium_bps: premium,
+                bridge_delay_seconds: 900,
+                enabled: true,
+                destination_contract: (chain_id == 1).then(|| ethereum_contract.clone()),
+            });
+        }
+
         Self {
-            orders: UnorderedMap::new(b"o"),
-            user_orders: LookupMap::new(b"u"),
+            orders: OrderStore::new(b"o"),
+            user_orders: LookupMap::new(storage::StorageKey::UserOrderLists),
             hashlock_to_order: LookupMap::new(b"h"),
+            used_secret_indices: LookupMap::new(b"z"),
             slippage_history: LookupMap::new(b"s"),
+            history_heads: LookupMap::new(storage::StorageKey::HistoryHeads),
             next_order_id: 1,
             owner: env::predecessor_account_id(),
-            ethereum_contract,
-            bridge_contract,
+            pending_owner: None,
+            paused: false,
+            ethereum_contract: ethereum_contract.clone(),
+            bridges,
+            default_routes: LookupMap::new(b"D"),
+            chains,
+            eth_prover: None,
+            escrow_proof: EscrowProofConfig::default(),
+            consumed_escrow_proofs: LookupSet::new(b"8"),
+            oracle: OracleConfig::default(),
             slippage_update_interval: U64(300_000_000_000), // 5 minutes
+            smoothing_window: 1,
+            max_history_entries: storage::DEFAULT_MAX_HISTORY_ENTRIES,
             max_slippage_change: 100,
             fill_attempt_limit: 10,
-            default_timelock_duration: U64(17280), // ~24 hours (assuming 5s blocks)
+            fill_attempt_reporters: LookupSet::new(b"9"),
+            default_timelock_duration: U64(86_400), // 24 hours
+            claim_windows: ClaimWindows::default(),
+            min_timelock_duration: U64(timing::MIN_TIMELOCK_DURATION_SECS),
+            max_timelock_duration: U64(timing::MAX_TIMELOCK_DURATION_SECS),
+            allow_unconfirmed_locks: false,
+            reconfirm_threshold_bps: 0,
+            awaiting_reconfirmation: UnorderedSet::new(b"N"),
+            token_out_policy: TokenOutPolicy::Open,
+            token_out_denylist: UnorderedSet::new(b"K"),
+            token_out_allowlist: UnorderedSet::new(b"L"),
+            supported_tokens: LookupMap::new(b"4"),
+            require_token_whitelist: false,
+            max_creates_per_block_per_account: 3,
+            max_open_orders_per_user: counters::DEFAULT_MAX_OPEN_ORDERS_PER_USER,
+            creation_windows: LookupMap::new(b"c"),
+            payload_encodings: LookupMap::new(b"e"),
+            outbound_lanes: LookupMap::new(b"l"),
+            outbox: UnorderedMap::new(b"x"),
+            next_message_id: 0,
+            max_bridge_retries: 5,
+            acked_messages: LookupMap::new(b"Y"),
+            acked_message_lanes: LookupMap::new(b"7"),
+            executed_bridge_nonces: LookupSet::new(b"6"),
+            chunked_messages: LookupMap::new(b"X"),
+            settlement_callbacks: LookupMap::new(b"w"),
+            order_metadata: LookupMap::new(b"M"),
+            audit_log: Vector::new(b"A"),
+            event_verbosity: EventLevel::Standard,
+            stuck_orders: UnorderedMap::new(b"G"),
+            pending_resolutions: LookupMap::new(b"H"),
+            interventions: Vector::new(b"I"),
+            order_interventions: LookupMap::new(b"J"),
+            admin_interventions_total: 0,
+            pairs: LookupMap::new(b"p"),
+            slippage_models: LookupMap::new(b"k"),
+            pair_class_stats: LookupMap::new(b"y"),
+            peg_break_threshold_bps: 50,
+            expiry_index: TreeMap::new(b"E"),
+            refund_priority: RefundPriority::OldestExpiryFirst,
+            retention_policy: RetentionPolicy::default(),
+            terminal_index: TreeMap::new(b"t"),
+            terminal_indexed_count: 0,
+            archived_orders: LookupMap::new(b"a"),
+            archived_count: 0,
+            storage_refunds: LookupMap::new(b"g"),
+            order_storage_deposits: LookupMap::new(b"S"),
+            last_order_storage_bytes: storage::DEFAULT_ORDER_STORAGE_BYTES,
+            shadow_balances: LookupMap::new(b"b"),
+            shadow_nonces: LookupMap::new(b"d"),
+            inbound_orders: LookupMap::new(b"i"),
+            consumed_inbound_proofs: LookupSet::new(b"j"),
+            inbound_limits: InboundLimits::default(),
+            incoming_orders: LookupMap::new(storage::StorageKey::IncomingOrders),
+            incoming_by_recipient: LookupMap::new(storage::StorageKey::IncomingByRecipient),
+            order_signing_keys: LookupMap::new(storage::StorageKey::OrderSigningKeys),
+            order_nonces: LookupMap::new(storage::StorageKey::OrderNonces),
+            order_balances: LookupMap::new(storage::StorageKey::OrderBalances),
+            supported_fts: UnorderedSet::new(b"F"),
+            order_size_limits: LookupMap::new(storage::StorageKey::OrderSizeLimits),
+            failed_ft_payouts: LookupMap::new(b"Q"),
+            protocol_fee_bps: 0,
+            collected_fees: 0,
+            collected_bridge_fees: 0,
+            keepers: UnorderedSet::new(b"5"),
+            keeper_reward: 0,
+            keeper_reward_pool: 0,
+            keeper_rewards_from_fees: false,
+            sweep_bounty: 0,
+            sweep_bounty_from_refunds: false,
+            collected_ft_fees: LookupMap::new(b"V"),
+            fee_exempt: LookupSet::new(b"f"),
+            promotions: Vector::new(b"P"),
+            next_promotion_id: 0,
+            roles: LookupMap::new(b"r"),
+            resolver_exposure: LookupMap::new(b"R"),
+            exposure_limits: ExposureLimits::default(),
+            exposure_overrides: LookupMap::new(b"O"),
+            registered_resolvers: UnorderedSet::new(b"0"),
+            permissioned_mode: false,
+            resolver_stakes: LookupMap::new(b"1"),
+            resolver_bonding: ResolverBonding::default(),
+            slash_history: Vector::new(b"2"),
+            store_usage: StoreUsage::default(),
+            storage_budgets: LookupMap::new(b"Z"),
+            status_counts: StatusCounts::default(),
+            status_index: StatusIndex::new(),
+            open_book_value: 0,
+            total_value_locked: 0,
+            max_total_value_locked: None,
+            user_history_counts: LookupMap::new(b"U"),
+            user_open_counts: LookupMap::new(storage::StorageKey::UserOpenCounts),
+            chain_active_counts: LookupMap::new(b"C"),
+            token_out_active_counts: LookupMap::new(b"T"),
+            pending_bridge_messages: 0,
+            dead_letter_count: 0,
+            maker_volume: LookupMap::new(b"v"),
+            resolver_fills: LookupMap::new(b"n"),
+            protocol_totals: ProtocolTotals::default(),
+            chain_volumes: LookupMap::new(storage::StorageKey::ChainVolumes),
+            maker_leaderboard: LazyOption::new(b"m", None),
+            resolver_leaderboard: LazyOption::new(b"q", None),
+            state_version: migration::STATE_VERSION,
         }
     }
 
@@ -107,247 +597,1303 @@ impl AdaptiveCrossChain {
         &mut self,
         token_out: String,           // Ethereum token address
         base_price: U128,
-        max_slippage_deviation: u64,
+        max_slippage_deviation: Option<u64>, // Defaults per pair class
         target_chain_id: u64,
-        secret: String,              // Secret for hashlock
+        hashlock: String,            // Hex hash of the maker's secret
+        private_economics: Option<bool>,
+        settlement_callback: Option<SettlementCallback>, // Notified on terminal state
+        bridge_route: Option<u8>,    // Defaults per target chain
+        gas_sponsorship: Option<U128>, // Escrowed out of the deposit for relayed claims
+        skip_reconfirmation: Option<bool>, // Never pause the order for slippage drift
+        hash_algorithm: Option<HashAlgorithm>, // Keccak256 to match EVM HTLCs, default Sha256
+        secret_parts: Option<u32>,   // Fill in parts, each claimed with its own secret
+        auction: Option<DutchAuction>, // Dutch auction instead of a static base_price
+        timelock_duration: Option<U64>, // In seconds, overrides default_timelock_duration
+        allow_public_slippage_updates: Option<bool>, // Let anyone update slippage, not just the maker and keepers
+        validity_duration: Option<U64>, // In seconds; refundable early if nobody locks it by then
+        amount_in: Option<U128>,     // Exact deposit; storage is paid on top and the excess refunded
+        memo: Option<String>,        // Reference for the maker's records, up to 256 bytes
+        taker_hashlock: Option<String>, // Second hashlock for symmetric swaps
+        hashlock_mode: Option<HashlockMode>, // Both by default when taker_hashlock is set
     ) -> u64 {
-        let deposit = env::attached_deposit();
-        require!(deposit > 0, "Must attach NEAR tokens");
-        
+        let attached = env::attached_deposit().as_yoctonear();
+        let storage = match amount_in {
+            Some(_) => StorageCharge::Attached(attached),
+            None => StorageCharge::FromEscrow,
+        };
+        let params = OrderParams {
+            token_out,
+            amount_in: amount_in.unwrap_or(U128(attached)),
+            base_price,
+            max_slippage_deviation,
+            target_chain_id,
+            hashlock,
+            hash_algorithm,
+            secret_parts,
+            private_economics,
+            settlement_callback,
+            bridge_route,
+            gas_sponsorship,
+            skip_reconfirmation,
+            auction,
+            timelock_duration,
+            allow_public_slippage_updates,
+            validity_duration,
+            memo,
+            taker_hashlock,
+            hashlock_mode,
+        };
+        self.internal_create_order(env::predecessor_account_id(), ft::NATIVE_TOKEN.parse().unwrap(), params, storage)
+    }
+
+    /// Creates several native orders in one call. The attached deposit must
+    /// equal the sum of their `amount_in`, and each order pays its storage
+    /// out of its own escrow. The whole batch counts against
+    /// max_creates_per_block_per_account.
+    #[payable]
+    pub fn create_cross_chain_orders(&mut self, orders: Vec<OrderParams>) -> Vec<u64> {
+        limits::assert_len(orders.len(), limits::MAX_BATCH_SIZE, limits::ERR_BATCH_TOO_LARGE);
+        require!(!orders.is_empty(), "No orders to create");
+        let total = orders
+            .iter()
+            .try_fold(0u128, |total, params| total.checked_add(params.amount_in.0))
+            .expect("Order amounts overflow");
+        require!(
+            env::attached_deposit().as_yoctonear() == total,
+            "Attached deposit must equal the sum of amount_in"
+        );
+
+        // Rejected before any order spends gas on its bridge message
         let maker = env::predecessor_account_id();
+        if let Some(violation) = self.creation_rate_violation(&maker, orders.len() as u32) {
+            violation.panic();
+        }
+        let order_ids: Vec<u64> = orders
+            .into_iter()
+            .map(|params| {
+                self.create_unmetered_order(
+                    maker.clone(),
+                    ft::NATIVE_TOKEN.parse().unwrap(),
+                    params,
+                    StorageCharge::FromEscrow,
+                )
+            })
+            .collect();
+        self.record_order_creations(&maker, order_ids.len() as u32);
+        order_ids
+    }
+
+    /// Called by a resolver once it has escrowed the matching funds on the
+    /// destination chain. Only the resolver holding the lock can claim.
+    /// Locks everything that is left of the order.
+    pub fn lock_order(&mut self, order_id: u64) {
+        self.assert_lock_without_proof_allowed();
+        self.internal_lock(env::predecessor_account_id(), order_id, None);
+    }
+
+    /// Locks `amount` out of what is left of the order. The claim pays out
+    /// that portion and reopens the order for the rest.
+    pub fn lock_partial(&mut self, order_id: u64, amount: U128) {
+        require!(amount.0 > 0, "Fill amount must be positive");
+        self.assert_lock_without_proof_allowed();
+        self.internal_lock(env::predecessor_account_id(), order_id, Some(amount.0));
+    }
+
+    /// Maker backs out of an order nobody has locked yet. The escrow and
+    /// gas sponsorship are returned and the hashlock can no longer be claimed.
+    pub fn cancel_order(&mut self, order_id: u64) -> Promise {
+        let order = self.orders.get(&order_id).expect("Order not found");
+        require!(
+            env::predecessor_account_id() == order.maker,
+            "Only the maker can cancel this order"
+        );
+        match order.status {
+            OrderStatus::Active => {}
+            OrderStatus::Locked | OrderStatus::Claiming => {
+                env::panic_str("Order is locked by a resolver and can no longer be cancelled")
+            }
+            _ => env::panic_str("Order is already completed, cancelled or expired"),
+        }
+        self.internal_cancel(order)
+    }
+
+    /// Cancels up to `limit` (max 100) of the caller's Active orders,
+    /// oldest first, and refunds each one. Locked orders are skipped, and
+    /// the batch stops early when gas runs short, so calling again picks
+    /// up what is left. Returns the ids that were cancelled.
+    pub fn cancel_all_orders(&mut self, limit: u64) -> Vec<u64> {
+        let maker = env::predecessor_account_id();
+        let limit = limit.min(limits::MAX_BATCH_SIZE as u64) as usize;
+        let order_ids = self.user_order_list(&maker);
+        let mut cancelled = Vec::new();
+        for order_id in order_ids.iter() {
+            if cancelled.len() >= limit || env::prepaid_gas().as_gas() - env::used_gas().as_gas() < GAS_PER_CANCEL {
+                break;
+            }
+            match self.orders.get(&order_id) {
+                Some(order) if matches!(order.status, OrderStatus::Active) => {
+                    self.internal_cancel(order);
+                    cancelled.push(order_id);
+                }
+                _ => continue,
+            }
+        }
+        env::log_str(&format!("{} cancelled {} orders", maker, cancelled.len()));
+        cancelled
+    }
+
+    /// `receiver_id` lets the taker have the payout sent elsewhere, e.g.
+    /// from a hot key to its treasury. Everything else about the claim is
+    /// the same.
+    pub fn claim_with_secret(&mut self, hashlock: String, secret: String, receiver_id: Option<AccountId>) -> Promise {
+        self.settle_claim(hashlock, secret, env::predecessor_account_id(), receiver_id, 0)
+    }
+
+    /// Claims a dual-hashlock order with the preimages of both its
+    /// hashlocks: `secret_a` for the maker's, `secret_b` for the taker's.
+    /// `hashlock` can be either of them. `Either` orders can also be
+    /// claimed with one secret through `claim_with_secret`.
+    pub fn claim_with_secrets(
+        &mut self,
+        hashlock: String,
+        secret_a: String,
+        secret_b: String,
+        receiver_id: Option<AccountId>,
+    ) -> Promise {
+        limits::assert_len(hashlock.len(), limits::MAX_HASHLOCK_LEN, limits::ERR_HASHLOCK_TOO_LONG);
+        let order_id = self
+            .hashlock_to_order
+            .get(&normalize::hex_id(&hashlock))
+            .expect("Order not found");
+        let order = self.orders.get(&order_id).expect("Order not found");
+        let taker_hashlock = order
+            .taker_hashlock
+            .clone()
+            .unwrap_or_else(|| env::panic_str("Order has a single hashlock; claim with claim_with_secret"));
+        for (secret, expected) in [(&secret_a, &order.hashlock), (&secret_b, &taker_hashlock)] {
+            self.assert_secret_matches(&order, secret, expected);
+        }
+        self.settle_whole_lock(order, Some(secret_a), Some(secret_b), env::predecessor_account_id(), receiver_id, 0)
+    }
+
+    /// Updates with the static volatility score. Use
+    /// `request_slippage_update` to price volatility from the oracle.
+    pub fn update_order_slippage(&mut self, order_id: u64) {
+        self.assert_not_paused();
+        let order = self.orders.get(&order_id).expect("Order not found");
+        let keeper = env::predecessor_account_id();
+        if let Some(reason) = self.slippage_update_refusal(&order, &keeper) {
+            env::panic_str(reason);
+        }
+        let volatility_score = self.calculate_volatility_score(&order.token_out);
+        self.apply_slippage_update(order, keeper, volatility_score, VolatilitySource::Static);
+    }
+
+    /// Reprices an Active, unlocked order in place, keeping its id and
+    /// hashlock. The slippage stays as it is; the update interval restarts
+    /// and the history records a `repriced` entry. Auction orders are
+    /// priced by their auction and cannot be repriced.
+    pub fn update_order_price(
+        &mut self,
+        order_id: u64,
+        new_base_price: U128,
+        new_max_slippage_deviation: Option<u64>,
+    ) {
+        self.assert_not_paused();
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        require!(
+            env::predecessor_account_id() == order.maker,
+            "Only the maker can reprice this order"
+        );
+        require!(
+            matches!(order.status, OrderStatus::Active) && order.locked_amount.0 == 0,
+            "Only Active, unlocked orders can be repriced"
+        );
+        require!(
+            timing::current_expiry_stage(&order) == ExpiryStage::BeforeTimelock,
+            "Order expired"
+        );
+        require!(order.auction.is_none(), "Auction orders cannot be repriced");
+        require!(new_base_price.0 > 0, "Base price must be positive");
+
+        let old_base_price = order.base_price;
+        let old_max_slippage_deviation = order.max_slippage_deviation;
+        order.base_price = new_base_price;
+        if let Some(deviation) = new_max_slippage_deviation {
+            order.max_slippage_deviation = deviation;
+        }
+        order.last_slippage_update = U64(env::block_timestamp());
+        self.save_order(&order);
+
+        self.record_slippage(order_id, &SlippageHistory {
+            timestamp: U64(env::block_timestamp()),
+            slippage: order.current_slippage,
+            raw_slippage: order.current_slippage,
+            smoothed_slippage: order.current_slippage,
+            volatility_score: self.calculate_volatility_score(&order.token_out),
+            volatility_source: VolatilitySource::Repriced,
+            cross_chain_delay: self.estimate_bridge_delay(order.bridge_route, order.target_chain_id),
+        });
+
+        self.send_bridge_message(self.build_bridge_message(&order, BridgeAction::Reprice.as_str()));
+
+        env::log_str(&format!("Order {} repriced", order_id));
+        // Private orders keep their prices out of the event until locked
+        let hidden = views::economics_hidden(&order);
+        self.emit_order_event(
+            EventLevel::Standard,
+            "order_repriced",
+            &order,
+            json!({
+                "old_base_price": if hidden { None } else { Some(old_base_price) },
+                "new_base_price": if hidden { None } else { Some(order.base_price) },
+                "old_max_slippage_deviation": old_max_slippage_deviation,
+                "new_max_slippage_deviation": order.max_slippage_deviation,
+            }),
+        );
+    }
+
+    // Trust-minimized alternative to the bridge ack: prove the Ethereum
+    // contract emitted OrderCreated for this order.
+    pub fn confirm_mirror_with_proof(&mut self, order_id: u64, proof: EthProof) -> Promise {
+        limits::assert_proof_len(&proof);
+
+        let prover = self.eth_prover.clone().expect("EthProver not configured");
+        let order = self.orders.get(&order_id).expect("Order not found");
+        require!(!order.mirror_confirmed, "Order already mirrored");
+
+        let log = decode_log_entry(&proof.log_entry_data).expect("Invalid log entry");
+        let ethereum_contract = parse_eth_address(&self.ethereum_contract)
+            .expect("Invalid Ethereum contract address");
+        require!(log.address == ethereum_contract, "Log not emitted by Ethereum contract");
+
+        let hashlock = hex::decode(&order.hashlock).expect("Invalid hashlock");
+        require!(
+            log.topics.len() == 3
+                && log.topics[0] == event_topic(eth_proof::ORDER_CREATED_EVENT)
+                && log.topics[1] == u64_topic(order_id)
+                && log.topics[2][..] == hashlock[..],
+            "Log is not OrderCreated for this order"
+        );
+
+        ext_eth_prover::ext(prover)
+            .with_static_gas(GAS_FOR_CROSS_CHAIN_CALL)
+            .verify_log_entry(
+                proof.log_index,
+                proof.log_entry_data,
+                proof.receipt_index,
+                proof.receipt_data,
+                proof.header_data,
+                proof.proof,
+                false,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_PROOF_CALLBACK)
+                    .on_mirror_proof_verified(order_id),
+            )
+    }
+
+    #[private]
+    pub fn on_mirror_proof_verified(&mut self, order_id: u64) -> bool {
+        let verified = match env::promise_result(0) {
+            PromiseResult::Successful(value) => bool::try_from_slice(&value).unwrap_or(false),
+            _ => false,
+        };
+
+        if verified {
+            self.mark_mirror_confirmed(order_id, "eth_proof");
+        } else {
+            env::log_str(&format!("Mirror proof rejected for order {}", order_id));
+        }
+        verified
+    }
+
+    // Owner functions
+    pub fn set_allow_unconfirmed_locks(&mut self, allow: bool) {
+        self.assert_owner();
+        self.allow_unconfirmed_locks = allow;
+        env::log_str(&format!("allow_unconfirmed_locks set to {}", allow));
+    }
+
+    pub fn set_eth_prover(&mut self, eth_prover: Option<AccountId>) {
+        self.assert_owner();
+        self.eth_prover = eth_prover;
+    }
+
+    pub fn set_slippage_update_interval(&mut self, interval: U64) {
+        self.assert_owner();
+        require!(
+            interval.0 >= timing::MIN_SLIPPAGE_UPDATE_INTERVAL_NS,
+            "Interval must be at least one minute"
+        );
+        env::log_str(&format!(
+            "slippage_update_interval set from {} to {}",
+            self.slippage_update_interval.0, interval.0
+        ));
+        self.slippage_update_interval = interval;
+        self.audit("set_slippage_update_interval", None);
+    }
+
+    // Raw slippage calculations averaged into each update; 1 applies the
+    // latest calculation as is
+    pub fn set_smoothing_window(&mut self, window: u64) {
+        self.assert_owner();
+        require!(
+            window >= 1 && window <= limits::MAX_BATCH_SIZE as u64,
+            "Smoothing window must be between 1 and 100"
+        );
+        env::log_str(&format!(
+            "smoothing_window set from {} to {}",
+            self.smoothing_window, window
+        ));
+        self.smoothing_window = window;
+        self.audit("set_smoothing_window", None);
+    }
+
+    pub fn set_max_slippage_change(&mut self, max_change_bps: u64) {
+        self.assert_owner();
+        require!(
+            max_change_bps > 0 && max_change_bps <= 10_000,
+            "Max slippage change must be between 1 and 10000 bps"
+        );
+        env::log_str(&format!(
+            "max_slippage_change set from {} to {}",
+            self.max_slippage_change, max_change_bps
+        ));
+        self.max_slippage_change = max_change_bps;
+        self.audit("set_max_slippage_change", None);
+    }
+
+    pub fn set_fill_attempt_limit(&mut self, limit: u64) {
+        self.assert_owner();
+        require!(limit > 0, "Limit must be positive");
+        env::log_str(&format!(
+            "fill_attempt_limit set from {} to {}",
+            self.fill_attempt_limit, limit
+        ));
+        self.fill_attempt_limit = limit;
+        self.audit("set_fill_attempt_limit", None);
+    }
+
+    // In seconds; applies to orders created afterwards
+    pub fn set_default_timelock_duration(&mut self, duration: U64) {
+        self.assert_owner();
+        require!(
+            duration.0 >= self.min_timelock_duration.0 && duration.0 <= self.max_timelock_duration.0,
+            format!(
+                "Timelock duration must be between {} and {} seconds",
+                self.min_timelock_duration.0, self.max_timelock_duration.0
+            )
+        );
+        env::log_str(&format!(
+            "default_timelock_duration set from {} to {}",
+            self.default_timelock_duration.0, duration.0
+        ));
+        self.default_timelock_duration = duration;
+        self.audit("set_default_timelock_duration", None);
+    }
+
+    // Range makers can pick timelock_duration from, in seconds, inside the
+    // 1 hour to 7 day hard bounds. Must contain the default.
+    pub fn set_timelock_bounds(&mut self, min: U64, max: U64) {
+        self.assert_owner();
+        require!(
+            min.0 >= timing::MIN_TIMELOCK_DURATION_SECS && max.0 <= timing::MAX_TIMELOCK_DURATION_SECS,
+            "Timelock bounds must be between 1 hour and 7 days"
+        );
+        require!(
+            min.0 <= self.default_timelock_duration.0 && self.default_timelock_duration.0 <= max.0,
+            "Timelock bounds must contain default_timelock_duration"
+        );
+        env::log_str(&format!(
+            "timelock bounds set from {}..{} to {}..{}",
+            self.min_timelock_duration.0, self.max_timelock_duration.0, min.0, max.0
+        ));
+        self.min_timelock_duration = min;
+        self.max_timelock_duration = max;
+        self.audit("set_timelock_bounds", None);
+    }
+
+    // Messages already sent keep the address they were sent to. Bridge
+    // messages go to each chain's destination_contract; this one is the
+    // fallback for orders from before their chain had one
+    pub fn set_ethereum_contract(&mut self, ethereum_contract: String) {
+        self.assert_owner();
+        let ethereum_contract = normalize::eth_address(&ethereum_contract).unwrap_or_else(|v| v.panic());
+        env::log_str(&format!(
+            "ethereum_contract set from {} to {}",
+            self.ethereum_contract, ethereum_contract
+        ));
+        self.ethereum_contract = ethereum_contract;
+        self.audit("set_ethereum_contract", None);
+    }
+
+    pub fn set_max_creates_per_block_per_account(&mut self, max_creates: u32) {
+        self.assert_owner();
+        require!(max_creates > 0, "Limit must be positive");
+        self.max_creates_per_block_per_account = max_creates;
+        env::log_str(&format!("max_creates_per_block_per_account set to {}", max_creates));
+    }
+
+    // Makers already over a lowered limit keep their orders
+    pub fn set_max_open_orders_per_user(&mut self, max_open: u64) {
+        self.assert_owner();
+        require!(max_open > 0, "Limit must be positive");
+        env::log_str(&format!(
+            "max_open_orders_per_user set from {} to {}",
+            self.max_open_orders_per_user, max_open
+        ));
+        self.max_open_orders_per_user = max_open;
+        self.audit("set_max_open_orders_per_user", None);
+    }
+
+    // Helper functions
+    fn assert_owner(&self) {
+        require!(
+            env::predecessor_account_id() == self.owner,
+            "Only owner can call this method"
+        );
+    }
+
+    fn mark_mirror_confirmed(&mut self, order_id: u64, source: &str) {
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        if order.mirror_confirmed {
+            return;
+        }
+        order.mirror_confirmed = true;
+        self.save_order(&order);
+
+        env::log_str(&format!(
+            "Mirror confirmed for order {} via {}",
+            order_id, source
+        ));
+        self.emit_event(
+            EventLevel::Standard,
+            "mirror_confirmed",
+            json!({ "order_id": order_id, "source": source }),
+        );
+    }
+
+    /// Claims the whole lock of an order for its taker with the preimage
+    /// of one hashlock; `claimant` is who the claim is made by. Only the taker can redirect
+    /// the payout with `receiver_id`. See `settle_fill`.
+    pub(crate) fn settle_claim(
+        &mut self,
+        hashlock: String,
+        secret: String,
+        claimant: AccountId,
+        receiver_id: Option<AccountId>,
+        relayer_fee: Balance,
+    ) -> Promise {
+        limits::assert_len(hashlock.len(), limits::MAX_HASHLOCK_LEN, limits::ERR_HASHLOCK_TOO_LONG);
+        let hashlock = normalize::hex_id(&hashlock);
+
+        let order_id = self.hashlock_to_order.get(&hashlock)
+            .expect("Order not found");
+        
+        let order = self.orders.get(&order_id).expect("Order not found");
+        require!(
+            order.hashlock_mode != HashlockMode::Both,
+            "Order needs both secrets; claim with claim_with_secrets"
+        );
+        self.assert_secret_matches(&order, &secret, &hashlock);
+        // Either-mode orders are also found by their taker hashlock
+        if hashlock == order.hashlock {
+            self.settle_whole_lock(order, Some(secret), None, claimant, receiver_id, relayer_fee)
+        } else {
+            self.settle_whole_lock(order, None, Some(secret), claimant, receiver_id, relayer_fee)
+        }
+    }
+
+    fn assert_secret_matches(&self, order: &CrossChainOrder, secret: &str, hashlock: &str) {
+        limits::assert_len(secret.len(), limits::MAX_SECRET_LEN, limits::ERR_SECRET_TOO_LONG);
+        if let Some(violation) = validation::secret_violation(secret) {
+            violation.panic();
+        }
+        require!(
+            order.secret_parts.is_none(),
+            "Order uses Merkle secrets; claim with claim_partial_with_secret"
+        );
+        // Verify secret matches hashlock
+        let computed_hash = self.generate_hashlock(secret, order.hash_algorithm);
+        require!(computed_hash == hashlock, "Invalid secret");
+    }
+
+    // `secret` and `taker_secret` are the preimages the claim reveals
+    fn settle_whole_lock(
+        &mut self,
+        order: CrossChainOrder,
+        secret: Option<String>,
+        taker_secret: Option<String>,
+        claimant: AccountId,
+        receiver_id: Option<AccountId>,
+        relayer_fee: Balance,
+    ) -> Promise {
+        let taker = self.authorize_claim(&order, &claimant);
+        require!(
+            receiver_id.is_none() || claimant == taker,
+            "Only the resolver holding the lock can choose the receiver"
+        );
+        let payee = receiver_id.unwrap_or_else(|| taker.clone());
+
+        let filled = order.locked_amount.0;
+        self.settle_fill(order, taker, payee, filled, secret, taker_secret, None, relayer_fee)
+    }
+
+    /// Settles `filled` of `receiver`'s lock, paying it to `payee` and the
+    /// caller `relayer_fee` out of the gas sponsorship. Once the lock is used up
+    /// the order reopens, or completes if nothing is left to fill; the rest
+    /// of the sponsorship then goes back to the maker. `secret_index` is
+    /// the Merkle leaf spent by the claim, if any. The claim only settles
+    /// once the transfer to `payee` succeeds; see `start_claim`.
+    pub(crate) fn settle_fill(
+        &mut self,
+        order: CrossChainOrder,
+        receiver: AccountId,
+        payee: AccountId,
+        filled: Balance,
+        secret: Option<String>,
+        taker_secret: Option<String>,
+        secret_index: Option<u32>,
+        relayer_fee: Balance,
+    ) -> Promise {
+        self.assert_not_paused();
+        self.assert_permitted_resolver(&receiver);
+        require!(relayer_fee <= order.gas_sponsorship.0, "Relayer fee exceeds gas sponsorship");
+
+        let claim = PendingClaim {
+            receiver,
+            payee,
+            filled: U128(filled),
+            secret,
+            taker_secret,
+            secret_index,
+            relayer: env::predecessor_account_id(),
+            relayer_fee: U128(relayer_fee),
+            fees: self.compute_settlement_fees(&order, filled),
+        };
+        self.start_claim(order, claim)
+    }
+
+    /// Everything a claim changes apart from paying the payee: the order,
+    /// fees, statistics, events and the relayer and sponsorship refunds.
+    /// Returns the saved order and whether it completed.
+    pub(crate) fn complete_fill(&mut self, mut order: CrossChainOrder, claim: &PendingClaim) -> (CrossChainOrder, bool) {
+        let order_id = order.order_id;
+        let filled = claim.filled.0;
+        let relayer_fee = claim.relayer_fee.0;
+        let sponsorship = order.gas_sponsorship.0;
+        let fees = &claim.fees;
+
+        // Publish the preimage for relayers. Merkle secrets are only
+        // revealed through the claim event.
+        if claim.secret_index.is_none() {
+            if claim.secret.is_some() {
+                order.secret = claim.secret.clone();
+            }
+            if claim.taker_secret.is_some() {
+                order.taker_secret = claim.taker_secret.clone();
+            }
+        }
+        self.reduce_exposure(&claim.receiver, filled);
+        order.locked_amount = U128(order.locked_amount.0 - filled);
+        order.amount_remaining = U128(order.amount_remaining.0 - filled);
+        self.count_fill(&order, filled);
+        let completed = order.amount_remaining.0 == 0;
+        if completed {
+            self.transition_status(&mut order, OrderStatus::Completed);
+        } else {
+            if order.locked_amount.0 == 0 {
+                self.transition_status(&mut order, OrderStatus::Active);
+                order.taker = None;
+                order.locked_at = None;
+                order.locked_price = None;
+                order.exclusive_claim_until = None;
+            }
+            order.gas_sponsorship = U128(sponsorship - relayer_fee);
+        }
+        self.save_order(&order);
+        self.record_completed_fill(&order.maker, &claim.receiver, filled);
+        if completed {
+            self.record_pair_class_completed(self.pair_class(order.token_in.as_str(), &order.token_out));
+            self.notify_settlement(&order);
+        }
+
+        self.record_protocol_fee(&order, fees.protocol_fee.0);
+        if let Some(promotion_id) = fees.promotion_id {
+            self.record_promotion_volume(promotion_id, filled);
+        }
+
+        env::log_str(&format!(
+            "Order {} claimed: gross {}, protocol fee {}, net {}, remaining {}, maker exempt: {}, fee waived: {}, promotion: {:?}",
+            order_id,
+            fees.gross_amount.0,
+            fees.protocol_fee.0,
+            fees.net_amount.0,
+            order.amount_remaining.0,
+            fees.maker_exempt,
+            fees.order_fee_waived,
+            fees.promotion_id
+        ));
+        self.emit_order_event(
+            EventLevel::Minimal,
+            "order_claimed",
+            &order,
+            json!({
+                "taker": claim.receiver,
+                "secret": claim.secret,
+                "taker_secret": claim.taker_secret,
+                "secret_index": claim.secret_index,
+                "price": order.locked_price,
+                "filled": U128(filled),
+                "amount_remaining": order.amount_remaining,
+                "gross_amount": fees.gross_amount,
+                "protocol_fee": fees.protocol_fee,
+                "net_amount": fees.net_amount,
+            }),
+        );
+        self.emit_gas_report("claim_with_secret", order_id);
+
+        if relayer_fee > 0 {
+            Promise::new(claim.relayer.clone()).transfer(NearToken::from_yoctonear(relayer_fee));
+        }
+        // Partial fills keep the rest of the sponsorship for later claims
+        if completed && sponsorship > relayer_fee {
+            Promise::new(order.maker.clone()).transfer(NearToken::from_yoctonear(sponsorship - relayer_fee));
+        }
+        (order, completed)
+    }
+
+    // Cancels an Active order and refunds its escrow and gas sponsorship
+    // to the maker, for the maker or a bridge cancel instruction
+    pub(crate) fn internal_cancel(&mut self, mut order: CrossChainOrder) -> Promise {
+        let order_id = order.order_id;
+        self.transition_status(&mut order, OrderStatus::Cancelled);
+        self.save_order(&order);
+        self.notify_settlement(&order);
+
+        self.send_bridge_message(self.build_bridge_message(&order, "cancel"));
+
+        let refund = order.amount_remaining.0;
+        env::log_str(&format!(
+            "Order {} cancelled, {} {} refunded to {}",
+            order_id, refund, order.token_in, order.maker
+        ));
+        self.emit_order_event(
+            EventLevel::Minimal,
+            "order_cancelled",
+            &order,
+            json!({ "refunded": U128(refund), "gas_sponsorship": order.gas_sponsorship }),
+        );
+        if order.gas_sponsorship.0 > 0 {
+            self.refund_sponsorship(&order);
+        }
+        self.pay_out(&order, order.maker.clone(), refund)
+    }
+
+    // Shared by lock_order, lock_partial and bridge lock instructions;
+    // `amount` None locks the rest
+    pub(crate) fn internal_lock(&mut self, resolver: AccountId, order_id: u64, amount: Option<Balance>) {
+        self.assert_not_paused();
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        require!(
+            matches!(order.status, OrderStatus::Active),
+            "Order not active"
+        );
+        require!(
+            timing::current_expiry_stage(&order) == ExpiryStage::BeforeTimelock,
+            "Order expired"
+        );
+        require!(
+            !timing::quote_expired(&order, env::block_timestamp()),
+            "Order quote expired; refund it with refund_stale_order"
+        );
+        // Claim windows are measured against a timestamp timelock
+        self.migrate_timelock(&mut order);
+        require!(self.is_mirror_ready(&order), "Order not yet mirrored on the destination chain");
+        require!(!order.needs_reconfirmation, "Order awaits reconfirmation by the maker");
+        require!(resolver != order.maker, "Maker cannot lock their own order");
+        self.assert_permitted_resolver(&resolver);
+        self.assert_min_stake(&resolver);
+        let amount = amount.unwrap_or(order.amount_remaining.0);
+        require!(amount <= order.amount_remaining.0, "Fill amount exceeds the remaining amount");
+        if order.secret_parts.is_some() {
+            merkle::assert_whole_parts(&order, amount);
+        }
+
+        order.locked_amount = U128(amount);
+        self.reserve_exposure(&resolver, &order);
+        order.taker = Some(resolver.clone());
+        order.locked_at = Some(U64(env::block_timestamp()));
+        order.economics_revealed = true;
+        order.exclusive_claim_until = Some(U64(timing::exclusive_claim_deadline(
+            &self.claim_windows,
+            env::block_timestamp(),
+            order.timelock_ns.0,
+        )));
+        order.locked_price = Some(U128(timing::current_price(&order, env::block_timestamp())));
+        self.record_fill_attempt(&mut order);
+        self.transition_status(&mut order, OrderStatus::Locked);
+        self.save_order(&order);
+
+        env::log_str(&format!(
+            "Order {} locked by {}: {} of {} remaining",
+            order_id, resolver, amount, order.amount_remaining.0
+        ));
+        self.emit_order_event(
+            EventLevel::Minimal,
+            "order_locked",
+            &order,
+            json!({
+                "taker": resolver,
+                "locked_at": order.locked_at,
+                "locked_amount": order.locked_amount,
+                "amount_remaining": order.amount_remaining,
+                "locked_price": order.locked_price,
+            }),
+        );
+        self.emit_gas_report("lock_order", order_id);
+    }
+
+    // Every status change goes through here so counters stay exact and
+    // terminal orders are stamped and indexed for archival. The caller
+    // persists the order.
+    fn transition_status(&mut self, order: &mut CrossChainOrder, status: OrderStatus) {
+        let from = std::mem::replace(&mut order.status, status);
+        self.count_transition(order, &from);
+        if !matches!(order.status, OrderStatus::Active) {
+            self.clear_reconfirmation(order);
+        }
+        if matches!(from, OrderStatus::Active | OrderStatus::Locked | OrderStatus::Claiming)
+            && !matches!(order.status, OrderStatus::Active | OrderStatus::Locked | OrderStatus::Claiming)
+        {
+            self.unindex_expiry(order);
+        }
+        // A claim in flight still holds its lock
+        if matches!(from, OrderStatus::Locked | OrderStatus::Claiming)
+            && !matches!(order.status, OrderStatus::Locked | OrderStatus::Claiming)
+        {
+            self.release_exposure(order);
+        }
+        if matches!(
+            order.status,
+            OrderStatus::Completed | OrderStatus::Expired | OrderStatus::Cancelled
+        ) {
+            let now = env::block_timestamp();
+            order.terminal_at = Some(U64(now));
+            self.index_terminal(order, now);
+            // A hashlock whose secret never went on chain is free for reuse;
+            // a revealed one stays taken, or anyone could claim the new order
+            if !self.secret_revealed(order) {
+                self.release_hashlock(order);
+            }
+        }
+    }
+
+    fn secret_revealed(&self, order: &CrossChainOrder) -> bool {
+        order.secret.is_some()
+            || order.taker_secret.is_some()
+            || self.used_secret_indices.get(&order.order_id).is_some()
+    }
+
+    // Only while the entry is still this order's
+    fn release_hashlock(&mut self, order: &CrossChainOrder) {
+        for hashlock in std::iter::once(&order.hashlock).chain(order.taker_hashlock.as_ref()) {
+            if self.hashlock_to_order.get(hashlock) == Some(order.order_id) {
+                let storage_before = env::storage_usage();
+                self.hashlock_to_order.remove(hashlock);
+                self.record_storage(StorageStore::Orders, storage_before);
+            }
+        }
+    }
+
+    // Shared creation path for every entry point that opens an order.
+    // `storage` says who pays for the storage the order writes.
+    fn internal_create_order(
+        &mut self,
+        maker: AccountId,
+        token_in: AccountId,
+        params: OrderParams,
+        storage: StorageCharge,
+    ) -> u64 {
+        let order_id = self.create_unmetered_order(maker.clone(), token_in, params, storage);
+        self.record_order_creations(&maker, 1);
+        order_id
+    }
+
+    // Creates an order without counting it against the creation rate
+    // limit; the caller records every order the call created at once
+    fn create_unmetered_order(
+        &mut self,
+        maker: AccountId,
+        token_in: AccountId,
+        params: OrderParams,
+        storage: StorageCharge,
+    ) -> u64 {
+        self.assert_not_paused();
+        self.assert_valid_order_params(&params, &maker, token_in.as_str());
+
+        let OrderParams {
+            token_out,
+            amount_in,
+            base_price,
+            max_slippage_deviation,
+            target_chain_id,
+            hashlock,
+            hash_algorithm,
+            secret_parts,
+            private_economics,
+            settlement_callback,
+            bridge_route,
+            gas_sponsorship,
+            skip_reconfirmation,
+            auction,
+            timelock_duration,
+            allow_public_slippage_updates,
+            validity_duration,
+            memo,
+            taker_hashlock,
+            hashlock_mode,
+        } = params;
+        let (bridge_route, route) = self
+            .resolve_bridge_route(bridge_route, target_chain_id)
+            .unwrap_or_else(|violation| violation.panic());
+        let token_out = normalize::eth_address(&token_out).unwrap_or_else(|violation| violation.panic());
+
         let order_id = self.next_order_id;
         self.next_order_id += 1;
 
-        // Generate hashlock from secret
-        let hashlock = self.generate_hashlock(&secret);
+        // The maker pays for the callback registration, the bridge fee and
+        // the sponsorship on top of an exact amount_in, otherwise out of the
+        // deposit
+        let callback_storage_cost = settlement_callback
+            .map(|callback| self.register_settlement_callback(order_id, callback))
+            .unwrap_or(0);
+        let gas_sponsorship = gas_sponsorship.map(|amount| amount.0).unwrap_or(0);
+        let creation_fees = callback_storage_cost + route.fee.0 + gas_sponsorship;
+        let deposit = match storage {
+            StorageCharge::Attached(_) => amount_in.0,
+            StorageCharge::None | StorageCharge::FromEscrow => {
+                require!(
+                    amount_in.0 > creation_fees,
+                    "Deposit does not cover callback storage, bridge fee and gas sponsorship"
+                );
+                amount_in.0 - creation_fees
+            }
+        };
+        self.collected_bridge_fees += route.fee.0;
+        let max_slippage_deviation = max_slippage_deviation
+            .unwrap_or_else(|| self.default_max_deviation(token_in.as_str(), &token_out));
+
+        // The secret stays with the maker until the claim reveals it
+        let hashlock = normalize::hex_id(&hashlock);
+        let taker_hashlock = taker_hashlock.map(|taker_hashlock| normalize::hex_id(&taker_hashlock));
+        let hashlock_mode = match (&taker_hashlock, hashlock_mode) {
+            (None, _) => HashlockMode::Single,
+            (Some(_), mode) => mode.unwrap_or(HashlockMode::Both),
+        };
         
         // Calculate initial slippage based on cross-chain factors
-        let initial_slippage = self.calculate_cross_chain_slippage(
-            &"near".to_string(),
-            &token_out,
-            deposit,
-            target_chain_id
-        );
+        let inputs = self.creation_slippage_inputs(deposit, target_chain_id, &token_out, bridge_route);
+        let initial_slippage = self.calculate_cross_chain_slippage(token_in.as_str(), &token_out, &inputs);
 
-        let timelock = U64(env::block_height() + self.default_timelock_duration.0);
+        let timelock_ns = U64(timing::timelock_deadline_ns(
+            timelock_duration.map(|duration| duration.0).unwrap_or(self.default_timelock_duration.0),
+            env::block_timestamp(),
+        ));
 
-        let order = CrossChainOrder {
+        let mut order = CrossChainOrder {
             order_id,
             maker: maker.clone(),
-            token_in: "near".to_string(),
+            token_in: token_in.clone(),
             token_out: token_out.clone(),
             amount_in: U128(deposit),
+            amount_remaining: U128(deposit),
+            locked_amount: U128(0),
             base_price,
             current_slippage: initial_slippage,
             max_slippage_deviation,
             target_chain_id,
             hashlock: hashlock.clone(),
-            timelock,
-            secret: Some(secret),
+            timelock: U64(0),
+            timelock_ns,
+            secret: None,
             status: OrderStatus::Active,
             created_at: U64(env::block_timestamp()),
             last_slippage_update: U64(env::block_timestamp()),
             fill_attempts: 0,
+            last_fill_attempt_at: None,
+            mirror_confirmed: false,
+            fee_waived: false,
+            private_economics: private_economics.unwrap_or(false),
+            terminal_at: None,
+            bridge_route,
+            taker: None,
+            locked_at: None,
+            gas_sponsorship: U128(gas_sponsorship),
+            baseline_slippage: initial_slippage,
+            needs_reconfirmation: false,
+            skip_reconfirmation: skip_reconfirmation.unwrap_or(false),
+            hash_algorithm: hash_algorithm.unwrap_or(HashAlgorithm::Sha256),
+            secret_parts,
+            auction,
+            locked_price: None,
+            timelock_extension: U64(0),
+            exclusive_claim_until: None,
+            public_slippage_updates: allow_public_slippage_updates.unwrap_or(false),
+            valid_until: validity_duration
+                .map(|duration| U64(timing::timelock_deadline_ns(duration.0, env::block_timestamp()))),
+            memo,
+            taker_hashlock: taker_hashlock.clone(),
+            hashlock_mode,
+            taker_secret: None,
+            economics_revealed: false,
         };
 
         // Store order
+        let storage_start = env::storage_usage();
+        let storage_before = storage_start;
         self.orders.insert(&order_id, &order);
         self.hashlock_to_order.insert(&hashlock, &order_id);
+        if let Some(taker_hashlock) = &taker_hashlock {
+            self.hashlock_to_order.insert(taker_hashlock, &order_id);
+        }
         
         // Track user orders
-        let mut user_order_list = self.user_orders
-            .get(&maker)
-            .unwrap_or_else(|| Vector::new(format!("u{}", maker).as_bytes()));
+        let mut user_order_list = self.user_order_list(&maker);
         user_order_list.push(&order_id);
         self.user_orders.insert(&maker, &user_order_list);
+        self.record_storage(StorageStore::Orders, storage_before);
 
         // Record initial slippage
-        let mut history = Vector::new(format!("s{}", order_id).as_bytes());
+        let storage_before = env::storage_usage();
+        let mut history = Vector::new(storage::StorageKey::SlippageHistory { order_id });
         history.push(&SlippageHistory {
             timestamp: U64(env::block_timestamp()),
             slippage: initial_slippage,
-            volatility_score: 0,
-            cross_chain_delay: 900, // 15 minutes typical bridge delay
+            raw_slippage: initial_slippage,
+            smoothed_slippage: initial_slippage,
+            volatility_score: inputs.volatility_score,
+            volatility_source: VolatilitySource::Static,
+            cross_chain_delay: inputs.bridge_delay_secs,
         });
         self.slippage_history.insert(&order_id, &history);
+        self.record_storage(StorageStore::Histories, storage_before);
 
-        // Send message to Ethereum via bridge
-        self.send_bridge_message(BridgeMessage {
-            order_id,
-            target_contract: self.ethereum_contract.clone(),
-            action: "create_order".to_string(),
-            data: serde_json::to_string(&order).unwrap(),
-        });
+        let storage_bytes = env::storage_usage() - storage_start;
+        match storage {
+            StorageCharge::None => {}
+            StorageCharge::FromEscrow => self.reserve_order_storage(&mut order, storage_bytes),
+            StorageCharge::Attached(attached) => {
+                self.charge_attached_storage(&order, storage_bytes, creation_fees, attached)
+            }
+        }
+        // Sizes apply to what the order escrows, net of fees and storage
+        if let Some(violation) = self.order_size_violation(token_in.as_str(), order.amount_in) {
+            violation.panic();
+        }
+        self.reserve_value_locked(&order);
+        self.count_created(&order);
+        self.index_expiry(&order);
+        self.record_pair_class_created(self.pair_class(token_in.as_str(), &token_out), order.amount_in.0);
+
+        // Send message to Ethereum via bridge. Only multi-part messages are
+        // chunked; a single order that does not fit its route is rejected.
+        let message = self.build_bridge_message(&order, encoding::create_action(&order));
+        if let Some(violation) = self.payload_size_violation(&message) {
+            violation.panic();
+        }
+        self.send_bridge_message(message);
 
         env::log_str(&format!(
             "Cross-chain order created: ID {}, Amount: {}, Target: {}", 
-            order_id, deposit, token_out
+            order_id, views::public_amount(&order), token_out
         ));
+        self.emit_order_event(
+            EventLevel::Minimal,
+            "order_created",
+            &order,
+            json!({ "timelock_ns": order.timelock_ns, "memo": order.memo }),
+        );
+        self.emit_gas_report("create_cross_chain_order", order_id);
 
         order_id
     }
 
-    pub fn claim_with_secret(&mut self, hashlock: String, secret: String) -> Promise {
-        // Verify secret matches hashlock
-        let computed_hash = self.generate_hashlock(&secret);
-        require!(computed_hash == hashlock, "Invalid secret");
+    // Counts `count` new orders against the maker's per-block allowance.
+    // Batch creation passes its batch size.
+    fn record_order_creations(&mut self, maker: &AccountId, count: u32) {
+        if let Some(violation) = self.creation_rate_violation(maker, count) {
+            violation.panic();
+        }
+        if self.has_role(maker.clone(), Role::MarketMaker) {
+            return;
+        }
 
-        let order_id = self.hashlock_to_order.get(&hashlock)
-            .expect("Order not found");
-        
-        let mut order = self.orders.get(&order_id).expect("Order not found");
-        require!(
-            matches!(order.status, OrderStatus::Locked),
-            "Order not in locked state"
-        );
-        require!(
-            env::block_height() < order.timelock.0,
-            "Order expired"
-        );
+        let height = env::block_height();
+        let created = self.created_in_block(maker, height);
+        self.creation_windows.insert(maker, &CreationWindow {
+            block_height: height,
+            count: created + count,
+        });
+    }
 
-        // Update order status
-        order.status = OrderStatus::Completed;
-        self.orders.insert(&order_id, &order);
+    fn creation_rate_violation(&self, maker: &AccountId, count: u32) -> Option<Violation> {
+        if self.has_role(maker.clone(), Role::MarketMaker) {
+            return None;
+        }
+        let created = self.created_in_block(maker, env::block_height());
+        if created + count > self.max_creates_per_block_per_account {
+            return Some(Violation::new(
+                validation::ERR_CREATE_RATE_LIMIT,
+                format!(
+                    "at most {} orders per block per account",
+                    self.max_creates_per_block_per_account
+                ),
+            ));
+        }
+        None
+    }
 
-        // Transfer tokens to claimer
-        Promise::new(env::predecessor_account_id())
-            .transfer(order.amount_in.0)
+    fn created_in_block(&self, maker: &AccountId, height: BlockHeight) -> u32 {
+        match self.creation_windows.get(maker) {
+            Some(window) if window.block_height == height => window.count,
+            _ => 0,
+        }
     }
 
-    pub fn update_order_slippage(&mut self, order_id: u64) {
-        let mut order = self.orders.get(&order_id).expect("Order not found");
-        require!(
-            matches!(order.status, OrderStatus::Active),
-            "Order not active"
-        );
-        require!(
-            env::block_timestamp() >= order.last_slippage_update.0 + self.slippage_update_interval.0,
-            "Too early to update"
-        );
+    // Locks (and bids) must wait until Ethereum knows about the order,
+    // unless the owner runs the contract in permissionless mode.
+    fn is_mirror_ready(&self, order: &CrossChainOrder) -> bool {
+        self.allow_unconfirmed_locks || order.mirror_confirmed
+    }
 
+    fn generate_hashlock(&self, secret: &str, algorithm: HashAlgorithm) -> String {
+        let secret = validation::secret_bytes(secret).unwrap_or_else(|violation| violation.panic());
+        hex::encode(merkle::digest(algorithm, &secret))
+    }
+
+    fn calculate_cross_chain_slippage(
+        &self,
+        token_in: &str,
+        token_out: &str,
+        inputs: &SlippageInputs,
+    ) -> u64 {
+        // Each pair class has its own model; unregistered pairs are Major
+        let model = self.slippage_model(self.pair_class(token_in, token_out));
+        pairs::model_slippage(&model, inputs)
+    }
+
+    /// Why `keeper` may not update the order's slippage now, if anything.
+    pub(crate) fn slippage_update_refusal(&self, order: &CrossChainOrder, keeper: &AccountId) -> Option<&'static str> {
+        if !order.public_slippage_updates && *keeper != order.maker && !self.keepers.contains(keeper) {
+            return Some("Only the maker or a registered keeper can update this order's slippage");
+        }
+        if !matches!(order.status, OrderStatus::Active) {
+            return Some("Order not active");
+        }
+        if env::block_timestamp()
+            < timing::next_slippage_update_at(order.last_slippage_update.0, self.slippage_update_interval.0)
+        {
+            return Some("Too early to update");
+        }
+        None
+    }
+
+    pub(crate) fn apply_slippage_update(
+        &mut self,
+        mut order: CrossChainOrder,
+        keeper: AccountId,
+        volatility_score: u64,
+        volatility_source: VolatilitySource,
+    ) {
+        let order_id = order.order_id;
         // Calculate new slippage with cross-chain factors
+        let inputs = SlippageInputs {
+            amount: order.amount_in.0,
+            target_chain_id: order.target_chain_id,
+            chain_premium_bps: self.chain_premium_bps(order.target_chain_id),
+            volatility_score,
+            bridge_delay_secs: self.estimate_bridge_delay(order.bridge_route, order.target_chain_id),
+        };
         let new_slippage = self.calculate_cross_chain_slippage(
-            &order.token_in,
+            order.token_in.as_str(),
             &order.token_out,
-            order.amount_in.0,
-            order.target_chain_id
+            &inputs,
         );
 
-        // Apply maximum deviation limits
-        let slippage_change = if new_slippage > order.current_slippage {
-            new_slippage - order.current_slippage
-        } else {
-            order.current_slippage - new_slippage
-        };
-
-        let final_slippage = if slippage_change > order.max_slippage_deviation {
-            if new_slippage > order.current_slippage {
-                order.current_slippage + order.max_slippage_deviation
-            } else {
-                if order.current_slippage > order.max_slippage_deviation {
-                    order.current_slippage - order.max_slippage_deviation
-                } else {
-                    0
-                }
-            }
-        } else {
-            new_slippage
-        };
+        // Smooth out brief spikes, then apply maximum deviation limits
+        let smoothed_slippage = self.smoothed_slippage(order_id, new_slippage);
+        let final_slippage = pairs::apply_max_deviation(
+            order.current_slippage,
+            smoothed_slippage,
+            order.max_slippage_deviation,
+        );
 
         // Update order
         let old_slippage = order.current_slippage;
         order.current_slippage = final_slippage;
         order.last_slippage_update = U64(env::block_timestamp());
-        self.orders.insert(&order_id, &order);
+        self.check_slippage_drift(&mut order);
+        self.save_order(&order);
 
         // Record slippage history
-        if let Some(mut history) = self.slippage_history.get(&order_id) {
-            history.push(&SlippageHistory {
-                timestamp: U64(env::block_timestamp()),
-                slippage: final_slippage,
-                volatility_score: self.calculate_volatility_score(&order.token_out),
-                cross_chain_delay: self.estimate_bridge_delay(order.target_chain_id),
-            });
-            self.slippage_history.insert(&order_id, &history);
-        }
+        self.record_slippage(order_id, &SlippageHistory {
+            timestamp: U64(env::block_timestamp()),
+            slippage: final_slippage,
+            raw_slippage: new_slippage,
+            smoothed_slippage,
+            volatility_score: inputs.volatility_score,
+            volatility_source,
+            cross_chain_delay: inputs.bridge_delay_secs,
+        });
 
         // Notify Ethereum contract of slippage update
-        self.send_bridge_message(BridgeMessage {
-            order_id,
-            target_contract: self.ethereum_contract.clone(),
-            action: "update_slippage".to_string(),
-            data: format!("{{\"slippage\":{}}}", final_slippage),
-        });
+        self.send_bridge_message(self.build_bridge_message(&order, "update_slippage"));
 
         env::log_str(&format!(
             "Slippage updated for order {}: {} -> {} basis points",
             order_id, old_slippage, final_slippage
         ));
+        self.emit_order_event(
+            EventLevel::Standard,
+            "slippage_updated",
+            &order,
+            json!({ "old_slippage_bps": old_slippage, "new_slippage_bps": final_slippage }),
+        );
+        // Updates that leave the slippage where it was earn nothing
+        if final_slippage != old_slippage {
+            self.pay_keeper_reward(order_id, keeper);
+        }
+        self.emit_event(
+            EventLevel::Verbose,
+            "slippage_update_detail",
+            json!({
+                "order_id": order_id,
+                "computed_slippage_bps": new_slippage,
+                "smoothed_slippage_bps": smoothed_slippage,
+                "max_slippage_deviation": order.max_slippage_deviation,
+                "volatility_score": inputs.volatility_score,
+                "volatility_source": volatility_source,
+                "bridge_delay_secs": inputs.bridge_delay_secs,
+            }),
+        );
     }
 
-    // Helper functions
-    fn generate_hashlock(&self, secret: &str) -> String {
-        let mut hasher = Sha256::new();
-        hasher.update(secret.as_bytes());
-        hex::encode(hasher.finalize())
-    }
-
-    fn calculate_cross_chain_slippage(
+    // What a new order is priced with; estimate_order quotes from the same
+    pub(crate) fn creation_slippage_inputs(
         &self,
-        token_in: &str,
-        token_out: &str,
         amount: Balance,
         target_chain_id: u64,
-    ) -> u64 {
-        // Base slippage calculation
-        let mut base_slippage = 50; // 0.5% base
-
-        // Cross-chain risk premium
-        let cross_chain_premium = match target_chain_id {
-            1 => 25,   // Ethereum mainnet: +0.25%
-            137 => 50, // Polygon: +0.5%
-            _ => 100,  // Other chains: +1%
-        };
+        token_out: &str,
+        bridge_route: u8,
+    ) -> SlippageInputs {
+        SlippageInputs {
+            amount,
+            target_chain_id,
+            chain_premium_bps: self.chain_premium_bps(target_chain_id),
+            volatility_score: self.calculate_volatility_score(token_out),
+            bridge_delay_secs: self.estimate_bridge_delay(bridge_route, target_chain_id),
+        }
+    }
 
-        // Bridge delay adjustment
-        let bridge_delay_premium = 25; // +0.25% for bridge timing risk
+    // Static score, used without an oracle or when its price is unusable
+    fn calculate_volatility_score(&self, _token: &str) -> u64 {
+        oracle::STATIC_VOLATILITY_SCORE
+    }
 
-        // Amount-based adjustment
-        let amount_adjustment = if amount > 1000_000_000_000_000_000_000_000 { // > 1000 NEAR
-            50 // +0.5% for large orders
-        } else {
-            0
+    // Time-weighted average of the last smoothing_window raw calculations,
+    // this one included. Each recorded value counts for the time until the
+    // next one; the new value counts for one update interval.
+    fn smoothed_slippage(&self, order_id: u64, raw_slippage: u64) -> u64 {
+        if self.smoothing_window <= 1 {
+            return raw_slippage;
+        }
+        let history = match self.slippage_log(order_id) {
+            Some(history) => history,
+            None => return raw_slippage,
         };
-
-        base_slippage + cross_chain_premium + bridge_delay_premium + amount_adjustment
+        let start = history.len().saturating_sub(self.smoothing_window - 1);
+        let samples: Vec<(u64, u64)> = (start..history.len())
+            .filter_map(|index| history.get(index))
+            .map(|entry| (entry.timestamp.0, entry.raw_slippage))
+            .collect();
+        pairs::time_weighted_average(
+            &samples,
+            (env::block_timestamp(), raw_slippage),
+            self.slippage_update_interval.0,
+        )
     }
 
-    fn calculate_volatility_score(&self, _token: &str) -> u64 {
-        // Simplified volatility calculation
-        // In production, this would use price oracles
-        100 // Default volatility score
+    // The chain's configured delay is a floor under the route's
+    fn estimate_bridge_delay(&self, bridge_route: u8, chain_id: u64) -> u64 {
+        self.route_delay_secs(bridge_route).max(self.chain_delay_secs(chain_id))
     }
 
-    fn estimate_bridge_delay(&self, target_chain_id: u64) -> u64 {
-        match target_chain_id {
-            1 => 900,   // Ethereum: 15 minutes
-            137 => 300, // Polygon: 5 minutes  
-            _ => 1800,  // Other chains: 30 minutes
+    // View functions
+    pub fn get_config(&self) -> ContractConfig {
+        ContractConfig {
+            owner: self.owner.clone(),
+            ethereum_contract: self.ethereum_contract.clone(),
+            bridge_routes: self
+                .bridges
+                .iter()
+                .map(|(route_id, config)| BridgeRoute { route_id, config, is_default: false })
+                .collect(),
+            eth_prover: self.eth_prover.clone(),
+            escrow_proof: self.escrow_proof.clone(),
+            oracle: self.oracle.clone(),
+            slippage_update_interval: self.slippage_update_interval,
+            smoothing_window: self.smoothing_window,
+            max_history_entries: self.max_history_entries,
+            max_slippage_change: self.max_slippage_change,
+            fill_attempt_limit: self.fill_attempt_limit,
+            max_bridge_retries: self.max_bridge_retries,
+            default_timelock_duration: self.default_timelock_duration,
+            claim_windows: self.claim_windows.clone(),
+            min_timelock_duration: self.min_timelock_duration,
+            max_timelock_duration: self.max_timelock_duration,
+            allow_unconfirmed_locks: self.allow_unconfirmed_locks,
+            reconfirm_threshold_bps: self.reconfirm_threshold_bps,
+            token_out_policy: self.token_out_policy,
+            require_token_whitelist: self.require_token_whitelist,
+            protocol_fee_bps: self.protocol_fee_bps,
+            keeper_reward: U128(self.keeper_reward),
+            keeper_rewards_from_fees: self.keeper_rewards_from_fees,
+            sweep_bounty: U128(self.sweep_bounty),
+            sweep_bounty_from_refunds: self.sweep_bounty_from_refunds,
+            max_creates_per_block_per_account: self.max_creates_per_block_per_account,
+            max_open_orders_per_user: self.max_open_orders_per_user,
+            input_limits: limits::input_limits(),
+            retention_policy: self.retention_policy.clone(),
+            peg_break_threshold_bps: self.peg_break_threshold_bps,
+            event_verbosity: self.event_verbosity,
+            inbound_limits: self.inbound_limits.clone(),
+            exposure_limits: self.exposure_limits.clone(),
+            max_total_value_locked: self.max_total_value_locked.map(U128),
+            paused: self.paused,
         }
     }
 
-    fn send_bridge_message(&self, message: BridgeMessage) {
-        // Send cross-chain message via Rainbow Bridge
-        // This would integrate with the actual bridge protocol
-        env::log_str(&format!(
-            "Bridge message sent: {} for order {}",
-            message.action, message.order_id
-        ));
+    pub fn get_order(&self, order_id: u64) -> Option<OrderView> {
+        self.orders
+            .get(&order_id)
+            .map(|order| self.public_order_view(order))
     }
 
-    // View functions
-    pub fn get_order(&self, order_id: u64) -> Option<CrossChainOrder> {
-        self.orders.get(&order_id)
+    // Resolver view: whether the order can be locked right now
+    pub fn is_order_lockable(&self, order_id: u64) -> bool {
+        self.orders
+            .get(&order_id)
+            .map(|order| {
+                matches!(order.status, OrderStatus::Active)
+                    && timing::current_expiry_stage(&order) == ExpiryStage::BeforeTimelock
+                    && !timing::quote_expired(&order, env::block_timestamp())
+                    && self.is_mirror_ready(&order)
+                    && !order.needs_reconfirmation
+            })
+            .unwrap_or(false)
     }
 
     pub fn get_user_orders(&self, user: AccountId) -> Vec<u64> {
@@ -362,11 +1908,165 @@ impl AdaptiveCrossChain {
     }
 }
 
-// Helper macro for requiring conditions
-macro_rules! require {
-    ($cond:expr, $msg:expr) => {
-        if !$cond {
-            env::panic_str($msg);
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::get_logs;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    const ONE_MINUTE_NS: u64 = 60_000_000_000;
+
+    type Setter = Box<dyn Fn(&mut AdaptiveCrossChain)>;
+
+    fn protocol_setters() -> Vec<(&'static str, Setter)> {
+        vec![
+            ("set_slippage_update_interval", Box::new(|c| c.set_slippage_update_interval(U64(2 * ONE_MINUTE_NS)))),
+            ("set_max_slippage_change", Box::new(|c| c.set_max_slippage_change(50))),
+            ("set_fill_attempt_limit", Box::new(|c| c.set_fill_attempt_limit(5))),
+            ("set_default_timelock_duration", Box::new(|c| c.set_default_timelock_duration(U64(7_200)))),
+            ("set_ethereum_contract", Box::new(|c| c.set_ethereum_contract(TOKEN_OUT.to_string()))),
+            ("set_bridge_contract", Box::new(|c| c.set_bridge_contract("bridge2.near".parse().unwrap()))),
+        ]
+    }
+
+    #[test]
+    fn protocol_setters_are_owner_only() {
+        let mut contract = setup();
+        for (name, setter) in protocol_setters() {
+            for caller in [maker(), resolver(), bridge()] {
+                assert_rejected_unchanged(&mut contract, caller, 0, "Only owner can call this method", |c| setter(c));
+            }
+            call(owner(), 0);
+            setter(&mut contract);
+            assert!(get_logs().iter().any(|log| log.contains(" set from ")), "{} logged no change", name);
         }
-    };
-} 
\ No newline at end of file
+        assert_eq!(contract.slippage_update_interval.0, 2 * ONE_MINUTE_NS);
+        assert_eq!(contract.max_slippage_change, 50);
+        assert_eq!(contract.fill_attempt_limit, 5);
+        assert_eq!(contract.default_timelock_duration.0, 7_200);
+        assert_eq!(contract.ethereum_contract, TOKEN_OUT);
+        assert_eq!(contract.get_bridge_route(0).unwrap().account, "bridge2.near".parse::<AccountId>().unwrap());
+    }
+
+    #[test]
+    fn setters_log_the_old_and_new_value() {
+        let mut contract = setup();
+        let old = contract.fill_attempt_limit;
+        call(owner(), 0);
+        contract.set_fill_attempt_limit(old + 1);
+        assert_eq!(get_logs(), [format!("fill_attempt_limit set from {} to {}", old, old + 1)]);
+        contract.set_bridge_contract("bridge2.near".parse().unwrap());
+        assert_eq!(get_logs()[1], format!("bridge_contract set from {} to bridge2.near", bridge()));
+    }
+
+    #[test]
+    fn setters_reject_values_out_of_bounds() {
+        let mut contract = setup();
+        let rejected: Vec<(&str, Setter)> = vec![
+            ("at least one minute", Box::new(|c| c.set_slippage_update_interval(U64(ONE_MINUTE_NS - 1)))),
+            ("between 1 and 10000", Box::new(|c| c.set_max_slippage_change(0))),
+            ("between 1 and 10000", Box::new(|c| c.set_max_slippage_change(10_001))),
+            ("Limit must be positive", Box::new(|c| c.set_fill_attempt_limit(0))),
+            ("Timelock duration must be between", Box::new(|c| c.set_default_timelock_duration(U64(3_599)))),
+            ("Timelock duration must be between", Box::new(|c| c.set_default_timelock_duration(U64(7 * 86_400 + 1)))),
+            ("ERR_INVALID_ETH_ADDRESS", Box::new(|c| c.set_ethereum_contract("0x1234".to_string()))),
+        ];
+        for (message, setter) in rejected {
+            assert_rejected_unchanged(&mut contract, owner(), 0, message, |c| setter(c));
+        }
+        // The bounds themselves are accepted
+        call(owner(), 0);
+        contract.set_slippage_update_interval(U64(ONE_MINUTE_NS));
+        contract.set_default_timelock_duration(U64(3_600));
+        contract.set_default_timelock_duration(U64(7 * 86_400));
+        contract.set_max_slippage_change(10_000);
+    }
+
+    // A second order under a live hashlock used to overwrite the first's
+    // entry, so claims for the first resolved to the second
+    #[test]
+    fn a_live_hashlock_is_never_reassigned() {
+        let mut contract = setup();
+        let params = order_params(&contract, 1, ONE_NEAR);
+        let first = create_order_with(&mut contract, params.clone(), ONE_NEAR);
+        let hashlock = contract.orders.get(&first).unwrap().hashlock;
+
+        set_clock(0, 1);
+        assert_rejected_unchanged(&mut contract, maker(), ONE_NEAR, validation::ERR_DUPLICATE_HASHLOCK, |c| {
+            create_order_with(c, params.clone(), ONE_NEAR);
+        });
+        assert_eq!(contract.get_order_by_hashlock(hashlock.clone()).unwrap().order_id, first);
+
+        // Cancelling without a reveal frees the hashlock for a new order
+        call(maker(), 0);
+        let _ = contract.cancel_order(first);
+        assert!(!contract.order_exists_for_hashlock(hashlock.clone()));
+        let second = create_order_with(&mut contract, params.clone(), ONE_NEAR);
+        assert_eq!(contract.get_order_by_hashlock(hashlock.clone()).unwrap().order_id, second);
+
+        // The claim settles the second order only, and its revealed
+        // hashlock stays taken
+        lock(&mut contract, second);
+        claim(&mut contract, second, 1);
+        assert!(matches!(contract.orders.get(&first).unwrap().status, OrderStatus::Cancelled));
+        assert!(matches!(contract.orders.get(&second).unwrap().status, OrderStatus::Completed));
+        assert_eq!(contract.get_order_by_hashlock(hashlock).unwrap().order_id, second);
+        set_clock(0, 2);
+        assert_rejected_unchanged(&mut contract, maker(), ONE_NEAR, validation::ERR_DUPLICATE_HASHLOCK, |c| {
+            create_order_with(c, params, ONE_NEAR);
+        });
+    }
+
+    fn create_batch(contract: &mut AdaptiveCrossChain, first: u8, count: u8) -> Result<Vec<u64>, String> {
+        let orders: Vec<OrderParams> = (first..first + count)
+            .map(|n| order_params(contract, n, ONE_NEAR))
+            .collect();
+        run_or_revert(contract, |c| {
+            call(maker(), count as u128 * ONE_NEAR);
+            c.create_cross_chain_orders(orders)
+        })
+    }
+
+    #[test]
+    fn batches_count_their_size_against_the_creation_limit() {
+        let mut contract = setup();
+        let rejected = create_batch(&mut contract, 1, 4).unwrap_err();
+        assert!(rejected.contains(validation::ERR_CREATE_RATE_LIMIT), "{}", rejected);
+        assert_eq!(contract.get_order_count(), 0);
+
+        assert_eq!(create_batch(&mut contract, 1, 3).unwrap(), vec![1, 2, 3]);
+        let params = order_params(&contract, 4, ONE_NEAR);
+        let rejected = run_or_revert(&mut contract, |c| create_order_with(c, params, ONE_NEAR)).unwrap_err();
+        assert!(rejected.contains(validation::ERR_CREATE_RATE_LIMIT), "{}", rejected);
+
+        // The window resets with the next block
+        set_clock(0, 1);
+        assert_eq!(create_batch(&mut contract, 4, 3).unwrap(), vec![4, 5, 6]);
+    }
+
+    fn cancel_all_with_gas(contract: &mut AdaptiveCrossChain, prepaid_gas: u64) -> Vec<u64> {
+        testing_env!(context(maker()).prepaid_gas(Gas::from_gas(prepaid_gas)).build());
+        contract.cancel_all_orders(100)
+    }
+
+    // Each cancellation attaches gas to a bridge call, a refund, the maker's
+    // notification and their callbacks; the batch stops before one of them
+    // could run out
+    #[test]
+    fn cancel_all_stops_while_a_whole_cancel_still_fits() {
+        let mut contract = setup();
+        for n in 1..=3 {
+            set_clock(0, n as u64);
+            let params = order_params(&contract, n, ONE_NEAR);
+            create_order_with(&mut contract, params, ONE_NEAR);
+        }
+
+        assert!(cancel_all_with_gas(&mut contract, GAS_PER_CANCEL - TGAS).is_empty());
+        assert_eq!(cancel_all_with_gas(&mut contract, 2 * GAS_PER_CANCEL + 5 * TGAS), vec![1, 2]);
+        assert!(env::used_gas().as_gas() <= 2 * GAS_PER_CANCEL + 5 * TGAS);
+        assert!(matches!(contract.orders.get(&3).unwrap().status, OrderStatus::Active));
+        assert_eq!(cancel_all_with_gas(&mut contract, 300 * TGAS), vec![3]);
+    }
+}
@@ -0,0 +1,585 @@
+use near_sdk::serde::{Deserialize, Serialize};
+
+use crate::validation::Violation;
+use crate::EthProof;
+
+// Upper bounds on variable-length inputs, checked at the top of every
+// external method before any state is touched.
+pub const MAX_TOKEN_OUT_LEN: usize = 42; // 0x + 40 hex chars
+pub const MAX_HASHLOCK_LEN: usize = 66; // optional 0x + 64 hex chars
+pub const MAX_SECRET_LEN: usize = 128;
+pub const MAX_METADATA_LEN: usize = 256;
+pub const MAX_MEMO_LEN: usize = 256;
+pub const MAX_ORDER_REF_LEN: usize = 128; // Ethereum order reference of incoming and inbound orders
+pub const MAX_TAG_LEN: usize = 32;
+pub const MAX_TAGS: usize = 8;
+pub const MAX_BRIDGE_DATA_LEN: usize = 8 * 1024;
+pub const MAX_PROOF_LEN: usize = 64 * 1024; // Sum of all EthProof byte fields
+pub const MAX_EVIDENCE_URI_LEN: usize = 512;
+pub const MAX_METHOD_NAME_LEN: usize = 64;
+pub const MAX_JUSTIFICATION_LEN: usize = 512;
+pub const MAX_FT_MSG_LEN: usize = 1024;
+pub const MAX_SIGNED_PAYLOAD_LEN: usize = 2 * 1024; // JSON payload of create_order_for
+pub const MAX_BATCH_SIZE: usize = 100;
+pub const MAX_VIEW_IDS: usize = 300; // Ids per batch lookup view
+pub const MAX_SECRET_PARTS: usize = 1024;
+pub const MAX_MERKLE_PROOF_LEN: usize = 10; // Depth of a MAX_SECRET_PARTS tree
+
+pub const ERR_TOKEN_OUT_TOO_LONG: &str = "ERR_TOKEN_OUT_TOO_LONG";
+pub const ERR_HASHLOCK_TOO_LONG: &str = "ERR_HASHLOCK_TOO_LONG";
+pub const ERR_SECRET_TOO_LONG: &str = "ERR_SECRET_TOO_LONG";
+pub const ERR_METADATA_TOO_LONG: &str = "ERR_METADATA_TOO_LONG";
+pub const ERR_MEMO_TOO_LONG: &str = "ERR_MEMO_TOO_LONG";
+pub const ERR_ORDER_REF_TOO_LONG: &str = "ERR_ORDER_REF_TOO_LONG";
+pub const ERR_TAG_TOO_LONG: &str = "ERR_TAG_TOO_LONG";
+pub const ERR_TOO_MANY_TAGS: &str = "ERR_TOO_MANY_TAGS";
+pub const ERR_BRIDGE_DATA_TOO_LONG: &str = "ERR_BRIDGE_DATA_TOO_LONG";
+pub const ERR_PROOF_TOO_LONG: &str = "ERR_PROOF_TOO_LONG";
+pub const ERR_EVIDENCE_URI_TOO_LONG: &str = "ERR_EVIDENCE_URI_TOO_LONG";
+pub const ERR_METHOD_NAME_TOO_LONG: &str = "ERR_METHOD_NAME_TOO_LONG";
+pub const ERR_JUSTIFICATION_TOO_LONG: &str = "ERR_JUSTIFICATION_TOO_LONG";
+pub const ERR_FT_MSG_TOO_LONG: &str = "ERR_FT_MSG_TOO_LONG";
+pub const ERR_SIGNED_PAYLOAD_TOO_LONG: &str = "ERR_SIGNED_PAYLOAD_TOO_LONG";
+pub const ERR_BATCH_TOO_LARGE: &str = "ERR_BATCH_TOO_LARGE";
+pub const ERR_MERKLE_PROOF_TOO_LONG: &str = "ERR_MERKLE_PROOF_TOO_LONG";
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct InputLimits {
+    pub max_token_out_len: u32,
+    pub max_hashlock_len: u32,
+    pub max_secret_len: u32,
+    pub max_metadata_len: u32,
+    pub max_memo_len: u32,
+    pub max_order_ref_len: u32,
+    pub max_tag_len: u32,
+    pub max_tags: u32,
+    pub max_bridge_data_len: u32,
+    pub max_proof_len: u32,
+    pub max_evidence_uri_len: u32,
+    pub max_method_name_len: u32,
+    pub max_justification_len: u32,
+    pub max_ft_msg_len: u32,
+    pub max_signed_payload_len: u32,
+    pub max_batch_size: u32,
+    pub max_view_ids: u32,
+    pub max_secret_parts: u32,
+    pub max_merkle_proof_len: u32,
+}
+
+pub fn input_limits() -> InputLimits {
+    InputLimits {
+        max_token_out_len: MAX_TOKEN_OUT_LEN as u32,
+        max_hashlock_len: MAX_HASHLOCK_LEN as u32,
+        max_secret_len: MAX_SECRET_LEN as u32,
+        max_metadata_len: MAX_METADATA_LEN as u32,
+        max_memo_len: MAX_MEMO_LEN as u32,
+        max_order_ref_len: MAX_ORDER_REF_LEN as u32,
+        max_tag_len: MAX_TAG_LEN as u32,
+        max_tags: MAX_TAGS as u32,
+        max_bridge_data_len: MAX_BRIDGE_DATA_LEN as u32,
+        max_proof_len: MAX_PROOF_LEN as u32,
+        max_evidence_uri_len: MAX_EVIDENCE_URI_LEN as u32,
+        max_method_name_len: MAX_METHOD_NAME_LEN as u32,
+        max_justification_len: MAX_JUSTIFICATION_LEN as u32,
+        max_ft_msg_len: MAX_FT_MSG_LEN as u32,
+        max_signed_payload_len: MAX_SIGNED_PAYLOAD_LEN as u32,
+        max_batch_size: MAX_BATCH_SIZE as u32,
+        max_view_ids: MAX_VIEW_IDS as u32,
+        max_secret_parts: MAX_SECRET_PARTS as u32,
+        max_merkle_proof_len: MAX_MERKLE_PROOF_LEN as u32,
+    }
+}
+
+pub fn check_len(len: usize, max: usize, code: &str) -> Option<Violation> {
+    if len > max {
+        Some(Violation::new(code, format!("length {} exceeds limit {}", len, max)))
+    } else {
+        None
+    }
+}
+
+pub fn assert_len(len: usize, max: usize, code: &str) {
+    if let Some(violation) = check_len(len, max, code) {
+        violation.panic();
+    }
+}
+
+pub fn assert_proof_len(proof: &EthProof) {
+    let len = proof.log_entry_data.len()
+        + proof.receipt_data.len()
+        + proof.header_data.len()
+        + proof.proof.iter().map(|node| node.len()).sum::<usize>();
+    assert_len(len, MAX_PROOF_LEN, ERR_PROOF_TOO_LONG);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    use near_sdk::json_types::{U128, U64};
+    use near_sdk::test_utils::accounts;
+    use near_sdk::AccountId;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::test_utils::*;
+    use crate::{
+        AdaptiveCrossChain, AdminResolution, BridgeMessage, OrderParams, PayloadEncoding, Role, SettlementCallback,
+        SignedOrderPayload,
+    };
+
+    const DEPOSIT: u128 = 10 * ONE_NEAR;
+
+    fn operator() -> AccountId {
+        accounts(3)
+    }
+
+    fn token() -> AccountId {
+        accounts(4)
+    }
+
+    // Order 1 is Active on secret(1); every role a limited method checks
+    // is granted
+    fn fixture() -> AdaptiveCrossChain {
+        let mut contract = setup();
+        let params = order_params(&contract, 1, DEPOSIT);
+        create_order_with(&mut contract, params, DEPOSIT);
+        call(owner(), 0);
+        contract.register_resolver(resolver());
+        contract.add_supported_ft(token());
+        for role in [Role::Operator, Role::Admin, Role::Relayer] {
+            contract.grant_role(operator(), role);
+        }
+        contract
+    }
+
+    fn oversized(len: usize) -> String {
+        "a".repeat(len)
+    }
+
+    fn proof(len: usize) -> EthProof {
+        EthProof {
+            log_index: 0,
+            log_entry_data: vec![0; len],
+            receipt_index: 0,
+            receipt_data: vec![],
+            header_data: vec![],
+            proof: vec![],
+        }
+    }
+
+    fn hashlock(contract: &AdaptiveCrossChain) -> String {
+        contract.orders.get(&1).unwrap().hashlock
+    }
+
+    fn create(contract: &mut AdaptiveCrossChain, edit: impl FnOnce(&mut OrderParams)) {
+        let mut params = order_params(contract, 2, DEPOSIT);
+        edit(&mut params);
+        create_order_with(contract, params, DEPOSIT);
+    }
+
+    struct EntryPoint {
+        name: &'static str,
+        caller: fn() -> AccountId,
+        deposit: u128,
+        max: usize,
+        code: &'static str,
+        call: fn(&mut AdaptiveCrossChain, usize),
+    }
+
+    fn entry_points() -> Vec<EntryPoint> {
+        vec![
+            EntryPoint {
+                name: "create_cross_chain_order token_out",
+                caller: maker,
+                deposit: DEPOSIT,
+                max: MAX_TOKEN_OUT_LEN,
+                code: ERR_TOKEN_OUT_TOO_LONG,
+                call: |c, len| create(c, |p| p.token_out = format!("0x{}", oversized(len - 2))),
+            },
+            EntryPoint {
+                name: "create_cross_chain_order hashlock",
+                caller: maker,
+                deposit: DEPOSIT,
+                max: MAX_HASHLOCK_LEN,
+                code: ERR_HASHLOCK_TOO_LONG,
+                call: |c, len| create(c, |p| p.hashlock = oversized(len)),
+            },
+            EntryPoint {
+                name: "create_cross_chain_order memo",
+                caller: maker,
+                deposit: DEPOSIT,
+                max: MAX_MEMO_LEN,
+                code: ERR_MEMO_TOO_LONG,
+                call: |c, len| create(c, |p| p.memo = Some(oversized(len))),
+            },
+            EntryPoint {
+                name: "create_cross_chain_order taker_hashlock",
+                caller: maker,
+                deposit: DEPOSIT,
+                max: MAX_HASHLOCK_LEN,
+                code: ERR_HASHLOCK_TOO_LONG,
+                call: |c, len| create(c, |p| p.taker_hashlock = Some(oversized(len))),
+            },
+            EntryPoint {
+                name: "create_cross_chain_order settlement_callback",
+                caller: maker,
+                deposit: DEPOSIT,
+                max: MAX_METHOD_NAME_LEN,
+                code: ERR_METHOD_NAME_TOO_LONG,
+                call: |c, len| {
+                    create(c, |p| {
+                        p.settlement_callback = Some(SettlementCallback {
+                            contract_id: token(),
+                            method_name: oversized(len),
+                            status: crate::NotificationStatus::Registered,
+                        })
+                    })
+                },
+            },
+            EntryPoint {
+                name: "create_order_for",
+                caller: resolver,
+                deposit: 0,
+                max: MAX_SIGNED_PAYLOAD_LEN,
+                code: ERR_SIGNED_PAYLOAD_TOO_LONG,
+                call: |c, len| {
+                    c.create_order_for(SignedOrderPayload { payload: oversized(len), signature: String::new() });
+                },
+            },
+            EntryPoint {
+                name: "claim_with_secret hashlock",
+                caller: resolver,
+                deposit: 0,
+                max: MAX_HASHLOCK_LEN,
+                code: ERR_HASHLOCK_TOO_LONG,
+                call: |c, len| {
+                    let _ = c.claim_with_secret(oversized(len), secret(1), None);
+                },
+            },
+            EntryPoint {
+                name: "claim_with_secret secret",
+                caller: resolver,
+                deposit: 0,
+                max: MAX_SECRET_LEN,
+                code: ERR_SECRET_TOO_LONG,
+                call: |c, len| {
+                    let _ = c.claim_with_secret(hashlock(c), oversized(len), None);
+                },
+            },
+            EntryPoint {
+                name: "claim_with_secrets",
+                caller: resolver,
+                deposit: 0,
+                max: MAX_HASHLOCK_LEN,
+                code: ERR_HASHLOCK_TOO_LONG,
+                call: |c, len| {
+                    let _ = c.claim_with_secrets(oversized(len), secret(1), secret(2), None);
+                },
+            },
+            EntryPoint {
+                name: "claim_with_secret_relayed",
+                caller: resolver,
+                deposit: 0,
+                max: MAX_HASHLOCK_LEN,
+                code: ERR_HASHLOCK_TOO_LONG,
+                call: |c, len| {
+                    let _ = c.claim_with_secret_relayed(
+                        oversized(len),
+                        secret(1),
+                        resolver(),
+                        U128(0),
+                        String::new(),
+                        String::new(),
+                    );
+                },
+            },
+            EntryPoint {
+                name: "claim_partial_with_secret secret",
+                caller: resolver,
+                deposit: 0,
+                max: MAX_SECRET_LEN,
+                code: ERR_SECRET_TOO_LONG,
+                call: |c, len| {
+                    let _ = c.claim_partial_with_secret(1, oversized(len), vec![], 0);
+                },
+            },
+            EntryPoint {
+                name: "claim_partial_with_secret proof",
+                caller: resolver,
+                deposit: 0,
+                max: MAX_MERKLE_PROOF_LEN,
+                code: ERR_MERKLE_PROOF_TOO_LONG,
+                call: |c, len| {
+                    let _ = c.claim_partial_with_secret(1, secret(1), vec![secret(2); len], 0);
+                },
+            },
+            EntryPoint {
+                name: "confirm_mirror_with_proof",
+                caller: resolver,
+                deposit: 0,
+                max: MAX_PROOF_LEN,
+                code: ERR_PROOF_TOO_LONG,
+                call: |c, len| {
+                    let _ = c.confirm_mirror_with_proof(1, proof(len));
+                },
+            },
+            EntryPoint {
+                name: "confirm_eth_escrow",
+                caller: resolver,
+                deposit: 0,
+                max: MAX_PROOF_LEN,
+                code: ERR_PROOF_TOO_LONG,
+                call: |c, len| {
+                    let _ = c.confirm_eth_escrow(1, proof(len));
+                },
+            },
+            EntryPoint {
+                name: "register_inbound_order_with_proof",
+                caller: resolver,
+                deposit: 0,
+                max: MAX_PROOF_LEN,
+                code: ERR_PROOF_TOO_LONG,
+                call: |c, len| {
+                    let _ = c.register_inbound_order_with_proof(proof(len));
+                },
+            },
+            EntryPoint {
+                name: "register_inbound_order",
+                caller: operator,
+                deposit: 0,
+                max: MAX_ORDER_REF_LEN,
+                code: ERR_ORDER_REF_TOO_LONG,
+                call: |c, len| c.register_inbound_order(oversized(len), hashlock(c), U128(1), maker().to_string()),
+            },
+            EntryPoint {
+                name: "claim_inbound_order",
+                caller: resolver,
+                deposit: 0,
+                max: MAX_SECRET_LEN,
+                code: ERR_SECRET_TOO_LONG,
+                call: |c, len| c.claim_inbound_order(hashlock(c), oversized(len)),
+            },
+            EntryPoint {
+                name: "ft_on_transfer",
+                caller: token,
+                deposit: 0,
+                max: MAX_FT_MSG_LEN,
+                code: ERR_FT_MSG_TOO_LONG,
+                call: |c, len| {
+                    let _ = c.ft_on_transfer(maker(), U128(1), oversized(len));
+                },
+            },
+            EntryPoint {
+                name: "register_incoming_order eth_order_ref",
+                caller: resolver,
+                deposit: DEPOSIT,
+                max: MAX_ORDER_REF_LEN,
+                code: ERR_ORDER_REF_TOO_LONG,
+                call: |c, len| c.register_incoming_order(oversized(len), maker(), secret(3), U64(u64::MAX), None),
+            },
+            EntryPoint {
+                name: "register_incoming_order hashlock",
+                caller: resolver,
+                deposit: DEPOSIT,
+                max: MAX_HASHLOCK_LEN,
+                code: ERR_HASHLOCK_TOO_LONG,
+                call: |c, len| c.register_incoming_order("ref".to_string(), maker(), oversized(len), U64(u64::MAX), None),
+            },
+            EntryPoint {
+                name: "claim_incoming hashlock",
+                caller: maker,
+                deposit: 0,
+                max: MAX_HASHLOCK_LEN,
+                code: ERR_HASHLOCK_TOO_LONG,
+                call: |c, len| {
+                    let _ = c.claim_incoming(oversized(len), secret(1));
+                },
+            },
+            EntryPoint {
+                name: "claim_incoming secret",
+                caller: maker,
+                deposit: 0,
+                max: MAX_SECRET_LEN,
+                code: ERR_SECRET_TOO_LONG,
+                call: |c, len| {
+                    let _ = c.claim_incoming(secret(3), oversized(len));
+                },
+            },
+            EntryPoint {
+                name: "reclaim_incoming",
+                caller: resolver,
+                deposit: 0,
+                max: MAX_HASHLOCK_LEN,
+                code: ERR_HASHLOCK_TOO_LONG,
+                call: |c, len| {
+                    let _ = c.reclaim_incoming(oversized(len));
+                },
+            },
+            EntryPoint {
+                name: "cancel_incoming_order",
+                caller: resolver,
+                deposit: 0,
+                max: MAX_HASHLOCK_LEN,
+                code: ERR_HASHLOCK_TOO_LONG,
+                call: |c, len| c.cancel_incoming_order(oversized(len)),
+            },
+            EntryPoint {
+                name: "flag_order_stuck",
+                caller: operator,
+                deposit: 0,
+                max: MAX_JUSTIFICATION_LEN,
+                code: ERR_JUSTIFICATION_TOO_LONG,
+                call: |c, len| c.flag_order_stuck(1, oversized(len), None),
+            },
+            EntryPoint {
+                name: "flag_order_stuck evidence_uri",
+                caller: operator,
+                deposit: 0,
+                max: MAX_EVIDENCE_URI_LEN,
+                code: ERR_EVIDENCE_URI_TOO_LONG,
+                call: |c, len| c.flag_order_stuck(1, "bridge relayer down".to_string(), Some(oversized(len))),
+            },
+            EntryPoint {
+                name: "propose_admin_resolution",
+                caller: operator,
+                deposit: 0,
+                max: MAX_JUSTIFICATION_LEN,
+                code: ERR_JUSTIFICATION_TOO_LONG,
+                call: |c, len| c.propose_admin_resolution(1, AdminResolution::RefundMaker, oversized(len)),
+            },
+            EntryPoint {
+                name: "set_order_metadata metadata",
+                caller: maker,
+                deposit: ONE_NEAR,
+                max: MAX_METADATA_LEN,
+                code: ERR_METADATA_TOO_LONG,
+                call: |c, len| c.set_order_metadata(1, Some(oversized(len)), vec![]),
+            },
+            EntryPoint {
+                name: "set_order_metadata tags",
+                caller: maker,
+                deposit: ONE_NEAR,
+                max: MAX_TAGS,
+                code: ERR_TOO_MANY_TAGS,
+                call: |c, len| c.set_order_metadata(1, None, vec!["tag".to_string(); len]),
+            },
+            EntryPoint {
+                name: "set_order_metadata tag",
+                caller: maker,
+                deposit: ONE_NEAR,
+                max: MAX_TAG_LEN,
+                code: ERR_TAG_TOO_LONG,
+                call: |c, len| c.set_order_metadata(1, None, vec![oversized(len)]),
+            },
+            EntryPoint {
+                name: "scrub_orders",
+                caller: maker,
+                deposit: 1,
+                max: MAX_BATCH_SIZE,
+                code: ERR_BATCH_TOO_LARGE,
+                call: |c, len| c.scrub_orders(vec![1; len]),
+            },
+            EntryPoint {
+                name: "get_orders_by_ids",
+                caller: maker,
+                deposit: 0,
+                max: MAX_VIEW_IDS,
+                code: ERR_BATCH_TOO_LARGE,
+                call: |c, len| {
+                    c.get_orders_by_ids(vec![1; len]);
+                },
+            },
+            EntryPoint {
+                name: "execute_bridge_message",
+                caller: bridge,
+                deposit: 0,
+                max: MAX_BRIDGE_DATA_LEN,
+                code: ERR_BRIDGE_DATA_TOO_LONG,
+                call: |c, len| {
+                    c.execute_bridge_message(
+                        BridgeMessage {
+                            order_id: 1,
+                            chain_id: 1,
+                            nonce: 1,
+                            route_id: 0,
+                            target_contract: ETHEREUM_CONTRACT.to_string(),
+                            action: "cancel_order".to_string(),
+                            encoding: PayloadEncoding::Abi,
+                            data: oversized(len),
+                            commitment: String::new(),
+                        },
+                        1,
+                    )
+                },
+            },
+        ]
+    }
+
+    // Just past the limit, then random lengths up to 64 times over it
+    #[test]
+    fn oversized_inputs_are_rejected_before_any_write() {
+        let mut rng = StdRng::seed_from_u64(209);
+        let mut contract = fixture();
+        for entry in entry_points() {
+            let mut lengths = vec![entry.max + 1];
+            lengths.extend((0..4).map(|_| rng.gen_range(entry.max + 2..=entry.max * 64)));
+            for len in lengths {
+                let outcome = catch_unwind(AssertUnwindSafe(|| {
+                    assert_rejected_unchanged(&mut contract, (entry.caller)(), entry.deposit, entry.code, |c| {
+                        (entry.call)(c, len)
+                    })
+                }));
+                if let Err(panic) = outcome {
+                    panic!("{} with length {}: {}", entry.name, len, panic_message(panic));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn error_codes_are_distinct() {
+        let mut codes = vec![
+            ERR_TOKEN_OUT_TOO_LONG,
+            ERR_HASHLOCK_TOO_LONG,
+            ERR_SECRET_TOO_LONG,
+            ERR_METADATA_TOO_LONG,
+            ERR_MEMO_TOO_LONG,
+            ERR_ORDER_REF_TOO_LONG,
+            ERR_TAG_TOO_LONG,
+            ERR_TOO_MANY_TAGS,
+            ERR_BRIDGE_DATA_TOO_LONG,
+            ERR_PROOF_TOO_LONG,
+            ERR_EVIDENCE_URI_TOO_LONG,
+            ERR_METHOD_NAME_TOO_LONG,
+            ERR_JUSTIFICATION_TOO_LONG,
+            ERR_FT_MSG_TOO_LONG,
+            ERR_SIGNED_PAYLOAD_TOO_LONG,
+            ERR_BATCH_TOO_LARGE,
+            ERR_MERKLE_PROOF_TOO_LONG,
+        ];
+        let count = codes.len();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), count);
+    }
+
+    #[test]
+    fn limits_are_in_the_config() {
+        let contract = setup();
+        let limits = contract.get_config().input_limits;
+        assert_eq!(limits.max_token_out_len as usize, MAX_TOKEN_OUT_LEN);
+        assert_eq!(limits.max_bridge_data_len as usize, MAX_BRIDGE_DATA_LEN);
+        assert_eq!(limits.max_signed_payload_len as usize, MAX_SIGNED_PAYLOAD_LEN);
+        assert_eq!(limits.max_evidence_uri_len as usize, MAX_EVIDENCE_URI_LEN);
+    }
+
+    #[test]
+    fn inputs_at_the_limit_pass_the_length_check() {
+        assert!(check_len(MAX_MEMO_LEN, MAX_MEMO_LEN, ERR_MEMO_TOO_LONG).is_none());
+        let violation = check_len(MAX_MEMO_LEN + 1, MAX_MEMO_LEN, ERR_MEMO_TOO_LONG).unwrap();
+        assert_eq!(violation.code, ERR_MEMO_TOO_LONG);
+    }
+}
@@ -0,0 +1,116 @@
+use near_sdk::{env, near_bindgen, require, Promise};
+use sha2::{Digest, Sha256};
+
+use crate::limits;
+use crate::normalize;
+use crate::validation;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, Balance, CrossChainOrder, HashAlgorithm};
+
+// Orders with `secret_parts` use their hashlock as the root of a Merkle tree
+// over the hashed secrets, one per part, padded with zero leaves to the next
+// power of two. Nodes hash `left || right` with the order's hash algorithm
+// and a leaf's position is its index, so each secret can be spent once and
+// revealing one part's secret does not unlock the others.
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    /// Claims one part of a Merkle order's lock with the secret of leaf
+    /// `index`. Each claim releases one part of the order; the last one of
+    /// a lock also takes what rounding left over.
+    pub fn claim_partial_with_secret(&mut self, order_id: u64, secret: String, proof: Vec<String>, index: u64) -> Promise {
+        limits::assert_len(secret.len(), limits::MAX_SECRET_LEN, limits::ERR_SECRET_TOO_LONG);
+        if let Some(violation) = validation::secret_violation(&secret) {
+            violation.panic();
+        }
+        limits::assert_len(proof.len(), limits::MAX_MERKLE_PROOF_LEN, limits::ERR_MERKLE_PROOF_TOO_LONG);
+        let claimant = env::predecessor_account_id();
+        let order = self.orders.get(&order_id).expect("Order not found");
+        let parts = order.secret_parts.expect("Order has a single secret; claim with claim_with_secret");
+        require!(index < parts as u64, "Secret index out of range");
+        let index = index as u32;
+        let taker = self.authorize_claim(&order, &claimant);
+
+        let mut used = self.used_secret_indices.get(&order_id).unwrap_or_default();
+        require!(!used.contains(&index), "Secret index already used");
+        require!(
+            verify_proof(&order, &secret, &proof, index),
+            "Invalid Merkle proof"
+        );
+        used.push(index);
+        self.used_secret_indices.insert(&order_id, &used);
+
+        let filled = part_release(&order);
+        self.settle_fill(order, taker.clone(), taker, filled, Some(secret), None, Some(index), 0)
+    }
+
+    // Leaf indices already claimed, in claim order
+    pub fn get_used_secret_indices(&self, order_id: u64) -> Vec<u32> {
+        self.used_secret_indices.get(&order_id).unwrap_or_default()
+    }
+}
+
+pub(crate) fn digest(algorithm: HashAlgorithm, data: &[u8]) -> Vec<u8> {
+    match algorithm {
+        HashAlgorithm::Sha256 => {
+            let mut hasher = Sha256::new();
+            hasher.update(data);
+            hasher.finalize().to_vec()
+        }
+        HashAlgorithm::Keccak256 => env::keccak256(data),
+    }
+}
+
+/// Locks on Merkle orders cover whole parts, unless they take everything
+/// that is left, and never leave less than a part behind; otherwise the
+/// order could run out of secrets before it is filled.
+pub(crate) fn assert_whole_parts(order: &CrossChainOrder, amount: Balance) {
+    let part = part_size(order);
+    let rest = order.amount_remaining.0 - amount;
+    require!(
+        rest == 0 || (amount.is_multiple_of(part) && rest >= part),
+        "Merkle orders fill in whole parts"
+    );
+}
+
+fn part_size(order: &CrossChainOrder) -> Balance {
+    order.amount_in.0 / order.secret_parts.unwrap_or(1) as Balance
+}
+
+// One part, or the rest of the lock when less than two parts are left in it
+fn part_release(order: &CrossChainOrder) -> Balance {
+    let part = part_size(order);
+    if order.locked_amount.0 >= 2 * part {
+        part
+    } else {
+        order.locked_amount.0
+    }
+}
+
+fn verify_proof(order: &CrossChainOrder, secret: &str, proof: &[String], index: u32) -> bool {
+    let parts = order.secret_parts.unwrap_or(1);
+    let depth = 32 - (parts - 1).leading_zeros();
+    if proof.len() != depth as usize {
+        return false;
+    }
+    let root = match hex::decode(&order.hashlock) {
+        Ok(root) => root,
+        Err(_) => return false,
+    };
+    let secret = match validation::secret_bytes(secret) {
+        Ok(secret) => secret,
+        Err(_) => return false,
+    };
+    let mut node = digest(order.hash_algorithm, &secret);
+    for (level, sibling) in proof.iter().enumerate() {
+        let sibling = match hex::decode(normalize::strip_hex_prefix(sibling)) {
+            Ok(sibling) if sibling.len() == 32 => sibling,
+            _ => return false,
+        };
+        let pair = if (index >> level) & 1 == 0 {
+            [node, sibling].concat()
+        } else {
+            [sibling, node].concat()
+        };
+        node = digest(order.hash_algorithm, &pair);
+    }
+    node == root
+}
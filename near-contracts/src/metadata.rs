@@ -0,0 +1,150 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, env, near_bindgen, require, AccountId, NearToken, Promise};
+
+use crate::limits;
+use crate::normalize;
+use crate::validation::Violation;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, Balance, OrderStatus, StorageStore};
+
+pub const ERR_ORDER_NOT_FOUND: &str = "ERR_ORDER_NOT_FOUND";
+pub const ERR_NOT_ORDER_MAKER: &str = "ERR_NOT_ORDER_MAKER";
+pub const ERR_ORDER_NOT_TERMINAL: &str = "ERR_ORDER_NOT_TERMINAL";
+
+/// Free-form integrator data attached to an order by its maker.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OrderMetadata {
+    pub metadata: Option<String>,
+    pub tags: Vec<String>,
+    pub storage_deposit: U128, // Paid by the maker, refunded on removal
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    /// Replaces the order's metadata. The attached deposit pays for storage;
+    /// any excess and the previous deposit are refunded.
+    #[payable]
+    pub fn set_order_metadata(&mut self, order_id: u64, metadata: Option<String>, tags: Vec<String>) {
+        if let Some(metadata) = &metadata {
+            limits::assert_len(metadata.len(), limits::MAX_METADATA_LEN, limits::ERR_METADATA_TOO_LONG);
+        }
+        limits::assert_len(tags.len(), limits::MAX_TAGS, limits::ERR_TOO_MANY_TAGS);
+        let tags: Vec<String> = tags.iter().map(|tag| normalize::tag(tag)).collect();
+        for tag in &tags {
+            limits::assert_len(tag.len(), limits::MAX_TAG_LEN, limits::ERR_TAG_TOO_LONG);
+        }
+
+        let maker = env::predecessor_account_id();
+        let order = self
+            .orders
+            .get(&order_id)
+            .unwrap_or_else(|| Violation::new(ERR_ORDER_NOT_FOUND, "order not found").panic());
+        if order.maker != maker {
+            Violation::new(ERR_NOT_ORDER_MAKER, "only the maker can set metadata").panic();
+        }
+
+        let previous_deposit = self
+            .order_metadata
+            .remove(&order_id)
+            .map(|entry| entry.storage_deposit.0)
+            .unwrap_or(0);
+        let storage_before = env::storage_usage();
+        let mut entry = OrderMetadata { metadata, tags, storage_deposit: U128(0) };
+        self.order_metadata.insert(&order_id, &entry);
+        let cost = env::storage_usage().saturating_sub(storage_before) as Balance
+            * env::storage_byte_cost().as_yoctonear();
+
+        let available = env::attached_deposit().as_yoctonear() + previous_deposit;
+        require!(available >= cost, "Attached deposit does not cover metadata storage");
+        entry.storage_deposit = U128(cost);
+        self.order_metadata.insert(&order_id, &entry);
+
+        if available > cost {
+            Promise::new(maker).transfer(NearToken::from_yoctonear(available - cost));
+        }
+    }
+
+    pub fn get_order_metadata(&self, order_id: u64) -> Option<OrderMetadata> {
+        self.order_metadata.get(&order_id)
+    }
+
+    /// Deletes the metadata, tags and callback registration of a terminal
+    /// order and refunds their storage. Amounts, status, hashes and
+    /// timestamps are kept.
+    #[payable]
+    pub fn scrub_order_metadata(&mut self, order_id: u64) {
+        assert_one_yocto();
+        let maker = env::predecessor_account_id();
+        let refund = self.scrub_order(order_id, &maker);
+        refund_scrub(maker, refund);
+    }
+
+    #[payable]
+    pub fn scrub_orders(&mut self, order_ids: Vec<u64>) {
+        assert_one_yocto();
+        limits::assert_len(order_ids.len(), limits::MAX_BATCH_SIZE, limits::ERR_BATCH_TOO_LARGE);
+        let maker = env::predecessor_account_id();
+        let refund = order_ids
+            .into_iter()
+            .map(|order_id| self.scrub_order(order_id, &maker))
+            .sum();
+        refund_scrub(maker, refund);
+    }
+}
+
+impl AdaptiveCrossChain {
+    // Works on live and archived orders alike; returns the storage refund
+    fn scrub_order(&mut self, order_id: u64, caller: &AccountId) -> Balance {
+        let (maker, status) = match self.orders.get(&order_id) {
+            Some(order) => (order.maker, order.status),
+            None => match self.archived_orders.get(&order_id) {
+                Some(archived) => (archived.maker, archived.status),
+                None => Violation::new(ERR_ORDER_NOT_FOUND, "order not found").panic(),
+            },
+        };
+        if maker != *caller {
+            Violation::new(ERR_NOT_ORDER_MAKER, "only the maker can scrub an order").panic();
+        }
+        if !matches!(
+            status,
+            OrderStatus::Completed | OrderStatus::Expired | OrderStatus::Cancelled
+        ) {
+            Violation::new(ERR_ORDER_NOT_TERMINAL, "only terminal orders can be scrubbed").panic();
+        }
+
+        let mut refund = self
+            .order_metadata
+            .remove(&order_id)
+            .map(|entry| entry.storage_deposit.0)
+            .unwrap_or(0);
+
+        let storage_before = env::storage_usage();
+        if self.settlement_callbacks.remove(&order_id).is_some() {
+            refund += (storage_before - env::storage_usage()) as Balance * env::storage_byte_cost().as_yoctonear();
+            self.record_storage(StorageStore::Notifications, storage_before);
+        }
+
+        if let Some(mut archived) = self.archived_orders.get(&order_id) {
+            archived.scrubbed = true;
+            self.archived_orders.insert(&order_id, &archived);
+        }
+
+        self.audit("scrub_order_metadata", Some(order_id));
+        refund
+    }
+
+    /// Drops metadata when an order is archived and returns its deposit.
+    pub(crate) fn release_order_metadata(&mut self, order_id: u64) -> Balance {
+        self.order_metadata
+            .remove(&order_id)
+            .map(|entry| entry.storage_deposit.0)
+            .unwrap_or(0)
+    }
+}
+
+fn refund_scrub(maker: AccountId, refund: Balance) {
+    // The 1 yocto confirmation deposit goes back with the refund
+    Promise::new(maker).transfer(NearToken::from_yoctonear(refund + 1));
+}
@@ -0,0 +1,1020 @@
+use near_sdk::IntoStorageKey;
+
+// The old layout names every type the contract stores
+use super::*;
+
+// Bumped by every migrate() that changes the contract's layout
+pub const STATE_VERSION: u32 = 1;
+
+/// Stored form of an order. A new order layout gets a new variant; reads
+/// convert older variants to the current CrossChainOrder and writes store
+/// the current variant, so orders migrate as they are touched instead of
+/// in one pass.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub enum VersionedOrder {
+    V1(CrossChainOrderV1),
+    V2(CrossChainOrderV2),
+    V3(CrossChainOrderV3),
+    V4(CrossChainOrder),
+}
+
+impl From<VersionedOrder> for CrossChainOrder {
+    fn from(order: VersionedOrder) -> Self {
+        match order {
+            VersionedOrder::V1(order) => order.into(),
+            VersionedOrder::V2(order) => order.into(),
+            VersionedOrder::V3(order) => order.into(),
+            VersionedOrder::V4(order) => order,
+        }
+    }
+}
+
+/// Order layout before `memo`, stored as V1 and by the unversioned state.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct CrossChainOrderV1 {
+    order_id: u64,
+    maker: AccountId,
+    token_in: AccountId,
+    token_out: String,
+    amount_in: U128,
+    amount_remaining: U128,
+    locked_amount: U128,
+    base_price: U128,
+    current_slippage: u64,
+    max_slippage_deviation: u64,
+    target_chain_id: u64,
+    hashlock: String,
+    timelock: U64,
+    timelock_ns: U64,
+    secret: Option<String>,
+    status: OrderStatus,
+    created_at: U64,
+    last_slippage_update: U64,
+    fill_attempts: u64,
+    last_fill_attempt_at: Option<U64>,
+    mirror_confirmed: bool,
+    fee_waived: bool,
+    private_economics: bool,
+    terminal_at: Option<U64>,
+    bridge_route: u8,
+    taker: Option<AccountId>,
+    locked_at: Option<U64>,
+    gas_sponsorship: U128,
+    baseline_slippage: u64,
+    needs_reconfirmation: bool,
+    skip_reconfirmation: bool,
+    hash_algorithm: HashAlgorithm,
+    secret_parts: Option<u32>,
+    auction: Option<DutchAuction>,
+    locked_price: Option<U128>,
+    timelock_extension: U64,
+    exclusive_claim_until: Option<U64>,
+    public_slippage_updates: bool,
+    valid_until: Option<U64>,
+}
+
+impl From<CrossChainOrderV1> for CrossChainOrder {
+    fn from(order: CrossChainOrderV1) -> Self {
+        CrossChainOrderV2 {
+            order_id: order.order_id,
+            maker: order.maker,
+            token_in: order.token_in,
+            token_out: order.token_out,
+            amount_in: order.amount_in,
+            amount_remaining: order.amount_remaining,
+            locked_amount: order.locked_amount,
+            base_price: order.base_price,
+            current_slippage: order.current_slippage,
+            max_slippage_deviation: order.max_slippage_deviation,
+            target_chain_id: order.target_chain_id,
+            hashlock: order.hashlock,
+            timelock: order.timelock,
+            timelock_ns: order.timelock_ns,
+            secret: order.secret,
+            status: order.status,
+            created_at: order.created_at,
+            last_slippage_update: order.last_slippage_update,
+            fill_attempts: order.fill_attempts,
+            last_fill_attempt_at: order.last_fill_attempt_at,
+            mirror_confirmed: order.mirror_confirmed,
+            fee_waived: order.fee_waived,
+            private_economics: order.private_economics,
+            terminal_at: order.terminal_at,
+            bridge_route: order.bridge_route,
+            taker: order.taker,
+            locked_at: order.locked_at,
+            gas_sponsorship: order.gas_sponsorship,
+            baseline_slippage: order.baseline_slippage,
+            needs_reconfirmation: order.needs_reconfirmation,
+            skip_reconfirmation: order.skip_reconfirmation,
+            hash_algorithm: order.hash_algorithm,
+            secret_parts: order.secret_parts,
+            auction: order.auction,
+            locked_price: order.locked_price,
+            timelock_extension: order.timelock_extension,
+            exclusive_claim_until: order.exclusive_claim_until,
+            public_slippage_updates: order.public_slippage_updates,
+            valid_until: order.valid_until,
+            memo: None,
+        }
+        .into()
+    }
+}
+
+/// Order layout before dual hashlocks, stored as V2.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct CrossChainOrderV2 {
+    order_id: u64,
+    maker: AccountId,
+    token_in: AccountId,
+    token_out: String,
+    amount_in: U128,
+    amount_remaining: U128,
+    locked_amount: U128,
+    base_price: U128,
+    current_slippage: u64,
+    max_slippage_deviation: u64,
+    target_chain_id: u64,
+    hashlock: String,
+    timelock: U64,
+    timelock_ns: U64,
+    secret: Option<String>,
+    status: OrderStatus,
+    created_at: U64,
+    last_slippage_update: U64,
+    fill_attempts: u64,
+    last_fill_attempt_at: Option<U64>,
+    mirror_confirmed: bool,
+    fee_waived: bool,
+    private_economics: bool,
+    terminal_at: Option<U64>,
+    bridge_route: u8,
+    taker: Option<AccountId>,
+    locked_at: Option<U64>,
+    gas_sponsorship: U128,
+    baseline_slippage: u64,
+    needs_reconfirmation: bool,
+    skip_reconfirmation: bool,
+    hash_algorithm: HashAlgorithm,
+    secret_parts: Option<u32>,
+    auction: Option<DutchAuction>,
+    locked_price: Option<U128>,
+    timelock_extension: U64,
+    exclusive_claim_until: Option<U64>,
+    public_slippage_updates: bool,
+    valid_until: Option<U64>,
+    memo: Option<String>,
+}
+
+impl From<CrossChainOrderV2> for CrossChainOrder {
+    fn from(order: CrossChainOrderV2) -> Self {
+        CrossChainOrderV3 {
+            order_id: order.order_id,
+            maker: order.maker,
+            token_in: order.token_in,
+            token_out: order.token_out,
+            amount_in: order.amount_in,
+            amount_remaining: order.amount_remaining,
+            locked_amount: order.locked_amount,
+            base_price: order.base_price,
+            current_slippage: order.current_slippage,
+            max_slippage_deviation: order.max_slippage_deviation,
+            target_chain_id: order.target_chain_id,
+            hashlock: order.hashlock,
+            timelock: order.timelock,
+            timelock_ns: order.timelock_ns,
+            secret: order.secret,
+            status: order.status,
+            created_at: order.created_at,
+            last_slippage_update: order.last_slippage_update,
+            fill_attempts: order.fill_attempts,
+            last_fill_attempt_at: order.last_fill_attempt_at,
+            mirror_confirmed: order.mirror_confirmed,
+            fee_waived: order.fee_waived,
+            private_economics: order.private_economics,
+            terminal_at: order.terminal_at,
+            bridge_route: order.bridge_route,
+            taker: order.taker,
+            locked_at: order.locked_at,
+            gas_sponsorship: order.gas_sponsorship,
+            baseline_slippage: order.baseline_slippage,
+            needs_reconfirmation: order.needs_reconfirmation,
+            skip_reconfirmation: order.skip_reconfirmation,
+            hash_algorithm: order.hash_algorithm,
+            secret_parts: order.secret_parts,
+            auction: order.auction,
+            locked_price: order.locked_price,
+            timelock_extension: order.timelock_extension,
+            exclusive_claim_until: order.exclusive_claim_until,
+            public_slippage_updates: order.public_slippage_updates,
+            valid_until: order.valid_until,
+            memo: order.memo,
+            taker_hashlock: None,
+            hashlock_mode: HashlockMode::Single,
+            taker_secret: None,
+        }
+        .into()
+    }
+}
+
+/// Order layout before `economics_revealed`, stored as V3.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct CrossChainOrderV3 {
+    order_id: u64,
+    maker: AccountId,
+    token_in: AccountId,
+    token_out: String,
+    amount_in: U128,
+    amount_remaining: U128,
+    locked_amount: U128,
+    base_price: U128,
+    current_slippage: u64,
+    max_slippage_deviation: u64,
+    target_chain_id: u64,
+    hashlock: String,
+    timelock: U64,
+    timelock_ns: U64,
+    secret: Option<String>,
+    status: OrderStatus,
+    created_at: U64,
+    last_slippage_update: U64,
+    fill_attempts: u64,
+    last_fill_attempt_at: Option<U64>,
+    mirror_confirmed: bool,
+    fee_waived: bool,
+    private_economics: bool,
+    terminal_at: Option<U64>,
+    bridge_route: u8,
+    taker: Option<AccountId>,
+    locked_at: Option<U64>,
+    gas_sponsorship: U128,
+    baseline_slippage: u64,
+    needs_reconfirmation: bool,
+    skip_reconfirmation: bool,
+    hash_algorithm: HashAlgorithm,
+    secret_parts: Option<u32>,
+    auction: Option<DutchAuction>,
+    locked_price: Option<U128>,
+    timelock_extension: U64,
+    exclusive_claim_until: Option<U64>,
+    public_slippage_updates: bool,
+    valid_until: Option<U64>,
+    memo: Option<String>,
+    taker_hashlock: Option<String>,
+    hashlock_mode: HashlockMode,
+    taker_secret: Option<String>,
+}
+
+impl From<CrossChainOrderV3> for CrossChainOrder {
+    fn from(order: CrossChainOrderV3) -> Self {
+        // Every order that was locked still has a lock or a partial fill
+        // to show for it, except one whose lock was slashed
+        let economics_revealed = order.locked_at.is_some() || order.amount_remaining != order.amount_in;
+        CrossChainOrder {
+            order_id: order.order_id,
+            maker: order.maker,
+            token_in: order.token_in,
+            token_out: order.token_out,
+            amount_in: order.amount_in,
+            amount_remaining: order.amount_remaining,
+            locked_amount: order.locked_amount,
+            base_price: order.base_price,
+            current_slippage: order.current_slippage,
+            max_slippage_deviation: order.max_slippage_deviation,
+            target_chain_id: order.target_chain_id,
+            hashlock: order.hashlock,
+            timelock: order.timelock,
+            timelock_ns: order.timelock_ns,
+            secret: order.secret,
+            status: order.status,
+            created_at: order.created_at,
+            last_slippage_update: order.last_slippage_update,
+            fill_attempts: order.fill_attempts,
+            last_fill_attempt_at: order.last_fill_attempt_at,
+            mirror_confirmed: order.mirror_confirmed,
+            fee_waived: order.fee_waived,
+            private_economics: order.private_economics,
+            terminal_at: order.terminal_at,
+            bridge_route: order.bridge_route,
+            taker: order.taker,
+            locked_at: order.locked_at,
+            gas_sponsorship: order.gas_sponsorship,
+            baseline_slippage: order.baseline_slippage,
+            needs_reconfirmation: order.needs_reconfirmation,
+            skip_reconfirmation: order.skip_reconfirmation,
+            hash_algorithm: order.hash_algorithm,
+            secret_parts: order.secret_parts,
+            auction: order.auction,
+            locked_price: order.locked_price,
+            timelock_extension: order.timelock_extension,
+            exclusive_claim_until: order.exclusive_claim_until,
+            public_slippage_updates: order.public_slippage_updates,
+            valid_until: order.valid_until,
+            memo: order.memo,
+            taker_hashlock: order.taker_hashlock,
+            hashlock_mode: order.hashlock_mode,
+            taker_secret: order.taker_secret,
+            economics_revealed,
+        }
+    }
+}
+
+/// The order map, read and written as current CrossChainOrders.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OrderStore {
+    orders: UnorderedMap<u64, VersionedOrder>,
+}
+
+impl OrderStore {
+    pub fn new<S: IntoStorageKey>(prefix: S) -> Self {
+        Self { orders: UnorderedMap::new(prefix) }
+    }
+
+    pub fn get(&self, order_id: &u64) -> Option<CrossChainOrder> {
+        self.orders.get(order_id).map(CrossChainOrder::from)
+    }
+
+    pub fn insert(&mut self, order_id: &u64, order: &CrossChainOrder) -> Option<CrossChainOrder> {
+        self.orders
+            .insert(order_id, &VersionedOrder::V4(order.clone()))
+            .map(CrossChainOrder::from)
+    }
+
+    pub fn remove(&mut self, order_id: &u64) -> Option<CrossChainOrder> {
+        self.orders.remove(order_id).map(CrossChainOrder::from)
+    }
+
+    pub fn len(&self) -> u64 {
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    // Position in the map's value vector, for paging; see get_orders
+    pub fn get_at(&self, index: u64) -> Option<CrossChainOrder> {
+        self.orders.values_as_vector().get(index).map(CrossChainOrder::from)
+    }
+}
+
+// Status statistics from before the Claiming status
+#[derive(BorshDeserialize)]
+#[cfg_attr(test, derive(BorshSerialize))]
+struct OldStatusCounts {
+    active: u64,
+    locked: u64,
+    completed: u64,
+    expired: u64,
+    cancelled: u64,
+}
+
+#[derive(BorshDeserialize)]
+#[cfg_attr(test, derive(BorshSerialize))]
+struct OldStatusIndex {
+    active: UnorderedSet<u64>,
+    locked: UnorderedSet<u64>,
+    completed: UnorderedSet<u64>,
+    expired: UnorderedSet<u64>,
+    cancelled: UnorderedSet<u64>,
+}
+
+// Chain entry from before per-chain destination contracts
+#[derive(BorshDeserialize, BorshSerialize)]
+struct OldChainConfig {
+    chain_id: u64,
+    name: String,
+    slippage_premium_bps: u64,
+    bridge_delay_seconds: u64,
+    enabled: bool,
+    ethereum_contract: String,
+}
+
+/// Layout of the contract before state_version, when orders were stored
+/// unversioned. Only read by migrate().
+#[derive(BorshDeserialize)]
+#[cfg_attr(test, derive(BorshSerialize))]
+struct OldAdaptiveCrossChain {
+    orders: UnorderedMap<u64, CrossChainOrderV1>,
+    user_orders: LookupMap<AccountId, Vector<u64>>,
+    hashlock_to_order: LookupMap<String, u64>,
+    used_secret_indices: LookupMap<u64, Vec<u32>>,
+    slippage_history: LookupMap<u64, Vector<SlippageHistory>>,
+    next_order_id: u64,
+    owner: AccountId,
+    pending_owner: Option<AccountId>,
+    paused: bool,
+    ethereum_contract: String,
+    bridges: UnorderedMap<u8, BridgeConfig>,
+    default_routes: LookupMap<u64, u8>,
+    chains: UnorderedMap<u64, OldChainConfig>,
+    eth_prover: Option<AccountId>,
+    escrow_proof: EscrowProofConfig,
+    consumed_escrow_proofs: LookupSet<String>,
+    oracle: OracleConfig,
+    smoothing_window: u64,
+    max_history_entries: u64,
+    slippage_update_interval: U64,
+    max_slippage_change: u64,
+    fill_attempt_limit: u64,
+    fill_attempt_reporters: LookupSet<(u64, AccountId)>,
+    default_timelock_duration: U64,
+    claim_windows: ClaimWindows,
+    min_timelock_duration: U64,
+    max_timelock_duration: U64,
+    allow_unconfirmed_locks: bool,
+    reconfirm_threshold_bps: u64,
+    awaiting_reconfirmation: UnorderedSet<u64>,
+    token_out_policy: TokenOutPolicy,
+    token_out_denylist: UnorderedSet<EthAddress>,
+    token_out_allowlist: UnorderedSet<EthAddress>,
+    supported_tokens: LookupMap<u64, Vec<EthAddress>>,
+    require_token_whitelist: bool,
+    max_creates_per_block_per_account: u32,
+    creation_windows: LookupMap<AccountId, CreationWindow>,
+    payload_encodings: LookupMap<u64, PayloadEncoding>,
+    outbound_lanes: LookupMap<u64, LaneState>,
+    outbox: UnorderedMap<u64, QueuedMessage>,
+    next_message_id: u64,
+    max_bridge_retries: u32,
+    acked_messages: LookupMap<(u64, u64), AckRecord>,
+    acked_message_lanes: LookupMap<u64, (u64, u64)>,
+    executed_bridge_nonces: LookupSet<(u64, u64)>,
+    chunked_messages: LookupMap<(u64, u64), ChunkedMessage>,
+    settlement_callbacks: LookupMap<u64, SettlementCallback>,
+    order_metadata: LookupMap<u64, OrderMetadata>,
+    audit_log: Vector<AuditEntry>,
+    event_verbosity: EventLevel,
+    stuck_orders: UnorderedMap<u64, StuckFlag>,
+    pending_resolutions: LookupMap<u64, PendingResolution>,
+    interventions: Vector<Intervention>,
+    order_interventions: LookupMap<u64, u64>,
+    admin_interventions_total: u64,
+    pairs: LookupMap<String, PairConfig>,
+    slippage_models: LookupMap<PairClass, SlippageModel>,
+    pair_class_stats: LookupMap<PairClass, PairClassStats>,
+    peg_break_threshold_bps: u64,
+    expiry_index: TreeMap<u64, Vec<ExpiryEntry>>,
+    refund_priority: RefundPriority,
+    retention_policy: RetentionPolicy,
+    terminal_index: TreeMap<(u8, u64), Vec<u64>>,
+    terminal_indexed_count: u64,
+    archived_orders: LookupMap<u64, ArchivedOrder>,
+    archived_count: u64,
+    storage_refunds: LookupMap<AccountId, Balance>,
+    order_storage_deposits: LookupMap<u64, Balance>,
+    last_order_storage_bytes: StorageUsage,
+    shadow_balances: LookupMap<EthAddress, Vec<ClaimableBalance>>,
+    shadow_nonces: LookupMap<EthAddress, u64>,
+    inbound_orders: LookupMap<String, InboundOrder>,
+    consumed_inbound_proofs: LookupSet<String>,
+    inbound_limits: InboundLimits,
+    supported_fts: UnorderedSet<AccountId>,
+    failed_ft_payouts: LookupMap<(AccountId, AccountId), Balance>,
+    protocol_fee_bps: u64,
+    collected_fees: Balance,
+    collected_bridge_fees: Balance,
+    keepers: UnorderedSet<AccountId>,
+    keeper_reward: Balance,
+    keeper_reward_pool: Balance,
+    keeper_rewards_from_fees: bool,
+    collected_ft_fees: LookupMap<AccountId, Balance>,
+    fee_exempt: LookupSet<AccountId>,
+    promotions: Vector<Promotion>,
+    next_promotion_id: u64,
+    roles: LookupMap<AccountId, Vec<Role>>,
+    resolver_exposure: LookupMap<AccountId, Balance>,
+    exposure_limits: ExposureLimits,
+    exposure_overrides: LookupMap<AccountId, Balance>,
+    registered_resolvers: UnorderedSet<AccountId>,
+    permissioned_mode: bool,
+    resolver_stakes: LookupMap<AccountId, Balance>,
+    resolver_bonding: ResolverBonding,
+    slash_history: Vector<SlashRecord>,
+    store_usage: StoreUsage,
+    storage_budgets: LookupMap<StorageStore, StorageUsage>,
+    status_counts: OldStatusCounts,
+    status_index: OldStatusIndex,
+    open_book_value: Balance,
+    user_history_counts: LookupMap<AccountId, u64>,
+    chain_active_counts: LookupMap<u64, u64>,
+    token_out_active_counts: LookupMap<String, u64>,
+    pending_bridge_messages: u64,
+    dead_letter_count: u64,
+    maker_volume: LookupMap<AccountId, DailyVolume>,
+    resolver_fills: LookupMap<AccountId, u64>,
+    maker_leaderboard: LazyOption<Vec<LeaderboardEntry>>,
+    resolver_leaderboard: LazyOption<Vec<LeaderboardEntry>>,
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    /// Upgrades state written before state_version. Deploy the new code
+    /// with this as the init call. Every order is rewritten once as a
+    /// VersionedOrder; later order layouts migrate lazily. Fails on state
+    /// that is already versioned.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let old: OldAdaptiveCrossChain = env::state_read().expect("No unversioned state to migrate");
+        let mut legacy_orders = old.orders;
+        let entries: Vec<(u64, CrossChainOrder)> = legacy_orders
+            .iter()
+            .map(|(order_id, order)| (order_id, order.into()))
+            .collect();
+        legacy_orders.clear();
+        let mut orders = OrderStore::new(b"o");
+        // Value locked and open order counts start from the orders as they
+        // are; from here they are kept up to date instead of recomputed
+        let mut total_value_locked = 0;
+        let mut user_open_counts = LookupMap::new(storage::StorageKey::UserOpenCounts);
+        // Protocol totals start from the stored orders too. Archived orders
+        // are counted as created only, and orders locked more than once as
+        // one lock
+        let mut protocol_totals = ProtocolTotals {
+            created: old.next_order_id - 1,
+            ..Default::default()
+        };
+        let mut chain_volumes: LookupMap<u64, Balance> = LookupMap::new(storage::StorageKey::ChainVolumes);
+        for (order_id, order) in &entries {
+            orders.insert(order_id, order);
+            if order.locked_at.is_some() {
+                protocol_totals.locked += 1;
+            }
+            match order.status {
+                OrderStatus::Completed => {
+                    protocol_totals.completed += 1;
+                    let completed_at = order.terminal_at.map_or(order.created_at.0, |at| at.0);
+                    protocol_totals.completion_time_ns += completed_at.saturating_sub(order.created_at.0) as u128;
+                }
+                OrderStatus::Cancelled => protocol_totals.cancelled += 1,
+                OrderStatus::Expired => protocol_totals.expired += 1,
+                _ => {}
+            }
+            if ft::is_native(order.token_in.as_str()) && old.chains.get(&order.target_chain_id).is_some() {
+                let volume = chain_volumes.get(&order.target_chain_id).unwrap_or(0);
+                chain_volumes.insert(&order.target_chain_id, &(volume + order.amount_in.0));
+            }
+            if matches!(order.status, OrderStatus::Active | OrderStatus::Locked) {
+                if ft::is_native(order.token_in.as_str()) {
+                    total_value_locked += order.amount_remaining.0;
+                }
+                let open = user_open_counts.get(&order.maker).unwrap_or(0);
+                user_open_counts.insert(&order.maker, &(open + 1));
+            }
+        }
+        // Chains used to share the init counterpart, which is the Ethereum
+        // escrow only. Other chains still on it take no new orders until
+        // set_destination_contract; their open orders keep falling back to it
+        let mut legacy_chains = old.chains;
+        let chain_entries: Vec<OldChainConfig> = legacy_chains.values().collect();
+        legacy_chains.clear();
+        let mut chains: UnorderedMap<u64, ChainConfig> = UnorderedMap::new(b"3");
+        for old_config in chain_entries {
+            let inherited = old_config.chain_id != 1 && old_config.ethereum_contract == old.ethereum_contract;
+            chains.insert(&old_config.chain_id, &ChainConfig {
+                chain_id: old_config.chain_id,
+                name: old_config.name,
+                slippage_premium_bps: old_config.slippage_premium_bps,
+                bridge_delay_seconds: old_config.bridge_delay_seconds,
+                enabled: old_config.enabled,
+                destination_contract: (!inherited).then_some(old_config.ethereum_contract),
+            });
+        }
+        env::log_str(&format!(
+            "State migrated to version {}, {} orders rewritten",
+            STATE_VERSION,
+            entries.len()
+        ));
+
+        Self {
+            orders,
+            user_orders: old.user_orders,
+            hashlock_to_order: old.hashlock_to_order,
+            used_secret_indices: old.used_secret_indices,
+            slippage_history: old.slippage_history,
+            history_heads: LookupMap::new(storage::StorageKey::HistoryHeads),
+            next_order_id: old.next_order_id,
+            owner: old.owner,
+            pending_owner: old.pending_owner,
+            paused: old.paused,
+            ethereum_contract: old.ethereum_contract,
+            bridges: old.bridges,
+            default_routes: old.default_routes,
+            chains,
+            eth_prover: old.eth_prover,
+            escrow_proof: old.escrow_proof,
+            consumed_escrow_proofs: old.consumed_escrow_proofs,
+            oracle: old.oracle,
+            smoothing_window: old.smoothing_window,
+            max_history_entries: old.max_history_entries,
+            slippage_update_interval: old.slippage_update_interval,
+            max_slippage_change: old.max_slippage_change,
+            fill_attempt_limit: old.fill_attempt_limit,
+            fill_attempt_reporters: old.fill_attempt_reporters,
+            default_timelock_duration: old.default_timelock_duration,
+            claim_windows: old.claim_windows,
+            min_timelock_duration: old.min_timelock_duration,
+            max_timelock_duration: old.max_timelock_duration,
+            allow_unconfirmed_locks: old.allow_unconfirmed_locks,
+            reconfirm_threshold_bps: old.reconfirm_threshold_bps,
+            awaiting_reconfirmation: old.awaiting_reconfirmation,
+            token_out_policy: old.token_out_policy,
+            token_out_denylist: old.token_out_denylist,
+            token_out_allowlist: old.token_out_allowlist,
+            supported_tokens: old.supported_tokens,
+            require_token_whitelist: old.require_token_whitelist,
+            max_creates_per_block_per_account: old.max_creates_per_block_per_account,
+            max_open_orders_per_user: counters::DEFAULT_MAX_OPEN_ORDERS_PER_USER,
+            creation_windows: old.creation_windows,
+            payload_encodings: old.payload_encodings,
+            outbound_lanes: old.outbound_lanes,
+            outbox: old.outbox,
+            next_message_id: old.next_message_id,
+            max_bridge_retries: old.max_bridge_retries,
+            acked_messages: old.acked_messages,
+            acked_message_lanes: old.acked_message_lanes,
+            executed_bridge_nonces: old.executed_bridge_nonces,
+            chunked_messages: old.chunked_messages,
+            settlement_callbacks: old.settlement_callbacks,
+            order_metadata: old.order_metadata,
+            audit_log: old.audit_log,
+            event_verbosity: old.event_verbosity,
+            stuck_orders: old.stuck_orders,
+            pending_resolutions: old.pending_resolutions,
+            interventions: old.interventions,
+            order_interventions: old.order_interventions,
+            admin_interventions_total: old.admin_interventions_total,
+            pairs: old.pairs,
+            slippage_models: old.slippage_models,
+            pair_class_stats: old.pair_class_stats,
+            peg_break_threshold_bps: old.peg_break_threshold_bps,
+            expiry_index: old.expiry_index,
+            refund_priority: old.refund_priority,
+            retention_policy: old.retention_policy,
+            terminal_index: old.terminal_index,
+            terminal_indexed_count: old.terminal_indexed_count,
+            archived_orders: old.archived_orders,
+            archived_count: old.archived_count,
+            storage_refunds: old.storage_refunds,
+            order_storage_deposits: old.order_storage_deposits,
+            last_order_storage_bytes: old.last_order_storage_bytes,
+            shadow_balances: old.shadow_balances,
+            shadow_nonces: old.shadow_nonces,
+            inbound_orders: old.inbound_orders,
+            consumed_inbound_proofs: old.consumed_inbound_proofs,
+            inbound_limits: old.inbound_limits,
+            incoming_orders: LookupMap::new(storage::StorageKey::IncomingOrders),
+            incoming_by_recipient: LookupMap::new(storage::StorageKey::IncomingByRecipient),
+            order_signing_keys: LookupMap::new(storage::StorageKey::OrderSigningKeys),
+            order_nonces: LookupMap::new(storage::StorageKey::OrderNonces),
+            order_balances: LookupMap::new(storage::StorageKey::OrderBalances),
+            supported_fts: old.supported_fts,
+            order_size_limits: LookupMap::new(storage::StorageKey::OrderSizeLimits),
+            failed_ft_payouts: old.failed_ft_payouts,
+            protocol_fee_bps: old.protocol_fee_bps,
+            collected_fees: old.collected_fees,
+            collected_bridge_fees: old.collected_bridge_fees,
+            keepers: old.keepers,
+            keeper_reward: old.keeper_reward,
+            keeper_reward_pool: old.keeper_reward_pool,
+            keeper_rewards_from_fees: old.keeper_rewards_from_fees,
+            sweep_bounty: 0,
+            sweep_bounty_from_refunds: false,
+            collected_ft_fees: old.collected_ft_fees,
+            fee_exempt: old.fee_exempt,
+            promotions: old.promotions,
+            next_promotion_id: old.next_promotion_id,
+            roles: old.roles,
+            resolver_exposure: old.resolver_exposure,
+            exposure_limits: old.exposure_limits,
+            exposure_overrides: old.exposure_overrides,
+            registered_resolvers: old.registered_resolvers,
+            permissioned_mode: old.permissioned_mode,
+            resolver_stakes: old.resolver_stakes,
+            resolver_bonding: old.resolver_bonding,
+            slash_history: old.slash_history,
+            store_usage: old.store_usage,
+            storage_budgets: old.storage_budgets,
+            status_counts: StatusCounts {
+                active: old.status_counts.active,
+                locked: old.status_counts.locked,
+                completed: old.status_counts.completed,
+                expired: old.status_counts.expired,
+                cancelled: old.status_counts.cancelled,
+                claiming: 0,
+            },
+            status_index: StatusIndex {
+                active: old.status_index.active,
+                locked: old.status_index.locked,
+                completed: old.status_index.completed,
+                expired: old.status_index.expired,
+                cancelled: old.status_index.cancelled,
+                claiming: UnorderedSet::new(storage::StorageKey::ClaimingOrders),
+            },
+            open_book_value: old.open_book_value,
+            total_value_locked,
+            max_total_value_locked: None,
+            user_history_counts: old.user_history_counts,
+            user_open_counts,
+            chain_active_counts: old.chain_active_counts,
+            token_out_active_counts: old.token_out_active_counts,
+            pending_bridge_messages: old.pending_bridge_messages,
+            dead_letter_count: old.dead_letter_count,
+            maker_volume: old.maker_volume,
+            resolver_fills: old.resolver_fills,
+            protocol_totals,
+            chain_volumes,
+            maker_leaderboard: old.maker_leaderboard,
+            resolver_leaderboard: old.resolver_leaderboard,
+            state_version: STATE_VERSION,
+        }
+    }
+
+    pub fn get_state_version(&self) -> u32 {
+        self.state_version
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use crate::test_utils::ONE_NEAR;
+
+    const OPTIMISM_CONTRACT: &str = "0x2222222222222222222222222222222222222222";
+
+    /// `order` in an older layout. Each layout is a prefix of the next, so
+    /// with no memo and a single hashlock the older encodings are the
+    /// current one less its last `dropped` bytes.
+    fn older_layout<T: BorshDeserialize>(order: &CrossChainOrder, dropped: usize) -> T {
+        assert!(order.memo.is_none() && order.taker_hashlock.is_none());
+        let bytes = borsh::to_vec(order).unwrap();
+        T::try_from_slice(&bytes[..bytes.len() - dropped]).unwrap()
+    }
+
+    fn v1(order: &CrossChainOrder) -> CrossChainOrderV1 {
+        older_layout(order, 5)
+    }
+
+    fn v2(order: &CrossChainOrder) -> CrossChainOrderV2 {
+        older_layout(order, 4)
+    }
+
+    fn v3(order: &CrossChainOrder) -> CrossChainOrderV3 {
+        older_layout(order, 1)
+    }
+
+    fn same_order(left: &CrossChainOrder, right: &CrossChainOrder) -> bool {
+        borsh::to_vec(left).unwrap() == borsh::to_vec(right).unwrap()
+    }
+
+    /// `contract` as it would have been stored before state_version, with
+    /// its orders unversioned and every chain on a shared counterpart
+    /// unless `own_contracts` gives it one. Counters the old layout did not
+    /// have are removed from storage, as migrate() has to rebuild them.
+    fn unversioned(mut contract: AdaptiveCrossChain, own_contracts: &[(u64, &str)]) -> OldAdaptiveCrossChain {
+        let order_ids: Vec<u64> = (1..contract.next_order_id).collect();
+        let orders: Vec<CrossChainOrder> = order_ids.iter().filter_map(|id| contract.orders.remove(id)).collect();
+        for order in &orders {
+            contract.user_open_counts.remove(&order.maker);
+            contract.chain_volumes.remove(&order.target_chain_id);
+        }
+        let mut old_orders = UnorderedMap::new(b"o");
+        for order in &orders {
+            old_orders.insert(&order.order_id, &v1(order));
+        }
+        let configs: Vec<ChainConfig> = contract.chains.values().collect();
+        contract.chains.clear();
+        let mut old_chains = UnorderedMap::new(b"3");
+        for config in configs {
+            let ethereum_contract = own_contracts
+                .iter()
+                .find(|(chain_id, _)| *chain_id == config.chain_id)
+                .map_or(contract.ethereum_contract.clone(), |(_, address)| address.to_string());
+            old_chains.insert(&config.chain_id, &OldChainConfig {
+                chain_id: config.chain_id,
+                name: config.name,
+                slippage_premium_bps: config.slippage_premium_bps,
+                bridge_delay_seconds: config.bridge_delay_seconds,
+                enabled: config.enabled,
+                ethereum_contract,
+            });
+        }
+        let counts = &contract.status_counts;
+        let status_counts = OldStatusCounts {
+            active: counts.active,
+            locked: counts.locked,
+            completed: counts.completed,
+            expired: counts.expired,
+            cancelled: counts.cancelled,
+        };
+        OldAdaptiveCrossChain {
+            orders: old_orders,
+            chains: old_chains,
+            status_counts,
+            status_index: OldStatusIndex {
+                active: contract.status_index.active,
+                locked: contract.status_index.locked,
+                completed: contract.status_index.completed,
+                expired: contract.status_index.expired,
+                cancelled: contract.status_index.cancelled,
+            },
+            user_orders: contract.user_orders,
+            hashlock_to_order: contract.hashlock_to_order,
+            used_secret_indices: contract.used_secret_indices,
+            slippage_history: contract.slippage_history,
+            next_order_id: contract.next_order_id,
+            owner: contract.owner,
+            pending_owner: contract.pending_owner,
+            paused: contract.paused,
+            ethereum_contract: contract.ethereum_contract,
+            bridges: contract.bridges,
+            default_routes: contract.default_routes,
+            eth_prover: contract.eth_prover,
+            escrow_proof: contract.escrow_proof,
+            consumed_escrow_proofs: contract.consumed_escrow_proofs,
+            oracle: contract.oracle,
+            smoothing_window: contract.smoothing_window,
+            max_history_entries: contract.max_history_entries,
+            slippage_update_interval: contract.slippage_update_interval,
+            max_slippage_change: contract.max_slippage_change,
+            fill_attempt_limit: contract.fill_attempt_limit,
+            fill_attempt_reporters: contract.fill_attempt_reporters,
+            default_timelock_duration: contract.default_timelock_duration,
+            claim_windows: contract.claim_windows,
+            min_timelock_duration: contract.min_timelock_duration,
+            max_timelock_duration: contract.max_timelock_duration,
+            allow_unconfirmed_locks: contract.allow_unconfirmed_locks,
+            reconfirm_threshold_bps: contract.reconfirm_threshold_bps,
+            awaiting_reconfirmation: contract.awaiting_reconfirmation,
+            token_out_policy: contract.token_out_policy,
+            token_out_denylist: contract.token_out_denylist,
+            token_out_allowlist: contract.token_out_allowlist,
+            supported_tokens: contract.supported_tokens,
+            require_token_whitelist: contract.require_token_whitelist,
+            max_creates_per_block_per_account: contract.max_creates_per_block_per_account,
+            creation_windows: contract.creation_windows,
+            payload_encodings: contract.payload_encodings,
+            outbound_lanes: contract.outbound_lanes,
+            outbox: contract.outbox,
+            next_message_id: contract.next_message_id,
+            max_bridge_retries: contract.max_bridge_retries,
+            acked_messages: contract.acked_messages,
+            acked_message_lanes: contract.acked_message_lanes,
+            executed_bridge_nonces: contract.executed_bridge_nonces,
+            chunked_messages: contract.chunked_messages,
+            settlement_callbacks: contract.settlement_callbacks,
+            order_metadata: contract.order_metadata,
+            audit_log: contract.audit_log,
+            event_verbosity: contract.event_verbosity,
+            stuck_orders: contract.stuck_orders,
+            pending_resolutions: contract.pending_resolutions,
+            interventions: contract.interventions,
+            order_interventions: contract.order_interventions,
+            admin_interventions_total: contract.admin_interventions_total,
+            pairs: contract.pairs,
+            slippage_models: contract.slippage_models,
+            pair_class_stats: contract.pair_class_stats,
+            peg_break_threshold_bps: contract.peg_break_threshold_bps,
+            expiry_index: contract.expiry_index,
+            refund_priority: contract.refund_priority,
+            retention_policy: contract.retention_policy,
+            terminal_index: contract.terminal_index,
+            terminal_indexed_count: contract.terminal_indexed_count,
+            archived_orders: contract.archived_orders,
+            archived_count: contract.archived_count,
+            storage_refunds: contract.storage_refunds,
+            order_storage_deposits: contract.order_storage_deposits,
+            last_order_storage_bytes: contract.last_order_storage_bytes,
+            shadow_balances: contract.shadow_balances,
+            shadow_nonces: contract.shadow_nonces,
+            inbound_orders: contract.inbound_orders,
+            consumed_inbound_proofs: contract.consumed_inbound_proofs,
+            inbound_limits: contract.inbound_limits,
+            supported_fts: contract.supported_fts,
+            failed_ft_payouts: contract.failed_ft_payouts,
+            protocol_fee_bps: contract.protocol_fee_bps,
+            collected_fees: contract.collected_fees,
+            collected_bridge_fees: contract.collected_bridge_fees,
+            keepers: contract.keepers,
+            keeper_reward: contract.keeper_reward,
+            keeper_reward_pool: contract.keeper_reward_pool,
+            keeper_rewards_from_fees: contract.keeper_rewards_from_fees,
+            collected_ft_fees: contract.collected_ft_fees,
+            fee_exempt: contract.fee_exempt,
+            promotions: contract.promotions,
+            next_promotion_id: contract.next_promotion_id,
+            roles: contract.roles,
+            resolver_exposure: contract.resolver_exposure,
+            exposure_limits: contract.exposure_limits,
+            exposure_overrides: contract.exposure_overrides,
+            registered_resolvers: contract.registered_resolvers,
+            permissioned_mode: contract.permissioned_mode,
+            resolver_stakes: contract.resolver_stakes,
+            resolver_bonding: contract.resolver_bonding,
+            slash_history: contract.slash_history,
+            store_usage: contract.store_usage,
+            storage_budgets: contract.storage_budgets,
+            open_book_value: contract.open_book_value,
+            user_history_counts: contract.user_history_counts,
+            chain_active_counts: contract.chain_active_counts,
+            token_out_active_counts: contract.token_out_active_counts,
+            pending_bridge_messages: contract.pending_bridge_messages,
+            dead_letter_count: contract.dead_letter_count,
+            maker_volume: contract.maker_volume,
+            resolver_fills: contract.resolver_fills,
+            maker_leaderboard: contract.maker_leaderboard,
+            resolver_leaderboard: contract.resolver_leaderboard,
+        }
+    }
+
+    #[test]
+    fn unversioned_state_migrates_with_its_totals() {
+        set_clock(1_000, 1);
+        let mut contract = setup();
+        contract.chains.insert(&10, &ChainConfig {
+            chain_id: 10,
+            name: "Optimism".to_string(),
+            slippage_premium_bps: 40,
+            bridge_delay_seconds: 900,
+            enabled: true,
+            destination_contract: Some(OPTIMISM_CONTRACT.to_string()),
+        });
+        let open = [1, 2].map(|n| {
+            let params = order_params(&contract, n, ONE_NEAR);
+            create_order_with(&mut contract, params, ONE_NEAR)
+        });
+        lock(&mut contract, open[1]);
+        let params = order_params(&contract, 3, 2 * ONE_NEAR);
+        let cancelled = create_order_with(&mut contract, params, 2 * ONE_NEAR);
+        call(maker(), 0);
+        let _ = contract.cancel_order(cancelled);
+        set_clock(2_000, 2);
+        let params = order_params(&contract, 4, 3 * ONE_NEAR);
+        let completed = create_order_with(&mut contract, params, 3 * ONE_NEAR);
+        lock(&mut contract, completed);
+        set_clock(5_000, 3);
+        claim(&mut contract, completed, 4);
+        let orders: Vec<CrossChainOrder> = (1..=4).map(|id| contract.orders.get(&id).unwrap()).collect();
+        assert!(matches!(orders[3].status, OrderStatus::Completed));
+        let open_value: u128 = open.iter().map(|id| contract.orders.get(id).unwrap().amount_remaining.0).sum();
+
+        let old = unversioned(contract, &[(1, ETHEREUM_CONTRACT), (10, OPTIMISM_CONTRACT)]);
+        env::state_write(&old);
+        call(owner(), 0);
+        let migrated = AdaptiveCrossChain::migrate();
+
+        assert_eq!(migrated.get_state_version(), STATE_VERSION);
+        assert_eq!(migrated.orders.len(), 4);
+        for order in &orders {
+            let stored = migrated.orders.get(&order.order_id).unwrap();
+            assert!(same_order(&stored, order), "order {} changed in migration", order.order_id);
+            assert!(matches!(migrated.orders.orders.get(&order.order_id), Some(VersionedOrder::V4(_))));
+        }
+        let totals = &migrated.protocol_totals;
+        assert_eq!((totals.created, totals.locked), (4, 2));
+        assert_eq!((totals.completed, totals.cancelled, totals.expired), (1, 1, 0));
+        let completed_at = orders[3].terminal_at.unwrap().0;
+        assert_eq!(totals.completion_time_ns, (completed_at - orders[3].created_at.0) as u128);
+        assert_eq!(migrated.total_value_locked, open_value);
+        assert_eq!(migrated.user_open_counts.get(&maker()), Some(2));
+        assert_eq!(migrated.status_counts.claiming, 0);
+        let volume: u128 = orders.iter().map(|order| order.amount_in.0).sum();
+        assert_eq!(migrated.chain_volumes.get(&1), Some(volume));
+
+        let destination = |chain_id| migrated.chains.get(&chain_id).unwrap().destination_contract;
+        assert_eq!(destination(1).as_deref(), Some(ETHEREUM_CONTRACT));
+        assert_eq!(destination(10).as_deref(), Some(OPTIMISM_CONTRACT));
+        assert_eq!(destination(137), None, "Polygon shared the Ethereum escrow and inherits it");
+    }
+
+    #[test]
+    fn older_order_layouts_are_read_as_current_orders() {
+        set_clock(1_000, 1);
+        let mut contract = setup();
+        let params = order_params(&contract, 1, ONE_NEAR);
+        let active = create_order_with(&mut contract, params, ONE_NEAR);
+        let params = order_params(&contract, 2, ONE_NEAR);
+        let locked = create_order_with(&mut contract, params, ONE_NEAR);
+        lock(&mut contract, locked);
+
+        for order_id in [active, locked] {
+            let current = contract.orders.get(&order_id).unwrap();
+            let layouts = [VersionedOrder::V1(v1(&current)), VersionedOrder::V2(v2(&current)), VersionedOrder::V3(v3(&current))];
+            for stored in layouts {
+                contract.orders.orders.insert(&order_id, &stored);
+                let read = contract.orders.get(&order_id).unwrap();
+                assert!(same_order(&read, &current), "order {} read differently from an older layout", order_id);
+                assert!(matches!(read.hashlock_mode, HashlockMode::Single) && read.memo.is_none());
+            }
+            // Reading converts without rewriting; the old variant stays
+            // until the order is next written
+            assert!(matches!(contract.orders.orders.get(&order_id), Some(VersionedOrder::V3(_))));
+            contract.orders.insert(&order_id, &current);
+            assert!(matches!(contract.orders.orders.get(&order_id), Some(VersionedOrder::V4(_))));
+        }
+        assert!(!contract.orders.get(&active).unwrap().economics_revealed);
+        assert!(contract.orders.get(&locked).unwrap().economics_revealed);
+    }
+}
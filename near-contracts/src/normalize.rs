@@ -0,0 +1,262 @@
+// Canonical forms for identifiers that arrive from outside the contract.
+// Everything stored or used as a map key goes through here first, and view
+// inputs are normalized the same way before lookup.
+
+use near_sdk::{env, AccountId};
+
+use crate::shadow::EthAddress;
+use crate::validation::Violation;
+
+pub const ERR_INVALID_ETH_ADDRESS: &str = "ERR_INVALID_ETH_ADDRESS";
+pub const ERR_BAD_CHECKSUM: &str = "ERR_BAD_CHECKSUM";
+pub const ERR_INVALID_ACCOUNT_ID: &str = "ERR_INVALID_ACCOUNT_ID";
+
+/// `0x` followed by 40 lowercase hex digits. Mixed-case input must carry a
+/// valid EIP-55 checksum; all-lowercase and all-uppercase input is accepted
+/// as is.
+pub fn eth_address(raw: &str) -> Result<String, Violation> {
+    let digits = strip_hex_prefix(raw.trim());
+    if digits.len() != 40 {
+        return Err(Violation::new(
+            ERR_INVALID_ETH_ADDRESS,
+            format!("Ethereum address must be 40 hex characters after 0x, got {}", digits.len()),
+        ));
+    }
+    if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(Violation::new(
+            ERR_INVALID_ETH_ADDRESS,
+            format!("Ethereum address contains non-hex character {:?}", c),
+        ));
+    }
+    let lower = digits.to_ascii_lowercase();
+    let mixed_case = digits != lower && digits != digits.to_ascii_uppercase();
+    if mixed_case {
+        let expected = checksummed(&lower);
+        if digits != expected {
+            return Err(Violation::new(
+                ERR_BAD_CHECKSUM,
+                format!("Ethereum address fails its EIP-55 checksum; expected 0x{}", expected),
+            ));
+        }
+    }
+    Ok(format!("0x{}", lower))
+}
+
+pub fn eth_address_bytes(raw: &str) -> Result<EthAddress, Violation> {
+    let canonical = eth_address(raw)?;
+    let mut out = [0u8; 20];
+    out.copy_from_slice(&hex::decode(&canonical[2..]).unwrap());
+    Ok(out)
+}
+
+/// Hashlocks and Ethereum order hashes: lowercase hex without a prefix,
+/// matching what `hex::encode` produces.
+pub fn hex_id(raw: &str) -> String {
+    strip_hex_prefix(raw.trim()).to_ascii_lowercase()
+}
+
+/// Drops one 0x or 0X prefix. Repeated prefixes are left for the hex
+/// checks to reject, so "0x0xab…" never passes for "ab…".
+pub fn strip_hex_prefix(raw: &str) -> &str {
+    raw.strip_prefix("0x")
+        .or_else(|| raw.strip_prefix("0X"))
+        .unwrap_or(raw)
+}
+
+pub fn account_id(raw: &str) -> Result<AccountId, Violation> {
+    raw.trim()
+        .parse::<AccountId>()
+        .map_err(|_| Violation::new(ERR_INVALID_ACCOUNT_ID, format!("invalid account id: {}", raw)))
+}
+
+/// Recipients are either an Ethereum address or a NEAR account.
+pub fn recipient(raw: &str) -> Result<String, Violation> {
+    let trimmed = raw.trim();
+    if trimmed.starts_with("0x") || trimmed.starts_with("0X") {
+        eth_address(trimmed)
+    } else {
+        account_id(trimmed).map(String::from)
+    }
+}
+
+pub fn tag(raw: &str) -> String {
+    raw.trim().to_lowercase()
+}
+
+// EIP-55: a hex letter is uppercase when the matching nibble of
+// keccak256(lowercase address) is 8 or more
+fn checksummed(lower: &str) -> String {
+    let hash = env::keccak256(lower.as_bytes());
+    lower
+        .chars()
+        .enumerate()
+        .map(|(index, c)| {
+            let nibble = (hash[index / 2] >> if index % 2 == 0 { 4 } else { 0 }) & 0x0f;
+            if c.is_ascii_alphabetic() && nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::accounts;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::pairs::{PairClass, PairConfig};
+    use crate::roles::Role;
+    use crate::test_utils::*;
+
+    const CASINGS: usize = 64;
+    // EIP-55 test vector
+    const CHECKSUMMED: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+
+    // `hex` with every letter in a random case and an optional 0x or 0X
+    fn any_casing(rng: &mut StdRng, hex: &str) -> String {
+        let digits: String = hex
+            .strip_prefix("0x")
+            .unwrap_or(hex)
+            .chars()
+            .map(|c| if rng.gen() { c.to_ascii_uppercase() } else { c.to_ascii_lowercase() })
+            .collect();
+        format!("{}{}", ["", "0x", "0X"][rng.gen_range(0..3)], digits)
+    }
+
+    // The casings an address is accepted in: lowercase, uppercase, EIP-55
+    fn any_valid_casing(rng: &mut StdRng, address: &str) -> String {
+        let digits = match rng.gen_range(0..3) {
+            0 => address[2..].to_ascii_lowercase(),
+            1 => address[2..].to_ascii_uppercase(),
+            _ => address[2..].to_string(),
+        };
+        format!("{}{}", ["0x", "0X"][rng.gen_range(0..2)], digits)
+    }
+
+    #[test]
+    fn hashlock_lookup_succeeds_for_any_casing() {
+        let mut rng = StdRng::seed_from_u64(231);
+        let mut contract = setup();
+        let params = order_params(&contract, 1, ONE_NEAR);
+        let order_id = create_order_with(&mut contract, params, ONE_NEAR);
+        let hashlock = contract.orders.get(&order_id).unwrap().hashlock;
+
+        for _ in 0..CASINGS {
+            let cased = any_casing(&mut rng, &hashlock);
+            assert_eq!(contract.get_order_by_hashlock(cased.clone()).unwrap().order_id, order_id, "{}", cased);
+            assert!(contract.order_exists_for_hashlock(cased));
+        }
+
+        lock(&mut contract, order_id);
+        call(resolver(), 0);
+        let _ = contract.claim_with_secret(any_casing(&mut rng, &hashlock), secret(1), None);
+        assert_eq!(scheduled_calls("on_claim_transfer").len(), 1);
+    }
+
+    #[test]
+    fn eth_addresses_are_stored_lowercase() {
+        let mut rng = StdRng::seed_from_u64(2310);
+        let canonical = CHECKSUMMED.to_ascii_lowercase();
+        for _ in 0..CASINGS {
+            let cased = any_valid_casing(&mut rng, CHECKSUMMED);
+            assert_eq!(eth_address(&cased).unwrap_or_else(|v| v.panic()), canonical);
+        }
+        // Mixed case other than the checksum is a typo, not another casing
+        let typo = CHECKSUMMED.replacen('a', "A", 1);
+        assert_eq!(eth_address(&typo).err().unwrap().code, ERR_BAD_CHECKSUM);
+        assert_eq!(eth_address("0x5aaeb6053f").err().unwrap().code, ERR_INVALID_ETH_ADDRESS);
+    }
+
+    #[test]
+    fn token_indexes_are_canonical() {
+        let mut rng = StdRng::seed_from_u64(2311);
+        let mut contract = setup();
+        let mut params = order_params(&contract, 1, ONE_NEAR);
+        params.token_out = format!("0x{}", CHECKSUMMED[2..].to_ascii_uppercase());
+        create_order_with(&mut contract, params, ONE_NEAR);
+        call(owner(), 0);
+        contract.register_pair("wrap.near".to_string(), CHECKSUMMED.to_string(), PairClass::Pegged);
+
+        for _ in 0..CASINGS {
+            let cased = any_valid_casing(&mut rng, CHECKSUMMED);
+            assert_eq!(contract.get_token_out_active_count(cased.clone()), 1, "{}", cased);
+            let pair = contract.get_pair("wrap.near".to_string(), cased).unwrap();
+            assert_eq!(pair.token_out, CHECKSUMMED.to_ascii_lowercase());
+        }
+    }
+
+    // Pairs registered before normalization sat under the bare lowercase
+    // hex; they are found from there and moved on the next write
+    #[test]
+    fn legacy_pair_keys_are_rekeyed_on_write() {
+        let mut rng = StdRng::seed_from_u64(2312);
+        let mut contract = setup();
+        let lower = CHECKSUMMED.to_ascii_lowercase();
+        let legacy_key = format!("wrap.near:{}", &lower[2..]);
+        let pair = PairConfig {
+            token_in: "wrap.near".to_string(),
+            token_out: lower[2..].to_string(),
+            pair_class: PairClass::Pegged,
+            peg_deviation_bps: 0,
+            suspended: false,
+        };
+        contract.pairs.insert(&legacy_key, &pair);
+
+        for _ in 0..CASINGS {
+            let cased = any_valid_casing(&mut rng, CHECKSUMMED);
+            assert!(contract.get_pair("wrap.near".to_string(), cased).is_some());
+        }
+        contract.grant_role(accounts(3), Role::Oracle);
+        call(accounts(3), 0);
+        contract.report_peg_deviation("wrap.near".to_string(), any_valid_casing(&mut rng, CHECKSUMMED), 10);
+        assert!(contract.pairs.get(&legacy_key).is_none());
+        assert_eq!(contract.pairs.get(&format!("wrap.near:{}", lower)).unwrap().peg_deviation_bps, 10);
+    }
+
+    #[test]
+    fn inbound_orders_are_found_by_any_casing() {
+        let mut rng = StdRng::seed_from_u64(2313);
+        let mut contract = setup();
+        contract.grant_role(accounts(3), Role::Relayer);
+        let order_hash = hex::encode([0xab; 32]);
+        call(accounts(3), 0);
+        contract.register_inbound_order(
+            any_casing(&mut rng, &order_hash),
+            any_casing(&mut rng, &hashlock_of(&contract, &secret(1))),
+            U128(ONE_NEAR),
+            any_valid_casing(&mut rng, CHECKSUMMED),
+        );
+
+        for _ in 0..CASINGS {
+            let order = contract.get_inbound_order(any_casing(&mut rng, &order_hash)).unwrap();
+            assert_eq!(order.order_hash, order_hash);
+            assert_eq!(order.hashlock, hashlock_of(&contract, &secret(1)));
+            assert_eq!(order.recipient, CHECKSUMMED.to_ascii_lowercase());
+        }
+    }
+
+    #[test]
+    fn tags_are_trimmed_and_lowercased() {
+        assert_eq!(tag("  Market-Maker\t"), "market-maker");
+        assert_eq!(tag("ÉTÉ"), "été");
+    }
+
+    #[test]
+    fn only_one_hex_prefix_is_stripped() {
+        let mut contract = setup();
+        let params = order_params(&contract, 1, ONE_NEAR);
+        let order_id = create_order_with(&mut contract, params, ONE_NEAR);
+        let hashlock = contract.orders.get(&order_id).unwrap().hashlock;
+
+        assert_eq!(strip_hex_prefix("0X0xab"), "0xab");
+        assert!(contract.order_exists_for_hashlock(format!("0x{}", hashlock)));
+        assert!(!contract.order_exists_for_hashlock(format!("0x0x{}", hashlock)));
+        assert!(contract.get_order_by_hashlock(format!("0X0x{}", hashlock)).is_none());
+        assert_eq!(eth_address(&format!("0x{}", CHECKSUMMED)).err().unwrap().code, ERR_INVALID_ETH_ADDRESS);
+    }
+}
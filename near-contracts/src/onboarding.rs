@@ -0,0 +1,104 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, NearToken, Promise};
+
+use crate::ft;
+use crate::storage::StorageCharge;
+use crate::validation::{OrderParams, Violation};
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, Balance};
+
+pub const ERR_DEPOSIT_SHORTFALL: &str = "ERR_DEPOSIT_SHORTFALL";
+
+/// Where the attached deposit of `onboard_and_create` went, in yoctoNEAR.
+/// The components always add up to `attached_deposit`.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OnboardReceipt {
+    pub order_id: u64,
+    pub attached_deposit: U128,
+    pub escrow: U128,
+    pub bridge_fee: U128,
+    pub gas_sponsorship: U128,  // Unused part returned when the order ends
+    pub order_storage: U128,    // Refunded to the maker when the order is archived
+    pub callback_storage: U128, // Likewise
+    pub refunded: U128,         // Excess sent back in this call
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    /// Creates an order with `params.amount_in` as the exact escrow, paying
+    /// the bridge fee and all storage the order needs out of the attached
+    /// deposit. Runs the same validation as `create_cross_chain_order`; a
+    /// shortfall panics, which reverts every write made by the call.
+    #[payable]
+    pub fn onboard_and_create(&mut self, params: OrderParams) -> OnboardReceipt {
+        let maker = env::predecessor_account_id();
+        let attached = env::attached_deposit().as_yoctonear();
+        let escrow = params.amount_in.0;
+        let (_, route) = self
+            .resolve_bridge_route(params.bridge_route, params.target_chain_id)
+            .unwrap_or_else(|violation| violation.panic());
+        let bridge_fee = route.fee.0;
+        let gas_sponsorship = params.gas_sponsorship.map(|amount| amount.0).unwrap_or(0);
+
+        // The callback is registered here so create_order does not take its
+        // storage out of the escrow
+        let mut params = params;
+        let settlement_callback = params.settlement_callback.take();
+        let storage_before = env::storage_usage();
+        let order_id = self.internal_create_order(
+            maker.clone(),
+            ft::NATIVE_TOKEN.parse().unwrap(),
+            OrderParams {
+                amount_in: U128(escrow + bridge_fee + gas_sponsorship),
+                ..params
+            },
+            StorageCharge::None,
+        );
+        let callback_storage = settlement_callback
+            .map(|callback| self.register_settlement_callback(order_id, callback))
+            .unwrap_or(0);
+        let storage_used = env::storage_usage().saturating_sub(storage_before) as Balance
+            * env::storage_byte_cost().as_yoctonear();
+        let order_storage = storage_used.saturating_sub(callback_storage);
+
+        let required = escrow + bridge_fee + gas_sponsorship + order_storage + callback_storage;
+        if attached < required {
+            Violation::new(
+                ERR_DEPOSIT_SHORTFALL,
+                format!(
+                    "attached {} is {} short of escrow {} + bridge fee {} + gas sponsorship {} + order storage {} + callback storage {}",
+                    attached,
+                    required - attached,
+                    escrow,
+                    bridge_fee,
+                    gas_sponsorship,
+                    order_storage,
+                    callback_storage
+                ),
+            )
+            .panic();
+        }
+        self.order_storage_deposits.insert(&order_id, &order_storage);
+
+        let refunded = attached - required;
+        if refunded > 0 {
+            Promise::new(maker).transfer(NearToken::from_yoctonear(refunded));
+        }
+        env::log_str(&format!(
+            "Order {} onboarded: escrow {}, bridge fee {}, order storage {}, callback storage {}, refunded {}",
+            order_id, escrow, bridge_fee, order_storage, callback_storage, refunded
+        ));
+
+        OnboardReceipt {
+            order_id,
+            attached_deposit: U128(attached),
+            escrow: U128(escrow),
+            bridge_fee: U128(bridge_fee),
+            gas_sponsorship: U128(gas_sponsorship),
+            order_storage: U128(order_storage),
+            callback_storage: U128(callback_storage),
+            refunded: U128(refunded),
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json;
+use near_sdk::{env, ext_contract, near_bindgen, require, AccountId, Gas, Promise, PromiseResult};
+
+use crate::{timing, AdaptiveCrossChain, AdaptiveCrossChainExt};
+
+pub const GAS_FOR_ORACLE_CALL: Gas = Gas::from_tgas(10);
+// The update itself notifies the bridge, which takes a call and a callback
+pub const GAS_FOR_ORACLE_CALLBACK: Gas = Gas::from_tgas(80);
+
+// Volatility score of the static model; the oracle path scores a move of
+// BASELINE_DEVIATION_BPS over the lookback window the same
+pub const STATIC_VOLATILITY_SCORE: u64 = 100;
+pub const BASELINE_DEVIATION_BPS: u64 = 200;
+pub const MAX_VOLATILITY_SCORE: u64 = 1_000;
+
+/// Where the price feed for volatility comes from.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OracleConfig {
+    pub oracle_account: Option<AccountId>, // None turns request_slippage_update off
+    pub lookback_secs: u64,                // Window the oracle measures the deviation over
+    pub max_staleness_secs: u64,           // Older prices fall back to the static score
+}
+
+impl Default for OracleConfig {
+    fn default() -> Self {
+        Self {
+            oracle_account: None,
+            lookback_secs: 3_600,
+            max_staleness_secs: 300,
+        }
+    }
+}
+
+/// The oracle's answer for a pair.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PriceReport {
+    pub price: U128,
+    pub deviation_bps: u64, // Largest move from the window's mean
+    pub timestamp: U64,     // When the price was observed, in nanoseconds
+}
+
+/// What a slippage update's volatility score was based on.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum VolatilitySource {
+    Static,            // update_order_slippage, or entries from before the oracle
+    Oracle,            // A fresh oracle price
+    OracleUnavailable, // The oracle call failed or its price was stale
+    Repriced,          // The maker repriced the order; slippage is unchanged
+}
+
+#[ext_contract(ext_price_oracle)]
+#[allow(dead_code)] // Only the generated call builders are used
+pub trait PriceOracle {
+    fn get_price(&self, token_in: AccountId, token_out: String, lookback_secs: u64) -> PriceReport;
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    pub fn set_oracle_config(&mut self, config: OracleConfig) {
+        self.assert_owner();
+        require!(config.lookback_secs > 0, "Lookback window must be positive");
+        require!(config.max_staleness_secs > 0, "Staleness bound must be positive");
+        env::log_str(&format!(
+            "oracle_account set from {:?} to {:?}",
+            self.oracle.oracle_account, config.oracle_account
+        ));
+        self.oracle = config;
+        self.audit("set_oracle_config", None);
+    }
+
+    pub fn get_oracle_config(&self) -> OracleConfig {
+        self.oracle.clone()
+    }
+
+    /// Asks the oracle for the pair's price deviation and updates the
+    /// order's slippage with it in the callback. If the oracle fails or its
+    /// price is older than `max_staleness_secs`, the update goes ahead with
+    /// the static volatility score and its history entry is marked
+    /// `oracle_unavailable`. Same permissions and interval as
+    /// `update_order_slippage`.
+    pub fn request_slippage_update(&mut self, order_id: u64) -> Promise {
+        self.assert_not_paused();
+        let oracle_account = self
+            .oracle
+            .oracle_account
+            .clone()
+            .expect("Oracle not configured; use update_order_slippage");
+        let order = self.orders.get(&order_id).expect("Order not found");
+        let keeper = env::predecessor_account_id();
+        if let Some(reason) = self.slippage_update_refusal(&order, &keeper) {
+            env::panic_str(reason);
+        }
+        ext_price_oracle::ext(oracle_account)
+            .with_static_gas(GAS_FOR_ORACLE_CALL)
+            .get_price(order.token_in, order.token_out, self.oracle.lookback_secs)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_ORACLE_CALLBACK)
+                    .on_oracle_price(order_id, keeper),
+            )
+    }
+
+    /// The order is checked again, since another update or a lock may have
+    /// landed while the oracle answered; an update that is no longer
+    /// allowed is dropped.
+    #[private]
+    pub fn on_oracle_price(&mut self, order_id: u64, keeper: AccountId) -> bool {
+        let report = match env::promise_result(0) {
+            PromiseResult::Successful(value) => serde_json::from_slice::<PriceReport>(&value).ok(),
+            _ => None,
+        };
+        let order = match self.orders.get(&order_id) {
+            Some(order) => order,
+            None => {
+                env::log_str(&format!("Slippage update for order {} dropped: order not found", order_id));
+                return false;
+            }
+        };
+        let refusal = if self.is_paused() {
+            Some("contract paused")
+        } else {
+            self.slippage_update_refusal(&order, &keeper)
+        };
+        if let Some(reason) = refusal {
+            env::log_str(&format!("Slippage update for order {} dropped: {}", order_id, reason));
+            return false;
+        }
+
+        let max_age = self.oracle.max_staleness_secs.saturating_mul(timing::NS_PER_SEC);
+        let (volatility_score, source) = match report {
+            Some(report) if env::block_timestamp().saturating_sub(report.timestamp.0) <= max_age => {
+                (volatility_score(report.deviation_bps), VolatilitySource::Oracle)
+            }
+            Some(report) => {
+                env::log_str(&format!(
+                    "Oracle price for order {} is stale (observed at {}), using the static score",
+                    order_id, report.timestamp.0
+                ));
+                (STATIC_VOLATILITY_SCORE, VolatilitySource::OracleUnavailable)
+            }
+            None => {
+                env::log_str(&format!("Oracle unavailable for order {}, using the static score", order_id));
+                (STATIC_VOLATILITY_SCORE, VolatilitySource::OracleUnavailable)
+            }
+        };
+        self.apply_slippage_update(order, keeper, volatility_score, source);
+        true
+    }
+}
+
+/// Scales a price deviation to the score the slippage models expect,
+/// capped so one bad reading cannot run away with the premium.
+pub fn volatility_score(deviation_bps: u64) -> u64 {
+    (deviation_bps.saturating_mul(STATIC_VOLATILITY_SCORE) / BASELINE_DEVIATION_BPS).min(MAX_VOLATILITY_SCORE)
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::get_logs;
+
+    use super::*;
+    use crate::test_utils::*;
+    use crate::test_utils::ONE_NEAR;
+    use crate::timing::NS_PER_SEC;
+
+    const START: u64 = 1_700_000_000 * NS_PER_SEC;
+    const UPDATE_INTERVAL_NS: u64 = 300 * NS_PER_SEC;
+
+    // An order whose next slippage update is due at `now`
+    fn due_order() -> (AdaptiveCrossChain, u64, u64) {
+        set_clock(START, 1);
+        let mut contract = setup();
+        let params = order_params(&contract, 1, 10 * ONE_NEAR);
+        let order_id = create_order_with(&mut contract, params, 10 * ONE_NEAR);
+        let now = START + UPDATE_INTERVAL_NS;
+        set_clock(now, 2);
+        (contract, order_id, now)
+    }
+
+    fn answer(contract: &mut AdaptiveCrossChain, order_id: u64, result: PromiseResult) -> bool {
+        callback_with(result);
+        contract.on_oracle_price(order_id, maker())
+    }
+
+    fn price_observed_at(timestamp: u64) -> PromiseResult {
+        let report = PriceReport { price: U128(1_000), deviation_bps: 400, timestamp: U64(timestamp) };
+        PromiseResult::Successful(serde_json::to_vec(&report).unwrap())
+    }
+
+    fn last_entry(contract: &AdaptiveCrossChain, order_id: u64) -> (u64, VolatilitySource) {
+        let history = contract.slippage_log(order_id).unwrap();
+        let entry = history.get(history.len() - 1).unwrap();
+        (entry.volatility_score, entry.volatility_source)
+    }
+
+    #[test]
+    fn fresh_prices_score_the_update() {
+        let (mut contract, order_id, now) = due_order();
+        assert!(answer(&mut contract, order_id, price_observed_at(now - 10 * NS_PER_SEC)));
+        assert!(last_entry(&contract, order_id) == (volatility_score(400), VolatilitySource::Oracle));
+        assert_eq!(volatility_score(400), 2 * STATIC_VOLATILITY_SCORE);
+        assert_eq!(contract.orders.get(&order_id).unwrap().last_slippage_update.0, now);
+    }
+
+    #[test]
+    fn failed_oracle_calls_fall_back_to_the_static_score() {
+        let (mut contract, order_id, now) = due_order();
+        assert!(answer(&mut contract, order_id, PromiseResult::Failed));
+        assert!(last_entry(&contract, order_id) == (STATIC_VOLATILITY_SCORE, VolatilitySource::OracleUnavailable));
+        assert!(get_logs().iter().any(|log| log.contains("Oracle unavailable")));
+        assert_eq!(contract.orders.get(&order_id).unwrap().last_slippage_update.0, now);
+    }
+
+    // A price one second past max_staleness_secs is not used
+    #[test]
+    fn stale_prices_fall_back_to_the_static_score() {
+        let (mut contract, order_id, now) = due_order();
+        let max_age = contract.oracle.max_staleness_secs * NS_PER_SEC;
+        assert!(answer(&mut contract, order_id, price_observed_at(now - max_age - NS_PER_SEC)));
+        assert!(last_entry(&contract, order_id) == (STATIC_VOLATILITY_SCORE, VolatilitySource::OracleUnavailable));
+        assert!(get_logs().iter().any(|log| log.contains("is stale")));
+    }
+}
@@ -0,0 +1,324 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, require};
+
+use crate::normalize;
+use crate::roles::Role;
+use crate::validation::Violation;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, Balance};
+
+pub const ERR_PEG_BROKEN: &str = "ERR_PEG_BROKEN";
+
+// Orders above this size pay the model's large-order premium
+const LARGE_ORDER_THRESHOLD: Balance = 1_000_000_000_000_000_000_000_000_000; // 1000 NEAR
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum PairClass {
+    Pegged,   // Stable-to-stable, e.g. USDC.e -> USDC
+    Major,    // Liquid pairs; default for unregistered pairs
+    LongTail, // Thin liquidity
+}
+
+/// Slippage model parameters for one pair class, all in basis points.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SlippageModel {
+    pub base: u64,
+    pub ethereum_premium: u64,
+    pub polygon_premium: u64,
+    pub other_chain_premium: u64,
+    pub bridge_delay_premium: u64,
+    pub large_order_premium: u64,
+    pub volatility_premium: u64,     // Per 100 points of volatility score
+    pub delay_premium_per_hour: u64, // Per hour of expected bridge delay
+    pub default_max_deviation: u64,  // Used when the maker does not pass one
+}
+
+/// Market inputs to a slippage calculation. Recorded in SlippageHistory so
+/// past decisions can be replayed.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SlippageInputs {
+    pub amount: Balance,
+    pub target_chain_id: u64,
+    pub chain_premium_bps: Option<u64>, // From the chain registry
+    pub volatility_score: u64,
+    pub bridge_delay_secs: u64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PairConfig {
+    pub token_in: String,
+    pub token_out: String,
+    pub pair_class: PairClass,
+    pub peg_deviation_bps: u64, // Last reported distance from the peg
+    pub suspended: bool,        // Peg circuit breaker tripped
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PairClassStats {
+    pub orders_created: u64,
+    pub orders_completed: u64,
+    pub volume: U128,
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    pub fn register_pair(&mut self, token_in: String, token_out: String, pair_class: PairClass) {
+        self.assert_owner();
+        let token_in = String::from(normalize::account_id(&token_in).unwrap_or_else(|v| v.panic()));
+        let token_out = normalize::eth_address(&token_out).unwrap_or_else(|v| v.panic());
+        let key = pair_key(&token_in, &token_out);
+        let existing = self.migrate_pair(&token_in, &token_out);
+        self.pairs.insert(&key, &PairConfig {
+            token_in: token_in.clone(),
+            token_out: token_out.clone(),
+            pair_class,
+            peg_deviation_bps: existing.as_ref().map(|p| p.peg_deviation_bps).unwrap_or(0),
+            suspended: existing.map(|p| p.suspended).unwrap_or(false) && pair_class == PairClass::Pegged,
+        });
+        env::log_str(&format!(
+            "Pair {} registered as {}",
+            key,
+            class_name(pair_class)
+        ));
+    }
+
+    pub fn remove_pair(&mut self, token_in: String, token_out: String) {
+        self.assert_owner();
+        let key = pair_key(&token_in, &token_out);
+        self.migrate_pair(&token_in, &token_out);
+        if self.pairs.remove(&key).is_some() {
+            env::log_str(&format!("Pair {} removed", key));
+        }
+    }
+
+    pub fn set_slippage_model(&mut self, pair_class: PairClass, model: SlippageModel) {
+        self.assert_owner();
+        self.slippage_models.insert(&pair_class, &model);
+        env::log_str(&format!("Slippage model updated for {}", class_name(pair_class)));
+    }
+
+    pub fn set_peg_break_threshold_bps(&mut self, threshold_bps: u64) {
+        self.assert_owner();
+        self.peg_break_threshold_bps = threshold_bps;
+        env::log_str(&format!("peg_break_threshold_bps set to {}", threshold_bps));
+    }
+
+    // Oracle feed for pegged pairs; trips or resets the circuit breaker
+    pub fn report_peg_deviation(&mut self, token_in: String, token_out: String, deviation_bps: u64) {
+        self.assert_role(Role::Oracle);
+        let key = pair_key(&token_in, &token_out);
+        let mut pair = self.migrate_pair(&token_in, &token_out).expect("Pair not registered");
+        require!(pair.pair_class == PairClass::Pegged, "Pair is not pegged");
+
+        let suspended = deviation_bps > self.peg_break_threshold_bps;
+        if suspended != pair.suspended {
+            env::log_str(&format!(
+                "Pair {} {}: peg off by {} bps",
+                key,
+                if suspended { "suspended" } else { "resumed" },
+                deviation_bps
+            ));
+        }
+        pair.peg_deviation_bps = deviation_bps;
+        pair.suspended = suspended;
+        self.pairs.insert(&key, &pair);
+    }
+
+    pub fn get_pair(&self, token_in: String, token_out: String) -> Option<PairConfig> {
+        self.find_pair(&token_in, &token_out)
+    }
+
+    pub fn get_slippage_model(&self, pair_class: PairClass) -> SlippageModel {
+        self.slippage_model(pair_class)
+    }
+
+    pub fn get_pair_class_stats(&self, pair_class: PairClass) -> PairClassStats {
+        self.pair_class_stats.get(&pair_class).unwrap_or_default()
+    }
+}
+
+impl AdaptiveCrossChain {
+    pub(crate) fn pair_class(&self, token_in: &str, token_out: &str) -> PairClass {
+        self.find_pair(token_in, token_out)
+            .map(|pair| pair.pair_class)
+            .unwrap_or(PairClass::Major)
+    }
+
+    pub(crate) fn slippage_model(&self, pair_class: PairClass) -> SlippageModel {
+        self.slippage_models
+            .get(&pair_class)
+            .unwrap_or_else(|| default_slippage_model(pair_class))
+    }
+
+    pub(crate) fn default_max_deviation(&self, token_in: &str, token_out: &str) -> u64 {
+        self.slippage_model(self.pair_class(token_in, token_out))
+            .default_max_deviation
+    }
+
+    pub(crate) fn peg_violation(&self, token_in: &str, token_out: &str) -> Option<Violation> {
+        let pair = self.find_pair(token_in, token_out)?;
+        if pair.suspended {
+            return Some(Violation::new(
+                ERR_PEG_BROKEN,
+                format!(
+                    "pegged pair suspended: peg off by {} bps (threshold {})",
+                    pair.peg_deviation_bps, self.peg_break_threshold_bps
+                ),
+            ));
+        }
+        None
+    }
+
+    // Reads fall back to the legacy key so unmigrated pairs still resolve
+    fn find_pair(&self, token_in: &str, token_out: &str) -> Option<PairConfig> {
+        self.pairs
+            .get(&pair_key(token_in, token_out))
+            .or_else(|| self.pairs.get(&legacy_pair_key(token_in, token_out)))
+    }
+
+    // Writes move a legacy entry to its canonical key first
+    fn migrate_pair(&mut self, token_in: &str, token_out: &str) -> Option<PairConfig> {
+        let key = pair_key(token_in, token_out);
+        if let Some(pair) = self.pairs.get(&key) {
+            return Some(pair);
+        }
+        let legacy_key = legacy_pair_key(token_in, token_out);
+        if legacy_key == key {
+            return None;
+        }
+        let pair = self.pairs.remove(&legacy_key)?;
+        self.pairs.insert(&key, &pair);
+        Some(pair)
+    }
+
+    pub(crate) fn record_pair_class_created(&mut self, pair_class: PairClass, amount: Balance) {
+        let mut stats = self.pair_class_stats.get(&pair_class).unwrap_or_default();
+        stats.orders_created += 1;
+        stats.volume = U128(stats.volume.0 + amount);
+        self.pair_class_stats.insert(&pair_class, &stats);
+    }
+
+    pub(crate) fn record_pair_class_completed(&mut self, pair_class: PairClass) {
+        let mut stats = self.pair_class_stats.get(&pair_class).unwrap_or_default();
+        stats.orders_completed += 1;
+        self.pair_class_stats.insert(&pair_class, &stats);
+    }
+}
+
+/// Slippage in basis points under `model`. The only slippage formula:
+/// production and backtests both call it. A registered chain's premium
+/// replaces the model's per-chain ones, which remain for unregistered
+/// chains.
+pub fn model_slippage(model: &SlippageModel, inputs: &SlippageInputs) -> u64 {
+    let cross_chain_premium = inputs.chain_premium_bps.unwrap_or(match inputs.target_chain_id {
+        1 => model.ethereum_premium,
+        137 => model.polygon_premium,
+        _ => model.other_chain_premium,
+    });
+    let amount_adjustment = if inputs.amount > LARGE_ORDER_THRESHOLD {
+        model.large_order_premium
+    } else {
+        0
+    };
+    let volatility_adjustment = model.volatility_premium * inputs.volatility_score / 100;
+    let delay_adjustment = model.delay_premium_per_hour * inputs.bridge_delay_secs / 3600;
+    model.base
+        + cross_chain_premium
+        + model.bridge_delay_premium
+        + amount_adjustment
+        + volatility_adjustment
+        + delay_adjustment
+}
+
+/// Moves `current` towards `target` by at most `max_deviation` bps.
+pub fn apply_max_deviation(current: u64, target: u64, max_deviation: u64) -> u64 {
+    if target > current {
+        target.min(current + max_deviation)
+    } else {
+        target.max(current.saturating_sub(max_deviation))
+    }
+}
+
+/// Time-weighted average of `samples`, `(timestamp, value)` oldest first,
+/// followed by `latest`. A sample counts for the time until the next one;
+/// `latest` counts for `latest_weight_ns`.
+pub fn time_weighted_average(samples: &[(u64, u64)], latest: (u64, u64), latest_weight_ns: u64) -> u64 {
+    let mut weighted = latest.1 as u128 * latest_weight_ns as u128;
+    let mut total = latest_weight_ns as u128;
+    for (index, (timestamp, value)) in samples.iter().enumerate() {
+        let until = samples.get(index + 1).map_or(latest.0, |next| next.0);
+        let weight = until.saturating_sub(*timestamp) as u128;
+        weighted += *value as u128 * weight;
+        total += weight;
+    }
+    if total == 0 {
+        return latest.1;
+    }
+    (weighted / total) as u64
+}
+
+// Major keeps the parameters every pair used before classes existed
+fn default_slippage_model(pair_class: PairClass) -> SlippageModel {
+    match pair_class {
+        PairClass::Pegged => SlippageModel {
+            base: 5,
+            ethereum_premium: 2,
+            polygon_premium: 3,
+            other_chain_premium: 5,
+            bridge_delay_premium: 2,
+            large_order_premium: 3,
+            volatility_premium: 0,
+            delay_premium_per_hour: 0,
+            default_max_deviation: 10,
+        },
+        PairClass::Major => SlippageModel {
+            base: 50,
+            ethereum_premium: 25,
+            polygon_premium: 50,
+            other_chain_premium: 100,
+            bridge_delay_premium: 25,
+            large_order_premium: 50,
+            volatility_premium: 0,
+            delay_premium_per_hour: 0,
+            default_max_deviation: 100,
+        },
+        PairClass::LongTail => SlippageModel {
+            base: 100,
+            ethereum_premium: 50,
+            polygon_premium: 75,
+            other_chain_premium: 150,
+            bridge_delay_premium: 50,
+            large_order_premium: 100,
+            volatility_premium: 0,
+            delay_premium_per_hour: 0,
+            default_max_deviation: 200,
+        },
+    }
+}
+
+// Canonical key; input that does not normalize falls back to lowercase
+// and simply finds nothing
+fn pair_key(token_in: &str, token_out: &str) -> String {
+    let token_out = normalize::eth_address(token_out).unwrap_or_else(|_| token_out.to_lowercase());
+    format!("{}:{}", token_in.trim(), token_out)
+}
+
+// Before normalization token_out was only lowercased, so pairs registered
+// without the 0x prefix were keyed without it
+fn legacy_pair_key(token_in: &str, token_out: &str) -> String {
+    format!("{}:{}", token_in.trim(), normalize::hex_id(token_out))
+}
+
+fn class_name(pair_class: PairClass) -> &'static str {
+    match pair_class {
+        PairClass::Pegged => "Pegged",
+        PairClass::Major => "Major",
+        PairClass::LongTail => "LongTail",
+    }
+}
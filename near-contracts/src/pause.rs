@@ -0,0 +1,131 @@
+use near_sdk::serde_json::json;
+use near_sdk::{env, near_bindgen, require};
+
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, EventLevel};
+
+// Circuit breaker for incidents on the other chain. Creating, locking,
+// claiming and slippage updates stop; cancels, refunds and withdrawals keep
+// working so funds are never trapped.
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    pub fn pause(&mut self) {
+        self.assert_owner();
+        require!(!self.paused, "Contract is already paused");
+        self.paused = true;
+        self.audit("pause", None);
+        self.emit_event(
+            EventLevel::Minimal,
+            "contract_paused",
+            json!({ "by": env::predecessor_account_id() }),
+        );
+    }
+
+    pub fn unpause(&mut self) {
+        self.assert_owner();
+        require!(self.paused, "Contract is not paused");
+        self.paused = false;
+        self.audit("unpause", None);
+        self.emit_event(
+            EventLevel::Minimal,
+            "contract_unpaused",
+            json!({ "by": env::predecessor_account_id() }),
+        );
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+}
+
+impl AdaptiveCrossChain {
+    pub(crate) fn assert_not_paused(&self) {
+        require!(!self.paused, "Contract is paused");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::get_logs;
+    use near_sdk::AccountId;
+
+    use super::*;
+    use crate::test_utils::*;
+    use crate::timing::NS_PER_SEC;
+    use crate::OrderStatus;
+
+    const START: u64 = 1_700_000_000 * NS_PER_SEC;
+
+    fn rejected_while_paused(
+        contract: &mut AdaptiveCrossChain,
+        caller: AccountId,
+        deposit: u128,
+        f: impl FnOnce(&mut AdaptiveCrossChain),
+    ) {
+        assert_rejected_unchanged(contract, caller, deposit, "Contract is paused", f);
+    }
+
+    #[test]
+    fn pause_stops_trading_but_not_exits() {
+        set_clock(START, 1);
+        let mut contract = setup();
+        let mut ids = vec![];
+        for n in 1..=3 {
+            let params = order_params(&contract, n, ONE_NEAR);
+            ids.push(create_order_with(&mut contract, params, ONE_NEAR));
+        }
+        let (active, locked, expiring) = (ids[0], ids[1], ids[2]);
+        lock(&mut contract, locked);
+
+        call(owner(), 0);
+        contract.pause();
+        assert!(contract.is_paused());
+        set_clock(START + 301 * NS_PER_SEC, 2);
+
+        let params = order_params(&contract, 4, ONE_NEAR);
+        rejected_while_paused(&mut contract, maker(), ONE_NEAR, |c| {
+            create_order_with(c, params, ONE_NEAR);
+        });
+        rejected_while_paused(&mut contract, resolver(), 0, |c| c.lock_order(active));
+        rejected_while_paused(&mut contract, resolver(), 0, |c| c.lock_partial(active, U128(ONE_NEAR / 10)));
+        let hashlock = contract.orders.get(&locked).unwrap().hashlock;
+        rejected_while_paused(&mut contract, resolver(), 0, |c| {
+            let _ = c.claim_with_secret(hashlock, secret(2), None);
+        });
+        rejected_while_paused(&mut contract, maker(), 0, |c| c.update_order_slippage(active));
+        rejected_while_paused(&mut contract, maker(), 0, |c| c.update_order_price(active, U128(2_000), None));
+
+        // Makers can still get their funds back
+        call(maker(), 0);
+        let _ = contract.cancel_order(active);
+        assert!(matches!(contract.orders.get(&active).unwrap().status, OrderStatus::Cancelled));
+        let timelock_ns = contract.orders.get(&expiring).unwrap().timelock_ns.0;
+        set_clock(timelock_ns + 1, 3);
+        call(resolver(), 0);
+        let _ = contract.refund_expired_order(expiring);
+        assert!(matches!(contract.orders.get(&expiring).unwrap().status, OrderStatus::Expired));
+
+        call(owner(), 0);
+        contract.unpause();
+        assert!(!contract.is_paused());
+        let params = order_params(&contract, 4, ONE_NEAR);
+        let reopened = create_order_with(&mut contract, params, ONE_NEAR);
+        lock(&mut contract, reopened);
+    }
+
+    #[test]
+    fn only_the_owner_pauses_and_each_change_is_an_event() {
+        let mut contract = setup();
+        assert_rejected_unchanged(&mut contract, maker(), 0, "Only owner", |c| c.pause());
+        call(owner(), 0);
+        contract.pause();
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"contract_paused\"")));
+        assert_rejected_unchanged(&mut contract, owner(), 0, "already paused", |c| c.pause());
+        assert_rejected_unchanged(&mut contract, maker(), 0, "Only owner", |c| c.unpause());
+
+        call(owner(), 0);
+        contract.unpause();
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"contract_unpaused\"")));
+        assert_rejected_unchanged(&mut contract, owner(), 0, "not paused", |c| c.unpause());
+    }
+}
@@ -0,0 +1,163 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, require};
+
+use crate::fees::BPS_DENOMINATOR;
+use crate::roles::Role;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, Balance};
+
+// Ended and exhausted promotions are pruned once this many are stored
+pub const MAX_PROMOTIONS: u64 = 20;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum PromotionScope {
+    Global,
+    Chain(u64),
+    Pair { token_in: String, token_out: String },
+}
+
+/// A time-boxed protocol fee discount. Applies while `starts_at <= now <
+/// ends_at` and the order fits in the remaining volume.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Promotion {
+    pub id: u64,
+    pub scope: PromotionScope,
+    pub fee_discount_bps: u64, // Subtracted from protocol_fee_bps
+    pub starts_at: U64,        // Nanoseconds
+    pub ends_at: U64,
+    pub max_total_volume: U128,
+    pub volume_used: U128, // amount_in over completed orders
+}
+
+/// What a fee computation is for, so promotions can be matched.
+pub struct FeeContext<'a> {
+    pub target_chain_id: u64,
+    pub token_in: &'a str,
+    pub token_out: Option<&'a str>, // Pair promotions need it
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    pub fn add_promotion(
+        &mut self,
+        scope: PromotionScope,
+        fee_discount_bps: u64,
+        starts_at: U64,
+        ends_at: U64,
+        max_total_volume: U128,
+    ) -> u64 {
+        self.assert_role(Role::Treasurer);
+        require!(starts_at.0 < ends_at.0, "Promotion must end after it starts");
+        require!(
+            fee_discount_bps as u128 <= BPS_DENOMINATOR,
+            "Discount cannot exceed 10000 bps"
+        );
+        if self.promotions.len() >= MAX_PROMOTIONS {
+            self.prune_promotions();
+        }
+        require!(self.promotions.len() < MAX_PROMOTIONS, "Too many live promotions");
+
+        let id = self.next_promotion_id;
+        self.next_promotion_id += 1;
+        self.promotions.push(&Promotion {
+            id,
+            scope,
+            fee_discount_bps,
+            starts_at,
+            ends_at,
+            max_total_volume,
+            volume_used: U128(0),
+        });
+        env::log_str(&format!(
+            "Promotion {} added: {} bps off until {}",
+            id, fee_discount_bps, ends_at.0
+        ));
+        id
+    }
+
+    // Ends the promotion now; its volume counter is kept
+    pub fn end_promotion(&mut self, promotion_id: u64) {
+        self.assert_role(Role::Treasurer);
+        let (index, mut promotion) = self.find_promotion(promotion_id).expect("Promotion not found");
+        promotion.ends_at = U64(promotion.ends_at.0.min(env::block_timestamp()));
+        self.promotions.replace(index, &promotion);
+        env::log_str(&format!("Promotion {} ended", promotion_id));
+    }
+
+    pub fn get_active_promotions(&self) -> Vec<Promotion> {
+        let now = env::block_timestamp();
+        self.promotions
+            .iter()
+            .filter(|promotion| is_live(promotion, now, 0))
+            .collect()
+    }
+}
+
+impl AdaptiveCrossChain {
+    /// The best single promotion covering an order of `amount`, if any.
+    pub(crate) fn best_promotion(&self, context: &FeeContext, amount: Balance) -> Option<Promotion> {
+        let now = env::block_timestamp();
+        self.promotions
+            .iter()
+            .filter(|promotion| is_live(promotion, now, amount) && covers(&promotion.scope, context))
+            // Ties go to the older promotion
+            .fold(None, |best: Option<Promotion>, promotion| match best {
+                Some(best) if best.fee_discount_bps >= promotion.fee_discount_bps => Some(best),
+                _ => Some(promotion),
+            })
+    }
+
+    /// Called at completion with the promotion recorded in the fee breakdown.
+    pub(crate) fn record_promotion_volume(&mut self, promotion_id: u64, amount: Balance) {
+        if let Some((index, mut promotion)) = self.find_promotion(promotion_id) {
+            promotion.volume_used = U128(promotion.volume_used.0 + amount);
+            self.promotions.replace(index, &promotion);
+        }
+    }
+
+    fn find_promotion(&self, promotion_id: u64) -> Option<(u64, Promotion)> {
+        self.promotions
+            .iter()
+            .enumerate()
+            .find(|(_, promotion)| promotion.id == promotion_id)
+            .map(|(index, promotion)| (index as u64, promotion))
+    }
+
+    fn prune_promotions(&mut self) {
+        let now = env::block_timestamp();
+        let mut index = 0;
+        while index < self.promotions.len() {
+            let promotion = self.promotions.get(index).unwrap();
+            if promotion.ends_at.0 <= now || promotion.volume_used.0 >= promotion.max_total_volume.0 {
+                self.promotions.swap_remove(index);
+            } else {
+                index += 1;
+            }
+        }
+    }
+}
+
+fn is_live(promotion: &Promotion, now: u64, amount: Balance) -> bool {
+    promotion.starts_at.0 <= now
+        && now < promotion.ends_at.0
+        && promotion.volume_used.0 + amount <= promotion.max_total_volume.0
+        && promotion.volume_used.0 < promotion.max_total_volume.0
+}
+
+fn covers(scope: &PromotionScope, context: &FeeContext) -> bool {
+    match scope {
+        PromotionScope::Global => true,
+        PromotionScope::Chain(chain_id) => *chain_id == context.target_chain_id,
+        // Ethereum addresses are case-insensitive
+        PromotionScope::Pair { token_in, token_out } => {
+            token_in == context.token_in
+                && context
+                    .token_out
+                    .map(|out| out.eq_ignore_ascii_case(token_out))
+                    .unwrap_or(false)
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen};
+
+use crate::fees::BPS_DENOMINATOR;
+use crate::ft;
+use crate::normalize;
+use crate::{timing, AdaptiveCrossChain, AdaptiveCrossChainExt};
+
+/// What creating an order now would be priced at, computed with the same
+/// helpers used at creation.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct QuoteView {
+    pub token_in: String,
+    pub token_out: String,             // Normalized the way creation stores it
+    pub amount: U128,
+    pub target_chain_id: u64,
+    pub bridge_route: u8,              // Default route for the chain
+    pub bridge_fee: U128,              // Route fee deducted at creation
+    pub storage_cost: U128,            // Estimated storage reserve, native orders only
+    pub net_escrow: U128,              // Escrow left after the bridge fee and storage
+    pub slippage_bps: u64,
+    pub max_slippage_deviation: u64,   // Default for the pair
+    pub bridge_delay_secs: u64,
+    pub timelock_duration: U64,        // Default, in seconds
+    pub timelock_ns: U64,              // Deadline if created in this block
+    pub base_price: Option<U128>,
+    pub effective_price: Option<U128>, // base_price less slippage_bps
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    /// Prices an order without creating it. Quotes use the default route
+    /// and timelock and leave out callback storage and gas sponsorship;
+    /// slippage moves with every later update, so a quote only holds for
+    /// the block it was read in.
+    pub fn estimate_order(
+        &self,
+        token_in: String,
+        token_out: String,
+        amount: U128,
+        target_chain_id: u64,
+        base_price: Option<U128>,
+    ) -> QuoteView {
+        let (bridge_route, route) = self
+            .resolve_bridge_route(None, target_chain_id)
+            .unwrap_or_else(|violation| violation.panic());
+        let token_out = normalize::eth_address(&token_out).unwrap_or_else(|violation| violation.panic());
+        let storage_cost = if ft::is_native(&token_in) {
+            self.get_storage_cost_estimate().0
+        } else {
+            0
+        };
+        // Creation prices slippage before it takes the storage reserve
+        let deposit = amount.0.saturating_sub(route.fee.0);
+        let net_escrow = deposit.saturating_sub(storage_cost);
+
+        let inputs = self.creation_slippage_inputs(deposit, target_chain_id, &token_out, bridge_route);
+        let slippage_bps = self.calculate_cross_chain_slippage(&token_in, &token_out, &inputs);
+        let max_slippage_deviation = self.default_max_deviation(&token_in, &token_out);
+        let effective_price = base_price.map(|price| {
+            U128(price.0 * BPS_DENOMINATOR.saturating_sub(slippage_bps as u128) / BPS_DENOMINATOR)
+        });
+
+        QuoteView {
+            token_in,
+            token_out,
+            amount,
+            target_chain_id,
+            bridge_route,
+            bridge_fee: route.fee,
+            storage_cost: U128(storage_cost),
+            net_escrow: U128(net_escrow),
+            slippage_bps,
+            max_slippage_deviation,
+            bridge_delay_secs: inputs.bridge_delay_secs,
+            timelock_duration: self.default_timelock_duration,
+            timelock_ns: U64(timing::timelock_deadline_ns(
+                self.default_timelock_duration.0,
+                env::block_timestamp(),
+            )),
+            base_price,
+            effective_price,
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use near_sdk::serde_json::json;
+use near_sdk::{env, near_bindgen, require};
+
+use crate::limits;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, CrossChainOrder, EventLevel, OrderStatus, OrderView};
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    // 0 disables reconfirmation
+    pub fn set_reconfirm_threshold_bps(&mut self, threshold_bps: u64) {
+        self.assert_owner();
+        self.reconfirm_threshold_bps = threshold_bps;
+        env::log_str(&format!("reconfirm_threshold_bps set to {}", threshold_bps));
+    }
+
+    /// Accepts the order's current terms: the current slippage becomes the
+    /// new baseline and the order can be locked again.
+    pub fn reconfirm_order(&mut self, order_id: u64) {
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        require!(
+            env::predecessor_account_id() == order.maker,
+            "Only the maker can reconfirm this order"
+        );
+        require!(order.needs_reconfirmation, "Order does not need reconfirmation");
+
+        let previous_baseline = order.baseline_slippage;
+        order.baseline_slippage = order.current_slippage;
+        self.clear_reconfirmation(&mut order);
+        self.save_order(&order);
+
+        self.emit_event(
+            EventLevel::Standard,
+            "order_reconfirmed",
+            json!({
+                "order_id": order_id,
+                "previous_baseline": previous_baseline,
+                "baseline_slippage": order.baseline_slippage,
+            }),
+        );
+    }
+
+    pub fn get_orders_awaiting_reconfirmation(&self, from_index: u64, limit: u64) -> Vec<OrderView> {
+        let limit = limit.min(limits::MAX_BATCH_SIZE as u64);
+        self.awaiting_reconfirmation
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .filter_map(|order_id| self.orders.get(&order_id))
+            .map(|order| self.public_order_view(order))
+            .collect()
+    }
+}
+
+impl AdaptiveCrossChain {
+    /// Flags an Active order whose slippage has drifted from the baseline
+    /// the maker agreed to by more than the threshold. Flagged orders cannot
+    /// be locked; they still expire and refund as usual.
+    pub(crate) fn check_slippage_drift(&mut self, order: &mut CrossChainOrder) {
+        if order.skip_reconfirmation
+            || order.needs_reconfirmation
+            || self.reconfirm_threshold_bps == 0
+            || !matches!(order.status, OrderStatus::Active)
+        {
+            return;
+        }
+        let drift = slippage_drift(order);
+        if drift <= self.reconfirm_threshold_bps {
+            return;
+        }
+
+        order.needs_reconfirmation = true;
+        self.awaiting_reconfirmation.insert(&order.order_id);
+        self.emit_event(
+            EventLevel::Minimal,
+            "reconfirmation_required",
+            json!({
+                "order_id": order.order_id,
+                "maker": order.maker,
+                "baseline_slippage": order.baseline_slippage,
+                "current_slippage": order.current_slippage,
+                "drift_bps": drift,
+            }),
+        );
+    }
+
+    /// Called on reconfirmation and by the status transition helper when a
+    /// flagged order leaves Active.
+    pub(crate) fn clear_reconfirmation(&mut self, order: &mut CrossChainOrder) {
+        if order.needs_reconfirmation {
+            order.needs_reconfirmation = false;
+            self.awaiting_reconfirmation.remove(&order.order_id);
+        }
+    }
+}
+
+pub(crate) fn slippage_drift(order: &CrossChainOrder) -> u64 {
+    order.current_slippage.abs_diff(order.baseline_slippage)
+}
@@ -0,0 +1,627 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+use near_sdk::{env, near_bindgen, require, NearToken, Promise};
+
+use crate::archive::GAS_RESERVED_FOR_TICK;
+use crate::ft;
+use crate::limits;
+use crate::timing::{self, ExpiryStage};
+use crate::webhooks;
+use crate::{
+    AdaptiveCrossChain, AdaptiveCrossChainExt, Balance, CrossChainOrder, EventLevel, OrderStatus, StorageStore, TGAS,
+};
+
+// Open orders are indexed by timelock_ns in buckets of this many ns
+pub const EXPIRY_BUCKET_NS: u64 = 600 * timing::NS_PER_SEC;
+
+// Bucket size of the block-height index legacy orders were filed in
+pub const EXPIRY_BUCKET_BLOCKS: u64 = 600;
+
+// Gas one refund can use: the payout transfer and its callback, the
+// callback of the sponsorship refund, the maker's settlement notification
+// and the bookkeeping around them. Sweeps only start a refund with this
+// much left above their floor.
+pub const GAS_PER_REFUND: u64 = ft::GAS_FOR_FT_TRANSFER.as_gas()
+    + 2 * ft::GAS_FOR_FT_PAYOUT_CALLBACK.as_gas()
+    + webhooks::GAS_FOR_SETTLEMENT_CALLBACK.as_gas()
+    + webhooks::GAS_FOR_NOTIFICATION_RESULT.as_gas()
+    + 15 * TGAS;
+
+// Largest-amount-first only ranks orders from this many expired buckets
+pub const MAX_REFUND_SCAN_BUCKETS: usize = 20;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum RefundPriority {
+    OldestExpiryFirst,  // Default
+    LargestAmountFirst, // For declared emergencies
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct ExpiryEntry {
+    pub order_id: u64,
+    pub amount: Balance,
+    pub timelock_ns: u64, // Block height for legacy orders; see migrate_timelocks
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SweepReport {
+    pub refunded: u32,
+    pub bounty_paid: U128,
+    pub more_expired: bool, // Call again to continue
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RefundQueueEntry {
+    pub order_id: u64,
+    pub amount: U128,
+    pub timelock_ns: U64,
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    /// Returns the escrow of an order whose timelock has passed, whatever
+    /// the order's place in the queue. Anyone can call it; the funds always
+    /// go to the maker. Locked orders refund too: the taker missed the claim
+    /// window.
+    pub fn refund_expired_order(&mut self, order_id: u64) -> Promise {
+        let order = self.orders.get(&order_id).expect("Order not found");
+        match timing::current_expiry_stage(&order) {
+            ExpiryStage::AfterTimelock => self.refund_expired(order),
+            ExpiryStage::BeforeTimelock => env::panic_str("Order is not expired"),
+            ExpiryStage::Terminal => env::panic_str("Order is already completed, cancelled or refunded"),
+        }
+    }
+
+    /// Refunds an Active order whose `valid_until` has passed without
+    /// waiting for the timelock; no resolver holds a lock on it. Anyone can
+    /// call it; the funds always go to the maker. Emits
+    /// `order_validity_expired`, with `never_filled` false for orders that
+    /// were partly filled before.
+    pub fn refund_stale_order(&mut self, order_id: u64) -> Promise {
+        let order = self.orders.get(&order_id).expect("Order not found");
+        require!(matches!(order.status, OrderStatus::Active), "Order not active");
+        require!(
+            timing::quote_expired(&order, env::block_timestamp()),
+            "Order quote has not expired"
+        );
+        self.emit_order_event(
+            EventLevel::Minimal,
+            "order_validity_expired",
+            &order,
+            json!({
+                "valid_until": order.valid_until,
+                "never_filled": order.amount_remaining == order.amount_in,
+            }),
+        );
+        self.refund_expired(order)
+    }
+
+    /// Refunds up to `limit` (max 100) orders past their timelock, in
+    /// refund queue order, and pays the caller `sweep_bounty` for each.
+    /// Locked orders are only refunded once their timelock has passed,
+    /// which also ends their claim windows. Refunded orders leave the
+    /// queue, so repeated calls work through a backlog.
+    pub fn sweep_expired_orders(&mut self, limit: u64) -> SweepReport {
+        let sweeper = env::predecessor_account_id();
+        let limit = limit.min(limits::MAX_BATCH_SIZE as u64) as usize;
+        let mut refunded = 0;
+        let mut bounty_paid = 0;
+        for entry in self.refund_candidates(limit) {
+            if env::prepaid_gas().as_gas() - env::used_gas().as_gas() < GAS_RESERVED_FOR_TICK + GAS_PER_REFUND {
+                break;
+            }
+            let order = match self.orders.get(&entry.order_id) {
+                Some(order) => order,
+                None => continue,
+            };
+            if timing::current_expiry_stage(&order) != ExpiryStage::AfterTimelock {
+                continue;
+            }
+            let (bounty, source) = self.take_sweep_bounty(&order);
+            if bounty > 0 {
+                self.emit_order_event(
+                    EventLevel::Verbose,
+                    "sweep_bounty_paid",
+                    &order,
+                    json!({ "sweeper": sweeper, "bounty": U128(bounty), "source": source }),
+                );
+            }
+            let from_refund = if source == "refund" { bounty } else { 0 };
+            self.refund_expired_less(order, from_refund);
+            bounty_paid += bounty;
+            refunded += 1;
+        }
+        if bounty_paid > 0 {
+            Promise::new(sweeper).transfer(NearToken::from_yoctonear(bounty_paid));
+        }
+        SweepReport {
+            refunded,
+            bounty_paid: U128(bounty_paid),
+            more_expired: !self.refund_candidates(1).is_empty(),
+        }
+    }
+
+    // 0 turns bounties off
+    pub fn set_sweep_bounty(&mut self, bounty: U128, from_refunds: bool) {
+        self.assert_owner();
+        env::log_str(&format!(
+            "sweep_bounty set from {} to {}, from refunds: {}",
+            self.sweep_bounty, bounty.0, from_refunds
+        ));
+        self.sweep_bounty = bounty.0;
+        self.sweep_bounty_from_refunds = from_refunds;
+        self.audit("set_sweep_bounty", None);
+    }
+
+    /// Registered resolvers record a failed attempt at filling an Active
+    /// order, once each; every lock counts as an attempt too. The attempt
+    /// that reaches fill_attempt_limit expires the order and refunds the
+    /// maker without waiting for the timelock.
+    pub fn register_fill_attempt(&mut self, order_id: u64) {
+        let resolver = env::predecessor_account_id();
+        require!(
+            self.registered_resolvers.contains(&resolver),
+            "Resolver is not registered"
+        );
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        require!(matches!(order.status, OrderStatus::Active), "Order not active");
+        require!(
+            self.fill_attempt_reporters.insert(&(order_id, resolver)),
+            "Attempt already registered for this order"
+        );
+        self.record_fill_attempt(&mut order);
+        self.save_order(&order);
+        self.expire_if_out_of_attempts(order);
+    }
+
+    pub fn is_order_refundable(&self, order_id: u64) -> bool {
+        self.orders
+            .get(&order_id)
+            .map(|order| timing::current_expiry_stage(&order) == ExpiryStage::AfterTimelock)
+            .unwrap_or(false)
+    }
+
+    pub fn set_refund_priority(&mut self, priority: RefundPriority) {
+        self.assert_owner();
+        self.refund_priority = priority;
+        self.audit("set_refund_priority", None);
+        env::log_str(&format!(
+            "Refund priority set to {}",
+            match priority {
+                RefundPriority::OldestExpiryFirst => "OldestExpiryFirst",
+                RefundPriority::LargestAmountFirst => "LargestAmountFirst",
+            }
+        ));
+    }
+
+    // Upcoming refunds in the order tick() will process them
+    pub fn get_refund_queue(&self, limit: u32) -> Vec<RefundQueueEntry> {
+        let limit = (limit as usize).min(limits::MAX_BATCH_SIZE);
+        self.refund_candidates(limit)
+            .into_iter()
+            .map(|entry| RefundQueueEntry {
+                order_id: entry.order_id,
+                amount: U128(entry.amount),
+                timelock_ns: U64(entry.timelock_ns),
+            })
+            .collect()
+    }
+}
+
+impl AdaptiveCrossChain {
+    pub(crate) fn index_expiry(&mut self, order: &CrossChainOrder) {
+        let storage_before = env::storage_usage();
+        let bucket = expiry_bucket(order);
+        let mut entries = self.expiry_index.get(&bucket).unwrap_or_default();
+        entries.push(ExpiryEntry {
+            order_id: order.order_id,
+            amount: order.amount_in.0,
+            timelock_ns: order.timelock_ns.0,
+        });
+        self.expiry_index.insert(&bucket, &entries);
+        self.record_storage(StorageStore::Indexes, storage_before);
+    }
+
+    /// Called by the status transition helper when an order leaves the
+    /// open statuses, however it got there.
+    pub(crate) fn unindex_expiry(&mut self, order: &CrossChainOrder) {
+        let bucket = expiry_bucket(order);
+        let mut entries = match self.expiry_index.get(&bucket) {
+            Some(entries) => entries,
+            None => return,
+        };
+        entries.retain(|entry| entry.order_id != order.order_id);
+        let storage_before = env::storage_usage();
+        if entries.is_empty() {
+            self.expiry_index.remove(&bucket);
+        } else {
+            self.expiry_index.insert(&bucket, &entries);
+        }
+        self.record_storage(StorageStore::Indexes, storage_before);
+    }
+
+    pub(crate) fn record_fill_attempt(&mut self, order: &mut CrossChainOrder) {
+        order.fill_attempts += 1;
+        order.last_fill_attempt_at = Some(U64(env::block_timestamp()));
+    }
+
+    /// Expires and refunds an Active order whose fill attempts are used up.
+    /// A Locked order gets its last attempt; it is checked again when the
+    /// lock ends without completing the order.
+    pub(crate) fn expire_if_out_of_attempts(&mut self, order: CrossChainOrder) {
+        if !matches!(order.status, OrderStatus::Active) || order.fill_attempts < self.fill_attempt_limit {
+            return;
+        }
+        env::log_str(&format!(
+            "Order {} used {} of {} fill attempts, expiring it",
+            order.order_id, order.fill_attempts, self.fill_attempt_limit
+        ));
+        self.emit_order_event(
+            EventLevel::Minimal,
+            "fill_attempts_exhausted",
+            &order,
+            json!({
+                "fill_attempts": order.fill_attempts,
+                "fill_attempt_limit": self.fill_attempt_limit,
+            }),
+        );
+        self.refund_expired(order);
+    }
+
+    /// Refunds expired orders in priority order while a refund still fits
+    /// above `gas_floor`. Returns how many were refunded.
+    pub(crate) fn sweep_expired(&mut self, gas_floor: u64) -> u32 {
+        let mut refunded = 0;
+        for entry in self.refund_candidates(limits::MAX_BATCH_SIZE) {
+            if env::prepaid_gas().as_gas() - env::used_gas().as_gas() < gas_floor + GAS_PER_REFUND {
+                break;
+            }
+            // Legacy entries are filed by height and can look expired early
+            if let Some(order) = self.orders.get(&entry.order_id) {
+                if timing::current_expiry_stage(&order) == ExpiryStage::AfterTimelock {
+                    self.refund_expired(order);
+                    refunded += 1;
+                }
+            }
+        }
+        refunded
+    }
+
+    fn refund_expired(&mut self, order: CrossChainOrder) -> Promise {
+        self.refund_expired_less(order, 0)
+    }
+
+    // `deduction` is kept back from the maker's refund, for a sweep bounty
+    fn refund_expired_less(&mut self, mut order: CrossChainOrder, deduction: Balance) -> Promise {
+        let order_id = order.order_id;
+        self.transition_status(&mut order, OrderStatus::Expired);
+        self.save_order(&order);
+        self.notify_settlement(&order);
+
+        let refund = order.amount_remaining.0 - deduction;
+        env::log_str(&format!(
+            "Order {} expired, {} {} refunded to {} (requested by {})",
+            order_id,
+            refund,
+            order.token_in,
+            order.maker,
+            env::predecessor_account_id()
+        ));
+        self.emit_order_event(
+            EventLevel::Standard,
+            "order_refunded",
+            &order,
+            json!({
+                "refunded": U128(refund),
+                "gas_sponsorship": order.gas_sponsorship,
+                "requested_by": env::predecessor_account_id(),
+            }),
+        );
+        // Unused gas sponsorship goes back with the escrow
+        if order.gas_sponsorship.0 > 0 {
+            self.refund_sponsorship(&order);
+        }
+        self.pay_out(&order, order.maker.clone(), refund)
+    }
+
+    // The keeper reward pool pays first; native refunds cover the bounty
+    // when the owner allows it and the refund is larger than the bounty
+    fn take_sweep_bounty(&mut self, order: &CrossChainOrder) -> (Balance, &'static str) {
+        let bounty = self.sweep_bounty;
+        if bounty == 0 {
+            (0, "none")
+        } else if self.keeper_reward_pool >= bounty {
+            self.keeper_reward_pool -= bounty;
+            (bounty, "pool")
+        } else if self.sweep_bounty_from_refunds
+            && ft::is_native(order.token_in.as_str())
+            && order.amount_remaining.0 > bounty
+        {
+            (bounty, "refund")
+        } else {
+            (0, "unfunded")
+        }
+    }
+
+    // Expired entries in processing order, read from the expiry index only.
+    // Entries of orders migrate_timelocks has not converted yet hold a block
+    // height and are compared with the current one; they sort first once due.
+    fn refund_candidates(&self, limit: usize) -> Vec<ExpiryEntry> {
+        let now = env::block_timestamp();
+        let height = env::block_height();
+        let expired_buckets = self
+            .expiry_index
+            .iter()
+            .take_while(|(bucket, _)| bucket * EXPIRY_BUCKET_NS <= now)
+            .map(|(bucket, mut entries)| {
+                entries.retain(|entry| {
+                    if is_legacy_entry(bucket, entry) {
+                        entry.timelock_ns <= height
+                    } else {
+                        entry.timelock_ns <= now
+                    }
+                });
+                entries
+            })
+            .filter(|entries| !entries.is_empty());
+
+        match self.refund_priority {
+            RefundPriority::OldestExpiryFirst => {
+                let mut candidates = Vec::new();
+                for mut entries in expired_buckets {
+                    entries.sort_by_key(|entry| (entry.timelock_ns, entry.order_id));
+                    candidates.extend(entries);
+                    if candidates.len() >= limit {
+                        break;
+                    }
+                }
+                candidates.truncate(limit);
+                candidates
+            }
+            RefundPriority::LargestAmountFirst => {
+                let mut candidates: Vec<ExpiryEntry> = expired_buckets
+                    .take(MAX_REFUND_SCAN_BUCKETS)
+                    .flatten()
+                    .collect();
+                candidates.sort_by(|a, b| {
+                    b.amount
+                        .cmp(&a.amount)
+                        .then(a.timelock_ns.cmp(&b.timelock_ns))
+                        .then(a.order_id.cmp(&b.order_id))
+                });
+                candidates.truncate(limit);
+                candidates
+            }
+        }
+    }
+}
+
+// Entries filed before timestamp timelocks sit in a block-height bucket,
+// which a timestamp in ns never maps back to
+fn is_legacy_entry(bucket: u64, entry: &ExpiryEntry) -> bool {
+    entry.timelock_ns / EXPIRY_BUCKET_NS != bucket
+}
+
+fn expiry_bucket(order: &CrossChainOrder) -> u64 {
+    if timing::is_legacy_timelock(order) {
+        order.timelock.0 / EXPIRY_BUCKET_BLOCKS
+    } else {
+        order.timelock_ns.0 / EXPIRY_BUCKET_NS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::json_types::U64;
+    use near_sdk::Gas;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::test_utils::*;
+
+    const BACKLOG: u8 = 80;
+    const START: u64 = 1_700_000_000 * timing::NS_PER_SEC;
+    // Past every timelock the backlog can have
+    const AFTER_EXPIRY: u64 = START + 2 * 86_400 * timing::NS_PER_SEC;
+
+    // BACKLOG orders with random amounts and timelocks between one hour
+    // and a day, all of them expired by AFTER_EXPIRY
+    fn backlog() -> AdaptiveCrossChain {
+        let mut contract = setup();
+        let mut rng = StdRng::seed_from_u64(222);
+        for n in 1..=BACKLOG {
+            set_clock(START, n as u64);
+            let amount = rng.gen_range(1..=50) * ONE_NEAR / 10;
+            let mut params = order_params(&contract, n, amount);
+            params.timelock_duration = Some(U64(rng.gen_range(3_600..=86_400)));
+            create_order_with(&mut contract, params, amount + ONE_NEAR);
+        }
+        contract
+    }
+
+    fn at(timestamp: u64, prepaid_tgas: u64) {
+        set_clock(timestamp, 1_000);
+        testing_env_with_gas(prepaid_tgas);
+    }
+
+    fn testing_env_with_gas(prepaid_tgas: u64) {
+        near_sdk::testing_env!(context(resolver()).prepaid_gas(Gas::from_tgas(prepaid_tgas)).build());
+    }
+
+    fn queue(contract: &AdaptiveCrossChain) -> Vec<RefundQueueEntry> {
+        contract.get_refund_queue(limits::MAX_BATCH_SIZE as u32)
+    }
+
+    // Ids refunded by tick() at `prepaid_tgas` of gas
+    fn tick_refunds(contract: &mut AdaptiveCrossChain, prepaid_tgas: u64) -> Vec<u64> {
+        let open: Vec<u64> = queue(contract).iter().map(|entry| entry.order_id).collect();
+        at(AFTER_EXPIRY, prepaid_tgas);
+        contract.tick();
+        open.into_iter()
+            .filter(|order_id| matches!(contract.orders.get(order_id).unwrap().status, OrderStatus::Expired))
+            .collect()
+    }
+
+    // A gas-limited call refunds part of the queue, from the front
+    fn assert_front_refunded(queued: &[RefundQueueEntry], refunded: &[u64]) {
+        assert!(!refunded.is_empty() && refunded.len() < queued.len(), "refunded {}", refunded.len());
+        let mut front: Vec<u64> = queued[..refunded.len()].iter().map(|entry| entry.order_id).collect();
+        let mut refunded = refunded.to_vec();
+        front.sort_unstable();
+        refunded.sort_unstable();
+        assert_eq!(refunded, front);
+    }
+
+    #[test]
+    fn nothing_is_queued_before_expiry() {
+        let contract = backlog();
+        at(START + 3_599 * timing::NS_PER_SEC, 300);
+        assert!(queue(&contract).is_empty());
+    }
+
+    #[test]
+    fn oldest_expiry_first_by_default() {
+        let mut contract = backlog();
+        at(AFTER_EXPIRY, 300);
+        let queued = queue(&contract);
+        assert_eq!(queued.len(), BACKLOG as usize);
+        assert!(queued
+            .windows(2)
+            .all(|pair| (pair[0].timelock_ns.0, pair[0].order_id) < (pair[1].timelock_ns.0, pair[1].order_id)));
+
+        let refunded = tick_refunds(&mut contract, 150);
+        assert_front_refunded(&queued, &refunded);
+        at(AFTER_EXPIRY, 300);
+        assert_eq!(queue(&contract)[0].order_id, queued[refunded.len()].order_id);
+    }
+
+    #[test]
+    fn largest_amount_first_in_emergencies() {
+        let mut contract = backlog();
+        call(owner(), 0);
+        contract.set_refund_priority(RefundPriority::LargestAmountFirst);
+        at(AFTER_EXPIRY, 300);
+        // Only the oldest MAX_REFUND_SCAN_BUCKETS buckets are ranked
+        let mut expected: Vec<CrossChainOrder> = (1..=BACKLOG as u64).map(|id| contract.orders.get(&id).unwrap()).collect();
+        let mut buckets: Vec<u64> = expected.iter().map(expiry_bucket).collect();
+        buckets.sort_unstable();
+        buckets.dedup();
+        expected.retain(|order| expiry_bucket(order) <= buckets[MAX_REFUND_SCAN_BUCKETS - 1]);
+        expected.sort_by_key(|order| (std::cmp::Reverse(order.amount_in.0), order.timelock_ns.0, order.order_id));
+
+        let queued = queue(&contract);
+        assert!(queued.len() < BACKLOG as usize);
+        assert_eq!(
+            queued.iter().map(|entry| entry.order_id).collect::<Vec<_>>(),
+            expected.iter().map(|order| order.order_id).collect::<Vec<_>>()
+        );
+
+        let refunded = tick_refunds(&mut contract, 150);
+        assert_front_refunded(&queued, &refunded);
+    }
+
+    #[test]
+    fn sweep_stops_when_gas_runs_low() {
+        let mut contract = backlog();
+        at(AFTER_EXPIRY, 300);
+        let first = queue(&contract)[0].order_id;
+
+        // Ranking the queue leaves too little for a single refund
+        at(AFTER_EXPIRY, 40);
+        let report = contract.sweep_expired_orders(limits::MAX_BATCH_SIZE as u64);
+        assert_eq!(report.refunded, 0);
+        assert!(report.more_expired);
+
+        at(AFTER_EXPIRY, 300);
+        let report = contract.sweep_expired_orders(5);
+        assert_eq!(report.refunded, 5);
+        assert!(report.more_expired);
+        assert!(matches!(contract.orders.get(&first).unwrap().status, OrderStatus::Expired));
+        assert_eq!(queue(&contract).len(), BACKLOG as usize - 5);
+    }
+
+    // Files the order as it was before timestamp timelocks: a block-height
+    // timelock, and an index entry holding that height in its height bucket
+    fn make_legacy(contract: &mut AdaptiveCrossChain, order_id: u64, height: u64) {
+        let mut order = contract.orders.get(&order_id).unwrap();
+        contract.unindex_expiry(&order);
+        order.timelock_ns = U64(0);
+        order.timelock = U64(height);
+        contract.save_order(&order);
+        let bucket = height / EXPIRY_BUCKET_BLOCKS;
+        let mut entries = contract.expiry_index.get(&bucket).unwrap_or_default();
+        entries.push(ExpiryEntry { order_id, amount: order.amount_in.0, timelock_ns: height });
+        contract.expiry_index.insert(&bucket, &entries);
+    }
+
+    fn queued_ids(contract: &AdaptiveCrossChain) -> Vec<u64> {
+        queue(contract).iter().map(|entry| entry.order_id).collect()
+    }
+
+    // Unmigrated orders used to look expired whatever their height, filling
+    // every sweep ahead of the orders that were due
+    #[test]
+    fn legacy_entries_wait_for_their_block_height() {
+        for priority in [RefundPriority::OldestExpiryFirst, RefundPriority::LargestAmountFirst] {
+            let mut contract = backlog();
+            call(owner(), 0);
+            contract.set_refund_priority(priority);
+            for order_id in 1..=5 {
+                make_legacy(&mut contract, order_id, 5_000);
+            }
+
+            at(AFTER_EXPIRY, 300);
+            assert!(queued_ids(&contract).iter().all(|order_id| *order_id > 5));
+            assert_eq!(contract.sweep_expired_orders(3).refunded, 3);
+            assert!((1..=5).all(|order_id| matches!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Active)));
+
+            set_clock(AFTER_EXPIRY, 5_000);
+            testing_env_with_gas(300);
+            let queued = queued_ids(&contract);
+            assert_eq!(queued.iter().filter(|order_id| **order_id <= 5).count(), 5);
+            if priority == RefundPriority::OldestExpiryFirst {
+                assert_eq!(queued[..5], [1, 2, 3, 4, 5]);
+            }
+        }
+    }
+
+    #[test]
+    fn migrated_entries_are_filed_by_timestamp() {
+        let mut contract = backlog();
+        make_legacy(&mut contract, 1, 5_000);
+        set_clock(AFTER_EXPIRY, 4_000);
+        call(owner(), 0);
+        assert_eq!(contract.migrate_timelocks(1, 1), 1);
+
+        // 1000 blocks left become 1000 seconds
+        let order = contract.orders.get(&1).unwrap();
+        assert_eq!(order.timelock_ns.0, AFTER_EXPIRY + 1_000 * timing::NS_PER_SEC);
+        let entries = contract.expiry_index.get(&expiry_bucket(&order)).unwrap();
+        assert!(entries.iter().any(|entry| entry.order_id == 1 && entry.timelock_ns == order.timelock_ns.0));
+        assert!(contract.expiry_index.get(&(5_000 / EXPIRY_BUCKET_BLOCKS)).is_none());
+
+        assert!(!queued_ids(&contract).contains(&1));
+        set_clock(order.timelock_ns.0, 4_000);
+        testing_env_with_gas(300);
+        assert_eq!(*queued_ids(&contract).last().unwrap(), 1);
+    }
+
+    // Makers do not wait for their place in the queue
+    #[test]
+    fn refund_expired_order_skips_the_queue() {
+        let mut contract = backlog();
+        at(AFTER_EXPIRY, 300);
+        let last = queue(&contract).last().unwrap().order_id;
+        call(maker(), 0);
+        let _ = contract.refund_expired_order(last);
+        assert!(matches!(contract.orders.get(&last).unwrap().status, OrderStatus::Expired));
+        at(AFTER_EXPIRY, 300);
+        assert!(!queue(&contract).iter().any(|entry| entry.order_id == last));
+    }
+}
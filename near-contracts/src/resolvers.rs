@@ -0,0 +1,221 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+use near_sdk::{env, near_bindgen, require, AccountId, NearToken, Promise};
+
+use crate::fees::{bps_of, BPS_DENOMINATOR};
+use crate::limits;
+use crate::{timing, AdaptiveCrossChain, AdaptiveCrossChainExt, EventLevel, ExpiryStage, OrderStatus};
+
+/// Stake resolvers must hold to lock, and the share of it an abandoned
+/// lock costs them.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ResolverBonding {
+    pub min_stake: U128, // 0 disables the requirement
+    pub slash_bps: u64,  // Of the resolver's stake, paid to the maker
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SlashRecord {
+    pub order_id: u64,
+    pub resolver: AccountId,
+    pub maker: AccountId,
+    pub amount: U128,
+    pub slashed_by: AccountId,
+    pub slashed_at: U64,
+}
+
+// In permissioned mode only registered resolvers can lock and claim. The
+// registry is kept either way so it can be filled before switching over.
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    pub fn register_resolver(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        if self.registered_resolvers.insert(&account_id) {
+            self.audit("register_resolver", None);
+            self.emit_event(
+                EventLevel::Standard,
+                "resolver_registered",
+                json!({ "account_id": account_id }),
+            );
+        }
+    }
+
+    // Locks the resolver already holds can no longer be claimed while
+    // permissioned mode is on; they expire and refund as usual
+    pub fn remove_resolver(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        if self.registered_resolvers.remove(&account_id) {
+            self.audit("remove_resolver", None);
+            self.emit_event(
+                EventLevel::Standard,
+                "resolver_removed",
+                json!({ "account_id": account_id }),
+            );
+        }
+    }
+
+    pub fn set_permissioned_mode(&mut self, enabled: bool) {
+        self.assert_owner();
+        self.permissioned_mode = enabled;
+        self.audit("set_permissioned_mode", None);
+        env::log_str(&format!("permissioned_mode set to {}", enabled));
+    }
+
+    pub fn is_registered_resolver(&self, account_id: AccountId) -> bool {
+        self.registered_resolvers.contains(&account_id)
+    }
+
+    pub fn is_permissioned_mode(&self) -> bool {
+        self.permissioned_mode
+    }
+
+    pub fn get_registered_resolvers(&self, from_index: u64, limit: u64) -> Vec<AccountId> {
+        let limit = limit.min(limits::MAX_BATCH_SIZE as u64);
+        self.registered_resolvers
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .collect()
+    }
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    pub fn set_resolver_bonding(&mut self, bonding: ResolverBonding) {
+        self.assert_owner();
+        require!(
+            bonding.slash_bps as u128 <= BPS_DENOMINATOR,
+            "slash_bps cannot exceed 10000"
+        );
+        env::log_str(&format!(
+            "Resolver bonding set: min stake {}, slash {} bps",
+            bonding.min_stake.0, bonding.slash_bps
+        ));
+        self.resolver_bonding = bonding;
+        self.audit("set_resolver_bonding", None);
+    }
+
+    pub fn get_resolver_bonding(&self) -> ResolverBonding {
+        self.resolver_bonding.clone()
+    }
+
+    #[payable]
+    pub fn deposit_resolver_stake(&mut self) -> U128 {
+        let resolver = env::predecessor_account_id();
+        let deposit = env::attached_deposit().as_yoctonear();
+        require!(deposit > 0, "Attach the stake to deposit");
+        let stake = self.resolver_stakes.get(&resolver).unwrap_or(0) + deposit;
+        self.resolver_stakes.insert(&resolver, &stake);
+        env::log_str(&format!("Resolver {} staked {}, stake now {}", resolver, deposit, stake));
+        U128(stake)
+    }
+
+    // While the resolver holds locks its stake cannot drop below the minimum
+    pub fn withdraw_resolver_stake(&mut self, amount: U128) -> Promise {
+        let resolver = env::predecessor_account_id();
+        let stake = self.resolver_stakes.get(&resolver).unwrap_or(0);
+        require!(amount.0 > 0 && amount.0 <= stake, "Amount exceeds the resolver stake");
+        let remaining = stake - amount.0;
+        require!(
+            self.resolver_exposure.get(&resolver).unwrap_or(0) == 0
+                || remaining >= self.resolver_bonding.min_stake.0,
+            "Stake cannot drop below the minimum while holding locks"
+        );
+        if remaining == 0 {
+            self.resolver_stakes.remove(&resolver);
+        } else {
+            self.resolver_stakes.insert(&resolver, &remaining);
+        }
+        env::log_str(&format!("Resolver {} withdrew {} of its stake", resolver, amount.0));
+        Promise::new(resolver).transfer(NearToken::from_yoctonear(amount.0))
+    }
+
+    /// Anyone can call this once a locked order is past its timelock without
+    /// a claim. The maker is paid `slash_bps` of the resolver's stake and the
+    /// order goes back to Active, where it can be refunded.
+    pub fn slash_abandoned_lock(&mut self, order_id: u64) -> Promise {
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        require!(
+            matches!(order.status, OrderStatus::Locked),
+            "Order not in locked state"
+        );
+        require!(
+            timing::current_expiry_stage(&order) == ExpiryStage::AfterTimelock,
+            "Lock has not expired yet"
+        );
+        let resolver = order.taker.clone().expect("Locked order without a taker");
+        let stake = self.resolver_stakes.get(&resolver).unwrap_or(0);
+        let slashed = bps_of(stake, self.resolver_bonding.slash_bps);
+        require!(slashed > 0, "Resolver has no stake to slash");
+        self.resolver_stakes.insert(&resolver, &(stake - slashed));
+
+        self.transition_status(&mut order, OrderStatus::Active);
+        order.taker = None;
+        order.locked_at = None;
+        order.locked_price = None;
+        order.exclusive_claim_until = None;
+        order.locked_amount = U128(0);
+        self.save_order(&order);
+
+        let record = SlashRecord {
+            order_id,
+            resolver: resolver.clone(),
+            maker: order.maker.clone(),
+            amount: U128(slashed),
+            slashed_by: env::predecessor_account_id(),
+            slashed_at: U64(env::block_timestamp()),
+        };
+        self.slash_history.push(&record);
+        self.audit("slash_abandoned_lock", Some(order_id));
+        env::log_str(&format!(
+            "Lock of order {} by {} abandoned, {} of its stake slashed to {}",
+            order_id, resolver, slashed, order.maker
+        ));
+        self.emit_order_event(
+            EventLevel::Minimal,
+            "lock_slashed",
+            &order,
+            json!({ "resolver": resolver, "slashed": U128(slashed) }),
+        );
+        let slash = Promise::new(order.maker.clone()).transfer(NearToken::from_yoctonear(slashed));
+        self.expire_if_out_of_attempts(order);
+        slash
+    }
+
+    pub fn get_resolver_stake(&self, account_id: AccountId) -> U128 {
+        U128(self.resolver_stakes.get(&account_id).unwrap_or(0))
+    }
+
+    pub fn get_slash_history(&self, from_index: u64, limit: u64) -> Vec<SlashRecord> {
+        let limit = limit.min(limits::MAX_BATCH_SIZE as u64);
+        (from_index..self.slash_history.len().min(from_index.saturating_add(limit)))
+            .filter_map(|index| self.slash_history.get(index))
+            .collect()
+    }
+
+    pub fn get_slash_history_len(&self) -> u64 {
+        self.slash_history.len()
+    }
+}
+
+impl AdaptiveCrossChain {
+    pub(crate) fn assert_min_stake(&self, resolver: &AccountId) {
+        require!(
+            self.resolver_stakes.get(resolver).unwrap_or(0) >= self.resolver_bonding.min_stake.0,
+            "Resolver stake is below the minimum"
+        );
+    }
+
+    /// Every lock and claim path calls this with the resolver taking the
+    /// order.
+    pub(crate) fn assert_permitted_resolver(&self, resolver: &AccountId) {
+        require!(
+            !self.permissioned_mode || self.registered_resolvers.contains(resolver),
+            "Resolver is not registered"
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+use near_sdk::{env, near_bindgen, require, AccountId};
+
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, EventLevel};
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Role {
+    Treasurer,   // Manages fee waivers and promotions
+    MarketMaker, // Exempt from per-block creation rate limits
+    Operator,    // Support staff: full view of private orders
+    Oracle,      // Reports peg deviation for pegged pairs
+    Relayer,     // Asserts inbound Ethereum escrows (limited)
+    Admin,       // Per-resolver exposure overrides
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    pub fn grant_role(&mut self, account_id: AccountId, role: Role) {
+        self.assert_owner();
+        let mut roles = self.roles.get(&account_id).unwrap_or_default();
+        if !roles.contains(&role) {
+            roles.push(role);
+            self.roles.insert(&account_id, &roles);
+        }
+        env::log_str(&format!("Role {} granted to {}", role_name(role), account_id));
+    }
+
+    pub fn revoke_role(&mut self, account_id: AccountId, role: Role) {
+        self.assert_owner();
+        let mut roles = self.roles.get(&account_id).unwrap_or_default();
+        roles.retain(|r| *r != role);
+        if roles.is_empty() {
+            self.roles.remove(&account_id);
+        } else {
+            self.roles.insert(&account_id, &roles);
+        }
+        env::log_str(&format!("Role {} revoked from {}", role_name(role), account_id));
+    }
+
+    pub fn has_role(&self, account_id: AccountId, role: Role) -> bool {
+        self.roles
+            .get(&account_id)
+            .map(|roles| roles.contains(&role))
+            .unwrap_or(false)
+    }
+
+    pub fn get_roles(&self, account_id: AccountId) -> Vec<Role> {
+        self.roles.get(&account_id).unwrap_or_default()
+    }
+
+    /// First step of an ownership handover. The current owner keeps every
+    /// owner permission until `new_owner` calls `accept_ownership`.
+    pub fn propose_owner(&mut self, new_owner: AccountId) {
+        self.assert_owner();
+        require!(new_owner != self.owner, "Account is already the owner");
+        self.pending_owner = Some(new_owner.clone());
+        self.audit("propose_owner", None);
+        self.emit_event(
+            EventLevel::Minimal,
+            "owner_proposed",
+            json!({ "owner": self.owner, "pending_owner": new_owner }),
+        );
+    }
+
+    pub fn accept_ownership(&mut self) {
+        let caller = env::predecessor_account_id();
+        require!(
+            self.pending_owner.as_ref() == Some(&caller),
+            "Only the pending owner can accept ownership"
+        );
+        let previous = std::mem::replace(&mut self.owner, caller.clone());
+        self.pending_owner = None;
+        self.audit("accept_ownership", None);
+        self.emit_event(
+            EventLevel::Minimal,
+            "ownership_transferred",
+            json!({ "previous_owner": previous, "owner": caller }),
+        );
+    }
+
+    pub fn renounce_pending_owner(&mut self) {
+        self.assert_owner();
+        let pending = self.pending_owner.take().expect("No pending owner");
+        self.audit("renounce_pending_owner", None);
+        env::log_str(&format!("Ownership proposal to {} withdrawn", pending));
+    }
+
+    pub fn get_owner(&self) -> AccountId {
+        self.owner.clone()
+    }
+
+    pub fn get_pending_owner(&self) -> Option<AccountId> {
+        self.pending_owner.clone()
+    }
+}
+
+impl AdaptiveCrossChain {
+    pub(crate) fn assert_role(&self, role: Role) {
+        require!(
+            self.has_role(env::predecessor_account_id(), role),
+            format!("Caller lacks the {} role", role_name(role))
+        );
+    }
+}
+
+fn role_name(role: Role) -> &'static str {
+    match role {
+        Role::Treasurer => "Treasurer",
+        Role::MarketMaker => "MarketMaker",
+        Role::Operator => "Operator",
+        Role::Oracle => "Oracle",
+        Role::Relayer => "Relayer",
+        Role::Admin => "Admin",
+    }
+}
@@ -0,0 +1,224 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, require, AccountId, Gas, NearToken, Promise, PromiseResult};
+
+use crate::normalize;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, Balance};
+
+pub type EthAddress = [u8; 20];
+
+pub const GAS_FOR_SHADOW_CLAIM_CALLBACK: Gas = Gas::from_tgas(10);
+
+// After this long an unclaimed balance can only be returned to its funder
+pub const SHADOW_BALANCE_TIMEOUT_NS: u64 = 180 * 86_400_000_000_000;
+
+/// Payout held for an Ethereum address that has no NEAR account yet.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ClaimableBalance {
+    pub order_id: u64,
+    pub amount: U128,       // Payout net of the storage charge
+    pub storage_cost: U128, // Charged against the payout, released on claim
+    pub refund_to: AccountId,
+    pub credited_at: U64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ShadowAccountView {
+    pub eth_address: String,
+    pub nonce: u64, // Must be signed by the next claim
+    pub claimable: U128,
+    pub expired: U128,
+    pub balances: Vec<ClaimableBalance>,
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    /// Transfers every unexpired balance of `eth_address` to `near_receiver`.
+    /// `eth_signature` is a 65-byte personal_sign signature over
+    /// eth_address ++ near_receiver ++ nonce (u64 big-endian).
+    pub fn claim_shadow_balance(
+        &mut self,
+        eth_address: String,
+        near_receiver: AccountId,
+        eth_signature: String,
+    ) -> Promise {
+        let address = normalize::eth_address_bytes(&eth_address).unwrap_or_else(|v| v.panic());
+        let nonce = self.shadow_nonces.get(&address).unwrap_or(0);
+        let signer = recover_signer(&claim_message(&address, &near_receiver, nonce), &eth_signature)
+            .expect("Invalid signature");
+        require!(signer == address, "Signature not from eth_address");
+
+        // Bump the nonce first so the signature cannot be replayed
+        self.shadow_nonces.insert(&address, &(nonce + 1));
+
+        let now = env::block_timestamp();
+        let (claimable, remaining): (Vec<_>, Vec<_>) = self
+            .shadow_balances
+            .get(&address)
+            .unwrap_or_default()
+            .into_iter()
+            .partition(|balance| !is_expired(balance, now));
+        require!(!claimable.is_empty(), "No claimable balance");
+        self.store_shadow_balances(&address, remaining);
+
+        let total: Balance = claimable
+            .iter()
+            .map(|balance| balance.amount.0 + balance.storage_cost.0)
+            .sum();
+        env::log_str(&format!(
+            "Shadow balance of {} claimed to {}: {} across {} orders",
+            eth_address,
+            near_receiver,
+            total,
+            claimable.len()
+        ));
+        Promise::new(near_receiver).transfer(NearToken::from_yoctonear(total)).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_SHADOW_CLAIM_CALLBACK)
+                .on_shadow_claim_transferred(eth_address, claimable),
+        )
+    }
+
+    // A failed transfer (e.g. a named receiver that does not exist) puts the
+    // balances back; the spent nonce stays spent.
+    #[private]
+    pub fn on_shadow_claim_transferred(
+        &mut self,
+        eth_address: String,
+        claimed: Vec<ClaimableBalance>,
+    ) -> bool {
+        if matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+            return true;
+        }
+        let address = normalize::eth_address_bytes(&eth_address).unwrap_or_else(|v| v.panic());
+        let mut balances = self.shadow_balances.get(&address).unwrap_or_default();
+        balances.extend(claimed);
+        self.shadow_balances.insert(&address, &balances);
+        env::log_str(&format!("Shadow claim for {} failed, balances restored", eth_address));
+        false
+    }
+
+    // Permissionless: sends expired balances back to whoever funded them
+    pub fn reclaim_expired_shadow_balances(&mut self, eth_address: String) -> u32 {
+        let address = normalize::eth_address_bytes(&eth_address).unwrap_or_else(|v| v.panic());
+        let now = env::block_timestamp();
+        let (expired, remaining): (Vec<_>, Vec<_>) = self
+            .shadow_balances
+            .get(&address)
+            .unwrap_or_default()
+            .into_iter()
+            .partition(|balance| is_expired(balance, now));
+        self.store_shadow_balances(&address, remaining);
+
+        for balance in &expired {
+            env::log_str(&format!(
+                "Expired shadow balance for order {} returned to {}",
+                balance.order_id, balance.refund_to
+            ));
+            Promise::new(balance.refund_to.clone())
+                .transfer(NearToken::from_yoctonear(balance.amount.0 + balance.storage_cost.0));
+        }
+        expired.len() as u32
+    }
+
+    pub fn get_shadow_account(&self, eth_address: String) -> ShadowAccountView {
+        let address = normalize::eth_address_bytes(&eth_address).unwrap_or_else(|v| v.panic());
+        let now = env::block_timestamp();
+        let balances = self.shadow_balances.get(&address).unwrap_or_default();
+        let (expired, claimable): (Vec<_>, Vec<_>) =
+            balances.iter().partition(|balance| is_expired(balance, now));
+
+        ShadowAccountView {
+            eth_address,
+            nonce: self.shadow_nonces.get(&address).unwrap_or(0),
+            claimable: U128(claimable.iter().map(|b| b.amount.0 + b.storage_cost.0).sum()),
+            expired: U128(expired.iter().map(|b| b.amount.0 + b.storage_cost.0).sum()),
+            balances,
+        }
+    }
+}
+
+impl AdaptiveCrossChain {
+    /// Credits an inbound payout to an Ethereum recipient. The storage the
+    /// entry needs is charged against the payout; returns the net credit.
+    pub(crate) fn credit_shadow_balance(
+        &mut self,
+        address: &EthAddress,
+        order_id: u64,
+        amount: Balance,
+        refund_to: AccountId,
+    ) -> Balance {
+        let mut balances = self.shadow_balances.get(address).unwrap_or_default();
+        let mut entry = ClaimableBalance {
+            order_id,
+            amount: U128(amount),
+            storage_cost: U128(0),
+            refund_to,
+            credited_at: U64(env::block_timestamp()),
+        };
+        balances.push(entry.clone());
+
+        let storage_before = env::storage_usage();
+        self.shadow_balances.insert(address, &balances);
+        let storage_cost =
+            env::storage_usage().saturating_sub(storage_before) as Balance * env::storage_byte_cost().as_yoctonear();
+        require!(amount > storage_cost, "Payout does not cover shadow balance storage");
+
+        entry.amount = U128(amount - storage_cost);
+        entry.storage_cost = U128(storage_cost);
+        *balances.last_mut().unwrap() = entry;
+        self.shadow_balances.insert(address, &balances);
+
+        env::log_str(&format!(
+            "Order {} payout of {} credited to shadow account 0x{}",
+            order_id,
+            amount - storage_cost,
+            hex::encode(address)
+        ));
+        amount - storage_cost
+    }
+
+    fn store_shadow_balances(&mut self, address: &EthAddress, balances: Vec<ClaimableBalance>) {
+        if balances.is_empty() {
+            self.shadow_balances.remove(address);
+        } else {
+            self.shadow_balances.insert(address, &balances);
+        }
+    }
+}
+
+fn is_expired(balance: &ClaimableBalance, now: u64) -> bool {
+    balance.credited_at.0.saturating_add(SHADOW_BALANCE_TIMEOUT_NS) <= now
+}
+
+fn claim_message(address: &EthAddress, near_receiver: &AccountId, nonce: u64) -> Vec<u8> {
+    let mut message = address.to_vec();
+    message.extend_from_slice(near_receiver.as_bytes());
+    message.extend_from_slice(&nonce.to_be_bytes());
+    message
+}
+
+// ecrecover over the EIP-191 personal_sign digest of `message`
+pub(crate) fn recover_signer(message: &[u8], signature: &str) -> Option<EthAddress> {
+    let signature = hex::decode(normalize::strip_hex_prefix(signature)).ok()?;
+    if signature.len() != 65 {
+        return None;
+    }
+    let v = match signature[64] {
+        v @ 0..=1 => v,
+        v @ 27..=28 => v - 27,
+        _ => return None,
+    };
+
+    let mut preimage = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    preimage.extend_from_slice(message);
+    let digest = env::keccak256(&preimage);
+
+    let public_key = env::ecrecover(&digest, &signature[..64], v, true)?;
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&env::keccak256(&public_key)[12..]);
+    Some(address)
+}
@@ -0,0 +1,210 @@
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json;
+use near_sdk::{env, near_bindgen, require, AccountId, CurveType, NearToken, Promise, PublicKey};
+
+use crate::ft;
+use crate::limits;
+use crate::normalize;
+use crate::storage::StorageCharge;
+use crate::validation::{OrderParams, Violation};
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, Balance};
+
+pub const ERR_INVALID_PAYLOAD: &str = "ERR_INVALID_PAYLOAD";
+pub const ERR_WRONG_CONTRACT: &str = "ERR_WRONG_CONTRACT";
+pub const ERR_PAYLOAD_EXPIRED: &str = "ERR_PAYLOAD_EXPIRED";
+pub const ERR_NO_SIGNING_KEY: &str = "ERR_NO_SIGNING_KEY";
+pub const ERR_INVALID_SIGNATURE: &str = "ERR_INVALID_SIGNATURE";
+pub const ERR_NONCE_USED: &str = "ERR_NONCE_USED";
+pub const ERR_DEPOSIT_MISMATCH: &str = "ERR_DEPOSIT_MISMATCH";
+pub const ERR_INSUFFICIENT_BALANCE: &str = "ERR_INSUFFICIENT_BALANCE";
+
+/// What the maker signs. `contract_id` keeps a signature from being
+/// replayed against another deployment.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OrderPayload {
+    pub contract_id: AccountId,
+    pub maker: AccountId,
+    pub nonce: u64,      // Above every nonce the maker used before
+    pub expires_at: U64, // Block timestamp, ns
+    pub params: OrderParams,
+}
+
+/// `payload` is the JSON of an OrderPayload exactly as signed;
+/// `signature` the hex ed25519 signature over its bytes.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SignedOrderPayload {
+    pub payload: String,
+    pub signature: String,
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    /// Sets the ed25519 key that signs payloads for `create_order_for`,
+    /// replacing any earlier one. A new entry's storage is paid out of the
+    /// attached deposit and the rest refunded.
+    #[payable]
+    pub fn register_order_signing_key(&mut self, public_key: PublicKey) {
+        require!(public_key.curve_type() == CurveType::ED25519, "Signing key must be ed25519");
+        let account = env::predecessor_account_id();
+        let storage_before = env::storage_usage();
+        self.order_signing_keys.insert(&account, &public_key);
+        let cost = env::storage_usage().saturating_sub(storage_before) as Balance * env::storage_byte_cost().as_yoctonear();
+        let attached = env::attached_deposit().as_yoctonear();
+        require!(attached >= cost, "Attached deposit does not cover signing key storage");
+        if attached > cost {
+            Promise::new(account.clone()).transfer(NearToken::from_yoctonear(attached - cost));
+        }
+        env::log_str(&format!("Order signing key of {} set to {:?}", account, public_key));
+    }
+
+    pub fn remove_order_signing_key(&mut self) {
+        let account = env::predecessor_account_id();
+        require!(self.order_signing_keys.remove(&account).is_some(), "No signing key registered");
+        env::log_str(&format!("Order signing key of {} removed", account));
+    }
+
+    pub fn get_order_signing_key(&self, account_id: AccountId) -> Option<PublicKey> {
+        self.order_signing_keys.get(&account_id)
+    }
+
+    // The next payload must carry a higher nonce
+    pub fn get_order_nonce(&self, account_id: AccountId) -> Option<u64> {
+        self.order_nonces.get(&account_id)
+    }
+
+    /// Creates an order for the account that signed `signed_payload`, with
+    /// it as the maker, whoever relays the call. The relayer attaches
+    /// exactly `params.amount_in`, or nothing to draw it from the maker's
+    /// deposited balance. Storage comes out of the escrow, as in
+    /// `create_cross_chain_order` without `amount_in`.
+    #[payable]
+    pub fn create_order_for(&mut self, signed_payload: SignedOrderPayload) -> u64 {
+        limits::assert_len(
+            signed_payload.payload.len(),
+            limits::MAX_SIGNED_PAYLOAD_LEN,
+            limits::ERR_SIGNED_PAYLOAD_TOO_LONG,
+        );
+        let payload: OrderPayload = serde_json::from_str(&signed_payload.payload)
+            .unwrap_or_else(|err| Violation::new(ERR_INVALID_PAYLOAD, err.to_string()).panic());
+        if payload.contract_id != env::current_account_id() {
+            Violation::new(ERR_WRONG_CONTRACT, "payload is signed for another contract").panic();
+        }
+        if env::block_timestamp() >= payload.expires_at.0 {
+            Violation::new(
+                ERR_PAYLOAD_EXPIRED,
+                format!("payload expired at {}", payload.expires_at.0),
+            )
+            .panic();
+        }
+        self.assert_payload_signed(&payload.maker, &signed_payload);
+        // Consumed with the order, so the same payload cannot create another
+        if let Some(last) = self.order_nonces.get(&payload.maker) {
+            if payload.nonce <= last {
+                Violation::new(
+                    ERR_NONCE_USED,
+                    format!("nonce {} is not above the last used nonce {}", payload.nonce, last),
+                )
+                .panic();
+            }
+        }
+        self.order_nonces.insert(&payload.maker, &payload.nonce);
+
+        let attached = env::attached_deposit().as_yoctonear();
+        let amount = payload.params.amount_in.0;
+        if attached == 0 {
+            self.debit_order_balance(&payload.maker, amount);
+        } else if attached != amount {
+            Violation::new(
+                ERR_DEPOSIT_MISMATCH,
+                format!("attached {} but the payload's amount_in is {}", attached, amount),
+            )
+            .panic();
+        }
+
+        let order_id = self.internal_create_order(
+            payload.maker.clone(),
+            ft::NATIVE_TOKEN.parse().unwrap(),
+            payload.params,
+            StorageCharge::FromEscrow,
+        );
+        env::log_str(&format!(
+            "Order {} created for {} by {} with nonce {}",
+            order_id,
+            payload.maker,
+            env::predecessor_account_id(),
+            payload.nonce
+        ));
+        order_id
+    }
+
+    /// Credits NEAR that `create_order_for` can draw on, to the caller or
+    /// to `account_id`, e.g. a custodian funding its users.
+    #[payable]
+    pub fn deposit_order_balance(&mut self, account_id: Option<AccountId>) -> U128 {
+        let account = account_id.unwrap_or_else(env::predecessor_account_id);
+        let deposit = env::attached_deposit().as_yoctonear();
+        require!(deposit > 0, "Attach the amount to deposit");
+        let balance = self.order_balances.get(&account).unwrap_or(0) + deposit;
+        self.order_balances.insert(&account, &balance);
+        env::log_str(&format!("Order balance of {} credited {}, now {}", account, deposit, balance));
+        U128(balance)
+    }
+
+    pub fn withdraw_order_balance(&mut self, amount: U128) -> Promise {
+        let account = env::predecessor_account_id();
+        let balance = self.order_balances.get(&account).unwrap_or(0);
+        require!(amount.0 > 0 && amount.0 <= balance, "Amount exceeds the order balance");
+        self.set_order_balance(&account, balance - amount.0);
+        env::log_str(&format!("{} withdrew {} of its order balance", account, amount.0));
+        Promise::new(account).transfer(NearToken::from_yoctonear(amount.0))
+    }
+
+    pub fn get_order_balance(&self, account_id: AccountId) -> U128 {
+        U128(self.order_balances.get(&account_id).unwrap_or(0))
+    }
+}
+
+impl AdaptiveCrossChain {
+    fn assert_payload_signed(&self, maker: &AccountId, signed_payload: &SignedOrderPayload) {
+        let public_key = self.order_signing_keys.get(maker).unwrap_or_else(|| {
+            Violation::new(ERR_NO_SIGNING_KEY, format!("{} has no order signing key", maker)).panic()
+        });
+        // as_bytes() leads with the curve type byte
+        let key: Option<[u8; 32]> = public_key.as_bytes()[1..].try_into().ok();
+        let signature: Option<[u8; 64]> = hex::decode(normalize::strip_hex_prefix(&signed_payload.signature))
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok());
+        let valid = match (key, signature) {
+            (Some(key), Some(signature)) => {
+                env::ed25519_verify(&signature, signed_payload.payload.as_bytes(), &key)
+            }
+            _ => false,
+        };
+        if !valid {
+            Violation::new(ERR_INVALID_SIGNATURE, "payload is not signed by the maker's signing key").panic();
+        }
+    }
+
+    fn debit_order_balance(&mut self, maker: &AccountId, amount: Balance) {
+        let balance = self.order_balances.get(maker).unwrap_or(0);
+        if balance < amount {
+            Violation::new(
+                ERR_INSUFFICIENT_BALANCE,
+                format!("order balance {} does not cover amount_in {}", balance, amount),
+            )
+            .panic();
+        }
+        self.set_order_balance(maker, balance - amount);
+    }
+
+    fn set_order_balance(&mut self, account: &AccountId, balance: Balance) {
+        if balance == 0 {
+            self.order_balances.remove(account);
+        } else {
+            self.order_balances.insert(account, &balance);
+        }
+    }
+}
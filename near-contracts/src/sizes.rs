@@ -0,0 +1,138 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, require, AccountId};
+
+use crate::validation::Violation;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt};
+
+pub const ERR_ORDER_TOO_SMALL: &str = "ERR_ORDER_TOO_SMALL";
+pub const ERR_ORDER_TOO_LARGE: &str = "ERR_ORDER_TOO_LARGE";
+
+/// Bounds on the deposit one order may escrow, in units of its token_in.
+/// `None` leaves that side open.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OrderSizeLimits {
+    pub min_amount: Option<U128>,
+    pub max_amount: Option<U128>,
+}
+
+// Checked against what the order escrows once its fees and storage are
+// paid, for NEAR and FT deposits alike. Open orders keep their size.
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    // Native orders use "near" as `token_in`
+    pub fn set_order_size_limits(&mut self, token_in: AccountId, limits: OrderSizeLimits) {
+        self.assert_owner();
+        if let (Some(min), Some(max)) = (limits.min_amount, limits.max_amount) {
+            require!(min.0 <= max.0, "Minimum order size is above the maximum");
+        }
+        env::log_str(&format!(
+            "Order size limits for {} set to {} - {}",
+            token_in,
+            bound_text(limits.min_amount),
+            bound_text(limits.max_amount)
+        ));
+        if limits.min_amount.is_none() && limits.max_amount.is_none() {
+            self.order_size_limits.remove(&token_in);
+        } else {
+            self.order_size_limits.insert(&token_in, &limits);
+        }
+        self.audit("set_order_size_limits", None);
+    }
+
+    pub fn get_order_size_limits(&self, token_in: AccountId) -> OrderSizeLimits {
+        self.order_size_limits.get(&token_in).unwrap_or_default()
+    }
+}
+
+impl AdaptiveCrossChain {
+    pub(crate) fn order_size_violation(&self, token_in: &str, amount: U128) -> Option<Violation> {
+        let token: AccountId = token_in.parse().ok()?;
+        let limits = self.order_size_limits.get(&token)?;
+        let out_of_bounds = |code| {
+            Violation::new(
+                code,
+                format!(
+                    "order of {} {} is outside the allowed size of {} - {}",
+                    amount.0,
+                    token_in,
+                    bound_text(limits.min_amount),
+                    bound_text(limits.max_amount)
+                ),
+            )
+        };
+        if limits.min_amount.is_some_and(|min| amount.0 < min.0) {
+            return Some(out_of_bounds(ERR_ORDER_TOO_SMALL));
+        }
+        if limits.max_amount.is_some_and(|max| amount.0 > max.0) {
+            return Some(out_of_bounds(ERR_ORDER_TOO_LARGE));
+        }
+        None
+    }
+}
+
+fn bound_text(bound: Option<U128>) -> String {
+    bound.map(|bound| bound.0.to_string()).unwrap_or_else(|| "unbounded".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::onboarding::ERR_DEPOSIT_SHORTFALL;
+    use crate::test_utils::*;
+    use crate::test_utils::ONE_NEAR;
+    use crate::validation::OrderParams;
+
+    const MIN_SIZE: u128 = 10 * ONE_NEAR;
+
+    fn sized_contract() -> AdaptiveCrossChain {
+        let mut contract = setup();
+        call(owner(), 0);
+        contract.set_order_size_limits(
+            "near".parse().unwrap(),
+            OrderSizeLimits { min_amount: Some(U128(MIN_SIZE)), max_amount: None },
+        );
+        contract
+    }
+
+    fn sponsored(contract: &AdaptiveCrossChain, amount_in: u128) -> OrderParams {
+        OrderParams { gas_sponsorship: Some(U128(ONE_NEAR)), ..order_params(contract, 1, amount_in) }
+    }
+
+    // Without amount_in the sponsorship and storage come out of the
+    // deposit, so a deposit at the minimum escrows less than it
+    #[test]
+    fn whole_deposits_are_sized_net_of_fees_and_storage() {
+        let mut contract = sized_contract();
+        let params = sponsored(&contract, MIN_SIZE);
+        let rejected = run_or_revert(&mut contract, |c| create_order_with(c, params, MIN_SIZE)).unwrap_err();
+        assert!(rejected.contains(ERR_ORDER_TOO_SMALL), "{}", rejected);
+
+        let deposit = MIN_SIZE + 2 * ONE_NEAR;
+        let params = sponsored(&contract, deposit);
+        let order_id = create_order_with(&mut contract, params, deposit);
+        let order = contract.orders.get(&order_id).unwrap();
+        assert!(order.amount_in.0 >= MIN_SIZE && order.amount_in.0 < deposit - ONE_NEAR);
+        assert_eq!(order.gas_sponsorship.0, ONE_NEAR);
+    }
+
+    // An exact amount_in is escrowed in full; the sponsorship and storage
+    // are attached on top of it
+    #[test]
+    fn exact_amounts_pay_fees_on_top() {
+        let mut contract = sized_contract();
+        let params = sponsored(&contract, MIN_SIZE);
+        let rejected =
+            run_or_revert(&mut contract, |c| create_order_with(c, params.clone(), MIN_SIZE + ONE_NEAR)).unwrap_err();
+        assert!(rejected.contains(ERR_DEPOSIT_SHORTFALL), "{}", rejected);
+
+        let order_id = create_order_with(&mut contract, params, MIN_SIZE + 2 * ONE_NEAR);
+        let order = contract.orders.get(&order_id).unwrap();
+        assert_eq!(order.amount_in.0, MIN_SIZE);
+        assert_eq!(order.gas_sponsorship.0, ONE_NEAR);
+        let storage_cost = contract.order_storage_deposits.get(&order_id).unwrap();
+        assert_eq!(transfers(), vec![(maker(), ONE_NEAR - storage_cost)]);
+    }
+}
@@ -0,0 +1,197 @@
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen, require, AccountId, Promise};
+
+use crate::limits;
+use crate::normalize;
+use crate::shadow::{recover_signer, EthAddress};
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt};
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    /// Claim submitted by a relayer on behalf of a taker without NEAR for
+    /// gas. The claim is made as `receiver`, which must be the taker during
+    /// its exclusive window; the payout goes to the taker. The caller is
+    /// paid `relayer_fee` out of the order's gas sponsorship, which caps it.
+    ///
+    /// `eth_signature` is a personal_sign signature by `eth_address` over
+    /// eth_address ++ order_id ++ receiver ++ relayer_fee ++ nonce (integers
+    /// big-endian), so a relayer cannot redirect the payout or raise its
+    /// fee. The nonce is the shadow account nonce of `eth_address`. A claim
+    /// that fails for any reason reverts entirely and pays no fee, so failed
+    /// attempts cannot drain the sponsorship.
+    pub fn claim_with_secret_relayed(
+        &mut self,
+        hashlock: String,
+        secret: String,
+        receiver: AccountId,
+        relayer_fee: U128,
+        eth_address: String,
+        eth_signature: String,
+    ) -> Promise {
+        limits::assert_len(hashlock.len(), limits::MAX_HASHLOCK_LEN, limits::ERR_HASHLOCK_TOO_LONG);
+        let hashlock = normalize::hex_id(&hashlock);
+        let order_id = self.hashlock_to_order.get(&hashlock).expect("Order not found");
+        let address = normalize::eth_address_bytes(&eth_address).unwrap_or_else(|v| v.panic());
+        let nonce = self.shadow_nonces.get(&address).unwrap_or(0);
+        let message = relayed_claim_message(&address, order_id, &receiver, relayer_fee.0, nonce);
+        let signer = recover_signer(&message, &eth_signature).expect("Invalid signature");
+        require!(signer == address, "Signature not from eth_address");
+
+        // Same nonce as shadow claims, so one signature is good for one call
+        self.shadow_nonces.insert(&address, &(nonce + 1));
+
+        env::log_str(&format!(
+            "Relayed claim of order {} by {} for {}, relayer fee {}",
+            order_id,
+            env::predecessor_account_id(),
+            receiver,
+            relayer_fee.0
+        ));
+        self.settle_claim(hashlock, secret, receiver, None, relayer_fee.0)
+    }
+}
+
+fn relayed_claim_message(
+    address: &EthAddress,
+    order_id: u64,
+    receiver: &AccountId,
+    relayer_fee: u128,
+    nonce: u64,
+) -> Vec<u8> {
+    let mut message = address.to_vec();
+    message.extend_from_slice(&order_id.to_be_bytes());
+    message.extend_from_slice(receiver.as_bytes());
+    message.extend_from_slice(&relayer_fee.to_be_bytes());
+    message.extend_from_slice(&nonce.to_be_bytes());
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::accounts;
+
+    use super::*;
+    use crate::test_utils::*;
+    use crate::OrderStatus;
+
+    const DEPOSIT: u128 = 10 * ONE_NEAR;
+    const SPONSORSHIP: u128 = ONE_NEAR / 10;
+    const KEY: u8 = 7;
+
+    fn relayer() -> AccountId {
+        accounts(4)
+    }
+
+    fn address() -> EthAddress {
+        normalize::eth_address_bytes(&eth_address_of(KEY)).unwrap_or_else(|v| v.panic())
+    }
+
+    fn sponsored_order(contract: &mut AdaptiveCrossChain, n: u8) -> u64 {
+        let mut params = order_params(contract, n, DEPOSIT);
+        params.gas_sponsorship = Some(U128(SPONSORSHIP));
+        let order_id = create_order_with(contract, params, DEPOSIT);
+        lock(contract, order_id);
+        order_id
+    }
+
+    fn signature(contract: &AdaptiveCrossChain, key: u8, order_id: u64, fee: u128) -> String {
+        let address = address();
+        let nonce = contract.shadow_nonces.get(&address).unwrap_or(0);
+        eth_sign(key, &relayed_claim_message(&address, order_id, &resolver(), fee, nonce))
+    }
+
+    fn relay(contract: &mut AdaptiveCrossChain, order_id: u64, n: u8, fee: u128, signature: String) {
+        call(relayer(), 0);
+        let hashlock = contract.orders.get(&order_id).unwrap().hashlock;
+        let _ = contract.claim_with_secret_relayed(hashlock, secret(n), resolver(), U128(fee), eth_address_of(KEY), signature);
+    }
+
+    fn settle(contract: &mut AdaptiveCrossChain, order_id: u64, paid: bool) {
+        let args = scheduled_calls("on_claim_transfer").pop().unwrap();
+        callback(paid);
+        assert_eq!(contract.on_claim_transfer(order_id, serde_json::from_value(args["claim"].clone()).unwrap()), paid);
+    }
+
+    // These fail after the nonce is consumed, which the revert undoes
+    fn reverts(contract: &mut AdaptiveCrossChain, code: &str, f: impl FnOnce(&mut AdaptiveCrossChain)) {
+        let message = run_or_revert(contract, f).expect_err("claim was accepted");
+        assert!(message.contains(code), "expected {}, got {:?}", code, message);
+    }
+
+    #[test]
+    fn relayer_is_paid_and_the_rest_returned_to_the_maker() {
+        let mut contract = setup();
+        let order_id = sponsored_order(&mut contract, 1);
+        let fee = SPONSORSHIP * 3 / 10;
+
+        let signature = signature(&contract, KEY, order_id, fee);
+        relay(&mut contract, order_id, 1, fee, signature);
+        settle(&mut contract, order_id, true);
+
+        let order = contract.orders.get(&order_id).unwrap();
+        assert!(matches!(order.status, OrderStatus::Completed));
+        let paid = transfers();
+        assert!(paid.contains(&(relayer(), fee)));
+        assert!(paid.contains(&(maker(), SPONSORSHIP - fee)));
+    }
+
+    #[test]
+    fn fee_is_capped_by_the_sponsorship() {
+        let mut contract = setup();
+        let order_id = sponsored_order(&mut contract, 1);
+        let signature = signature(&contract, KEY, order_id, SPONSORSHIP + 1);
+        reverts(&mut contract, "Relayer fee exceeds gas sponsorship", |c| {
+            relay(c, order_id, 1, SPONSORSHIP + 1, signature)
+        });
+        assert_eq!(contract.orders.get(&order_id).unwrap().gas_sponsorship.0, SPONSORSHIP);
+    }
+
+    // The signature covers the receiver, the fee and the nonce, so a
+    // relayer cannot raise its fee or reuse one
+    #[test]
+    fn only_the_signed_claim_is_accepted() {
+        let mut contract = setup();
+        let order_id = sponsored_order(&mut contract, 1);
+        let fee = SPONSORSHIP / 2;
+
+        let other_key = signature(&contract, KEY + 1, order_id, fee);
+        assert_rejected_unchanged(&mut contract, relayer(), 0, "Signature not from eth_address", |c| {
+            relay(c, order_id, 1, fee, other_key)
+        });
+        let lower_fee = signature(&contract, KEY, order_id, fee / 2);
+        assert_rejected_unchanged(&mut contract, relayer(), 0, "Signature not from eth_address", |c| {
+            relay(c, order_id, 1, fee, lower_fee)
+        });
+        assert_rejected_unchanged(&mut contract, relayer(), 0, "Invalid signature", |c| {
+            relay(c, order_id, 1, fee, "00".repeat(65))
+        });
+
+        let signature = signature(&contract, KEY, order_id, fee);
+        relay(&mut contract, order_id, 1, fee, signature.clone());
+        settle(&mut contract, order_id, true);
+        // A second order signed for the same nonce no longer verifies
+        let second = sponsored_order(&mut contract, 2);
+        let stale = eth_sign(KEY, &relayed_claim_message(&address(), second, &resolver(), fee, 0));
+        assert_rejected_unchanged(&mut contract, relayer(), 0, "Signature not from eth_address", |c| {
+            relay(c, second, 2, fee, stale)
+        });
+    }
+
+    #[test]
+    fn failed_claims_do_not_drain_the_sponsorship() {
+        let mut contract = setup();
+        let order_id = sponsored_order(&mut contract, 1);
+        let fee = SPONSORSHIP;
+
+        let signature = signature(&contract, KEY, order_id, fee);
+        reverts(&mut contract, "Invalid secret", |c| relay(c, order_id, 2, fee, signature.clone()));
+
+        // A payout that fails pays no one and keeps the sponsorship
+        relay(&mut contract, order_id, 1, fee, signature);
+        settle(&mut contract, order_id, false);
+        assert!(transfers().is_empty());
+        let order = contract.orders.get(&order_id).unwrap();
+        assert!(matches!(order.status, OrderStatus::Locked));
+        assert_eq!(order.gas_sponsorship.0, SPONSORSHIP);
+    }
+}
@@ -0,0 +1,99 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen};
+
+use crate::ft;
+use crate::timing;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, CrossChainOrder, OrderStatus};
+
+/// Lifetime totals since deployment. Unlike the status counts they are
+/// not reduced when orders are archived.
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct ProtocolTotals {
+    pub created: u64,
+    pub locked: u64, // Locks taken, each partial lock included
+    pub completed: u64,
+    pub cancelled: u64,
+    pub expired: u64,
+    pub completion_time_ns: u128, // Summed over completed orders
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ChainVolume {
+    pub chain_id: u64,
+    pub volume_in: U128, // yoctoNEAR
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProtocolStats {
+    pub orders_created: u64,
+    pub orders_locked: u64,
+    pub orders_completed: u64,
+    pub orders_cancelled: u64,
+    pub orders_expired: u64,
+    pub volume_by_chain: Vec<ChainVolume>, // Configured chains, disabled ones included
+    pub average_completion_secs: u64,      // created_at to the completing claim
+}
+
+// Updated at creation and by the status transition helper, never
+// recomputed from the order book. Volume counts native deposits only, as
+// FT amounts are in other units.
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    pub fn get_protocol_stats(&self) -> ProtocolStats {
+        let totals = &self.protocol_totals;
+        ProtocolStats {
+            orders_created: totals.created,
+            orders_locked: totals.locked,
+            orders_completed: totals.completed,
+            orders_cancelled: totals.cancelled,
+            orders_expired: totals.expired,
+            volume_by_chain: self
+                .chains
+                .keys()
+                .map(|chain_id| ChainVolume {
+                    chain_id,
+                    volume_in: U128(self.chain_volumes.get(&chain_id).unwrap_or(0)),
+                })
+                .collect(),
+            average_completion_secs: average_completion_secs(totals),
+        }
+    }
+}
+
+impl AdaptiveCrossChain {
+    pub(crate) fn record_created(&mut self, order: &CrossChainOrder) {
+        self.protocol_totals.created += 1;
+        // Only chains in the registry get an entry, which bounds the map
+        if ft::is_native(order.token_in.as_str()) && self.chains.get(&order.target_chain_id).is_some() {
+            let volume = self.chain_volumes.get(&order.target_chain_id).unwrap_or(0);
+            self.chain_volumes.insert(&order.target_chain_id, &(volume + order.amount_in.0));
+        }
+    }
+
+    // A failed claim going back to Locked is not a new lock
+    pub(crate) fn record_transition(&mut self, order: &CrossChainOrder, from: &OrderStatus) {
+        let totals = &mut self.protocol_totals;
+        match (from, &order.status) {
+            (OrderStatus::Active, OrderStatus::Locked) => totals.locked += 1,
+            (_, OrderStatus::Completed) => {
+                totals.completed += 1;
+                totals.completion_time_ns +=
+                    env::block_timestamp().saturating_sub(order.created_at.0) as u128;
+            }
+            (_, OrderStatus::Cancelled) => totals.cancelled += 1,
+            (_, OrderStatus::Expired) => totals.expired += 1,
+            _ => {}
+        }
+    }
+}
+
+fn average_completion_secs(totals: &ProtocolTotals) -> u64 {
+    if totals.completed == 0 {
+        return 0;
+    }
+    (totals.completion_time_ns / totals.completed as u128 / timing::NS_PER_SEC as u128) as u64
+}
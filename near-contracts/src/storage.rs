@@ -0,0 +1,611 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+use near_sdk::collections::{LookupMap, Vector};
+use near_sdk::{env, near_bindgen, require, AccountId, BorshStorageKey, CryptoHash, NearToken, Promise, StorageUsage};
+
+use crate::onboarding::ERR_DEPOSIT_SHORTFALL;
+use crate::validation::Violation;
+use crate::limits;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, Balance, CrossChainOrder, EventLevel, SlippageHistory};
+
+// Tightened behaviour while a store is over its soft budget:
+//
+// | Store over budget | Effect                                              |
+// |-------------------|-----------------------------------------------------|
+// | Orders            | min order size TIGHT_MIN_ORDER_AMOUNT; archival     |
+// |                   | retention divided by TIGHT_RETENTION_DIVISOR        |
+// | Histories         | slippage histories keep the last TIGHT_HISTORY_LEN  |
+// | Indexes           | archival retention divided by TIGHT_RETENTION_DIVISOR |
+// | AuditLog          | event only; entries are never dropped               |
+// | Archive           | event only; summaries are the smallest form kept    |
+// | Notifications     | new settlement callbacks are rejected               |
+//
+// Effects lift as soon as the store is back under budget. Outside of that,
+// histories keep the last max_history_entries.
+pub const TIGHT_MIN_ORDER_AMOUNT: Balance = 10_000_000_000_000_000_000_000_000; // 10 NEAR
+pub const TIGHT_HISTORY_LEN: u64 = 10;
+pub const DEFAULT_MAX_HISTORY_ENTRIES: u64 = 50;
+pub const MAX_HISTORY_ENTRIES: u64 = 500;
+pub const TIGHT_RETENTION_DIVISOR: u64 = 4;
+
+// Storage estimate until the first native order has been measured
+pub const DEFAULT_ORDER_STORAGE_BYTES: StorageUsage = 1_000;
+// Escrow a native order must keep after paying for its storage
+pub const MIN_SWAP_AMOUNT: Balance = 1_000_000_000_000_000_000_000; // 0.001 NEAR
+
+pub const ERR_STORAGE_BUDGET: &str = "ERR_STORAGE_BUDGET";
+
+// Prefix of the user order lists before StorageKey. Each list's elements
+// sat under "u" followed by the account id, which could collide with the
+// map's own keys and with other accounts' lists.
+const LEGACY_USER_ORDERS_PREFIX: &[u8] = b"u";
+
+/// Prefixes of per-account and per-order collections. Borsh writes the
+/// variant index first, a control byte, while every byte-string prefix
+/// starts with a printable one; `storage_prefixes_never_collide` checks it.
+#[derive(BorshSerialize, BorshStorageKey)]
+pub(crate) enum StorageKey {
+    UserOrderLists,
+    UserOrders { account_hash: CryptoHash },
+    SlippageHistory { order_id: u64 },
+    OrderSizeLimits,
+    UserOpenCounts,
+    ChainVolumes,
+    IncomingOrders,
+    IncomingByRecipient,
+    OrderSigningKeys,
+    OrderNonces,
+    OrderBalances,
+    HistoryHeads,
+    ClaimingOrders,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum StorageStore {
+    Orders,        // Order records, hashlock and per-user lookups
+    Histories,     // Slippage histories
+    Indexes,       // Expiry and terminal indexes
+    AuditLog,
+    Archive,       // Archived order summaries
+    Notifications, // Settlement callback registrations
+}
+
+pub const ALL_STORES: [StorageStore; 6] = [
+    StorageStore::Orders,
+    StorageStore::Histories,
+    StorageStore::Indexes,
+    StorageStore::AuditLog,
+    StorageStore::Archive,
+    StorageStore::Notifications,
+];
+
+/// Bytes per store, maintained from storage deltas in each write helper.
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct StoreUsage {
+    pub orders: StorageUsage,
+    pub histories: StorageUsage,
+    pub indexes: StorageUsage,
+    pub audit_log: StorageUsage,
+    pub archive: StorageUsage,
+    pub notifications: StorageUsage,
+}
+
+impl StoreUsage {
+    fn slot(&mut self, store: StorageStore) -> &mut StorageUsage {
+        match store {
+            StorageStore::Orders => &mut self.orders,
+            StorageStore::Histories => &mut self.histories,
+            StorageStore::Indexes => &mut self.indexes,
+            StorageStore::AuditLog => &mut self.audit_log,
+            StorageStore::Archive => &mut self.archive,
+            StorageStore::Notifications => &mut self.notifications,
+        }
+    }
+
+    fn get(&self, store: StorageStore) -> StorageUsage {
+        match store {
+            StorageStore::Orders => self.orders,
+            StorageStore::Histories => self.histories,
+            StorageStore::Indexes => self.indexes,
+            StorageStore::AuditLog => self.audit_log,
+            StorageStore::Archive => self.archive,
+            StorageStore::Notifications => self.notifications,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StoreReport {
+    pub store: StorageStore,
+    pub bytes: U64,
+    pub budget: Option<U64>,
+    pub over_budget: bool,
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    // Soft budget in bytes; None removes it
+    pub fn set_storage_budget(&mut self, store: StorageStore, budget: Option<U64>) {
+        self.assert_owner();
+        match budget {
+            Some(budget) => {
+                self.storage_budgets.insert(&store, &budget.0);
+            }
+            None => {
+                self.storage_budgets.remove(&store);
+            }
+        }
+        env::log_str(&format!(
+            "Storage budget for {} set to {:?}",
+            store_name(store),
+            budget.map(|b| b.0)
+        ));
+    }
+
+    // Longer histories are trimmed on their next update
+    pub fn set_max_history_entries(&mut self, max_entries: u64) {
+        self.assert_owner();
+        require!(
+            (1..=MAX_HISTORY_ENTRIES).contains(&max_entries),
+            format!("History cap must be between 1 and {}", MAX_HISTORY_ENTRIES)
+        );
+        env::log_str(&format!(
+            "max_history_entries set from {} to {}",
+            self.max_history_entries, max_entries
+        ));
+        self.max_history_entries = max_entries;
+        self.audit("set_max_history_entries", None);
+    }
+
+    /// What the storage of a new native order is expected to cost, in
+    /// yoctoNEAR, taken out of its deposit. Based on the latest order, so
+    /// long account ids or a maker's first order can cost slightly more.
+    pub fn get_storage_cost_estimate(&self) -> U128 {
+        U128(self.last_order_storage_bytes as Balance * env::storage_byte_cost().as_yoctonear())
+    }
+
+    /// Moves the user order lists and slippage histories of orders among
+    /// ids [from_order_id, from_order_id + limit) to StorageKey prefixes.
+    /// The first call on state from before StorageKey moves the list map
+    /// itself, so makers not migrated yet list no orders until their
+    /// orders come up. Lists are rebuilt from the orders and archived
+    /// summaries, so ids a collision put in the wrong list are dropped;
+    /// the legacy list is deleted the first time its maker comes up. Safe
+    /// to run again over the same range. Returns how many orders were
+    /// migrated.
+    pub fn migrate_storage_keys(&mut self, from_order_id: u64, limit: u64) -> u64 {
+        self.assert_owner();
+        let end = from_order_id
+            .saturating_add(limit.min(limits::MAX_BATCH_SIZE as u64))
+            .min(self.next_order_id);
+        let mut legacy_lists: LookupMap<AccountId, Vector<u64>> = LookupMap::new(LEGACY_USER_ORDERS_PREFIX);
+        // A map serializes as its prefix
+        if borsh::to_vec(&self.user_orders).ok() == borsh::to_vec(&legacy_lists).ok() {
+            self.user_orders = LookupMap::new(StorageKey::UserOrderLists);
+            env::log_str("User order lists moved to their StorageKey prefix");
+        }
+        let mut migrated = 0;
+        for order_id in from_order_id..end {
+            let maker = match self.orders.get(&order_id) {
+                Some(order) => order.maker,
+                None => match self.archived_orders.get(&order_id) {
+                    Some(archived) => archived.maker,
+                    None => continue,
+                },
+            };
+
+            let storage_before = env::storage_usage();
+            if let Some(mut legacy) = legacy_lists.remove(&maker) {
+                legacy.clear();
+            }
+            let mut list = self.user_order_list(&maker);
+            insert_sorted(&mut list, order_id);
+            self.user_orders.insert(&maker, &list);
+            self.record_storage(StorageStore::Orders, storage_before);
+
+            if let Some(mut history) = self.slippage_log(order_id) {
+                let storage_before = env::storage_usage();
+                let entries = history.to_vec();
+                history.entries.clear();
+                let mut rekeyed = Vector::new(StorageKey::SlippageHistory { order_id });
+                for entry in &entries {
+                    rekeyed.push(entry);
+                }
+                self.slippage_history.insert(&order_id, &rekeyed);
+                self.history_heads.remove(&order_id);
+                self.record_storage(StorageStore::Histories, storage_before);
+            }
+            migrated += 1;
+        }
+        env::log_str(&format!(
+            "Migrated storage keys of {} orders in [{}, {})",
+            migrated, from_order_id, end
+        ));
+        migrated
+    }
+
+    pub fn get_storage_breakdown(&self) -> Vec<StoreReport> {
+        ALL_STORES
+            .iter()
+            .map(|&store| StoreReport {
+                store,
+                bytes: U64(self.store_usage.get(store)),
+                budget: self.storage_budgets.get(&store).map(U64),
+                over_budget: self.over_budget(store),
+            })
+            .collect()
+    }
+}
+
+impl AdaptiveCrossChain {
+    /// The single write path for order records.
+    pub(crate) fn save_order(&mut self, order: &CrossChainOrder) {
+        let before = env::storage_usage();
+        self.orders.insert(&order.order_id, order);
+        self.record_storage(StorageStore::Orders, before);
+    }
+
+    /// Attributes the storage change since `before` to `store` and emits an
+    /// event when that moves the store across its budget.
+    pub(crate) fn record_storage(&mut self, store: StorageStore, before: StorageUsage) {
+        let after = env::storage_usage();
+        let was_over = self.over_budget(store);
+        let slot = self.store_usage.slot(store);
+        if after >= before {
+            *slot += after - before;
+        } else {
+            *slot = slot.saturating_sub(before - after);
+        }
+
+        let over = self.over_budget(store);
+        if over != was_over {
+            self.emit_event(
+                EventLevel::Minimal,
+                if over { "storage_budget_exceeded" } else { "storage_budget_recovered" },
+                json!({
+                    "store": store_name(store),
+                    "bytes": U64(self.store_usage.get(store)),
+                    "budget": self.storage_budgets.get(&store).map(U64),
+                }),
+            );
+        }
+    }
+
+    pub(crate) fn over_budget(&self, store: StorageStore) -> bool {
+        self.storage_budgets
+            .get(&store)
+            .map(|budget| self.store_usage.get(store) > budget)
+            .unwrap_or(false)
+    }
+
+    pub(crate) fn min_order_amount(&self) -> Balance {
+        if self.over_budget(StorageStore::Orders) {
+            TIGHT_MIN_ORDER_AMOUNT
+        } else {
+            0
+        }
+    }
+
+    pub(crate) fn retention_divisor(&self) -> u64 {
+        if self.over_budget(StorageStore::Orders) || self.over_budget(StorageStore::Indexes) {
+            TIGHT_RETENTION_DIVISOR
+        } else {
+            1
+        }
+    }
+
+    /// Takes the cost of `bytes` out of a new order's escrow and holds it
+    /// for the maker until the order is archived. Panics if the deposit
+    /// does not also leave MIN_SWAP_AMOUNT to swap.
+    pub(crate) fn reserve_order_storage(&mut self, order: &mut CrossChainOrder, bytes: StorageUsage) {
+        let cost = bytes as Balance * env::storage_byte_cost().as_yoctonear();
+        let deposit = order.amount_in.0;
+        if deposit < cost + MIN_SWAP_AMOUNT {
+            Violation::new(
+                ERR_DEPOSIT_SHORTFALL,
+                format!(
+                    "deposit {} does not cover order storage {} plus the minimum swap of {}",
+                    deposit, cost, MIN_SWAP_AMOUNT
+                ),
+            )
+            .panic();
+        }
+        order.amount_in = U128(deposit - cost);
+        order.amount_remaining = order.amount_in;
+        self.save_order(order);
+        self.order_storage_deposits.insert(&order.order_id, &cost);
+        self.last_order_storage_bytes = bytes;
+    }
+
+    /// Holds the cost of `bytes` for the maker out of what was attached on
+    /// top of the declared `amount_in` and its creation `fees`, and refunds
+    /// the rest in this call. Panics with the exact shortfall.
+    pub(crate) fn charge_attached_storage(
+        &mut self,
+        order: &CrossChainOrder,
+        bytes: StorageUsage,
+        fees: Balance,
+        attached: Balance,
+    ) {
+        let cost = bytes as Balance * env::storage_byte_cost().as_yoctonear();
+        let required = order.amount_in.0 + fees + cost;
+        if attached < required {
+            Violation::new(
+                ERR_DEPOSIT_SHORTFALL,
+                format!(
+                    "attached {} is {} short of amount_in {} + fees {} + order storage {}",
+                    attached,
+                    required - attached,
+                    order.amount_in.0,
+                    fees,
+                    cost
+                ),
+            )
+            .panic();
+        }
+        self.order_storage_deposits.insert(&order.order_id, &cost);
+        self.last_order_storage_bytes = bytes;
+        let refunded = attached - required;
+        if refunded > 0 {
+            env::log_str(&format!("Refunded {} attached above amount_in, fees and storage", refunded));
+            Promise::new(order.maker.clone()).transfer(NearToken::from_yoctonear(refunded));
+        }
+    }
+
+    /// The maker's order list, or a new one under its StorageKey prefix.
+    pub(crate) fn user_order_list(&self, maker: &AccountId) -> Vector<u64> {
+        self.user_orders.get(maker).unwrap_or_else(|| {
+            Vector::new(StorageKey::UserOrders {
+                account_hash: env::sha256_array(maker.as_bytes()),
+            })
+        })
+    }
+
+    pub(crate) fn slippage_log(&self, order_id: u64) -> Option<History> {
+        let entries = self.slippage_history.get(&order_id)?;
+        let head = self.history_heads.get(&order_id).unwrap_or(0);
+        Some(History { entries, head })
+    }
+
+    /// Appends to an order's slippage history within `history_cap`.
+    /// Orders without a history, like legacy ones, are skipped.
+    pub(crate) fn record_slippage(&mut self, order_id: u64, entry: &SlippageHistory) {
+        let cap = self.history_cap();
+        if let Some(mut history) = self.slippage_log(order_id) {
+            let storage_before = env::storage_usage();
+            history.push(entry, cap);
+            self.slippage_history.insert(&order_id, &history.entries);
+            if history.head == 0 {
+                self.history_heads.remove(&order_id);
+            } else {
+                self.history_heads.insert(&order_id, &history.head);
+            }
+            self.record_storage(StorageStore::Histories, storage_before);
+        }
+    }
+
+    pub(crate) fn history_cap(&self) -> u64 {
+        if self.over_budget(StorageStore::Histories) {
+            TIGHT_HISTORY_LEN.min(self.max_history_entries)
+        } else {
+            self.max_history_entries
+        }
+    }
+}
+
+/// Who pays for the storage a new order writes.
+pub(crate) enum StorageCharge {
+    None,              // FT deposits and onboarding, which charge for it themselves
+    FromEscrow,        // Taken out of the deposit
+    Attached(Balance), // Out of this attached deposit, on top of the declared amount_in
+}
+
+/// An order's slippage history, read oldest first. Once it holds `cap`
+/// entries it is a ring: each push overwrites the oldest entry in place at
+/// `head` and moves `head` on, so no entry is rewritten but the one
+/// evicted. Entry `i` is stored at `(head + i) % len`.
+pub(crate) struct History {
+    pub entries: Vector<SlippageHistory>,
+    pub head: u64,
+}
+
+impl History {
+    pub fn len(&self) -> u64 {
+        self.entries.len()
+    }
+
+    pub fn get(&self, index: u64) -> Option<SlippageHistory> {
+        let len = self.len();
+        (index < len).then(|| self.entries.get((self.head + index) % len).unwrap())
+    }
+
+    pub fn to_vec(&self) -> Vec<SlippageHistory> {
+        (0..self.len()).filter_map(|index| self.get(index)).collect()
+    }
+
+    /// Appends `entry`, evicting the oldest entries beyond `cap`. A cap
+    /// changed since the last push unrolls the ring first, which rewrites
+    /// the history once.
+    pub fn push(&mut self, entry: &SlippageHistory, cap: u64) {
+        let len = self.len();
+        if len == cap {
+            self.entries.replace(self.head, entry);
+            self.head = (self.head + 1) % cap;
+            return;
+        }
+        if len > cap || self.head != 0 {
+            let kept = self.to_vec();
+            let skip = kept.len().saturating_sub(cap as usize - 1);
+            self.entries.clear();
+            for kept in &kept[skip..] {
+                self.entries.push(kept);
+            }
+            self.head = 0;
+        }
+        self.entries.push(entry);
+    }
+}
+
+// Order lists are kept in creation order; migration can meet ids that
+// were pushed after the upgrade
+fn insert_sorted(list: &mut Vector<u64>, order_id: u64) {
+    match list.len().checked_sub(1).and_then(|last| list.get(last)) {
+        None => list.push(&order_id),
+        Some(last) if last < order_id => list.push(&order_id),
+        Some(_) => {
+            let mut ids = list.to_vec();
+            if let Err(position) = ids.binary_search(&order_id) {
+                ids.insert(position, order_id);
+                list.clear();
+                list.extend(ids);
+            }
+        }
+    }
+}
+
+fn store_name(store: StorageStore) -> &'static str {
+    match store {
+        StorageStore::Orders => "Orders",
+        StorageStore::Histories => "Histories",
+        StorageStore::Indexes => "Indexes",
+        StorageStore::AuditLog => "AuditLog",
+        StorageStore::Archive => "Archive",
+        StorageStore::Notifications => "Notifications",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oracle::VolatilitySource;
+    use crate::test_utils::*;
+    use crate::test_utils::ONE_NEAR;
+
+    fn entry(timestamp: u64) -> SlippageHistory {
+        SlippageHistory {
+            timestamp: U64(timestamp),
+            slippage: timestamp,
+            raw_slippage: timestamp,
+            smoothed_slippage: timestamp,
+            volatility_score: 0,
+            volatility_source: VolatilitySource::Static,
+            cross_chain_delay: 0,
+        }
+    }
+
+    fn timestamps(contract: &AdaptiveCrossChain, order_id: u64) -> Vec<u64> {
+        contract.get_slippage_history(order_id, 0, 100).iter().map(|entry| entry.timestamp.0).collect()
+    }
+
+    // Keys written by `f`, other than the contract state itself
+    fn keys_written(f: impl FnOnce()) -> Vec<Vec<u8>> {
+        let before = storage();
+        f();
+        let after = storage();
+        let mut keys: Vec<_> = after
+            .iter()
+            .filter(|(key, value)| key.as_slice() != b"STATE" && before.get(*key) != Some(*value))
+            .map(|(key, _)| key.clone())
+            .collect();
+        keys.extend(before.keys().filter(|key| !after.contains_key(*key)).cloned());
+        keys
+    }
+
+    #[test]
+    fn full_histories_evict_their_oldest_entry_in_place() {
+        set_clock(1, 1);
+        let mut contract = setup();
+        call(owner(), 0);
+        contract.set_max_history_entries(3);
+        let params = order_params(&contract, 1, ONE_NEAR);
+        let order_id = create_order_with(&mut contract, params, ONE_NEAR);
+        contract.record_slippage(order_id, &entry(2));
+        contract.record_slippage(order_id, &entry(3));
+        assert_eq!(timestamps(&contract, order_id), vec![1, 2, 3]);
+
+        let element_prefix = borsh::to_vec(&StorageKey::SlippageHistory { order_id }).unwrap();
+        for timestamp in 4..=8 {
+            let written = keys_written(|| contract.record_slippage(order_id, &entry(timestamp)));
+            let elements = written.iter().filter(|key| key.starts_with(&element_prefix)).count();
+            assert_eq!(elements, 1, "push {} rewrote {} entries", timestamp, elements);
+            let expected: Vec<u64> = (timestamp - 2..=timestamp).collect();
+            assert_eq!(timestamps(&contract, order_id), expected);
+        }
+        assert_eq!(contract.get_slippage_history_len(order_id), 3);
+        assert_eq!(contract.get_slippage_history(order_id, 1, 1)[0].timestamp.0, 7);
+
+        // A lower cap keeps the newest entries, still oldest first
+        contract.set_max_history_entries(2);
+        contract.record_slippage(order_id, &entry(9));
+        assert_eq!(timestamps(&contract, order_id), vec![8, 9]);
+        contract.set_max_history_entries(4);
+        for timestamp in 10..=12 {
+            contract.record_slippage(order_id, &entry(timestamp));
+        }
+        assert_eq!(timestamps(&contract, order_id), vec![9, 10, 11, 12]);
+    }
+
+    // Every `::new(b"…")` prefix in the sources, with its file
+    fn byte_string_prefixes() -> Vec<(String, Vec<u8>)> {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/src");
+        let mut prefixes = Vec::new();
+        for file in std::fs::read_dir(dir).unwrap() {
+            let path = file.unwrap().path();
+            let source = std::fs::read_to_string(&path).unwrap();
+            for marker in ["::new(b\"", "_PREFIX: &[u8] = b\""] {
+                for (start, _) in source.match_indices(marker) {
+                    let rest = &source[start + marker.len()..];
+                    let prefix = rest.as_bytes()[..rest.find('"').unwrap()].to_vec();
+                    prefixes.push((path.display().to_string(), prefix));
+                }
+            }
+        }
+        prefixes
+    }
+
+    #[test]
+    fn storage_prefixes_never_collide() {
+        let prefixes = byte_string_prefixes();
+        assert!(prefixes.len() > 50, "found only {} prefixes", prefixes.len());
+
+        let source = include_str!("storage.rs");
+        let body = &source[source.find("enum StorageKey {").unwrap()..];
+        let variants = body[..body.find('}').unwrap()].lines().skip(1).filter(|line| !line.trim().is_empty()).count();
+        assert!(variants < 0x20, "StorageKey variant indexes reach the printable bytes");
+        for (file, prefix) in &prefixes {
+            assert!(prefix[0] >= 0x20, "{}: prefix {:?} starts with a control byte", file, prefix);
+            for (other_file, other) in &prefixes {
+                assert!(
+                    prefix == other || !other.starts_with(prefix),
+                    "{} prefix {:?} is a prefix of {} prefix {:?}",
+                    file,
+                    String::from_utf8_lossy(prefix),
+                    other_file,
+                    String::from_utf8_lossy(other)
+                );
+            }
+        }
+
+        // The same prefix twice in `new`, or in the status index it builds,
+        // is two collections on one key space
+        let lib = include_str!("lib.rs");
+        let init = &lib[lib.find("pub fn new(").unwrap()..];
+        let init = &init[..init.find("state_version: migration::STATE_VERSION").unwrap()];
+        let counters = include_str!("counters.rs");
+        let status_index = &counters[counters.find("impl StatusIndex").unwrap()..];
+        let status_index = &status_index[..status_index.find("fn get(").unwrap()];
+        let mut seen = std::collections::HashSet::new();
+        for body in [init, status_index] {
+            for (start, _) in body.match_indices("::new(b\"") {
+                let rest = &body[start + "::new(b\"".len()..];
+                let prefix = &rest[..rest.find('"').unwrap()];
+                assert!(seen.insert(prefix), "prefix {:?} is used twice", prefix);
+            }
+        }
+        assert!(seen.len() > 45, "found only {} prefixes in new", seen.len());
+    }
+}
@@ -0,0 +1,332 @@
+//! Shared setup for the unit tests next to each module.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use near_sdk::json_types::U128;
+use near_sdk::mock::{with_mocked_blockchain, MockAction};
+use near_sdk::serde_json::{self, Value};
+use near_sdk::test_utils::{accounts, get_created_receipts, get_logs, VMContextBuilder};
+use near_sdk::{borsh, env, testing_env, AccountId, MockedBlockchain, NearToken, PromiseResult, RuntimeFeesConfig};
+
+use crate::ft::PendingClaim;
+use crate::validation::OrderParams;
+use crate::{AdaptiveCrossChain, HashAlgorithm};
+
+pub const ETHEREUM_CONTRACT: &str = "0x1111111111111111111111111111111111111111";
+pub const TOKEN_OUT: &str = "0x2222222222222222222222222222222222222222";
+pub const ONE_NEAR: u128 = 1_000_000_000_000_000_000_000_000;
+
+pub fn owner() -> AccountId {
+    accounts(0)
+}
+
+pub fn maker() -> AccountId {
+    accounts(1)
+}
+
+pub fn resolver() -> AccountId {
+    accounts(2)
+}
+
+pub fn bridge() -> AccountId {
+    accounts(5)
+}
+
+thread_local! {
+    // (block timestamp, block height) of every context built from here on
+    static CLOCK: Cell<(u64, u64)> = const { Cell::new((0, 0)) };
+}
+
+/// Moves the clock of the following calls. It never goes back on its own.
+pub fn set_clock(timestamp_ns: u64, height: u64) {
+    CLOCK.with(|clock| clock.set((timestamp_ns, height)));
+}
+
+pub fn context(predecessor: AccountId) -> VMContextBuilder {
+    let (timestamp, height) = CLOCK.with(Cell::get);
+    let mut builder = VMContextBuilder::new();
+    builder
+        .current_account_id("contract.near".parse().unwrap())
+        .predecessor_account_id(predecessor.clone())
+        .signer_account_id(predecessor)
+        .account_balance(NearToken::from_near(1_000))
+        .block_timestamp(timestamp)
+        .block_height(height);
+    builder
+}
+
+/// Sets the context to `predecessor` calling with `deposit` attached.
+pub fn call(predecessor: AccountId, deposit: u128) {
+    testing_env!(context(predecessor).attached_deposit(NearToken::from_yoctonear(deposit)).build());
+}
+
+/// A contract initialised by `owner()` on empty storage, with locks
+/// allowed before the destination chain mirrors an order.
+pub fn setup() -> AdaptiveCrossChain {
+    env::set_blockchain_interface(MockedBlockchain::new(
+        context(owner()).build(),
+        near_sdk::test_vm_config(),
+        RuntimeFeesConfig::test(),
+        vec![],
+        Default::default(),
+        Default::default(),
+        None,
+    ));
+    let mut contract = AdaptiveCrossChain::new(ETHEREUM_CONTRACT.to_string(), bridge());
+    contract.set_allow_unconfirmed_locks(true);
+    contract
+}
+
+/// The `n`th test secret: 32 bytes, hex encoded.
+pub fn secret(n: u8) -> String {
+    hex::encode([n; 32])
+}
+
+pub fn hashlock_of(contract: &AdaptiveCrossChain, secret: &str) -> String {
+    contract.generate_hashlock(secret, HashAlgorithm::Sha256)
+}
+
+/// Parameters of a plain order to Ethereum hashed to `secret(n)`.
+pub fn order_params(contract: &AdaptiveCrossChain, n: u8, amount_in: u128) -> OrderParams {
+    OrderParams {
+        token_out: TOKEN_OUT.to_string(),
+        amount_in: U128(amount_in),
+        base_price: U128(1_000),
+        max_slippage_deviation: None,
+        target_chain_id: 1,
+        hashlock: hashlock_of(contract, &secret(n)),
+        hash_algorithm: None,
+        secret_parts: None,
+        private_economics: None,
+        settlement_callback: None,
+        bridge_route: None,
+        gas_sponsorship: None,
+        skip_reconfirmation: None,
+        auction: None,
+        timelock_duration: None,
+        allow_public_slippage_updates: None,
+        validity_duration: None,
+        memo: None,
+        taker_hashlock: None,
+        hashlock_mode: None,
+    }
+}
+
+/// Creates `maker()`'s order with `attached`. Attaching more than
+/// `params.amount_in` passes it as the exact deposit, with storage paid
+/// out of the rest.
+pub fn create_order_with(contract: &mut AdaptiveCrossChain, params: OrderParams, attached: u128) -> u64 {
+    create_order_as(contract, maker(), params, attached)
+}
+
+pub fn create_order_as(contract: &mut AdaptiveCrossChain, maker: AccountId, params: OrderParams, attached: u128) -> u64 {
+    call(maker, attached);
+    let amount_in = (attached != params.amount_in.0).then_some(params.amount_in);
+    contract.create_cross_chain_order(
+        params.token_out,
+        params.base_price,
+        params.max_slippage_deviation,
+        params.target_chain_id,
+        params.hashlock,
+        params.private_economics,
+        params.settlement_callback,
+        params.bridge_route,
+        params.gas_sponsorship,
+        params.skip_reconfirmation,
+        params.hash_algorithm,
+        params.secret_parts,
+        params.auction,
+        params.timelock_duration,
+        params.allow_public_slippage_updates,
+        params.validity_duration,
+        amount_in,
+        params.memo,
+        params.taker_hashlock,
+        params.hashlock_mode,
+    )
+}
+
+pub fn lock(contract: &mut AdaptiveCrossChain, order_id: u64) {
+    call(resolver(), 0);
+    contract.lock_order(order_id);
+}
+
+/// JSON arguments of every `method` call the current call scheduled.
+pub fn scheduled_calls(method: &str) -> Vec<Value> {
+    get_created_receipts()
+        .into_iter()
+        .flat_map(|receipt| receipt.actions)
+        .filter_map(|action| match action {
+            MockAction::FunctionCallWeight { method_name, args, .. } if method_name == method.as_bytes() => {
+                serde_json::from_slice(&args).ok()
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn eth_key(n: u8) -> secp256k1::SecretKey {
+    secp256k1::SecretKey::from_slice(&[n; 32]).unwrap()
+}
+
+/// Ethereum address of the `n`th test key, 0x-prefixed lowercase hex.
+pub fn eth_address_of(n: u8) -> String {
+    let public_key = eth_key(n).public_key(&secp256k1::Secp256k1::new()).serialize_uncompressed();
+    format!("0x{}", hex::encode(&env::keccak256(&public_key[1..])[12..]))
+}
+
+/// personal_sign signature of `message` by the `n`th test key: r ++ s ++ v.
+pub fn eth_sign(n: u8, message: &[u8]) -> String {
+    let mut preimage = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    preimage.extend_from_slice(message);
+    let digest = secp256k1::Message::from_slice(&env::keccak256(&preimage)).unwrap();
+    let (recovery_id, signature) =
+        secp256k1::Secp256k1::new().sign_ecdsa_recoverable(&digest, &eth_key(n)).serialize_compact();
+    let mut bytes = signature.to_vec();
+    bytes.push(27 + recovery_id.to_i32() as u8);
+    hex::encode(bytes)
+}
+
+/// Native transfers the current call scheduled, as (receiver, amount).
+/// Data of the last `event` the call just made emitted.
+pub fn event_data(event: &str) -> Option<Value> {
+    get_logs()
+        .iter()
+        .rev()
+        .filter_map(|log| log.strip_prefix("EVENT_JSON:"))
+        .map(|log| serde_json::from_str::<Value>(log).unwrap())
+        .find(|logged| logged["event"] == event)
+        .map(|logged| logged["data"][0].clone())
+}
+
+pub fn transfers() -> Vec<(AccountId, u128)> {
+    get_created_receipts()
+        .into_iter()
+        .flat_map(|receipt| {
+            let receiver = receipt.receiver_id;
+            receipt.actions.into_iter().filter_map(move |action| match action {
+                MockAction::Transfer { deposit, .. } => Some((receiver.clone(), deposit.as_yoctonear())),
+                _ => None,
+            })
+        })
+        .collect()
+}
+
+/// Sets the context to the contract's own callback, after a promise that
+/// succeeded or failed.
+pub fn callback(succeeded: bool) {
+    callback_with(if succeeded {
+        PromiseResult::Successful(vec![])
+    } else {
+        PromiseResult::Failed
+    });
+}
+
+/// Like `callback`, with the promise's own result, e.g. a returned value.
+pub fn callback_with(result: PromiseResult) {
+    testing_env!(
+        context("contract.near".parse().unwrap()).build(),
+        near_sdk::test_vm_config(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![result]
+    );
+}
+
+/// The resolver claims its lock with `secret(n)` and the payout transfer
+/// goes through. Returns the claim as settled.
+pub fn claim(contract: &mut AdaptiveCrossChain, order_id: u64, n: u8) -> PendingClaim {
+    call(resolver(), 0);
+    let hashlock = contract.orders.get(&order_id).unwrap().hashlock;
+    let _ = contract.claim_with_secret(hashlock, secret(n), None);
+    let args = scheduled_calls("on_claim_transfer").pop().expect("claim scheduled no callback");
+    let claim: PendingClaim = serde_json::from_value(args["claim"].clone()).unwrap();
+    callback(true);
+    assert!(contract.on_claim_transfer(order_id, claim.clone()));
+    claim
+}
+
+fn install_storage(storage: HashMap<Vec<u8>, Vec<u8>>) {
+    env::set_blockchain_interface(MockedBlockchain::new(
+        context(owner()).build(),
+        near_sdk::test_vm_config(),
+        RuntimeFeesConfig::test(),
+        vec![],
+        storage,
+        Default::default(),
+        None,
+    ));
+}
+
+/// A copy of everything in storage.
+pub fn storage() -> HashMap<Vec<u8>, Vec<u8>> {
+    let storage = with_mocked_blockchain(|blockchain| blockchain.take_storage());
+    install_storage(storage.clone());
+    storage
+}
+
+/// Runs `f` like a transaction: if it panics, the contract and its storage
+/// are put back as they were and None is returned. `f` sets its own context.
+pub fn try_call<R>(contract: &mut AdaptiveCrossChain, f: impl FnOnce(&mut AdaptiveCrossChain) -> R) -> Option<R> {
+    run_or_revert(contract, f).ok()
+}
+
+/// Like `try_call`, returning the panic message of a reverted call.
+pub fn run_or_revert<R>(
+    contract: &mut AdaptiveCrossChain,
+    f: impl FnOnce(&mut AdaptiveCrossChain) -> R,
+) -> Result<R, String> {
+    let storage = with_mocked_blockchain(|blockchain| blockchain.take_storage());
+    install_storage(storage.clone());
+    let state = borsh::to_vec(&*contract).unwrap();
+    catch_unwind(AssertUnwindSafe(|| f(contract))).map_err(|panic| {
+        *contract = borsh::from_slice(&state).unwrap();
+        install_storage(storage);
+        panic_message(panic)
+    })
+}
+
+pub fn panic_message(panic: Box<dyn std::any::Any + Send>) -> String {
+    panic
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| panic.downcast_ref::<&str>().map(|message| message.to_string()))
+        .unwrap_or_default()
+}
+
+/// Runs `f` as `caller` with `deposit` attached and asserts it panics
+/// with `code` before writing anything, to storage or to the contract.
+pub fn assert_rejected_unchanged(
+    contract: &mut AdaptiveCrossChain,
+    caller: AccountId,
+    deposit: u128,
+    code: &str,
+    f: impl FnOnce(&mut AdaptiveCrossChain),
+) {
+    let build = || context(caller.clone()).attached_deposit(NearToken::from_yoctonear(deposit)).build();
+    let install = |storage| {
+        env::set_blockchain_interface(MockedBlockchain::new(
+            build(),
+            near_sdk::test_vm_config(),
+            RuntimeFeesConfig::test(),
+            vec![],
+            storage,
+            Default::default(),
+            None,
+        ))
+    };
+    let storage = with_mocked_blockchain(|blockchain| blockchain.take_storage());
+    install(storage.clone());
+    let state = borsh::to_vec(&*contract).unwrap();
+
+    let panic = catch_unwind(AssertUnwindSafe(|| f(contract))).expect_err("call was not rejected");
+    let message = panic_message(panic);
+    assert!(message.contains(code), "expected {}, got {:?}", code, message);
+
+    assert!(borsh::to_vec(&*contract).unwrap() == state, "{} changed the contract state", code);
+    let after = with_mocked_blockchain(|blockchain| blockchain.take_storage());
+    assert!(after == storage, "{} wrote to storage", code);
+    install(storage);
+}
@@ -0,0 +1,584 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+use near_sdk::{env, near_bindgen, require, AccountId, BlockHeight};
+
+use crate::encoding::BridgeAction;
+use crate::limits;
+use crate::views;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, CrossChainOrder, EventLevel, OrderStatus};
+
+// Time math shared by the mutating methods and the calculation views
+// below. Wallets rely on the views matching exactly, so any change here
+// changes both.
+
+// Bounds on the owner-set timing parameters
+pub const MIN_TIMELOCK_DURATION_SECS: u64 = 3_600;
+pub const MAX_TIMELOCK_DURATION_SECS: u64 = 7 * 86_400;
+pub const MIN_SLIPPAGE_UPDATE_INTERVAL_NS: u64 = 60_000_000_000;
+
+// Public claim window left before the timelock unless the owner sets one
+pub const DEFAULT_PUBLIC_CLAIM_SECS: u64 = 1_800;
+
+// Most a maker can add to an order's timelock over its lifetime
+pub const MAX_TIMELOCK_EXTENSION_SECS: u64 = 3 * 86_400;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum ExpiryStage {
+    BeforeTimelock, // Lock and claim allowed
+    AfterTimelock,  // Claim window closed; funds belong back to the maker
+    Terminal,       // Completed, cancelled or expired
+}
+
+/// Which HTLC window an order is in. A lock starts with an exclusive
+/// window for its taker, then anyone holding the secret can complete the
+/// claim for the taker until the timelock; after it only refunds remain.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum OrderPhase {
+    Open,           // Active, waiting for a lock
+    ExclusiveClaim, // Only the taker can claim
+    PublicClaim,    // Anyone with the secret can claim; the taker is paid
+    Refund,         // Past the timelock; funds go back to the maker
+    Terminal,
+    Claiming,       // A token claim is waiting for its transfer
+}
+
+/// Offsets of the claim windows from the lock. The exclusive window is cut
+/// short where needed to leave `public_secs` of public claiming before the
+/// timelock. By default only the last half hour is public.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ClaimWindows {
+    pub exclusive_secs: u64,
+    pub public_secs: u64,
+}
+
+impl Default for ClaimWindows {
+    fn default() -> Self {
+        Self {
+            exclusive_secs: MAX_TIMELOCK_DURATION_SECS + MAX_TIMELOCK_EXTENSION_SECS,
+            public_secs: DEFAULT_PUBLIC_CLAIM_SECS,
+        }
+    }
+}
+
+/// Dutch auction on an order's price: decays linearly from `start_price`
+/// to the `end_price` reserve over `duration` ns from creation.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DutchAuction {
+    pub start_price: U128,
+    pub end_price: U128,
+    pub duration: U64,
+}
+
+/// What create_cross_chain_order would stamp on an order created now.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TimelockPreview {
+    pub timelock_ns: U64,
+    pub timelock_height: U64, // Block-height deadline, as legacy orders count it
+    pub created_at: U64,
+    pub next_slippage_update_at: U64,
+}
+
+pub const NS_PER_SEC: u64 = 1_000_000_000;
+
+// Timelocks are block timestamp deadlines, so they line up with the
+// seconds-based escrow on the other chain
+pub fn timelock_deadline_ns(duration_secs: u64, now_ns: u64) -> u64 {
+    now_ns + duration_secs * NS_PER_SEC
+}
+
+// Legacy timelocks count blocks, estimated at one block per second in
+// both directions
+pub const BLOCKS_PER_SEC: u64 = 1;
+
+pub fn timelock_deadline_height(duration_secs: u64, now_height: BlockHeight) -> BlockHeight {
+    now_height + duration_secs * BLOCKS_PER_SEC
+}
+
+pub fn blocks_to_secs(blocks: u64) -> u64 {
+    blocks / BLOCKS_PER_SEC
+}
+
+/// Whether the order's quote deadline has passed. Orders without one stay
+/// valid until their timelock.
+pub fn quote_expired(order: &CrossChainOrder, now_ns: u64) -> bool {
+    order.valid_until.is_some_and(|valid_until| now_ns >= valid_until.0)
+}
+
+pub fn next_slippage_update_at(last_update_ns: u64, interval_ns: u64) -> u64 {
+    last_update_ns + interval_ns
+}
+
+// Orders created before timestamp timelocks have timelock_ns 0 and expire
+// at their block height until migrate_timelocks converts them
+pub fn is_legacy_timelock(order: &CrossChainOrder) -> bool {
+    order.timelock_ns.0 == 0
+}
+
+pub fn timelock_passed(order: &CrossChainOrder, now_height: BlockHeight, now_ns: u64) -> bool {
+    if is_legacy_timelock(order) {
+        now_height >= order.timelock.0
+    } else {
+        now_ns >= order.timelock_ns.0
+    }
+}
+
+pub fn expiry_stage(order: &CrossChainOrder, now_height: BlockHeight, now_ns: u64) -> ExpiryStage {
+    match order.status {
+        // Refunds wait for the claim in flight, whatever the timelock
+        OrderStatus::Claiming => ExpiryStage::BeforeTimelock,
+        OrderStatus::Active | OrderStatus::Locked if !timelock_passed(order, now_height, now_ns) => {
+            ExpiryStage::BeforeTimelock
+        }
+        OrderStatus::Active | OrderStatus::Locked => ExpiryStage::AfterTimelock,
+        _ => ExpiryStage::Terminal,
+    }
+}
+
+pub fn current_expiry_stage(order: &CrossChainOrder) -> ExpiryStage {
+    expiry_stage(order, env::block_height(), env::block_timestamp())
+}
+
+// End of the taker's exclusive window for a lock taken at `locked_at_ns`
+pub fn exclusive_claim_deadline(windows: &ClaimWindows, locked_at_ns: u64, timelock_ns: u64) -> u64 {
+    let exclusive_end = locked_at_ns.saturating_add(windows.exclusive_secs.saturating_mul(NS_PER_SEC));
+    let public_start = timelock_ns.saturating_sub(windows.public_secs.saturating_mul(NS_PER_SEC));
+    exclusive_end.min(public_start).max(locked_at_ns)
+}
+
+// Locks taken before claim windows existed stay exclusive to the timelock
+pub fn order_phase(order: &CrossChainOrder, now_height: BlockHeight, now_ns: u64) -> OrderPhase {
+    match expiry_stage(order, now_height, now_ns) {
+        ExpiryStage::Terminal => OrderPhase::Terminal,
+        ExpiryStage::AfterTimelock => OrderPhase::Refund,
+        ExpiryStage::BeforeTimelock => match (&order.status, order.exclusive_claim_until) {
+            (OrderStatus::Locked, Some(until)) if now_ns >= until.0 => OrderPhase::PublicClaim,
+            (OrderStatus::Locked, _) => OrderPhase::ExclusiveClaim,
+            (OrderStatus::Claiming, _) => OrderPhase::Claiming,
+            _ => OrderPhase::Open,
+        },
+    }
+}
+
+pub fn current_order_phase(order: &CrossChainOrder) -> OrderPhase {
+    order_phase(order, env::block_height(), env::block_timestamp())
+}
+
+// Timelock as the seconds timestamp the other chain's escrow compares with
+pub fn timelock_secs(order: &CrossChainOrder) -> u64 {
+    order.timelock_ns.0 / NS_PER_SEC
+}
+
+/// Linear price decay from `start_price` to `end_price` over `duration_ns`
+/// starting at `started_at_ns`; clamps to the end price afterwards.
+pub fn auction_price(
+    start_price: u128,
+    end_price: u128,
+    started_at_ns: u64,
+    duration_ns: u64,
+    now_ns: u64,
+) -> u128 {
+    if now_ns <= started_at_ns {
+        return start_price;
+    }
+    let elapsed = (now_ns - started_at_ns) as u128;
+    if duration_ns == 0 || elapsed >= duration_ns as u128 {
+        return end_price;
+    }
+    if start_price >= end_price {
+        start_price - (start_price - end_price) * elapsed / duration_ns as u128
+    } else {
+        start_price + (end_price - start_price) * elapsed / duration_ns as u128
+    }
+}
+
+/// Price a resolver locking now would get: the auction price, or
+/// base_price for orders without an auction.
+pub fn current_price(order: &CrossChainOrder, now_ns: u64) -> u128 {
+    match &order.auction {
+        Some(auction) => auction_price(
+            auction.start_price.0,
+            auction.end_price.0,
+            order.created_at.0,
+            auction.duration.0,
+            now_ns,
+        ),
+        None => order.base_price.0,
+    }
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    /// Pushes the timelock of an open order back, e.g. while a bridge is
+    /// stalled. Extensions only ever give a locking resolver more time, so
+    /// the maker can extend Locked orders without the taker. The
+    /// destination escrow is told the new timelock.
+    pub fn extend_timelock(&mut self, order_id: u64, additional_secs: U64) {
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        require!(
+            env::predecessor_account_id() == order.maker,
+            "Only the maker can extend the timelock"
+        );
+        require!(additional_secs.0 > 0, "additional_secs must be positive");
+        require!(
+            current_expiry_stage(&order) == ExpiryStage::BeforeTimelock,
+            "Only open orders whose timelock has not passed can be extended"
+        );
+        let extension = order.timelock_extension.0.saturating_add(additional_secs.0);
+        require!(
+            extension <= MAX_TIMELOCK_EXTENSION_SECS,
+            format!(
+                "Timelock can be extended by at most {} seconds in total, {} already used",
+                MAX_TIMELOCK_EXTENSION_SECS, order.timelock_extension.0
+            )
+        );
+
+        self.migrate_timelock(&mut order);
+        let old_timelock_ns = order.timelock_ns;
+        self.unindex_expiry(&order);
+        order.timelock_ns = U64(order.timelock_ns.0 + additional_secs.0 * NS_PER_SEC);
+        order.timelock_extension = U64(extension);
+        self.index_expiry(&order);
+        self.save_order(&order);
+
+        self.send_bridge_message(self.build_bridge_message(&order, BridgeAction::ExtendTimelock.as_str()));
+        self.emit_order_event(
+            EventLevel::Standard,
+            "timelock_extended",
+            &order,
+            json!({
+                "old_timelock_ns": old_timelock_ns,
+                "new_timelock_ns": order.timelock_ns,
+                "timelock_extension": order.timelock_extension,
+            }),
+        );
+    }
+
+    /// Converts open orders among ids [from_order_id, from_order_id + limit)
+    /// that still expire at a block height to a timestamp deadline. Returns
+    /// how many were converted.
+    pub fn migrate_timelocks(&mut self, from_order_id: u64, limit: u64) -> u64 {
+        self.assert_owner();
+        let end = from_order_id
+            .saturating_add(limit.min(limits::MAX_BATCH_SIZE as u64))
+            .min(self.next_order_id);
+        let mut migrated = 0;
+        for order_id in from_order_id..end {
+            let mut order = match self.orders.get(&order_id) {
+                Some(order) => order,
+                None => continue,
+            };
+            if !is_legacy_timelock(&order) || current_expiry_stage(&order) == ExpiryStage::Terminal {
+                continue;
+            }
+            self.migrate_timelock(&mut order);
+            self.save_order(&order);
+            migrated += 1;
+        }
+        if migrated > 0 {
+            self.audit("migrate_timelocks", None);
+        }
+        migrated
+    }
+
+    pub fn compute_timelock(&self, duration_secs: U64, now_height: U64, now_ts: U64) -> TimelockPreview {
+        TimelockPreview {
+            timelock_ns: U64(timelock_deadline_ns(duration_secs.0, now_ts.0)),
+            timelock_height: U64(timelock_deadline_height(duration_secs.0, now_height.0)),
+            created_at: now_ts,
+            next_slippage_update_at: U64(next_slippage_update_at(
+                now_ts.0,
+                self.slippage_update_interval.0,
+            )),
+        }
+    }
+
+    pub fn compute_auction_price(
+        &self,
+        start: U128,
+        end: U128,
+        created_at: U64,
+        duration: U64,
+        now: U64,
+    ) -> U128 {
+        U128(auction_price(start.0, end.0, created_at.0, duration.0, now.0))
+    }
+
+    // None for unknown orders and while private economics are hidden
+    pub fn get_current_price(&self, order_id: u64) -> Option<U128> {
+        let order = self.orders.get(&order_id)?;
+        if views::economics_hidden(&order) {
+            return None;
+        }
+        Some(U128(current_price(&order, env::block_timestamp())))
+    }
+
+    pub fn get_order_phase(&self, order_id: u64) -> Option<OrderPhase> {
+        self.orders.get(&order_id).map(|order| current_order_phase(&order))
+    }
+
+    // Applies to locks taken afterwards
+    pub fn set_claim_windows(&mut self, windows: ClaimWindows) {
+        self.assert_owner();
+        require!(windows.exclusive_secs > 0, "exclusive_secs must be positive");
+        require!(
+            windows.public_secs <= MAX_TIMELOCK_DURATION_SECS,
+            "public_secs cannot exceed the maximum timelock duration"
+        );
+        env::log_str(&format!(
+            "Claim windows set: exclusive {}s, public {}s before the timelock",
+            windows.exclusive_secs, windows.public_secs
+        ));
+        self.claim_windows = windows;
+        self.audit("set_claim_windows", None);
+    }
+
+    pub fn get_claim_windows(&self) -> ClaimWindows {
+        self.claim_windows.clone()
+    }
+
+    pub fn compute_expiry_stage(&self, order_id: u64) -> Option<ExpiryStage> {
+        self.orders
+            .get(&order_id)
+            .map(|order| current_expiry_stage(&order))
+    }
+
+    pub fn compute_next_slippage_update_at(&self, order_id: u64) -> Option<U64> {
+        self.orders.get(&order_id).map(|order| {
+            U64(next_slippage_update_at(
+                order.last_slippage_update.0,
+                self.slippage_update_interval.0,
+            ))
+        })
+    }
+}
+
+impl AdaptiveCrossChain {
+    /// Checks `claimant` may claim the order now and returns who is paid:
+    /// always the taker, whoever submits the claim in the public window.
+    pub(crate) fn authorize_claim(&self, order: &CrossChainOrder, claimant: &AccountId) -> AccountId {
+        let taker = order.taker.clone().expect("Locked order without a taker");
+        match current_order_phase(order) {
+            OrderPhase::ExclusiveClaim => require!(
+                *claimant == taker,
+                format!(
+                    "Only the resolver holding the lock can claim during the exclusive window, which ends at {}",
+                    order.exclusive_claim_until.map(|until| until.0).unwrap_or(order.timelock_ns.0)
+                )
+            ),
+            OrderPhase::PublicClaim => {}
+            OrderPhase::Open => env::panic_str("Order not in locked state"),
+            OrderPhase::Claiming => env::panic_str("A claim of this order is already in flight"),
+            OrderPhase::Refund | OrderPhase::Terminal => env::panic_str("Order expired"),
+        }
+        taker
+    }
+
+    // Estimates the timestamp of a legacy order's block-height timelock at
+    // BLOCKS_PER_SEC, and moves its expiry index entry along
+    pub(crate) fn migrate_timelock(&mut self, order: &mut CrossChainOrder) {
+        if !is_legacy_timelock(order) {
+            return;
+        }
+        let remaining_secs = blocks_to_secs(order.timelock.0.saturating_sub(env::block_height()));
+        self.unindex_expiry(order);
+        order.timelock_ns = U64(timelock_deadline_ns(remaining_secs, env::block_timestamp()));
+        self.index_expiry(order);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::json_types::{U128, U64};
+
+    use near_sdk::serde_json;
+    use near_sdk::test_utils::accounts;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    const NOW: u64 = 1_700_000_000 * NS_PER_SEC;
+
+    fn at(timestamp: u64, height: u64) {
+        set_clock(timestamp, height);
+        call(maker(), 0);
+    }
+
+    #[test]
+    fn compute_timelock_golden() {
+        let contract = setup();
+        let preview = contract.compute_timelock(U64(86_400), U64(120_000_000), U64(NOW));
+        assert_eq!(preview.timelock_ns.0, 1_700_086_400_000_000_000);
+        assert_eq!(preview.timelock_height.0, 120_086_400);
+        assert_eq!(preview.created_at.0, NOW);
+        assert_eq!(preview.next_slippage_update_at.0, 1_700_000_300_000_000_000);
+        assert_eq!(timelock_deadline_ns(3_600, 0), 3_600_000_000_000);
+    }
+
+    #[test]
+    fn compute_auction_price_golden() {
+        let contract = setup();
+        let price = |now| contract.compute_auction_price(U128(1_000), U128(400), U64(100), U64(600), U64(now)).0;
+        assert_eq!(price(0), 1_000);
+        assert_eq!(price(100), 1_000);
+        assert_eq!(price(101), 999);
+        assert_eq!(price(250), 850);
+        assert_eq!(price(400), 700);
+        assert_eq!(price(699), 401);
+        assert_eq!(price(700), 400);
+        assert_eq!(price(10_000), 400);
+        // Rising curves and zero durations
+        assert_eq!(auction_price(400, 1_000, 100, 600, 400), 700);
+        assert_eq!(auction_price(1_000, 400, 100, 0, 101), 400);
+    }
+
+    #[test]
+    fn exclusive_claim_deadline_golden() {
+        let windows = ClaimWindows { exclusive_secs: 600, public_secs: 1_800 };
+        let timelock = NOW + 3_600 * NS_PER_SEC;
+        // Full exclusive window when it ends before the public one starts
+        assert_eq!(exclusive_claim_deadline(&windows, NOW, timelock), NOW + 600 * NS_PER_SEC);
+        // Cut short to leave the public window
+        let late = NOW + 1_500 * NS_PER_SEC;
+        assert_eq!(exclusive_claim_deadline(&windows, late, timelock), NOW + 1_800 * NS_PER_SEC);
+        // Never before the lock itself
+        let later = NOW + 3_000 * NS_PER_SEC;
+        assert_eq!(exclusive_claim_deadline(&windows, later, timelock), later);
+    }
+
+    // Order 1 locked by resolver() at NOW, with its exclusive window's end
+    // and its timelock
+    fn locked_at_now() -> (AdaptiveCrossChain, u64, u64) {
+        let mut contract = setup();
+        at(NOW, 100);
+        let params = order_params(&contract, 1, 10 * ONE_NEAR);
+        let order_id = create_order_with(&mut contract, params, 10 * ONE_NEAR);
+        lock(&mut contract, order_id);
+        let order = contract.orders.get(&order_id).unwrap();
+        (contract, order.exclusive_claim_until.unwrap().0, order.timelock_ns.0)
+    }
+
+    fn claim_as(claimant: AccountId, now: u64) -> Result<AccountId, String> {
+        let (mut contract, _, _) = locked_at_now();
+        let hashlock = contract.orders.get(&1).unwrap().hashlock;
+        run_or_revert(&mut contract, |c| {
+            at(now, 100);
+            call(claimant, 0);
+            let _ = c.claim_with_secret(hashlock, secret(1), None);
+            let args = scheduled_calls("on_claim_transfer").pop().unwrap();
+            serde_json::from_value::<AccountId>(args["claim"]["payee"].clone()).unwrap()
+        })
+    }
+
+    fn refund_at(now: u64) -> Result<(), String> {
+        let (mut contract, _, _) = locked_at_now();
+        run_or_revert(&mut contract, |c| {
+            at(now, 100);
+            let _ = c.refund_expired_order(1);
+        })
+    }
+
+    fn outsider() -> AccountId {
+        accounts(3)
+    }
+
+    #[test]
+    fn non_takers_claim_for_the_taker_once_the_exclusive_window_ends() {
+        let (_, exclusive_end, _) = locked_at_now();
+        let rejected = claim_as(outsider(), exclusive_end - 1).unwrap_err();
+        assert!(rejected.contains("Only the resolver holding the lock can claim"), "{}", rejected);
+        assert_eq!(claim_as(outsider(), exclusive_end).unwrap(), resolver());
+    }
+
+    #[test]
+    fn claim_and_refund_windows_meet_at_their_deadlines() {
+        let (_, exclusive_end, timelock_ns) = locked_at_now();
+        assert!(exclusive_end > NOW && exclusive_end < timelock_ns);
+
+        assert_eq!(claim_as(resolver(), exclusive_end - 1).unwrap(), resolver());
+        assert!(claim_as(outsider(), exclusive_end - 1).is_err());
+        assert!(refund_at(exclusive_end - 1).unwrap_err().contains("Order is not expired"));
+
+        assert_eq!(claim_as(outsider(), exclusive_end).unwrap(), resolver());
+        assert!(refund_at(exclusive_end).unwrap_err().contains("Order is not expired"));
+
+        assert_eq!(claim_as(outsider(), timelock_ns - 1).unwrap(), resolver());
+        assert!(refund_at(timelock_ns - 1).unwrap_err().contains("Order is not expired"));
+
+        for claimant in [resolver(), outsider()] {
+            assert!(claim_as(claimant, timelock_ns).unwrap_err().contains("Order expired"));
+        }
+        refund_at(timelock_ns).unwrap();
+    }
+
+    #[test]
+    fn compute_expiry_stage_golden() {
+        let mut contract = setup();
+        at(NOW, 100);
+        let params = order_params(&contract, 1, 10 * ONE_NEAR);
+        let order_id = create_order_with(&mut contract, params, 10 * ONE_NEAR);
+        let timelock_ns = contract.orders.get(&order_id).unwrap().timelock_ns.0;
+        assert_eq!(timelock_ns, NOW + 86_400 * NS_PER_SEC);
+
+        at(timelock_ns - 1, 100);
+        assert!(contract.compute_expiry_stage(order_id) == Some(ExpiryStage::BeforeTimelock));
+        at(timelock_ns, 100);
+        assert!(contract.compute_expiry_stage(order_id) == Some(ExpiryStage::AfterTimelock));
+        assert!(contract.compute_expiry_stage(order_id + 1).is_none());
+    }
+
+    #[test]
+    fn legacy_timelocks_expire_at_their_block_height() {
+        let mut contract = setup();
+        at(NOW, 100);
+        let params = order_params(&contract, 1, 10 * ONE_NEAR);
+        let order_id = create_order_with(&mut contract, params, 10 * ONE_NEAR);
+        let mut order = contract.orders.get(&order_id).unwrap();
+        order.timelock = U64(200);
+        order.timelock_ns = U64(0);
+
+        assert!(expiry_stage(&order, 199, u64::MAX) == ExpiryStage::BeforeTimelock);
+        assert!(expiry_stage(&order, 200, 0) == ExpiryStage::AfterTimelock);
+        order.status = OrderStatus::Claiming;
+        assert!(expiry_stage(&order, 200, 0) == ExpiryStage::BeforeTimelock);
+        order.status = OrderStatus::Cancelled;
+        assert!(expiry_stage(&order, 0, 0) == ExpiryStage::Terminal);
+    }
+
+    // The height compute_timelock returns converts back to its timestamp
+    #[test]
+    fn migrated_height_previews_match_their_timestamp() {
+        let mut contract = setup();
+        at(NOW, 100);
+        let preview = contract.compute_timelock(U64(86_400), U64(100), U64(NOW));
+        let params = order_params(&contract, 1, 10 * ONE_NEAR);
+        let order_id = create_order_with(&mut contract, params, 10 * ONE_NEAR);
+        let mut order = contract.orders.get(&order_id).unwrap();
+        order.timelock = preview.timelock_height;
+        order.timelock_ns = U64(0);
+        contract.save_order(&order);
+
+        call(owner(), 0);
+        assert_eq!(contract.migrate_timelocks(order_id, 1), 1);
+        assert_eq!(contract.orders.get(&order_id).unwrap().timelock_ns, preview.timelock_ns);
+    }
+
+    #[test]
+    fn compute_next_slippage_update_at_golden() {
+        let mut contract = setup();
+        at(NOW, 100);
+        let params = order_params(&contract, 1, 10 * ONE_NEAR);
+        let order_id = create_order_with(&mut contract, params, 10 * ONE_NEAR);
+        assert_eq!(contract.compute_next_slippage_update_at(order_id), Some(U64(NOW + 300 * NS_PER_SEC)));
+
+        call(owner(), 0);
+        contract.set_slippage_update_interval(U64(MIN_SLIPPAGE_UPDATE_INTERVAL_NS));
+        assert_eq!(contract.compute_next_slippage_update_at(order_id), Some(U64(NOW + 60 * NS_PER_SEC)));
+    }
+}
@@ -0,0 +1,208 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, require};
+
+use crate::limits;
+use crate::normalize;
+use crate::shadow::EthAddress;
+use crate::validation::Violation;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, OrderStatus, OrderView};
+
+pub const ERR_TOKEN_OUT_DENIED: &str = "ERR_TOKEN_OUT_DENIED";
+pub const ERR_TOKEN_OUT_NOT_ALLOWLISTED: &str = "ERR_TOKEN_OUT_NOT_ALLOWLISTED";
+pub const ERR_TOKEN_OUT_UNSUPPORTED: &str = "ERR_TOKEN_OUT_UNSUPPORTED";
+
+// Per-chain whitelists are returned whole by get_supported_tokens
+pub const MAX_SUPPORTED_TOKENS_PER_CHAIN: usize = 100;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum TokenOutPolicy {
+    Open,      // Anything not denylisted
+    Allowlist, // Only allowlisted tokens, and never denylisted ones
+}
+
+// Policy is checked when an order is created. Existing orders keep their
+// terms and refund paths whatever the lists say later.
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    pub fn set_token_out_policy(&mut self, policy: TokenOutPolicy) {
+        self.assert_owner();
+        self.token_out_policy = policy;
+        env::log_str(&format!("token_out policy set to {}", policy_name(policy)));
+    }
+
+    pub fn deny_token_out(&mut self, token: String) {
+        self.assert_owner();
+        let address = normalize::eth_address_bytes(&token).unwrap_or_else(|v| v.panic());
+        if self.token_out_denylist.insert(&address) {
+            env::log_str(&format!("token_out 0x{} denylisted", hex::encode(address)));
+        }
+    }
+
+    pub fn undeny_token_out(&mut self, token: String) {
+        self.assert_owner();
+        let address = normalize::eth_address_bytes(&token).unwrap_or_else(|v| v.panic());
+        if self.token_out_denylist.remove(&address) {
+            env::log_str(&format!("token_out 0x{} removed from denylist", hex::encode(address)));
+        }
+    }
+
+    pub fn allow_token_out(&mut self, token: String) {
+        self.assert_owner();
+        let address = normalize::eth_address_bytes(&token).unwrap_or_else(|v| v.panic());
+        if self.token_out_allowlist.insert(&address) {
+            env::log_str(&format!("token_out 0x{} allowlisted", hex::encode(address)));
+        }
+    }
+
+    pub fn disallow_token_out(&mut self, token: String) {
+        self.assert_owner();
+        let address = normalize::eth_address_bytes(&token).unwrap_or_else(|v| v.panic());
+        if self.token_out_allowlist.remove(&address) {
+            env::log_str(&format!("token_out 0x{} removed from allowlist", hex::encode(address)));
+        }
+    }
+
+    // Per-chain whitelist, enforced only while require_token_whitelist is on
+    pub fn add_supported_token(&mut self, chain_id: u64, token: String) {
+        self.assert_owner();
+        let address = normalize::eth_address_bytes(&token).unwrap_or_else(|v| v.panic());
+        let mut tokens = self.supported_tokens.get(&chain_id).unwrap_or_default();
+        if tokens.contains(&address) {
+            return;
+        }
+        require!(
+            tokens.len() < MAX_SUPPORTED_TOKENS_PER_CHAIN,
+            format!("Chain {} already has {} supported tokens", chain_id, MAX_SUPPORTED_TOKENS_PER_CHAIN)
+        );
+        tokens.push(address);
+        self.supported_tokens.insert(&chain_id, &tokens);
+        env::log_str(&format!("token_out 0x{} supported on chain {}", hex::encode(address), chain_id));
+    }
+
+    pub fn remove_supported_token(&mut self, chain_id: u64, token: String) {
+        self.assert_owner();
+        let address = normalize::eth_address_bytes(&token).unwrap_or_else(|v| v.panic());
+        let mut tokens = self.supported_tokens.get(&chain_id).unwrap_or_default();
+        let before = tokens.len();
+        tokens.retain(|entry| *entry != address);
+        if tokens.len() == before {
+            return;
+        }
+        if tokens.is_empty() {
+            self.supported_tokens.remove(&chain_id);
+        } else {
+            self.supported_tokens.insert(&chain_id, &tokens);
+        }
+        env::log_str(&format!("token_out 0x{} no longer supported on chain {}", hex::encode(address), chain_id));
+    }
+
+    pub fn set_require_token_whitelist(&mut self, enabled: bool) {
+        self.assert_owner();
+        self.require_token_whitelist = enabled;
+        env::log_str(&format!("require_token_whitelist set to {}", enabled));
+    }
+
+    pub fn get_supported_tokens(&self, chain_id: u64) -> Vec<String> {
+        self.supported_tokens
+            .get(&chain_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|address| format!("0x{}", hex::encode(address)))
+            .collect()
+    }
+
+    pub fn is_token_out_allowed(&self, token: String) -> bool {
+        self.token_out_violation(&token).is_none()
+    }
+
+    pub fn get_token_out_denylist(&self, from_index: u64, limit: u64) -> Vec<String> {
+        list_page(self.token_out_denylist.iter(), from_index, limit)
+    }
+
+    pub fn get_token_out_allowlist(&self, from_index: u64, limit: u64) -> Vec<String> {
+        list_page(self.token_out_allowlist.iter(), from_index, limit)
+    }
+
+    /// Open orders in ids [from_order_id, from_order_id + limit) whose
+    /// token_out is now denylisted, so their makers can be contacted.
+    pub fn get_open_orders_with_denied_token_out(&self, from_order_id: u64, limit: u64) -> Vec<OrderView> {
+        let end = from_order_id
+            .saturating_add(limit.min(limits::MAX_BATCH_SIZE as u64))
+            .min(self.next_order_id);
+        (from_order_id..end)
+            .filter_map(|order_id| self.orders.get(&order_id))
+            .filter(|order| matches!(order.status, OrderStatus::Active | OrderStatus::Locked | OrderStatus::Claiming))
+            .filter(|order| {
+                normalize::eth_address_bytes(&order.token_out)
+                    .map(|address| self.token_out_denylist.contains(&address))
+                    .unwrap_or(false)
+            })
+            .map(|order| self.public_order_view(order))
+            .collect()
+    }
+}
+
+impl AdaptiveCrossChain {
+    pub(crate) fn token_out_violation(&self, token: &str) -> Option<Violation> {
+        // Malformed addresses are reported by the token_out format check
+        let address = normalize::eth_address_bytes(token).ok()?;
+        if self.token_out_denylist.contains(&address) {
+            return Some(Violation::new(
+                ERR_TOKEN_OUT_DENIED,
+                format!("token_out 0x{} is on the denylist", hex::encode(address)),
+            ));
+        }
+        if self.token_out_policy == TokenOutPolicy::Allowlist
+            && !self.token_out_allowlist.contains(&address)
+        {
+            return Some(Violation::new(
+                ERR_TOKEN_OUT_NOT_ALLOWLISTED,
+                format!(
+                    "token_out 0x{} is not on the allowlist required by the Allowlist policy",
+                    hex::encode(address)
+                ),
+            ));
+        }
+        None
+    }
+
+    pub(crate) fn supported_token_violation(&self, chain_id: u64, token: &str) -> Option<Violation> {
+        if !self.require_token_whitelist {
+            return None;
+        }
+        let address = normalize::eth_address_bytes(token).ok()?;
+        let supported = self
+            .supported_tokens
+            .get(&chain_id)
+            .map(|tokens| tokens.contains(&address))
+            .unwrap_or(false);
+        if supported {
+            return None;
+        }
+        Some(Violation::new(
+            ERR_TOKEN_OUT_UNSUPPORTED,
+            format!(
+                "token_out 0x{} is not a supported token on chain {}; see get_supported_tokens",
+                hex::encode(address),
+                chain_id
+            ),
+        ))
+    }
+}
+
+fn list_page(entries: impl Iterator<Item = EthAddress>, from_index: u64, limit: u64) -> Vec<String> {
+    entries
+        .skip(from_index as usize)
+        .take(limit.min(limits::MAX_BATCH_SIZE as u64) as usize)
+        .map(|address| format!("0x{}", hex::encode(address)))
+        .collect()
+}
+
+fn policy_name(policy: TokenOutPolicy) -> &'static str {
+    match policy {
+        TokenOutPolicy::Open => "Open",
+        TokenOutPolicy::Allowlist => "Allowlist",
+    }
+}
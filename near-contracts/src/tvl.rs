@@ -0,0 +1,69 @@
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen};
+
+use crate::ft;
+use crate::validation::Violation;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, Balance, CrossChainOrder};
+
+pub const ERR_TVL_CAP: &str = "ERR_TVL_CAP";
+
+// Value locked is amount_remaining summed over Active and Locked native
+// orders. It is kept up to date by creation, claims and the status
+// transition helper, never recomputed from the order book. FT orders are
+// in other units and are not counted.
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    // None lifts the cap. Orders already open are not affected.
+    pub fn set_tvl_cap(&mut self, cap: Option<U128>) {
+        self.assert_owner();
+        env::log_str(&format!(
+            "max_total_value_locked set from {:?} to {:?}",
+            self.max_total_value_locked,
+            cap.map(|cap| cap.0)
+        ));
+        self.max_total_value_locked = cap.map(|cap| cap.0);
+        self.audit("set_tvl_cap", None);
+    }
+
+    pub fn get_total_value_locked(&self) -> U128 {
+        U128(self.total_value_locked)
+    }
+
+    pub fn get_tvl_cap(&self) -> Option<U128> {
+        self.max_total_value_locked.map(U128)
+    }
+}
+
+impl AdaptiveCrossChain {
+    /// Adds a new order's escrow to the value locked, failing with the
+    /// remaining headroom if that would exceed the cap. Every creation
+    /// path calls this once the escrow is final.
+    pub(crate) fn reserve_value_locked(&mut self, order: &CrossChainOrder) {
+        if !ft::is_native(order.token_in.as_str()) {
+            return;
+        }
+        let amount = order.amount_remaining.0;
+        if let Some(cap) = self.max_total_value_locked {
+            if self.total_value_locked + amount > cap {
+                Violation::new(
+                    ERR_TVL_CAP,
+                    format!(
+                        "a deposit of {} would exceed the value locked cap; available headroom is {}",
+                        amount,
+                        cap.saturating_sub(self.total_value_locked)
+                    ),
+                )
+                .panic();
+            }
+        }
+        self.total_value_locked += amount;
+    }
+
+    // A claim paid out `amount`, or the order left the open book with
+    // `amount` still escrowed
+    pub(crate) fn release_value_locked(&mut self, order: &CrossChainOrder, amount: Balance) {
+        if ft::is_native(order.token_in.as_str()) {
+            self.total_value_locked = self.total_value_locked.saturating_sub(amount);
+        }
+    }
+}
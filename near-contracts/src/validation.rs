@@ -0,0 +1,539 @@
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId};
+
+use crate::ft;
+use crate::normalize;
+use crate::limits::{self, check_len};
+use crate::storage::ERR_STORAGE_BUDGET;
+use crate::{
+    timing, AdaptiveCrossChain, AdaptiveCrossChainExt, DutchAuction, FeePreview, HashAlgorithm, HashlockMode, SettlementCallback, SlippageInputs,
+};
+
+pub const ERR_NO_DEPOSIT: &str = "ERR_NO_DEPOSIT";
+pub const ERR_INVALID_TOKEN_OUT: &str = "ERR_INVALID_TOKEN_OUT";
+pub const ERR_CREATE_RATE_LIMIT: &str = "ERR_CREATE_RATE_LIMIT";
+pub const ERR_OPEN_ORDER_LIMIT: &str = "ERR_OPEN_ORDER_LIMIT";
+pub const ERR_INVALID_CALLBACK: &str = "ERR_INVALID_CALLBACK";
+pub const ERR_INVALID_BRIDGE_ROUTE: &str = "ERR_INVALID_BRIDGE_ROUTE";
+pub const ERR_ROUTE_TOO_SLOW: &str = "ERR_ROUTE_TOO_SLOW";
+pub const ERR_INVALID_HASHLOCK: &str = "ERR_INVALID_HASHLOCK";
+pub const ERR_FT_ROUTE_FEE: &str = "ERR_FT_ROUTE_FEE";
+pub const ERR_DUPLICATE_HASHLOCK: &str = "ERR_DUPLICATE_HASHLOCK";
+pub const ERR_INVALID_SECRET_PARTS: &str = "ERR_INVALID_SECRET_PARTS";
+pub const ERR_INVALID_AUCTION: &str = "ERR_INVALID_AUCTION";
+pub const ERR_WEAK_SECRET: &str = "ERR_WEAK_SECRET";
+pub const ERR_INVALID_TIMELOCK: &str = "ERR_INVALID_TIMELOCK";
+pub const ERR_INVALID_VALIDITY: &str = "ERR_INVALID_VALIDITY";
+
+// Secrets are 32 random bytes, hex-encoded
+pub const SECRET_HEX_LEN: usize = 64;
+
+/// Parameters of a new order, shared by the create methods and the
+/// pre-flight validation view.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OrderParams {
+    pub token_out: String,
+    pub amount_in: U128, // Attached deposit for native orders
+    pub base_price: U128,
+    pub max_slippage_deviation: Option<u64>, // Defaults per pair class
+    pub target_chain_id: u64,
+    pub hashlock: String, // Hex hash of the maker's secret, optional 0x
+    pub hash_algorithm: Option<HashAlgorithm>, // Defaults to Sha256
+    pub secret_parts: Option<u32>, // hashlock is then the Merkle root of that many secrets
+    pub private_economics: Option<bool>,
+    pub settlement_callback: Option<SettlementCallback>,
+    pub bridge_route: Option<u8>, // Defaults per target chain
+    pub gas_sponsorship: Option<U128>, // Pays relayers of claim_with_secret_relayed
+    pub skip_reconfirmation: Option<bool>,
+    pub auction: Option<DutchAuction>, // Price decays from creation instead of staying at base_price
+    pub timelock_duration: Option<U64>, // In seconds, within the owner's bounds; defaults to default_timelock_duration
+    pub allow_public_slippage_updates: Option<bool>, // Default: only the maker and keepers update slippage
+    pub validity_duration: Option<U64>, // In seconds, shorter than the timelock; default: valid until the timelock
+    pub memo: Option<String>, // Up to MAX_MEMO_LEN bytes, stored as is
+    pub taker_hashlock: Option<String>, // Second hashlock, for symmetric swaps
+    pub hashlock_mode: Option<HashlockMode>, // Both or Either with taker_hashlock, default Both
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Violation {
+    pub code: String,
+    pub message: String,
+}
+
+impl Violation {
+    pub fn new(code: &str, message: impl Into<String>) -> Self {
+        Self {
+            code: code.to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub fn panic(&self) -> ! {
+        env::panic_str(&format!("{}: {}", self.code, self.message))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub violations: Vec<Violation>,
+    pub initial_slippage: u64,
+    pub timelock_ns: U64,
+    pub fees: FeePreview,
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    /// Runs every check create_cross_chain_order performs, without
+    /// creating anything, and returns the values it would derive.
+    pub fn validate_order_params(&self, params: OrderParams, maker: AccountId) -> ValidationReport {
+        let violations = self.order_param_violations(&params, &maker, ft::NATIVE_TOKEN);
+        let inputs = SlippageInputs {
+            amount: params.amount_in.0,
+            target_chain_id: params.target_chain_id,
+            chain_premium_bps: self.chain_premium_bps(params.target_chain_id),
+            volatility_score: self.calculate_volatility_score(&params.token_out),
+            bridge_delay_secs: self
+                .resolve_bridge_route(params.bridge_route, params.target_chain_id)
+                .map(|(route_id, _)| self.estimate_bridge_delay(route_id, params.target_chain_id))
+                .unwrap_or_else(|_| self.chain_delay_secs(params.target_chain_id)),
+        };
+        let initial_slippage = self.calculate_cross_chain_slippage(ft::NATIVE_TOKEN, &params.token_out, &inputs);
+        let timelock_ns = timing::timelock_deadline_ns(self.timelock_duration_for(&params), env::block_timestamp());
+        let fees = self.preview_fees(
+            maker,
+            params.amount_in,
+            params.target_chain_id,
+            None,
+            params.bridge_route,
+            Some(params.token_out.clone()),
+        );
+
+        ValidationReport {
+            valid: violations.is_empty(),
+            violations,
+            initial_slippage,
+            timelock_ns: U64(timelock_ns),
+            fees,
+        }
+    }
+
+    // Lets frontends check a generated secret before hashing it
+    pub fn validate_secret_format(&self, secret: String) -> bool {
+        secret_violation(&secret).is_none()
+    }
+}
+
+impl AdaptiveCrossChain {
+    pub(crate) fn order_param_violations(
+        &self,
+        params: &OrderParams,
+        maker: &AccountId,
+        token_in: &str,
+    ) -> Vec<Violation> {
+        let mut violations: Vec<Violation> = [
+            check_len(params.token_out.len(), limits::MAX_TOKEN_OUT_LEN, limits::ERR_TOKEN_OUT_TOO_LONG),
+            check_len(params.hashlock.len(), limits::MAX_HASHLOCK_LEN, limits::ERR_HASHLOCK_TOO_LONG),
+            check_len(
+                params.memo.as_ref().map_or(0, |memo| memo.len()),
+                limits::MAX_MEMO_LEN,
+                limits::ERR_MEMO_TOO_LONG,
+            ),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        // Oversized fields are not used as storage keys by the checks below
+        if !violations.is_empty() {
+            return violations;
+        }
+        violations.extend(self.hashlock_violation(&params.hashlock));
+        violations.extend(self.taker_hashlock_violation(params));
+        violations.extend(secret_parts_violation(params));
+        violations.extend(auction_violation(params));
+        violations.extend(self.timelock_violation(params));
+        violations.extend(self.validity_violation(params));
+
+        // The storage-budget minimum is denominated in NEAR
+        if params.amount_in.0 == 0 {
+            violations.push(Violation::new(ERR_NO_DEPOSIT, "Must attach NEAR tokens"));
+        } else if ft::is_native(token_in) && params.amount_in.0 < self.min_order_amount() {
+            violations.push(Violation::new(
+                ERR_STORAGE_BUDGET,
+                format!(
+                    "order storage is over budget; minimum order is {}",
+                    self.min_order_amount()
+                ),
+            ));
+        }
+        violations.extend(self.order_size_violation(token_in, params.amount_in));
+        // Every registered chain is an EVM chain; the escrow there rejects
+        // anything but a full 0x address
+        if !params.token_out.trim().starts_with("0x") {
+            violations.push(Violation::new(ERR_INVALID_TOKEN_OUT, "token_out must start with 0x"));
+        } else if let Err(violation) = normalize::eth_address(&params.token_out) {
+            violations.push(Violation::new(
+                ERR_INVALID_TOKEN_OUT,
+                format!("token_out must be a 20-byte hex address: {}", violation.message),
+            ));
+        }
+        violations.extend(self.token_out_violation(&params.token_out));
+        violations.extend(self.supported_token_violation(params.target_chain_id, &params.token_out));
+        if let Some(callback) = &params.settlement_callback {
+            violations.extend(self.callback_violation(callback));
+        }
+        violations.extend(self.chain_violation(params.target_chain_id));
+        match self.resolve_bridge_route(params.bridge_route, params.target_chain_id) {
+            Ok((_, route)) => {
+                violations.extend(self.route_delay_violation(&route, self.timelock_duration_for(params)));
+                if !ft::is_native(token_in) && route.fee.0 > 0 {
+                    violations.push(Violation::new(
+                        ERR_FT_ROUTE_FEE,
+                        "route charges a NEAR fee, which token orders cannot pay",
+                    ));
+                }
+            }
+            Err(violation) => violations.push(violation),
+        }
+        violations.extend(self.peg_violation(token_in, &params.token_out));
+        violations.extend(self.creation_rate_violation(maker, 1));
+        violations.extend(self.open_order_violation(maker));
+
+        violations
+    }
+
+    // 32 bytes of hex, not yet used by another order
+    fn hashlock_violation(&self, hashlock: &str) -> Option<Violation> {
+        let hashlock = normalize::hex_id(hashlock);
+        if hashlock.len() != 64 || hex::decode(&hashlock).is_err() {
+            return Some(Violation::new(ERR_INVALID_HASHLOCK, "hashlock must be 64 hex characters"));
+        }
+        if self.hashlock_to_order.get(&hashlock).is_some() {
+            return Some(Violation::new(ERR_DUPLICATE_HASHLOCK, "hashlock is already registered"));
+        }
+        None
+    }
+
+    // The taker hashlock follows the same rules and must differ from the
+    // maker's; Merkle orders keep a single hashlock
+    fn taker_hashlock_violation(&self, params: &OrderParams) -> Option<Violation> {
+        let taker_hashlock = match &params.taker_hashlock {
+            Some(taker_hashlock) => taker_hashlock,
+            None if matches!(params.hashlock_mode, None | Some(HashlockMode::Single)) => return None,
+            None => {
+                return Some(Violation::new(
+                    ERR_INVALID_HASHLOCK,
+                    "hashlock_mode Both and Either need a taker_hashlock",
+                ))
+            }
+        };
+        if let Some(violation) = check_len(taker_hashlock.len(), limits::MAX_HASHLOCK_LEN, limits::ERR_HASHLOCK_TOO_LONG) {
+            return Some(violation);
+        }
+        if params.hashlock_mode == Some(HashlockMode::Single) {
+            return Some(Violation::new(ERR_INVALID_HASHLOCK, "hashlock_mode Single takes no taker_hashlock"));
+        }
+        if params.secret_parts.is_some() {
+            return Some(Violation::new(ERR_INVALID_HASHLOCK, "Merkle orders take no taker_hashlock"));
+        }
+        if normalize::hex_id(taker_hashlock) == normalize::hex_id(&params.hashlock) {
+            return Some(Violation::new(ERR_DUPLICATE_HASHLOCK, "taker_hashlock must differ from hashlock"));
+        }
+        self.hashlock_violation(taker_hashlock)
+    }
+
+    fn timelock_violation(&self, params: &OrderParams) -> Option<Violation> {
+        let duration = params.timelock_duration?.0;
+        if duration < self.min_timelock_duration.0 || duration > self.max_timelock_duration.0 {
+            return Some(Violation::new(
+                ERR_INVALID_TIMELOCK,
+                format!(
+                    "timelock_duration must be between {} and {} seconds",
+                    self.min_timelock_duration.0, self.max_timelock_duration.0
+                ),
+            ));
+        }
+        None
+    }
+
+    // A quote that outlives the timelock would never end early
+    fn validity_violation(&self, params: &OrderParams) -> Option<Violation> {
+        let validity = params.validity_duration?.0;
+        let timelock = self.timelock_duration_for(params);
+        if validity == 0 || validity >= timelock {
+            return Some(Violation::new(
+                ERR_INVALID_VALIDITY,
+                format!("validity_duration must be between 1 and {} seconds", timelock - 1),
+            ));
+        }
+        None
+    }
+
+    pub(crate) fn timelock_duration_for(&self, params: &OrderParams) -> u64 {
+        params
+            .timelock_duration
+            .map(|duration| duration.0)
+            .unwrap_or(self.default_timelock_duration.0)
+    }
+
+    pub(crate) fn assert_valid_order_params(&self, params: &OrderParams, maker: &AccountId, token_in: &str) {
+        if let Some(violation) = self.order_param_violations(params, maker, token_in).first() {
+            violation.panic();
+        }
+    }
+}
+
+/// Short or human-chosen secrets can be brute-forced from the hashlock, so
+/// claims only accept exactly 32 bytes as 64 hex characters.
+pub fn secret_violation(secret: &str) -> Option<Violation> {
+    secret_bytes(secret).err()
+}
+
+/// The 32 bytes a secret encodes, in either hex case. Hashlocks are the
+/// hash of these bytes, as on the Ethereum escrow, never of the hex text.
+pub fn secret_bytes(secret: &str) -> Result<[u8; 32], Violation> {
+    let mut bytes = [0u8; 32];
+    if secret.len() == SECRET_HEX_LEN && hex::decode_to_slice(secret, &mut bytes).is_ok() {
+        return Ok(bytes);
+    }
+    Err(Violation::new(
+        ERR_WEAK_SECRET,
+        "secret must be 32 random bytes encoded as 64 hex characters, without a 0x prefix; \
+         the hashlock is the hash of the 32 decoded bytes",
+    ))
+}
+
+// Every part must be worth at least one unit of token_in
+fn secret_parts_violation(params: &OrderParams) -> Option<Violation> {
+    let parts = params.secret_parts?;
+    if parts < 2 || parts as usize > limits::MAX_SECRET_PARTS {
+        return Some(Violation::new(
+            ERR_INVALID_SECRET_PARTS,
+            format!("secret_parts must be between 2 and {}", limits::MAX_SECRET_PARTS),
+        ));
+    }
+    if params.amount_in.0 < parts as u128 {
+        return Some(Violation::new(ERR_INVALID_SECRET_PARTS, "amount_in is too small to split into secret_parts"));
+    }
+    None
+}
+
+// Prices only decay, down to a non-zero reserve
+fn auction_violation(params: &OrderParams) -> Option<Violation> {
+    let auction = params.auction.as_ref()?;
+    if auction.duration.0 == 0 {
+        return Some(Violation::new(ERR_INVALID_AUCTION, "auction duration must be positive"));
+    }
+    if auction.end_price.0 == 0 || auction.start_price.0 < auction.end_price.0 {
+        return Some(Violation::new(
+            ERR_INVALID_AUCTION,
+            "auction must decay from start_price to a non-zero end_price",
+        ));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::json_types::{U128, U64};
+
+    use super::*;
+    use crate::chains::{ERR_NO_DESTINATION_CONTRACT, ERR_UNSUPPORTED_CHAIN};
+    use crate::test_utils::*;
+    use crate::{BridgeConfig, BridgeKind, NotificationStatus, OrderStatus};
+
+    const AMOUNT: u128 = 10 * ONE_NEAR;
+
+    fn variants(contract: &AdaptiveCrossChain) -> Vec<OrderParams> {
+        let plain = || order_params(contract, 1, AMOUNT);
+        vec![
+            plain(),
+            OrderParams { timelock_duration: Some(U64(3_600)), validity_duration: Some(U64(600)), ..plain() },
+            OrderParams { taker_hashlock: Some(hashlock_of(contract, &secret(2))), hashlock_mode: Some(HashlockMode::Either), ..plain() },
+            OrderParams { secret_parts: Some(4), ..plain() },
+            OrderParams {
+                auction: Some(DutchAuction { start_price: U128(2_000), end_price: U128(1_000), duration: U64(600) }),
+                ..plain()
+            },
+            OrderParams {
+                settlement_callback: Some(SettlementCallback {
+                    contract_id: "hooks.near".parse().unwrap(),
+                    method_name: "on_settled".to_string(),
+                    status: NotificationStatus::Registered,
+                }),
+                memo: Some("rebalance".to_string()),
+                ..plain()
+            },
+            OrderParams { hashlock: format!("0x{}", plain().hashlock.to_uppercase()), bridge_route: Some(1), ..plain() },
+        ]
+    }
+
+    // Each variant goes on a fresh contract so its hashlock is free
+    #[test]
+    fn clean_report_means_creation_succeeds() {
+        for (i, params) in variants(&setup()).into_iter().enumerate() {
+            let mut contract = setup();
+            call(owner(), 0);
+            contract.add_bridge_route(1, BridgeConfig {
+                account: bridge(),
+                kind: BridgeKind::LightClient,
+                fee: U128(0),
+                delay: U64(900),
+                enabled: true,
+                chain_ids: vec![1],
+                max_payload_bytes: None,
+            });
+            contract.set_protocol_fee_bps(30);
+
+            call(maker(), 0);
+            let report = contract.validate_order_params(params.clone(), maker());
+            assert!(report.valid, "variant {}: {:?}", i, report.violations.iter().map(|v| &v.code).collect::<Vec<_>>());
+
+            let order_id = create_order_with(&mut contract, params, AMOUNT + ONE_NEAR);
+            let order = contract.orders.get(&order_id).unwrap();
+            assert!(matches!(order.status, OrderStatus::Active), "variant {}", i);
+            assert_eq!(order.current_slippage, report.initial_slippage, "variant {}", i);
+            assert_eq!(order.timelock_ns, report.timelock_ns, "variant {}", i);
+            // The callback's storage is attached on top, like the route fee
+            assert_eq!(order.amount_in, report.fees.net_escrow, "variant {}", i);
+        }
+    }
+
+    // The first violation in the report is the one the real call panics with
+    #[test]
+    fn violations_match_the_create_rejection() {
+        let mut contract = setup();
+        let taken = order_params(&contract, 9, AMOUNT);
+        create_order_with(&mut contract, taken.clone(), AMOUNT + ONE_NEAR);
+
+        let plain = || order_params(&contract, 1, AMOUNT);
+        let cases = vec![
+            (OrderParams { hashlock: "0x1234".to_string(), ..plain() }, ERR_INVALID_HASHLOCK),
+            (OrderParams { hashlock: taken.hashlock.clone(), ..plain() }, ERR_DUPLICATE_HASHLOCK),
+            (OrderParams { hashlock_mode: Some(HashlockMode::Both), ..plain() }, ERR_INVALID_HASHLOCK),
+            (OrderParams { token_out: "usdc".to_string(), ..plain() }, ERR_INVALID_TOKEN_OUT),
+            (OrderParams { token_out: "0x1234".to_string(), ..plain() }, ERR_INVALID_TOKEN_OUT),
+            (OrderParams { target_chain_id: 999, ..plain() }, ERR_UNSUPPORTED_CHAIN),
+            (OrderParams { target_chain_id: 137, ..plain() }, ERR_NO_DESTINATION_CONTRACT),
+            (OrderParams { timelock_duration: Some(U64(1)), ..plain() }, ERR_INVALID_TIMELOCK),
+            (OrderParams { validity_duration: Some(U64(86_400)), ..plain() }, ERR_INVALID_VALIDITY),
+            (OrderParams { secret_parts: Some(1), ..plain() }, ERR_INVALID_SECRET_PARTS),
+            (
+                OrderParams {
+                    auction: Some(DutchAuction { start_price: U128(1_000), end_price: U128(2_000), duration: U64(600) }),
+                    ..plain()
+                },
+                ERR_INVALID_AUCTION,
+            ),
+            (OrderParams { bridge_route: Some(7), ..plain() }, ERR_INVALID_BRIDGE_ROUTE),
+        ];
+        for (params, code) in cases {
+            call(maker(), 0);
+            let report = contract.validate_order_params(params.clone(), maker());
+            assert!(!report.valid, "{}", code);
+            assert_eq!(report.violations[0].code, code);
+            assert_rejected_unchanged(&mut contract, maker(), 0, code, |c| {
+                create_order_with(c, params, AMOUNT + ONE_NEAR);
+            });
+        }
+    }
+
+    #[test]
+    fn token_out_must_be_a_full_evm_address() {
+        // EIP-55 test vector
+        let checksummed = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        let lower = checksummed.to_ascii_lowercase();
+        let mut contract = setup();
+        let spellings = [lower.clone(), format!("0x{}", checksummed[2..].to_ascii_uppercase()), checksummed.to_string()];
+        for (i, token_out) in spellings.into_iter().enumerate() {
+            set_clock(0, i as u64);
+            let params = OrderParams { token_out, ..order_params(&contract, i as u8 + 1, AMOUNT) };
+            let order_id = create_order_with(&mut contract, params, AMOUNT + ONE_NEAR);
+            assert_eq!(contract.orders.get(&order_id).unwrap().token_out, lower);
+        }
+
+        let plain = order_params(&contract, 9, AMOUNT);
+        let cases = [
+            (checksummed[2..].to_string(), "must start with 0x"),
+            (lower[..41].to_string(), "got 39"),
+            (lower[..40].to_string(), "got 38"),
+            (lower.replacen('a', "g", 1), "non-hex character 'g'"),
+            (checksummed.replacen('a', "A", 1), "expected 0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"),
+        ];
+        for (token_out, reason) in cases {
+            let params = OrderParams { token_out: token_out.clone(), ..plain.clone() };
+            call(maker(), 0);
+            let report = contract.validate_order_params(params.clone(), maker());
+            assert_eq!(report.violations[0].code, ERR_INVALID_TOKEN_OUT, "{}", token_out);
+            assert!(report.violations[0].message.contains(reason), "{}: {}", token_out, report.violations[0].message);
+            assert_rejected_unchanged(&mut contract, maker(), 0, ERR_INVALID_TOKEN_OUT, |c| {
+                create_order_with(c, params, AMOUNT + ONE_NEAR);
+            });
+        }
+    }
+
+    #[test]
+    fn secrets_must_be_32_bytes_of_hex() {
+        assert!(secret_violation(&secret(1)).is_none());
+        assert!(secret_violation(&secret(1).to_uppercase()).is_none());
+        for secret in ["", "00", "my password", &format!("0x{}", secret(1))[..64], &format!("{}0", secret(1))] {
+            assert_eq!(secret_violation(secret).unwrap().code, ERR_WEAK_SECRET, "{:?}", secret);
+        }
+        // The rejection tells integrators what gets hashed
+        assert!(secret_violation("password").unwrap().message.contains("hash of the 32 decoded bytes"));
+        let contract = setup();
+        assert!(contract.validate_secret_format(secret(0xab).to_uppercase()));
+        assert!(!contract.validate_secret_format("password".to_string()));
+    }
+
+    // Known answers over bytes32(0), which is what the Ethereum escrow hashes
+    #[test]
+    fn hashlocks_hash_the_decoded_secret_bytes() {
+        let contract = setup();
+        let zero = "00".repeat(32);
+        assert_eq!(
+            contract.generate_hashlock(&zero, HashAlgorithm::Keccak256),
+            "290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e563"
+        );
+        assert_eq!(
+            contract.generate_hashlock(&zero, HashAlgorithm::Sha256),
+            "66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925"
+        );
+        // The README example
+        assert_eq!(
+            contract.generate_hashlock(
+                "9721bb562a366bda17684f6c1304b12262d4679eaaa340e98a0a2dd98c8f626a",
+                HashAlgorithm::Sha256
+            ),
+            "1b668fb81ed831d52fd09aaa71f32fb170e220eea37edbd8ed51952306d7b0bd"
+        );
+        for algorithm in [HashAlgorithm::Sha256, HashAlgorithm::Keccak256] {
+            assert_eq!(
+                contract.generate_hashlock(&"ab".repeat(32), algorithm),
+                contract.generate_hashlock(&"AB".repeat(32), algorithm)
+            );
+        }
+    }
+
+    #[test]
+    fn claims_verify_secrets_in_either_case() {
+        let mut contract = setup();
+        let mut params = order_params(&contract, 0xab, AMOUNT);
+        params.hash_algorithm = Some(HashAlgorithm::Keccak256);
+        params.hashlock = contract.generate_hashlock(&secret(0xab), HashAlgorithm::Keccak256);
+        let order_id = create_order_with(&mut contract, params, AMOUNT);
+        lock(&mut contract, order_id);
+
+        call(resolver(), 0);
+        let hashlock = contract.orders.get(&order_id).unwrap().hashlock;
+        assert_rejected_unchanged(&mut contract, resolver(), 0, "Invalid secret", |c| {
+            let _ = c.claim_with_secret(hashlock.clone(), secret(0xac), None);
+        });
+        let _ = contract.claim_with_secret(hashlock, secret(0xab).to_uppercase(), None);
+        assert_eq!(scheduled_calls("on_claim_transfer").len(), 1);
+    }
+}
@@ -0,0 +1,454 @@
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, require, AccountId};
+
+use crate::limits;
+use crate::normalize;
+use crate::roles::Role;
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, Balance, CrossChainOrder, DutchAuction, HashAlgorithm, HashlockMode, OrderStatus, SlippageHistory};
+
+const ONE_NEAR: Balance = 1_000_000_000_000_000_000_000_000;
+
+/// Order as returned by view methods. Economics are `None` for private
+/// orders unless the viewer is entitled to see them; the secret is `None`
+/// until the order is completed.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OrderView {
+    pub order_id: u64,
+    pub maker: AccountId,
+    pub token_in: AccountId,
+    pub token_out: String,
+    pub amount_in: Option<U128>,
+    pub amount_bucket: Option<String>, // Set instead of amount_in when redacted
+    pub amount_remaining: Option<U128>,
+    pub locked_amount: U128,
+    pub base_price: Option<U128>,
+    pub current_slippage: u64,
+    pub max_slippage_deviation: u64,
+    pub target_chain_id: u64,
+    pub hashlock: String,
+    pub timelock: U64,
+    pub timelock_ns: U64,
+    pub secret: Option<String>,
+    pub status: OrderStatus,
+    pub created_at: U64,
+    pub last_slippage_update: U64,
+    pub fill_attempts: u64,
+    pub last_fill_attempt_at: Option<U64>,
+    pub mirror_confirmed: bool,
+    pub fee_waived: bool,
+    pub private_economics: bool,
+    pub terminal_at: Option<U64>,
+    pub bridge_route: u8,
+    pub taker: Option<AccountId>,
+    pub locked_at: Option<U64>,
+    pub gas_sponsorship: U128,
+    pub baseline_slippage: u64,
+    pub needs_reconfirmation: bool,
+    pub skip_reconfirmation: bool,
+    pub hash_algorithm: HashAlgorithm,
+    pub secret_parts: Option<u32>,
+    pub auction: Option<DutchAuction>,
+    pub locked_price: Option<U128>,
+    pub timelock_extension: U64,
+    pub exclusive_claim_until: Option<U64>,
+    pub public_slippage_updates: bool,
+    pub valid_until: Option<U64>,
+    pub memo: Option<String>,
+    pub taker_hashlock: Option<String>,
+    pub hashlock_mode: HashlockMode,
+    pub taker_secret: Option<String>, // None until completed, like secret
+}
+
+/// Orders looked up by id, with the block time they were read at.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OrdersByIds {
+    pub block_timestamp: U64,          // Compare with timelock_ns for expiry
+    pub orders: Vec<Option<OrderView>>, // In request order, None if not stored
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    // Full order details for the maker and Operators. Must be called as a
+    // transaction so the caller can be authenticated.
+    pub fn get_order_full(&self, order_id: u64) -> Option<OrderView> {
+        let order = self.orders.get(&order_id)?;
+        let caller = env::predecessor_account_id();
+        require!(
+            self.can_view_economics(&order, &caller),
+            "Not authorized to view order economics"
+        );
+        Some(order_view(order, false))
+    }
+
+    /// Page through every stored order, `limit` capped at MAX_BATCH_SIZE.
+    /// Pages past the end are empty. Archiving moves the last stored order
+    /// into the archived one's slot, so indexes are only stable between
+    /// archive runs.
+    pub fn get_orders(&self, from_index: u64, limit: u64) -> Vec<OrderView> {
+        let limit = limit.min(limits::MAX_BATCH_SIZE as u64);
+        (from_index..self.orders.len().min(from_index.saturating_add(limit)))
+            .filter_map(|index| self.orders.get_at(index))
+            .map(|order| self.public_order_view(order))
+            .collect()
+    }
+
+    /// Up to MAX_VIEW_IDS orders by id, public views only. Unknown and
+    /// archived ids come back as None in their position.
+    pub fn get_orders_by_ids(&self, order_ids: Vec<u64>) -> OrdersByIds {
+        limits::assert_len(order_ids.len(), limits::MAX_VIEW_IDS, limits::ERR_BATCH_TOO_LARGE);
+        OrdersByIds {
+            block_timestamp: U64(env::block_timestamp()),
+            orders: order_ids
+                .iter()
+                .map(|order_id| self.orders.get(order_id).map(|order| self.public_order_view(order)))
+                .collect(),
+        }
+    }
+
+    // Orders get_orders pages over; archived orders are not included
+    pub fn get_orders_len(&self) -> u64 {
+        self.orders.len()
+    }
+
+    // For relayers that only know the hashlock from the EVM escrow event.
+    // Accepts any case and an optional 0x prefix. Cancelled and expired
+    // orders whose secret was never revealed release their hashlock and
+    // are no longer found here.
+    pub fn get_order_by_hashlock(&self, hashlock: String) -> Option<OrderView> {
+        let order_id = self.order_id_for_hashlock(&hashlock)?;
+        self.orders.get(&order_id).map(|order| self.public_order_view(order))
+    }
+
+    pub fn order_exists_for_hashlock(&self, hashlock: String) -> bool {
+        self.order_id_for_hashlock(&hashlock).is_some()
+    }
+
+    // The preimage the claim revealed, for relayers unlocking the EVM
+    // escrow. None until the order is Completed; Merkle secrets are only
+    // published in the claim events.
+    pub fn get_revealed_secret(&self, order_id: u64) -> Option<String> {
+        let order = self.orders.get(&order_id)?;
+        if !matches!(order.status, OrderStatus::Completed) {
+            return None;
+        }
+        order.secret
+    }
+
+    /// Slippage updates of an order, oldest first, `limit` capped at
+    /// MAX_BATCH_SIZE. Empty for unknown and archived orders. Histories
+    /// trimmed under storage pressure start at their oldest kept entry.
+    pub fn get_slippage_history(&self, order_id: u64, from_index: u64, limit: u64) -> Vec<SlippageHistory> {
+        let history = match self.slippage_log(order_id) {
+            Some(history) => history,
+            None => return Vec::new(),
+        };
+        let limit = limit.min(limits::MAX_BATCH_SIZE as u64);
+        (from_index..history.len().min(from_index.saturating_add(limit)))
+            .filter_map(|index| history.get(index))
+            .collect()
+    }
+
+    pub fn get_slippage_history_len(&self, order_id: u64) -> u64 {
+        self.slippage_log(order_id).map(|history| history.len()).unwrap_or(0)
+    }
+
+    // Orders created before hashlocks were passed in hold the maker's
+    // secret; only the maker and the owner can read it back
+    pub fn get_order_secret(&self, order_id: u64) -> Option<String> {
+        let order = self.orders.get(&order_id)?;
+        let caller = env::predecessor_account_id();
+        require!(
+            caller == order.maker || caller == self.owner,
+            "Only the maker or the owner can read the secret"
+        );
+        order.secret
+    }
+}
+
+impl AdaptiveCrossChain {
+    /// The only way views and events should expose an order publicly.
+    pub(crate) fn public_order_view(&self, order: CrossChainOrder) -> OrderView {
+        let redact = economics_hidden(&order);
+        order_view(order, redact)
+    }
+
+    fn order_id_for_hashlock(&self, hashlock: &str) -> Option<u64> {
+        if hashlock.len() > limits::MAX_HASHLOCK_LEN {
+            return None;
+        }
+        self.hashlock_to_order.get(&normalize::hex_id(hashlock))
+    }
+
+    pub(crate) fn can_view_economics(&self, order: &CrossChainOrder, viewer: &AccountId) -> bool {
+        !economics_hidden(order)
+            || order.maker == *viewer
+            || self.has_role(viewer.clone(), Role::Operator)
+    }
+}
+
+/// Private economics stay hidden until the order is first locked;
+/// settlement needs them public from then on. Registered fill attempts
+/// take no lock and reveal nothing.
+pub(crate) fn economics_hidden(order: &CrossChainOrder) -> bool {
+    order.private_economics && matches!(order.status, OrderStatus::Active) && !order.economics_revealed
+}
+
+/// Amount to show in logs and events, honouring private economics.
+pub(crate) fn public_amount(order: &CrossChainOrder) -> String {
+    if economics_hidden(order) {
+        amount_bucket(order.amount_in.0).to_string()
+    } else {
+        order.amount_in.0.to_string()
+    }
+}
+
+pub(crate) fn amount_bucket(amount: Balance) -> &'static str {
+    let near = amount / ONE_NEAR;
+    match near {
+        0..=9 => "<10 NEAR",
+        10..=99 => "10-100 NEAR",
+        100..=999 => "100-1000 NEAR",
+        1000..=9999 => "1000-10000 NEAR",
+        _ => ">=10000 NEAR",
+    }
+}
+
+fn order_view(order: CrossChainOrder, redact: bool) -> OrderView {
+    let completed = matches!(order.status, OrderStatus::Completed);
+    OrderView {
+        order_id: order.order_id,
+        maker: order.maker,
+        token_in: order.token_in,
+        token_out: order.token_out,
+        amount_in: if redact { None } else { Some(order.amount_in) },
+        amount_bucket: if redact {
+            Some(amount_bucket(order.amount_in.0).to_string())
+        } else {
+            None
+        },
+        amount_remaining: if redact { None } else { Some(order.amount_remaining) },
+        locked_amount: order.locked_amount,
+        base_price: if redact { None } else { Some(order.base_price) },
+        current_slippage: order.current_slippage,
+        max_slippage_deviation: order.max_slippage_deviation,
+        target_chain_id: order.target_chain_id,
+        hashlock: order.hashlock,
+        timelock: order.timelock,
+        timelock_ns: order.timelock_ns,
+        secret: if completed {
+            order.secret
+        } else {
+            None
+        },
+        status: order.status,
+        created_at: order.created_at,
+        last_slippage_update: order.last_slippage_update,
+        fill_attempts: order.fill_attempts,
+        last_fill_attempt_at: order.last_fill_attempt_at,
+        mirror_confirmed: order.mirror_confirmed,
+        fee_waived: order.fee_waived,
+        private_economics: order.private_economics,
+        terminal_at: order.terminal_at,
+        bridge_route: order.bridge_route,
+        taker: order.taker,
+        locked_at: order.locked_at,
+        gas_sponsorship: order.gas_sponsorship,
+        baseline_slippage: order.baseline_slippage,
+        needs_reconfirmation: order.needs_reconfirmation,
+        skip_reconfirmation: order.skip_reconfirmation,
+        hash_algorithm: order.hash_algorithm,
+        secret_parts: order.secret_parts,
+        auction: if redact { None } else { order.auction },
+        locked_price: order.locked_price,
+        timelock_extension: order.timelock_extension,
+        exclusive_claim_until: order.exclusive_claim_until,
+        public_slippage_updates: order.public_slippage_updates,
+        valid_until: order.valid_until,
+        memo: order.memo,
+        taker_hashlock: order.taker_hashlock,
+        hashlock_mode: order.hashlock_mode,
+        taker_secret: if completed {
+            order.taker_secret
+        } else {
+            None
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::serde_json;
+    use near_sdk::test_utils::accounts;
+
+    use super::*;
+    use crate::test_utils::*;
+    use crate::test_utils::ONE_NEAR;
+
+    const STATUSES: [OrderStatus; 6] = [
+        OrderStatus::Active,
+        OrderStatus::Locked,
+        OrderStatus::Completed,
+        OrderStatus::Expired,
+        OrderStatus::Cancelled,
+        OrderStatus::Claiming,
+    ];
+
+    // Everything a stranger can read about `order_id`, as JSON
+    fn public_json(contract: &AdaptiveCrossChain, order_id: u64) -> String {
+        call(accounts(4), 0);
+        let hashlock = contract.orders.get(&order_id).unwrap().hashlock;
+        let by_status: Vec<_> = STATUSES
+            .iter()
+            .map(|status| contract.get_orders_by_status(status.clone(), 0, 100))
+            .collect();
+        serde_json::json!({
+            "order": contract.get_order(order_id),
+            "orders": contract.get_orders(0, 100),
+            "by_ids": contract.get_orders_by_ids(vec![order_id]),
+            "by_hashlock": contract.get_order_by_hashlock(hashlock),
+            "by_status": by_status,
+            "awaiting_reconfirmation": contract.get_orders_awaiting_reconfirmation(0, 100),
+            "denied_token_out": contract.get_open_orders_with_denied_token_out(0, 100),
+            "revealed": contract.get_revealed_secret(order_id),
+        })
+        .to_string()
+    }
+
+    fn assert_secret_hidden(contract: &AdaptiveCrossChain, order_id: u64, state: &str) {
+        let json = public_json(contract, order_id).to_ascii_lowercase();
+        assert!(!json.contains(&secret(1)), "secret visible while {}", state);
+        call(maker(), 0);
+        let full = serde_json::to_string(&contract.get_order_full(order_id)).unwrap();
+        assert!(!full.contains(&secret(1)), "secret in the maker's view while {}", state);
+    }
+
+    #[test]
+    fn secret_never_appears_in_views_before_completion() {
+        let mut contract = setup();
+        let params = order_params(&contract, 1, 10 * ONE_NEAR);
+        let order_id = create_order_with(&mut contract, params, 10 * ONE_NEAR);
+        assert_secret_hidden(&contract, order_id, "Active");
+
+        let half = U128(contract.orders.get(&order_id).unwrap().amount_remaining.0 / 2);
+        call(resolver(), 0);
+        contract.lock_partial(order_id, half);
+        assert_secret_hidden(&contract, order_id, "Locked");
+
+        call(resolver(), 0);
+        let hashlock = contract.orders.get(&order_id).unwrap().hashlock;
+        let _ = contract.claim_with_secret(hashlock, secret(1), None);
+        let args = scheduled_calls("on_claim_transfer").pop().unwrap();
+        assert_secret_hidden(&contract, order_id, "Claiming");
+        callback(true);
+        contract.on_claim_transfer(order_id, serde_json::from_value(args["claim"].clone()).unwrap());
+
+        // Partly filled: the contract holds the secret, views do not show it
+        let order = contract.orders.get(&order_id).unwrap();
+        assert!(matches!(order.status, OrderStatus::Active) && order.secret.is_some());
+        assert_secret_hidden(&contract, order_id, "partly filled");
+
+        lock(&mut contract, order_id);
+        claim(&mut contract, order_id, 1);
+        assert!(public_json(&contract, order_id).contains(&secret(1)));
+        assert_eq!(contract.get_revealed_secret(order_id), Some(secret(1)));
+    }
+
+    #[test]
+    fn cancelled_orders_keep_the_secret_hidden() {
+        let mut contract = setup();
+        let params = order_params(&contract, 1, ONE_NEAR);
+        let order_id = create_order_with(&mut contract, params, ONE_NEAR);
+        call(maker(), 0);
+        let _ = contract.cancel_order(order_id);
+        assert_secret_hidden(&contract, order_id, "Cancelled");
+    }
+
+    #[test]
+    fn only_the_maker_and_owner_read_a_stored_secret() {
+        let mut contract = setup();
+        let params = order_params(&contract, 1, ONE_NEAR);
+        let order_id = create_order_with(&mut contract, params, ONE_NEAR);
+        let mut order = contract.orders.get(&order_id).unwrap();
+        order.secret = Some(secret(1)); // As legacy orders stored it
+        contract.orders.insert(&order_id, &order);
+
+        for reader in [maker(), owner()] {
+            call(reader, 0);
+            assert_eq!(contract.get_order_secret(order_id), Some(secret(1)));
+        }
+        assert_rejected_unchanged(&mut contract, resolver(), 0, "Only the maker or the owner", |c| {
+            c.get_order_secret(order_id);
+        });
+        assert_secret_hidden(&contract, order_id, "a legacy Active order");
+    }
+
+    fn private_order(contract: &mut AdaptiveCrossChain) -> u64 {
+        let mut params = order_params(contract, 1, 10 * ONE_NEAR);
+        params.private_economics = Some(true);
+        create_order_with(contract, params, 10 * ONE_NEAR)
+    }
+
+    fn register_resolvers(contract: &mut AdaptiveCrossChain, count: usize) -> Vec<AccountId> {
+        call(owner(), 0);
+        let reporters: Vec<AccountId> = (0..count).map(|i| format!("reporter{}.near", i).parse().unwrap()).collect();
+        for reporter in &reporters {
+            contract.register_resolver(reporter.clone());
+        }
+        reporters
+    }
+
+    fn economics_shown(contract: &AdaptiveCrossChain, order_id: u64) -> bool {
+        call(accounts(4), 0);
+        let view = contract.get_order(order_id).unwrap();
+        assert_eq!(view.amount_in.is_some(), view.base_price.is_some());
+        view.amount_in.is_some()
+    }
+
+    // Attempts count towards the limit but take no lock, so they must not
+    // reveal what the order is worth
+    #[test]
+    fn fill_attempts_without_a_lock_keep_economics_private() {
+        let mut contract = setup();
+        let order_id = private_order(&mut contract);
+        for reporter in register_resolvers(&mut contract, 3) {
+            call(reporter, 0);
+            contract.register_fill_attempt(order_id);
+            assert!(!economics_shown(&contract, order_id));
+        }
+        assert_eq!(contract.orders.get(&order_id).unwrap().fill_attempts, 3);
+
+        // Once locked they stay public, even after a partial fill reopens it
+        let half = U128(contract.orders.get(&order_id).unwrap().amount_remaining.0 / 2);
+        call(resolver(), 0);
+        contract.lock_partial(order_id, half);
+        assert!(economics_shown(&contract, order_id));
+        claim(&mut contract, order_id, 1);
+        let order = contract.orders.get(&order_id).unwrap();
+        assert!(matches!(order.status, OrderStatus::Active) && order.locked_at.is_none());
+        assert!(economics_shown(&contract, order_id));
+    }
+
+    #[test]
+    fn the_attempt_that_reaches_the_limit_expires_the_order() {
+        let mut contract = setup();
+        let order_id = private_order(&mut contract);
+        let escrow = contract.orders.get(&order_id).unwrap().amount_in.0;
+        call(owner(), 0);
+        contract.set_fill_attempt_limit(3);
+        let reporters = register_resolvers(&mut contract, 3);
+        for reporter in &reporters[..2] {
+            call(reporter.clone(), 0);
+            contract.register_fill_attempt(order_id);
+        }
+        assert!(matches!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Active));
+        assert!(!economics_shown(&contract, order_id));
+
+        call(reporters[2].clone(), 0);
+        contract.register_fill_attempt(order_id);
+        assert_eq!(transfers(), vec![(maker(), escrow)]);
+        assert!(matches!(contract.orders.get(&order_id).unwrap().status, OrderStatus::Expired));
+    }
+}
@@ -0,0 +1,261 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+use near_sdk::{env, near_bindgen, AccountId, Gas, NearToken, Promise, PromiseResult};
+
+use crate::limits;
+use crate::storage::ERR_STORAGE_BUDGET;
+use crate::validation::{Violation, ERR_INVALID_CALLBACK};
+use crate::{AdaptiveCrossChain, AdaptiveCrossChainExt, Balance, CrossChainOrder, OrderStatus, StorageStore};
+
+// Fixed allowance for the maker's contract; the notification is best effort
+pub const GAS_FOR_SETTLEMENT_CALLBACK: Gas = Gas::from_tgas(10);
+pub const GAS_FOR_NOTIFICATION_RESULT: Gas = Gas::from_tgas(5);
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum NotificationStatus {
+    Registered,
+    Pending,
+    Delivered,
+    Failed,
+}
+
+/// NEAR contract notified when the order reaches a terminal state.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SettlementCallback {
+    pub contract_id: AccountId,
+    pub method_name: String,
+    #[serde(default = "default_status")]
+    pub status: NotificationStatus,
+}
+
+fn default_status() -> NotificationStatus {
+    NotificationStatus::Registered
+}
+
+/// Compact payload passed to the callback contract as `outcome`.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OrderOutcome {
+    pub order_id: u64,
+    pub maker: AccountId,
+    pub status: OrderStatus,
+    pub amount_in: U128,
+    pub settled_at: U64,
+}
+
+#[near_bindgen]
+impl AdaptiveCrossChain {
+    #[private]
+    pub fn on_settlement_notified(&mut self, order_id: u64) -> bool {
+        let delivered = matches!(env::promise_result(0), PromiseResult::Successful(_));
+        if let Some(mut callback) = self.settlement_callbacks.get(&order_id) {
+            callback.status = if delivered {
+                NotificationStatus::Delivered
+            } else {
+                NotificationStatus::Failed
+            };
+            self.settlement_callbacks.insert(&order_id, &callback);
+        }
+
+        env::log_str(&format!(
+            "Settlement notification for order {} {}",
+            order_id,
+            if delivered { "delivered" } else { "failed" }
+        ));
+        delivered
+    }
+
+    pub fn get_settlement_callback(&self, order_id: u64) -> Option<SettlementCallback> {
+        self.settlement_callbacks.get(&order_id)
+    }
+}
+
+impl AdaptiveCrossChain {
+    /// Stores the callback for a new order and returns the storage cost the
+    /// maker must pay for it.
+    pub(crate) fn register_settlement_callback(
+        &mut self,
+        order_id: u64,
+        callback: SettlementCallback,
+    ) -> Balance {
+        if let Some(violation) = self.callback_violation(&callback) {
+            violation.panic();
+        }
+
+        let storage_before = env::storage_usage();
+        self.settlement_callbacks.insert(&order_id, &SettlementCallback {
+            status: NotificationStatus::Registered,
+            ..callback
+        });
+        let bytes = env::storage_usage() - storage_before;
+        self.record_storage(StorageStore::Notifications, storage_before);
+        bytes as Balance * env::storage_byte_cost().as_yoctonear()
+    }
+
+    pub(crate) fn callback_violation(&self, callback: &SettlementCallback) -> Option<Violation> {
+        if let Some(violation) = limits::check_len(
+            callback.method_name.len(),
+            limits::MAX_METHOD_NAME_LEN,
+            limits::ERR_METHOD_NAME_TOO_LONG,
+        ) {
+            return Some(violation);
+        }
+        let valid_name = !callback.method_name.is_empty()
+            && callback
+                .method_name
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_');
+        if !valid_name {
+            return Some(Violation::new(
+                ERR_INVALID_CALLBACK,
+                "Callback method name must be a lowercase identifier",
+            ));
+        }
+        if callback.contract_id == env::current_account_id() {
+            return Some(Violation::new(
+                ERR_INVALID_CALLBACK,
+                "Callback contract cannot be this contract",
+            ));
+        }
+        if self.over_budget(StorageStore::Notifications) {
+            return Some(Violation::new(
+                ERR_STORAGE_BUDGET,
+                "Notification storage is over budget; callbacks are not accepted",
+            ));
+        }
+        None
+    }
+
+    /// Fires the maker's callback for an order that has just become
+    /// terminal. The promise is detached from settlement, so a failing or
+    /// reentrant callback only ever sees (and can only affect) a finished order.
+    pub(crate) fn notify_settlement(&mut self, order: &CrossChainOrder) {
+        let mut callback = match self.settlement_callbacks.get(&order.order_id) {
+            Some(callback) => callback,
+            None => return,
+        };
+        callback.status = NotificationStatus::Pending;
+        self.settlement_callbacks.insert(&order.order_id, &callback);
+
+        let outcome = OrderOutcome {
+            order_id: order.order_id,
+            maker: order.maker.clone(),
+            status: order.status.clone(),
+            amount_in: order.amount_in,
+            settled_at: U64(env::block_timestamp()),
+        };
+        let args = json!({ "outcome": outcome }).to_string().into_bytes();
+
+        Promise::new(callback.contract_id)
+            .function_call(callback.method_name, args, NearToken::from_yoctonear(0), GAS_FOR_SETTLEMENT_CALLBACK)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_NOTIFICATION_RESULT)
+                    .on_settlement_notified(order.order_id),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::AccountId;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    const DEPOSIT: u128 = 10 * ONE_NEAR;
+
+    fn hooks() -> AccountId {
+        "hooks.near".parse().unwrap()
+    }
+
+    fn notified_order(contract: &mut AdaptiveCrossChain) -> u64 {
+        let mut params = order_params(contract, 1, DEPOSIT);
+        params.settlement_callback = Some(SettlementCallback {
+            contract_id: hooks(),
+            method_name: "on_order_settled".to_string(),
+            status: NotificationStatus::Registered,
+        });
+        create_order_with(contract, params, DEPOSIT)
+    }
+
+    #[test]
+    fn completion_notifies_the_callback_contract() {
+        let mut contract = setup();
+        let order_id = notified_order(&mut contract);
+        assert!(contract.get_settlement_callback(order_id).unwrap().status == NotificationStatus::Registered);
+        lock(&mut contract, order_id);
+
+        claim(&mut contract, order_id, 1);
+
+        let outcome = &scheduled_calls("on_order_settled")[0]["outcome"];
+        assert_eq!(outcome["order_id"], order_id);
+        assert_eq!(outcome["status"], "Completed");
+        assert_eq!(outcome["maker"], maker().as_str());
+        assert!(contract.get_settlement_callback(order_id).unwrap().status == NotificationStatus::Pending);
+    }
+
+    // The callback runs after the order is terminal, so calling back in
+    // cannot settle, cancel or lock it again
+    #[test]
+    fn reentrant_callback_cannot_touch_the_settled_order() {
+        let mut contract = setup();
+        let order_id = notified_order(&mut contract);
+        lock(&mut contract, order_id);
+        claim(&mut contract, order_id, 1);
+        let fees = contract.get_collected_fees();
+        let hashlock = contract.orders.get(&order_id).unwrap().hashlock;
+
+        assert_rejected_unchanged(&mut contract, hooks(), 0, "Order expired", |c| {
+            let _ = c.claim_with_secret(hashlock.clone(), secret(1), None);
+        });
+        assert_rejected_unchanged(&mut contract, hooks(), 0, "Order not active", |c| c.lock_order(order_id));
+        assert_rejected_unchanged(&mut contract, hooks(), 0, "Only the maker", |c| {
+            let _ = c.cancel_order(order_id);
+        });
+        assert_rejected_unchanged(&mut contract, maker(), 0, "already completed", |c| {
+            let _ = c.cancel_order(order_id);
+        });
+
+        // A failed notification is recorded and nothing else
+        callback(false);
+        assert!(!contract.on_settlement_notified(order_id));
+        assert!(contract.get_settlement_callback(order_id).unwrap().status == NotificationStatus::Failed);
+        let order = contract.orders.get(&order_id).unwrap();
+        assert!(matches!(order.status, OrderStatus::Completed));
+        assert_eq!(order.amount_remaining.0, 0);
+        assert_eq!(contract.get_collected_fees(), fees);
+    }
+
+    #[test]
+    fn delivered_notification_is_recorded() {
+        let mut contract = setup();
+        let order_id = notified_order(&mut contract);
+        call(maker(), 0);
+        let _ = contract.cancel_order(order_id);
+        assert_eq!(scheduled_calls("on_order_settled")[0]["outcome"]["status"], "Cancelled");
+
+        callback(true);
+        assert!(contract.on_settlement_notified(order_id));
+        assert!(contract.get_settlement_callback(order_id).unwrap().status == NotificationStatus::Delivered);
+    }
+
+    #[test]
+    fn callbacks_into_this_contract_are_rejected() {
+        let contract = setup();
+        let callback = SettlementCallback {
+            contract_id: "contract.near".parse().unwrap(),
+            method_name: "cancel_order".to_string(),
+            status: NotificationStatus::Registered,
+        };
+        call(maker(), 0);
+        let violation = contract.callback_violation(&callback).unwrap();
+        assert_eq!(violation.code, ERR_INVALID_CALLBACK);
+        let invalid_name = SettlementCallback { contract_id: hooks(), method_name: "Settle!".to_string(), ..callback };
+        assert_eq!(contract.callback_violation(&invalid_name).unwrap().code, ERR_INVALID_CALLBACK);
+    }
+}